@@ -1 +1,2 @@
 // This is a stub lib.rs.
+#![no_std]
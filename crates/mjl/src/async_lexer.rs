@@ -0,0 +1,109 @@
+//! Lexing of input read from a tokio [`AsyncBufRead`], for use in async servers without blocking
+//! tasks.
+
+use futures_util::Stream;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+
+use crate::{ErrorKind, Feed, FeedLexer, LexError, LexerOptions, OwnedToken};
+
+/// Lexes the input of `reader` as a [`Stream`] of tokens, reading no more input than it needs to
+/// produce the next token. Errors end the stream.
+pub fn token_stream<R: AsyncBufRead + Unpin>(
+    reader: R,
+    options: LexerOptions,
+) -> impl Stream<Item = Result<OwnedToken, LexError>> {
+    let state = Some((reader, FeedLexer::with_options(options)));
+    futures_util::stream::unfold(state, |state| async move {
+        let (mut reader, mut lexer) = state?;
+        loop {
+            let error = match lexer.next_token() {
+                Ok(Feed::Token(token)) => {
+                    let token = OwnedToken::from(token);
+                    return Some((Ok(token), Some((reader, lexer))));
+                }
+                Ok(Feed::End) => return None,
+                Ok(Feed::NeedMoreData) => match reader.fill_buf().await {
+                    Ok([]) => {
+                        lexer.finish();
+                        continue;
+                    }
+                    Ok(chunk) => {
+                        let len = chunk.len();
+                        lexer.feed(chunk);
+                        reader.consume(len);
+                        continue;
+                    }
+                    Err(e) => LexError {
+                        kind: ErrorKind::Io,
+                        message: e.to_string(),
+                        offset: lexer.byte_offset(),
+                    },
+                },
+                Err(e) => e,
+            };
+            return Some((Err(error), None));
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use futures_util::StreamExt;
+    use tokio::io::BufReader;
+
+    use crate::{
+        CommentMode, ErrorKind, LexerOptions, NumberKind, OwnedToken, async_lexer::token_stream,
+    };
+
+    #[tokio::test]
+    async fn stream_tokens() {
+        let input = "{\"k\u{e9}\": [12.5, true, null]} // end".as_bytes();
+        // a tiny buffer splits every token
+        let reader = BufReader::with_capacity(1, input);
+        let tokens: Vec<_> = token_stream(
+            reader,
+            LexerOptions {
+                comments: CommentMode::Emit,
+                ..Default::default()
+            },
+        )
+        .map(Result::unwrap)
+        .collect()
+        .await;
+        assert_eq!(
+            vec![
+                OwnedToken::LBrace,
+                OwnedToken::String("k\u{e9}".to_string()),
+                OwnedToken::Colon,
+                OwnedToken::LBracket,
+                OwnedToken::Number("12.5".to_string(), NumberKind::Float),
+                OwnedToken::Comma,
+                OwnedToken::True,
+                OwnedToken::Comma,
+                OwnedToken::Null,
+                OwnedToken::RBracket,
+                OwnedToken::RBrace,
+                OwnedToken::Comment("// end".to_string()),
+            ],
+            tokens
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_errors() {
+        let results: Vec<_> = token_stream(&b"[1, x]"[..], LexerOptions::default())
+            .collect()
+            .await;
+        assert_eq!(4, results.len());
+        let err = results[3].as_ref().unwrap_err();
+        assert_eq!((ErrorKind::Syntax, 4), (err.kind(), err.offset()));
+
+        let results: Vec<_> = token_stream(&b"[\"a"[..], LexerOptions::default())
+            .collect()
+            .await;
+        assert_eq!(
+            ErrorKind::UnexpectedEof,
+            results[1].as_ref().unwrap_err().kind()
+        );
+    }
+}
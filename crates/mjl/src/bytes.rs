@@ -0,0 +1,255 @@
+//! Lexing of input that arrives as bytes, such as network buffers and memory-mapped files,
+//! without validating it as UTF-8 up front.
+
+use alloc::string::ToString;
+use core::ops::Range;
+
+use crate::{
+    Dialect, ErrorKind, JsonLexer, LexError, LexerOptions, LexerStats, Token, TokenSource,
+};
+
+/// A lexer over bytes that may not be UTF-8.
+///
+/// Outside of strings, comments and JSON5 identifiers, valid tokens are ASCII, so the input is
+/// only validated token by token, as each is lexed: no pass over the whole input is made before
+/// the first token is returned, and invalid UTF-8 is reported as [`ErrorKind::InvalidUtf8`] when
+/// it is reached.
+#[derive(Debug, Clone)]
+pub struct ByteLexer<'a> {
+    pub input: &'a [u8],
+    pub byte_offset: usize,
+    pub options: LexerOptions,
+    /// What was lexed so far, also counting tokens for [`LexerOptions::max_tokens`].
+    stats: LexerStats,
+}
+
+impl<'a> ByteLexer<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        Self::with_options(input, LexerOptions::default())
+    }
+
+    pub fn with_options(input: &'a [u8], options: LexerOptions) -> Self {
+        ByteLexer {
+            input,
+            byte_offset: 0,
+            options,
+            stats: LexerStats::default(),
+        }
+    }
+
+    pub fn next_token(&mut self) -> Result<Option<Token<'a>>, LexError> {
+        Ok(self.next_spanned()?.map(|(token, _)| token))
+    }
+
+    /// Returns the next token with its byte range in the input.
+    pub fn next_spanned(&mut self) -> Result<Option<(Token<'a>, Range<usize>)>, LexError> {
+        loop {
+            let rest = &self.input[self.byte_offset..];
+            self.byte_offset += rest
+                .iter()
+                .position(|b| !matches!(b, b' ' | b'\t' | b'\n' | b'\r'))
+                .unwrap_or(rest.len());
+            let start = self.byte_offset;
+            let end = start + self.window_len(&self.input[start..]);
+            let text = core::str::from_utf8(&self.input[start..end]).map_err(|e| LexError {
+                kind: ErrorKind::InvalidUtf8,
+                message: "invalid UTF-8 in input".to_string(),
+                offset: start + e.valid_up_to(),
+            })?;
+
+            let mut lexer = JsonLexer::with_options(text, self.options.clone());
+            lexer.stats = self.stats;
+            let token = match lexer.next_spanned() {
+                Ok(token) => token,
+                // the window ended before a char no token continues with
+                Err(e) if e.kind == ErrorKind::UnexpectedEof && end < self.input.len() => {
+                    return Err(LexError {
+                        kind: ErrorKind::Syntax,
+                        offset: start + e.offset,
+                        ..e
+                    });
+                }
+                Err(e) => {
+                    return Err(LexError {
+                        offset: start + e.offset,
+                        ..e
+                    });
+                }
+            };
+            self.stats = lexer.stats;
+            self.byte_offset = start + lexer.byte_offset;
+            match token {
+                Some((token, span)) => {
+                    return Ok(Some((token, start + span.start..start + span.end)));
+                }
+                // the window held only whitespace or skipped comments
+                None if end < self.input.len() => {}
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Counts of what was lexed so far, see [`JsonLexer::stats`].
+    pub fn stats(&self) -> LexerStats {
+        self.stats.consumed(self.byte_offset)
+    }
+
+    /// Length of a window at the start of `rest` that holds the whole token starting there,
+    /// if any, and ends on a char boundary: up to the closing quote of a string, the end of a
+    /// comment, or else the next ASCII whitespace or punctuation that no token continues with.
+    fn window_len(&self, rest: &[u8]) -> usize {
+        let single_quotes = self.options.dialect == Dialect::Json5 || self.options.python_literals;
+        let delimited = |from: usize| {
+            rest[from..]
+                .iter()
+                .position(|b| {
+                    matches!(
+                        b,
+                        b' ' | b'\t'
+                            | b'\n'
+                            | b'\r'
+                            | b'{'
+                            | b'}'
+                            | b'['
+                            | b']'
+                            | b','
+                            | b':'
+                            | b'"'
+                            | b'\''
+                            | b'/'
+                    )
+                })
+                .map_or(rest.len(), |i| from + i)
+        };
+        match rest {
+            [] => 0,
+            [quote @ (b'"' | b'\''), ..] if *quote == b'"' || single_quotes => {
+                let mut i = 1;
+                loop {
+                    // escaped bytes are skipped, none of them continuing a multibyte char
+                    match rest[i..].iter().position(|b| b == quote || *b == b'\\') {
+                        Some(j) if rest[i + j] == b'\\' => i += j + 2,
+                        Some(j) => return i + j + 1,
+                        None => return rest.len(),
+                    }
+                    if i >= rest.len() {
+                        return rest.len();
+                    }
+                }
+            }
+            [b'/', b'/', ..] => rest.iter().position(|b| *b == b'\n').unwrap_or(rest.len()),
+            [b'/', b'*', ..] => rest[2..]
+                .windows(2)
+                .position(|w| w == b"*/")
+                .map_or(rest.len(), |i| i + 4),
+            [b'/', ..] => delimited(1),
+            _ => delimited(0).max(1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::ops::Range;
+
+    use crate::{
+        CommentMode, Dialect, JsonLexer, LexError, LexerOptions, Token, TokenSource,
+        bytes::ByteLexer,
+    };
+
+    /// The tokens and spans returned by `next`, ending with the kind and offset of the error, if
+    /// any.
+    fn collect<'a>(
+        mut next: impl FnMut() -> Result<Option<(Token<'a>, Range<usize>)>, LexError>,
+    ) -> Vec<String> {
+        let mut tokens = Vec::new();
+        loop {
+            match next() {
+                Ok(Some((token, span))) => tokens.push(format!("{token:?} {span:?}")),
+                Ok(None) => return tokens,
+                Err(e) => {
+                    tokens.push(format!("{:?} {}", e.kind(), e.offset()));
+                    return tokens;
+                }
+            }
+        }
+    }
+
+    fn tokens(input: &[u8], options: &LexerOptions) -> Vec<String> {
+        let mut lexer = ByteLexer::with_options(input, options.clone());
+        collect(|| lexer.next_spanned())
+    }
+
+    fn str_tokens(input: &str, options: &LexerOptions) -> Vec<String> {
+        let mut lexer = JsonLexer::with_options(input, options.clone());
+        collect(|| lexer.next_spanned())
+    }
+
+    #[test]
+    fn lex_like_str_input() {
+        let json = LexerOptions::default();
+        let json5 = LexerOptions {
+            dialect: Dialect::Json5,
+            comments: CommentMode::Emit,
+            ..Default::default()
+        };
+        let skip = LexerOptions {
+            comments: CommentMode::Skip,
+            ..Default::default()
+        };
+        let python = LexerOptions {
+            python_literals: true,
+            ..Default::default()
+        };
+        for (input, options) in [
+            (r#" {"café \"é\"": [-12.5e3, true, null, false]} "#, &json),
+            ("[1.]", &json),
+            ("[1.", &json),
+            ("[tru]", &json),
+            ("[tru", &json),
+            ("[-", &json),
+            ("\"abc", &json),
+            ("\"a\\", &json),
+            ("'a'", &json),
+            ("/x", &json),
+            ("{'clé': \"x\\'\", ünï: 0x1F, /* c */ n: .5} // end", &json5),
+            ("\u{a0}\"a\"\u{feff}'b'", &json5),
+            ("[1, /* a */ 2 // b\n]", &skip),
+            ("[1, /* a", &skip),
+            ("/", &skip),
+            ("{'a\\'': None, \"b\": True}", &python),
+        ] {
+            assert_eq!(
+                str_tokens(input, options),
+                tokens(input.as_bytes(), options),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn validate_lazily() {
+        let options = LexerOptions::default();
+        assert_eq!(
+            vec![
+                "LBracket 0..1",
+                "String(\"é\") 1..5",
+                "Comma 5..6",
+                "InvalidUtf8 8"
+            ],
+            tokens(b"[\"\xC3\xA9\", \"\xFF\"]", &options)
+        );
+        assert_eq!(
+            vec!["LBracket 0..1", "InvalidUtf8 1"],
+            tokens(b"[\xC3]", &options)
+        );
+        assert_eq!(
+            vec!["LBracket 0..1", "Syntax 1"],
+            tokens("[é]".as_bytes(), &options)
+        );
+        assert_eq!(
+            vec!["String(\"a\") 0..3", "InvalidUtf8 5"],
+            tokens(b"\"a\" \"\xC3", &options)
+        );
+    }
+}
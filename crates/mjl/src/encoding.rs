@@ -0,0 +1,182 @@
+//! Detection of the Unicode encoding of raw JSON input, and its decoding into UTF-8 text.
+
+use alloc::{borrow::Cow, format, string::String};
+
+use crate::{ErrorKind, LexError};
+
+/// Unicode encodings JSON text can be found in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be,
+}
+
+impl Encoding {
+    /// The byte order mark of this encoding.
+    pub fn bom(&self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8 => &[0xEF, 0xBB, 0xBF],
+            Encoding::Utf16Le => &[0xFF, 0xFE],
+            Encoding::Utf16Be => &[0xFE, 0xFF],
+            Encoding::Utf32Le => &[0xFF, 0xFE, 0x00, 0x00],
+            Encoding::Utf32Be => &[0x00, 0x00, 0xFE, 0xFF],
+        }
+    }
+}
+
+/// Detects the encoding of JSON text from its first bytes.
+///
+/// A byte order mark takes precedence. Otherwise the pattern of zero bytes is used, relying on
+/// the first two characters of a JSON text being ASCII (RFC 4627, section 3):
+///
+/// ```text
+/// 00 00 00 xx  UTF-32BE
+/// 00 xx 00 xx  UTF-16BE
+/// xx 00 00 00  UTF-32LE
+/// xx 00 xx 00  UTF-16LE
+/// xx xx xx xx  UTF-8
+/// ```
+///
+/// Anything that matches none of the patterns is assumed to be UTF-8.
+pub fn detect_encoding(bytes: &[u8]) -> Encoding {
+    // longest marks first, the UTF-32LE mark starts with the UTF-16LE one
+    for encoding in [
+        Encoding::Utf32Le,
+        Encoding::Utf32Be,
+        Encoding::Utf8,
+        Encoding::Utf16Le,
+        Encoding::Utf16Be,
+    ] {
+        if bytes.starts_with(encoding.bom()) {
+            return encoding;
+        }
+    }
+
+    match bytes {
+        [0, 0, 0, _, ..] => Encoding::Utf32Be,
+        [_, 0, 0, 0, ..] => Encoding::Utf32Le,
+        [0, _, ..] => Encoding::Utf16Be,
+        [_, 0, ..] => Encoding::Utf16Le,
+        _ => Encoding::Utf8,
+    }
+}
+
+/// Decodes JSON text in the encoding [`detect_encoding`] finds into UTF-8, without its byte
+/// order mark, for legacy producers of UTF-16 or UTF-32. UTF-8 input is borrowed.
+///
+/// Invalid input, such as an unpaired surrogate or a truncated code unit, fails with
+/// [`ErrorKind::InvalidUtf8`] at its byte offset in `bytes`.
+pub fn decode(bytes: &[u8]) -> Result<Cow<'_, str>, LexError> {
+    let encoding = detect_encoding(bytes);
+    let bom = if bytes.starts_with(encoding.bom()) {
+        encoding.bom().len()
+    } else {
+        0
+    };
+    let body = &bytes[bom..];
+    let error = |offset: usize, name: &str| LexError {
+        kind: ErrorKind::InvalidUtf8,
+        message: format!("invalid {name} in input"),
+        offset: bom + offset,
+    };
+    match encoding {
+        Encoding::Utf8 => core::str::from_utf8(body)
+            .map(Cow::Borrowed)
+            .map_err(|e| error(e.valid_up_to(), "UTF-8")),
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            let units = body.chunks(2).map(|unit| match (encoding, unit) {
+                (Encoding::Utf16Le, &[a, b]) => u16::from_le_bytes([a, b]),
+                (_, &[a, b]) => u16::from_be_bytes([a, b]),
+                // a truncated unit, never a valid char
+                _ => 0xDC00,
+            });
+            let mut text = String::with_capacity(body.len() / 2);
+            let mut offset = 0;
+            for c in char::decode_utf16(units) {
+                let c = c.map_err(|_| error(offset, "UTF-16"))?;
+                offset += 2 * c.len_utf16();
+                text.push(c);
+            }
+            Ok(Cow::Owned(text))
+        }
+        Encoding::Utf32Le | Encoding::Utf32Be => {
+            let mut text = String::with_capacity(body.len() / 4);
+            for (i, unit) in body.chunks(4).enumerate() {
+                let c = match (encoding, unit) {
+                    (Encoding::Utf32Le, &[a, b, c, d]) => u32::from_le_bytes([a, b, c, d]),
+                    (_, &[a, b, c, d]) => u32::from_be_bytes([a, b, c, d]),
+                    _ => return Err(error(4 * i, "UTF-32")),
+                };
+                text.push(char::from_u32(c).ok_or_else(|| error(4 * i, "UTF-32"))?);
+            }
+            Ok(Cow::Owned(text))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        ErrorKind,
+        encoding::{Encoding, decode, detect_encoding},
+    };
+
+    #[test]
+    fn detect_from_bom() {
+        assert_eq!(Encoding::Utf8, detect_encoding(b"\xEF\xBB\xBF{}"));
+        assert_eq!(Encoding::Utf16Le, detect_encoding(b"\xFF\xFE{\0}\0"));
+        assert_eq!(Encoding::Utf16Be, detect_encoding(b"\xFE\xFF\0{\0}"));
+        assert_eq!(Encoding::Utf32Le, detect_encoding(b"\xFF\xFE\0\0{\0\0\0"));
+        assert_eq!(Encoding::Utf32Be, detect_encoding(b"\0\0\xFE\xFF\0\0\0{"));
+    }
+
+    #[test]
+    fn detect_from_zero_bytes() {
+        assert_eq!(Encoding::Utf8, detect_encoding(b"{}"));
+        assert_eq!(Encoding::Utf8, detect_encoding(b""));
+        assert_eq!(Encoding::Utf16Le, detect_encoding(b"1\0"));
+        assert_eq!(Encoding::Utf16Be, detect_encoding(b"\0[\0]"));
+        assert_eq!(Encoding::Utf32Le, detect_encoding(b"1\0\0\0"));
+        assert_eq!(Encoding::Utf32Be, detect_encoding(b"\0\0\0["));
+    }
+
+    #[test]
+    fn decode_encodings() {
+        let utf16le: Vec<u8> = "[\"\u{e9}\u{1f600}\"]"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        assert_eq!("[\"\u{e9}\u{1f600}\"]", decode(&utf16le).unwrap());
+        let utf16be: Vec<u8> = "\u{feff}{}"
+            .encode_utf16()
+            .flat_map(u16::to_be_bytes)
+            .collect();
+        assert_eq!("{}", decode(&utf16be).unwrap());
+        let utf32le: Vec<u8> = "[1, \"\u{1f600}\"]"
+            .chars()
+            .flat_map(|c| u32::from(c).to_le_bytes())
+            .collect();
+        assert_eq!("[1, \"\u{1f600}\"]", decode(&utf32le).unwrap());
+        assert_eq!("[]", decode(b"\0\0\0[\0\0\0]").unwrap());
+        assert_eq!("{}", decode(b"\xEF\xBB\xBF{}").unwrap());
+
+        let invalid = |bytes: &[u8]| {
+            let e = decode(bytes).unwrap_err();
+            (e.kind(), e.offset())
+        };
+        // an unpaired surrogate, a truncated unit, invalid UTF-8, a char out of range
+        assert_eq!(
+            (ErrorKind::InvalidUtf8, 4),
+            invalid(b"[\0\"\0\x00\xD8\"\0]\0")
+        );
+        assert_eq!((ErrorKind::InvalidUtf8, 2), invalid(b"1\0\x32"));
+        assert_eq!(
+            (ErrorKind::InvalidUtf8, 5),
+            invalid(b"\xEF\xBB\xBF[\"\xFF\"]")
+        );
+        assert_eq!((ErrorKind::InvalidUtf8, 4), invalid(b"1\0\0\0\0\0\x11\0"));
+    }
+}
@@ -0,0 +1,304 @@
+//! Push-based lexing of input that arrives in chunks, such as from a network socket.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::{ErrorKind, JsonLexer, LexError, LexerOptions, LexerStats, Token};
+
+/// A lexer fed with chunks of input as they arrive, which may split tokens and UTF-8 sequences
+/// anywhere.
+///
+/// Input is buffered until the tokens it holds are lexed, so only the part of the input that
+/// was not lexed yet is kept in memory.
+#[derive(Debug, Default)]
+pub struct FeedLexer {
+    options: LexerOptions,
+    /// The input fed so far, validated as UTF-8 once as it is fed.
+    text: String,
+    /// Bytes after `text` that aren't valid UTF-8 yet, such as a sequence split across chunks.
+    pending: Vec<u8>,
+    /// Whether `pending` starts with an invalid sequence, which no chunk can complete.
+    invalid_utf8: bool,
+    /// Bytes at the start of `text` that were lexed.
+    consumed: usize,
+    /// Byte offset of the start of `text` in the input.
+    offset: usize,
+    /// What was lexed so far, also counting tokens for [`LexerOptions::max_tokens`].
+    stats: LexerStats,
+    finished: bool,
+}
+
+/// The outcome of [`FeedLexer::next_token`].
+#[derive(Debug, PartialEq, Clone)]
+pub enum Feed<'a> {
+    Token(Token<'a>),
+    /// The next token isn't complete yet, or may continue in the next chunk, as numbers do.
+    NeedMoreData,
+    /// The input was [finished](FeedLexer::finish) and every token was lexed.
+    End,
+}
+
+impl FeedLexer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_options(options: LexerOptions) -> Self {
+        FeedLexer {
+            options,
+            ..Self::default()
+        }
+    }
+
+    /// Appends a chunk of UTF-8 input.
+    pub fn feed(&mut self, chunk: impl AsRef<[u8]>) {
+        self.offset += self.consumed;
+        self.text.drain(..self.consumed);
+        self.consumed = 0;
+        if self.invalid_utf8 {
+            // nothing past an invalid sequence is lexed
+            return;
+        }
+        self.pending.extend_from_slice(chunk.as_ref());
+        let valid = match core::str::from_utf8(&self.pending) {
+            Ok(_) => self.pending.len(),
+            // an incomplete sequence at the end may be completed by the next chunk
+            Err(e) => {
+                self.invalid_utf8 = e.error_len().is_some();
+                e.valid_up_to()
+            }
+        };
+        self.text
+            .push_str(core::str::from_utf8(&self.pending[..valid]).unwrap());
+        self.pending.drain(..valid);
+    }
+
+    /// Marks the end of the input, so that the last token is lexed even though more data could
+    /// have extended it.
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    /// Byte offset in the input of the next token, or of the whitespace before it.
+    pub fn byte_offset(&self) -> usize {
+        self.offset + self.consumed
+    }
+
+    /// Counts of the tokens lexed so far, see [`JsonLexer::stats`]. A token is only counted once
+    /// complete, so not while more data is needed.
+    pub fn stats(&self) -> LexerStats {
+        self.stats.consumed(self.byte_offset())
+    }
+
+    /// Lexes the next token of the input fed so far. Error offsets are counted from the start
+    /// of the input.
+    pub fn next_token(&mut self) -> Result<Feed<'_>, LexError> {
+        let invalid_utf8 = self.invalid_utf8 || (self.finished && !self.pending.is_empty());
+        let more_data = !self.finished && !invalid_utf8;
+        let text = &self.text[self.consumed..];
+        let offset = self.offset + self.consumed;
+        let utf8_error = || LexError {
+            kind: ErrorKind::InvalidUtf8,
+            message: "invalid UTF-8 in input".to_string(),
+            offset: offset + text.len(),
+        };
+
+        // the options are lent to the lexer and taken back, rather than cloned for each token
+        let mut lexer = JsonLexer::with_options(text, core::mem::take(&mut self.options));
+        lexer.stats = self.stats;
+        let result = lexer.next_token();
+        self.options = lexer.options;
+        let token = match result {
+            // numbers, line comments and JSON5 identifiers may continue in the next chunk
+            Ok(Some(
+                Token::Number(..)
+                | Token::Comment(_)
+                | Token::Identifier(_)
+                | Token::True
+                | Token::False
+                | Token::Null,
+            )) if lexer.byte_offset == text.len() && more_data => {
+                return Ok(Feed::NeedMoreData);
+            }
+            Ok(Some(token)) => token,
+            Ok(None) if more_data => return Ok(Feed::NeedMoreData),
+            Ok(None) if invalid_utf8 => return Err(utf8_error()),
+            Ok(None) => return Ok(Feed::End),
+            Err(e) if e.kind == ErrorKind::UnexpectedEof && more_data => {
+                return Ok(Feed::NeedMoreData);
+            }
+            Err(e) if e.kind == ErrorKind::UnexpectedEof && invalid_utf8 => {
+                return Err(utf8_error());
+            }
+            Err(e) => {
+                return Err(LexError {
+                    offset: offset + e.offset,
+                    ..e
+                });
+            }
+        };
+        self.consumed += lexer.byte_offset;
+        self.stats = lexer.stats;
+        Ok(Feed::Token(token))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        CommentMode, ErrorKind, LexerOptions, NumberKind, Token,
+        feed::{Feed, FeedLexer},
+    };
+
+    /// Feeds `chunks` one by one, collecting the tokens as text.
+    fn lex(lexer: &mut FeedLexer, chunks: &[&[u8]]) -> Vec<String> {
+        let mut tokens = Vec::new();
+        let mut chunks = chunks.iter();
+        loop {
+            match lexer.next_token().unwrap() {
+                Feed::Token(token) => tokens.push(format!("{token:?}")),
+                Feed::NeedMoreData => match chunks.next() {
+                    Some(chunk) => lexer.feed(chunk),
+                    None => lexer.finish(),
+                },
+                Feed::End => return tokens,
+            }
+        }
+    }
+
+    #[test]
+    fn lex_split_tokens() {
+        let input = "{\"caf\u{e9}\": [-12.5e3, true, null], \"b\": 7}".as_bytes();
+        let whole = lex(&mut FeedLexer::new(), &[input]);
+        assert_eq!(15, whole.len());
+        for split in 1..input.len() {
+            let (a, b) = input.split_at(split);
+            assert_eq!(
+                whole,
+                lex(&mut FeedLexer::new(), &[a, b]),
+                "split at {split}"
+            );
+        }
+        let bytes: Vec<&[u8]> = input.chunks(1).collect();
+        assert_eq!(whole, lex(&mut FeedLexer::new(), &bytes));
+    }
+
+    #[test]
+    fn lex_split_comments() {
+        let options = LexerOptions {
+            comments: CommentMode::Emit,
+            ..Default::default()
+        };
+        let mut lexer = FeedLexer::with_options(options);
+        let tokens = lex(&mut lexer, &[b"[1 // a", b" comment\n, 2 /* b", b" */]"]);
+        let expected = [
+            Token::LBracket,
+            Token::Number("1", NumberKind::Integer),
+            Token::Comment("// a comment"),
+            Token::Comma,
+            Token::Number("2", NumberKind::Integer),
+            Token::Comment("/* b */"),
+            Token::RBracket,
+        ];
+        let expected: Vec<_> = expected.iter().map(|t| format!("{t:?}")).collect();
+        assert_eq!(expected, tokens);
+
+        let options = LexerOptions {
+            comments: CommentMode::Skip,
+            ..Default::default()
+        };
+        let mut lexer = FeedLexer::with_options(options);
+        let tokens = lex(
+            &mut lexer,
+            &[b"[1 /", b"/ a", b" comment\n, 2 /* b", b" */]"],
+        );
+        assert_eq!(
+            [
+                "LBracket",
+                "Number(\"1\", Integer)",
+                "Comma",
+                "Number(\"2\", Integer)",
+                "RBracket"
+            ],
+            &tokens[..]
+        );
+    }
+
+    #[test]
+    fn report_errors() {
+        let mut lexer = FeedLexer::new();
+        lexer.feed("[1, ");
+        assert!(matches!(
+            lexer.next_token(),
+            Ok(Feed::Token(Token::LBracket))
+        ));
+        assert!(matches!(
+            lexer.next_token(),
+            Ok(Feed::Token(Token::Number("1", NumberKind::Integer)))
+        ));
+        assert!(matches!(lexer.next_token(), Ok(Feed::Token(Token::Comma))));
+        assert_eq!(Ok(Feed::NeedMoreData), lexer.next_token());
+        lexer.feed("x]");
+        let err = lexer.next_token().unwrap_err();
+        assert_eq!(ErrorKind::Syntax, err.kind());
+        assert_eq!(4, err.offset());
+
+        let mut lexer = FeedLexer::new();
+        lexer.feed(b"\"\xC3");
+        assert_eq!(Ok(Feed::NeedMoreData), lexer.next_token());
+        lexer.finish();
+        assert_eq!(
+            ErrorKind::InvalidUtf8,
+            lexer.next_token().unwrap_err().kind()
+        );
+
+        // an invalid sequence isn't completed by later chunks
+        let mut lexer = FeedLexer::new();
+        lexer.feed(b"[1, \xFF");
+        lexer.feed("2]");
+        assert_eq!(3, lex_until_error(&mut lexer));
+        let err = lexer.next_token().unwrap_err();
+        assert_eq!(ErrorKind::InvalidUtf8, err.kind());
+        assert_eq!(4, err.offset());
+    }
+
+    /// Lexes tokens until the next one is an error, returning how many there were.
+    fn lex_until_error(lexer: &mut FeedLexer) -> usize {
+        let mut tokens = 0;
+        while let Ok(Feed::Token(_)) = lexer.next_token() {
+            tokens += 1;
+        }
+        tokens
+    }
+
+    #[test]
+    fn enforce_limits() {
+        let options = LexerOptions {
+            max_string_len: Some(4),
+            max_tokens: Some(3),
+            ..Default::default()
+        };
+        // an overlong string fails before it is closed
+        let mut lexer = FeedLexer::with_options(options.clone());
+        lexer.feed("\"abc");
+        assert_eq!(Ok(Feed::NeedMoreData), lexer.next_token());
+        lexer.feed("de");
+        let err = lexer.next_token().unwrap_err();
+        assert_eq!(ErrorKind::LimitExceeded, err.kind());
+
+        // tokens are counted across chunks
+        let mut lexer = FeedLexer::with_options(options);
+        lexer.feed("[1,");
+        assert_eq!(Ok(Feed::Token(Token::LBracket)), lexer.next_token());
+        assert_eq!(
+            Ok(Feed::Token(Token::Number("1", NumberKind::Integer))),
+            lexer.next_token()
+        );
+        lexer.feed(" 2]");
+        assert_eq!(Ok(Feed::Token(Token::Comma)), lexer.next_token());
+        let err = lexer.next_token().unwrap_err();
+        assert_eq!((ErrorKind::LimitExceeded, 4), (err.kind(), err.offset()));
+    }
+}
@@ -0,0 +1,237 @@
+//! Lexing of the JSON5 syntax that JSON lacks, see [`Dialect::Json5`](crate::Dialect::Json5).
+
+use crate::{Dialect, ErrorKind, JsonLexer, LexError, NumberKind, Token, decimal_len};
+
+pub(crate) fn is_identifier_start(c: char) -> bool {
+    c.is_alphabetic() || c == '$' || c == '_' || c == '\\'
+}
+
+fn is_identifier_part(c: char) -> bool {
+    is_identifier_start(c) || c.is_alphanumeric() || c == '\u{200c}' || c == '\u{200d}'
+}
+
+/// Length and kind of the JSON5 number at the start of `s`: a signed decimal, hexadecimal,
+/// `Infinity` or `NaN`.
+fn number_len(s: &str) -> Option<(usize, NumberKind)> {
+    let sign = usize::from(s.starts_with(['+', '-']));
+    let rest = &s[sign..];
+    let (len, kind) = if rest.starts_with("Infinity") {
+        (8, NumberKind::Float)
+    } else if rest.starts_with("NaN") {
+        (3, NumberKind::Float)
+    } else if let Some(hex) = rest.strip_prefix("0x").or_else(|| rest.strip_prefix("0X"))
+        && hex.starts_with(|c: char| c.is_ascii_hexdigit())
+    {
+        let digits = hex.bytes().take_while(u8::is_ascii_hexdigit).count();
+        (2 + digits, NumberKind::Integer)
+    } else {
+        decimal_len(rest.as_bytes(), false)?
+    };
+    Some((sign + len, kind))
+}
+
+impl<'a> JsonLexer<'a> {
+    /// Lexes the token starting with `c` if JSON5 lexes it differently from JSON: strings,
+    /// numbers and identifiers.
+    pub(crate) fn lex_json5(&mut self, c: char) -> Option<Result<Option<Token<'a>>, LexError>> {
+        match c {
+            '"' | '\'' => Some(self.lex_json5_string(c)),
+            '+' | '-' | '.' | '0'..='9' => Some(self.lex_json5_number()),
+            c if is_identifier_start(c) => Some(self.lex_identifier()),
+            _ => None,
+        }
+    }
+
+    fn lex_json5_number(&mut self) -> Result<Option<Token<'a>>, LexError> {
+        let rest = &self.input[self.byte_offset..];
+        let Some((len, kind)) = number_len(rest) else {
+            return Err(self.error("invalid number"));
+        };
+        if rest[len..].starts_with(|c: char| is_identifier_part(c) || c == '.') {
+            return Err(self.error("invalid number"));
+        }
+        self.byte_offset += len;
+        Ok(Some(Token::Number(&rest[..len], kind)))
+    }
+
+    /// Lexes an identifier, which is a key unless it is a literal such as `true` or `Infinity`.
+    pub(crate) fn lex_identifier(&mut self) -> Result<Option<Token<'a>>, LexError> {
+        let rest = &self.input[self.byte_offset..];
+        let mut len = 0;
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            if !is_identifier_part(c) {
+                break;
+            }
+            len += c.len_utf8();
+            if c == '\\' {
+                if chars.next() != Some('u') {
+                    return Err(self.error("invalid escape sequence in identifier"));
+                }
+                for _ in 0..4 {
+                    if !chars.next().is_some_and(|h| h.is_ascii_hexdigit()) {
+                        return Err(self.error("invalid unicode escape sequence"));
+                    }
+                }
+                len += 5;
+            }
+        }
+        let word = &rest[..len];
+        self.byte_offset += len;
+        Ok(Some(match word {
+            "true" => Token::True,
+            "false" => Token::False,
+            "null" => Token::Null,
+            "True" if self.options.python_literals => Token::True,
+            "False" if self.options.python_literals => Token::False,
+            "None" if self.options.python_literals => Token::Null,
+            "Infinity" | "NaN" if self.options.dialect == Dialect::Json5 => {
+                Token::Number(word, NumberKind::Float)
+            }
+            _ => Token::Identifier(word),
+        }))
+    }
+
+    /// Lexes a string quoted with `quote`, which may hold any escape sequence, including line
+    /// continuations.
+    pub(crate) fn lex_json5_string(&mut self, quote: char) -> Result<Option<Token<'a>>, LexError> {
+        let start = self.byte_offset + 1;
+        let mut chars = self.input[start..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            if self.string_too_long(i) {
+                self.byte_offset = start;
+                return Err(self.error_kind(ErrorKind::LimitExceeded, "string too long"));
+            }
+            match c {
+                c if c == quote => {
+                    self.byte_offset = start + i + 1;
+                    return Ok(Some(Token::String(&self.input[start..start + i])));
+                }
+                '\n' | '\r' => {
+                    self.byte_offset = start + i;
+                    return Err(self.error("unescaped line break in string"));
+                }
+                '\\' => {
+                    let error = match chars.next().map(|(_, e)| e) {
+                        None => break,
+                        Some('x') => (0..2)
+                            .any(|_| !chars.next().is_some_and(|(_, h)| h.is_ascii_hexdigit()))
+                            .then_some("invalid hex escape sequence"),
+                        Some('u') => (0..4)
+                            .any(|_| !chars.next().is_some_and(|(_, h)| h.is_ascii_hexdigit()))
+                            .then_some("invalid unicode escape sequence"),
+                        Some('1'..='9') => Some("invalid escape sequence"),
+                        // a CRLF line continuation
+                        Some('\r') => {
+                            if chars.clone().next().is_some_and(|(_, n)| n == '\n') {
+                                chars.next();
+                            }
+                            None
+                        }
+                        Some('0')
+                            if chars
+                                .clone()
+                                .next()
+                                .is_some_and(|(_, d)| d.is_ascii_digit()) =>
+                        {
+                            Some("invalid escape sequence")
+                        }
+                        Some(_) => None,
+                    };
+                    if let Some(message) = error {
+                        self.byte_offset = start + i;
+                        return Err(self.error(message));
+                    }
+                }
+                _ => {}
+            }
+        }
+        if self.string_too_long(self.input.len() - start) {
+            self.byte_offset = start;
+            return Err(self.error_kind(ErrorKind::LimitExceeded, "string too long"));
+        }
+        Err(self.error_kind(ErrorKind::UnexpectedEof, "unclosed string literal"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Dialect, ErrorKind, JsonLexer, LexerOptions, NumberKind, Token};
+
+    fn tokens(input: &str) -> Result<Vec<Token<'_>>, ErrorKind> {
+        let options = LexerOptions {
+            dialect: Dialect::Json5,
+            ..Default::default()
+        };
+        let mut lexer = JsonLexer::with_options(input, options);
+        std::iter::from_fn(|| lexer.next_token().transpose())
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.kind())
+    }
+
+    #[test]
+    fn lex_json5() {
+        let input = "\u{feff}{unquoted: 'it\\'s', $a_1: \"line\\\ncontinued\", // note\n \
+                     hex: 0xFF, n: [+1, .5, 5., -Infinity, NaN, 1e3], t: true,}";
+        assert_eq!(
+            Ok(vec![
+                Token::LBrace,
+                Token::Identifier("unquoted"),
+                Token::Colon,
+                Token::String("it\\'s"),
+                Token::Comma,
+                Token::Identifier("$a_1"),
+                Token::Colon,
+                Token::String("line\\\ncontinued"),
+                Token::Comma,
+                Token::Identifier("hex"),
+                Token::Colon,
+                Token::Number("0xFF", NumberKind::Integer),
+                Token::Comma,
+                Token::Identifier("n"),
+                Token::Colon,
+                Token::LBracket,
+                Token::Number("+1", NumberKind::Integer),
+                Token::Comma,
+                Token::Number(".5", NumberKind::Float),
+                Token::Comma,
+                Token::Number("5.", NumberKind::Float),
+                Token::Comma,
+                Token::Number("-Infinity", NumberKind::Float),
+                Token::Comma,
+                Token::Number("NaN", NumberKind::Float),
+                Token::Comma,
+                Token::Number("1e3", NumberKind::Exponent),
+                Token::RBracket,
+                Token::Comma,
+                Token::Identifier("t"),
+                Token::Colon,
+                Token::True,
+                Token::Comma,
+                Token::RBrace,
+            ]),
+            tokens(input)
+        );
+        assert_eq!(
+            Ok(vec![Token::String("a\\\r\nb"), Token::String("c\\\rd")]),
+            tokens("'a\\\r\nb' 'c\\\rd'")
+        );
+        assert_eq!(
+            Ok(vec![Token::Identifier("caf\\u00e9")]),
+            tokens("caf\\u00e9")
+        );
+    }
+
+    #[test]
+    fn reject_invalid_json5() {
+        assert_eq!(Err(ErrorKind::Syntax), tokens("'a\nb'"));
+        assert_eq!(Err(ErrorKind::Syntax), tokens("'\\1'"));
+        assert_eq!(Err(ErrorKind::Syntax), tokens("'\\xZZ'"));
+        assert_eq!(Err(ErrorKind::Syntax), tokens("01"));
+        assert_eq!(Err(ErrorKind::Syntax), tokens("0x"));
+        assert_eq!(Err(ErrorKind::Syntax), tokens("1.2.3"));
+        assert_eq!(Err(ErrorKind::UnexpectedEof), tokens("'abc"));
+        // JSON keeps rejecting JSON5
+        assert!(JsonLexer::new("'a'").next_token().is_err());
+    }
+}
@@ -1,8 +1,36 @@
-use lazy_static::lazy_static;
-use regex::Regex;
-use std::error::Error;
-use std::fmt::Display;
-use std::str::Chars;
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::borrow::Cow;
+use alloc::format;
+use alloc::string::{String, ToString};
+use core::error::Error;
+use core::fmt::Display;
+use core::ops::Range;
+
+#[cfg(feature = "tokio")]
+pub mod async_lexer;
+pub mod bytes;
+pub mod encoding;
+pub mod feed;
+mod json5;
+pub mod lines;
+pub mod observe;
+pub mod record;
+pub mod slice;
+pub mod stats;
+pub mod trivia;
+
+pub use bytes::ByteLexer;
+pub use encoding::detect_encoding;
+pub use feed::{Feed, FeedLexer};
+pub use lines::Lines;
+pub use observe::{Observed, TokenObserver};
+pub use record::{Recording, Replayer};
+pub use slice::SliceTokens;
+pub use stats::LexerStats;
+pub use trivia::{Element, TriviaTokens};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Token<'a> {
@@ -14,39 +42,379 @@ pub enum Token<'a> {
     Colon,
     True,
     False,
-    Number(&'a str),
+    /// The text of the number, borrowed from the input, and whether it is an integer.
+    Number(&'a str, NumberKind),
+    /// The raw text of the string literal between its quotes, escapes included, borrowed from
+    /// the input. See [`unescape`] to decode it.
     String(&'a str),
     Null,
+    /// An unquoted object key, escapes included. Only produced in [`Dialect::Json5`].
+    Identifier(&'a str),
+    /// A `// line` or `/* block */` comment, delimiters included. Only produced when
+    /// [`LexerOptions::comments`] is [`CommentMode::Emit`].
+    Comment(&'a str),
+}
+
+impl Token<'_> {
+    pub fn kind(&self) -> TokenKind {
+        match self {
+            Token::LBrace => TokenKind::LBrace,
+            Token::RBrace => TokenKind::RBrace,
+            Token::LBracket => TokenKind::LBracket,
+            Token::RBracket => TokenKind::RBracket,
+            Token::Comma => TokenKind::Comma,
+            Token::Colon => TokenKind::Colon,
+            Token::True => TokenKind::True,
+            Token::False => TokenKind::False,
+            Token::Number(..) => TokenKind::Number,
+            Token::String(_) => TokenKind::String,
+            Token::Null => TokenKind::Null,
+            Token::Identifier(_) => TokenKind::Identifier,
+            Token::Comment(_) => TokenKind::Comment,
+        }
+    }
+}
+
+/// Writes the token as it appears in JSON: strings between double quotes, with their raw text,
+/// the escapes of JSON5 and lenient strings converted to JSON ones.
+impl Display for Token<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Token::LBrace => f.write_str("{"),
+            Token::RBrace => f.write_str("}"),
+            Token::LBracket => f.write_str("["),
+            Token::RBracket => f.write_str("]"),
+            Token::Comma => f.write_str(","),
+            Token::Colon => f.write_str(":"),
+            Token::True => f.write_str("true"),
+            Token::False => f.write_str("false"),
+            Token::Null => f.write_str("null"),
+            Token::String(s) => write_json_string(s, f),
+            Token::Number(text, _) | Token::Identifier(text) | Token::Comment(text) => {
+                f.write_str(text)
+            }
+        }
+    }
+}
+
+/// Writes the raw text of a string literal between double quotes, converting what isn't JSON:
+/// unescaped double quotes and control chars, as found in single-quoted JSON5 and lenient
+/// strings, and the escapes only JSON5 has.
+fn write_json_string(raw: &str, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    use core::fmt::Write;
+
+    f.write_char('"')?;
+    let mut chars = raw.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            c if c.is_ascii_control() => write!(f, "\\u{:04x}", c as u32)?,
+            '\\' => match chars.next() {
+                Some(e @ ('"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't' | 'u')) => {
+                    write!(f, "\\{e}")?;
+                }
+                Some('x') => {
+                    f.write_str("\\u00")?;
+                    chars.by_ref().take(2).try_for_each(|c| f.write_char(c))?;
+                }
+                Some('v') => f.write_str("\\u000b")?,
+                Some('0') => f.write_str("\\u0000")?,
+                // line continuations
+                Some('\r') => {
+                    chars.next_if_eq(&'\n');
+                }
+                Some('\n' | '\u{2028}' | '\u{2029}') => {}
+                // any other char escapes itself
+                Some(c) if c.is_ascii_control() => write!(f, "\\u{:04x}", c as u32)?,
+                Some(c) => f.write_char(c)?,
+                None => {}
+            },
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+/// The kind of a [`Token`], without its text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+    Comma,
+    Colon,
+    True,
+    False,
+    Number,
+    String,
+    Null,
+    Identifier,
+    Comment,
+}
+
+/// The shape of a number literal, told while lexing it, so that it needs no scanning again to
+/// pick how to convert it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NumberKind {
+    /// Digits only, such as `-12`, or a JSON5 hexadecimal number such as `0xFF`.
+    Integer,
+    /// A number with a fraction but no exponent, such as `1.5`, or JSON5's `Infinity` and `NaN`.
+    Float,
+    /// A number with an exponent, such as `1e3` or `2.5E-2`.
+    Exponent,
+}
+
+impl NumberKind {
+    /// The kind of the number literal `text`, for numbers that weren't just lexed.
+    pub fn of(text: &str) -> NumberKind {
+        let digits = text.trim_start_matches(['+', '-']);
+        if digits.starts_with("0x") || digits.starts_with("0X") {
+            NumberKind::Integer
+        } else if digits.contains(['e', 'E']) {
+            NumberKind::Exponent
+        } else if digits.contains('.') || digits.starts_with(['I', 'N']) {
+            NumberKind::Float
+        } else {
+            NumberKind::Integer
+        }
+    }
+}
+
+/// A [`Token`] owning its text, for tokens that outlive the input they were lexed from.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum OwnedToken {
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+    Comma,
+    Colon,
+    True,
+    False,
+    Number(String, NumberKind),
+    String(String),
+    Null,
+    Identifier(String),
+    Comment(String),
+}
+
+impl OwnedToken {
+    pub fn as_token(&self) -> Token<'_> {
+        match self {
+            OwnedToken::LBrace => Token::LBrace,
+            OwnedToken::RBrace => Token::RBrace,
+            OwnedToken::LBracket => Token::LBracket,
+            OwnedToken::RBracket => Token::RBracket,
+            OwnedToken::Comma => Token::Comma,
+            OwnedToken::Colon => Token::Colon,
+            OwnedToken::True => Token::True,
+            OwnedToken::False => Token::False,
+            OwnedToken::Number(n, kind) => Token::Number(n, *kind),
+            OwnedToken::String(s) => Token::String(s),
+            OwnedToken::Null => Token::Null,
+            OwnedToken::Identifier(i) => Token::Identifier(i),
+            OwnedToken::Comment(c) => Token::Comment(c),
+        }
+    }
+}
+
+impl From<Token<'_>> for OwnedToken {
+    fn from(token: Token<'_>) -> Self {
+        match token {
+            Token::LBrace => OwnedToken::LBrace,
+            Token::RBrace => OwnedToken::RBrace,
+            Token::LBracket => OwnedToken::LBracket,
+            Token::RBracket => OwnedToken::RBracket,
+            Token::Comma => OwnedToken::Comma,
+            Token::Colon => OwnedToken::Colon,
+            Token::True => OwnedToken::True,
+            Token::False => OwnedToken::False,
+            Token::Number(n, kind) => OwnedToken::Number(n.to_string(), kind),
+            Token::String(s) => OwnedToken::String(s.to_string()),
+            Token::Null => OwnedToken::Null,
+            Token::Identifier(i) => OwnedToken::Identifier(i.to_string()),
+            Token::Comment(c) => OwnedToken::Comment(c.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LexerOptions {
+    /// Whether `// line` and `/* block */` comments, as found in JSONC files such as VS Code's
+    /// `settings.json` or `tsconfig.json`, are accepted.
+    pub comments: CommentMode,
+    pub dialect: Dialect,
+    pub strictness: Strictness,
+    /// How control chars such as tabs and line feeds are lexed inside strings, where JSON
+    /// requires them to be escaped but some legacy producers write them as is.
+    pub control_chars: ControlChars,
+    /// Whether the literals of Python's `repr` are accepted, as when ingesting the output of a
+    /// Python `print` mistaken for JSON: `True`, `False` and `None` are lexed as
+    /// [`Token::True`], [`Token::False`] and [`Token::Null`], and strings may be single-quoted,
+    /// with escape sequences such as `\'` or `\x41`.
+    pub python_literals: bool,
+    /// Whether the object literals of JavaScript are accepted, as when reading data pasted from
+    /// JavaScript source or a browser console: keys may be unquoted identifiers, lexed as
+    /// [`Token::Identifier`], and parsers accept trailing commas. The rest of JSON5 is not.
+    pub js_object_literals: bool,
+    /// Longest string, in bytes of its raw text, before lexing fails with
+    /// [`ErrorKind::LimitExceeded`]. The limits guard against untrusted input.
+    pub max_string_len: Option<usize>,
+    /// Longest number, in bytes.
+    pub max_number_len: Option<usize>,
+    /// Largest number of tokens lexed, comments included.
+    pub max_tokens: Option<usize>,
+}
+
+/// The syntax accepted by the lexer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dialect {
+    #[default]
+    Json,
+    /// [JSON5](https://spec.json5.org): single-quoted strings with any escape sequence and line
+    /// continuations, unquoted identifier keys, hexadecimal numbers, numbers with a `+` sign or
+    /// a leading or trailing dot, `Infinity` and `NaN`, trailing commas, and comments, which are
+    /// skipped unless [`LexerOptions::comments`] is [`CommentMode::Emit`].
+    Json5,
+}
+
+/// How closely the input must follow the grammar of its dialect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strictness {
+    /// Exactly the grammar of [RFC 8259](https://www.rfc-editor.org/rfc/rfc8259).
+    #[default]
+    Strict,
+    /// Also accepts the deviations common in the output of hand-written producers: control
+    /// chars in strings, kept as with [`ControlChars::Preserve`] unless another policy is
+    /// chosen, escape sequences such as `\'` or `\x41` that are only valid in JavaScript,
+    /// numbers with leading zeros, a `+` sign, or a leading or trailing dot, and Unicode
+    /// whitespace such as U+00A0 between tokens.
+    Lenient,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CommentMode {
+    /// Comments are syntax errors, as in standard JSON.
+    #[default]
+    Reject,
+    /// Comments are skipped like whitespace.
+    Skip,
+    /// Comments are produced as [`Token::Comment`].
+    Emit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ControlChars {
+    /// Control chars in strings are syntax errors, as in standard JSON.
+    #[default]
+    Reject,
+    /// Control chars are accepted and kept as is in the string tokens.
+    Preserve,
+    /// Control chars are accepted, and escaped by consumers such as parsers when they build
+    /// values, so that they are escaped on output.
+    Escape,
+}
+
+/// A source of the tokens of an input text, such as a [`JsonLexer`] or the [`Replayer`] of a
+/// recording.
+pub trait TokenSource<'a> {
+    /// The text the tokens come from.
+    fn input(&self) -> &'a str;
+
+    /// Returns the next token with its byte range in [`TokenSource::input`].
+    fn next_spanned(&mut self) -> Result<Option<(Token<'a>, Range<usize>)>, LexError>;
+
+    /// The options the tokens were lexed with.
+    fn options(&self) -> LexerOptions {
+        LexerOptions::default()
+    }
 }
 
 pub struct JsonLexer<'a> {
     pub input: &'a str,
     pub byte_offset: usize,
+    pub options: LexerOptions,
+    /// The token returned by `peek_token`, with its start and end offsets.
+    peeked: Option<(Token<'a>, usize, usize)>,
+    /// What was lexed so far, also counting tokens for [`LexerOptions::max_tokens`].
+    pub(crate) stats: LexerStats,
 }
 
-lazy_static! {
-    static ref NULL_REGEX: Regex = Regex::new(r"^null\b").unwrap();
-    static ref NUM_REGEX: Regex =
-        Regex::new(r"^(-?(?:0|[1-9]\d*)(?:\.\d+)?(?:(?:e|E)[+-]?\d+)?)\b").unwrap();
-    static ref TRUE_REGEX: Regex = Regex::new(r"^true\b").unwrap();
-    static ref FALSE_REGEX: Regex = Regex::new(r"^false\b").unwrap();
+/// The position of a [`JsonLexer`], saved by [`JsonLexer::checkpoint`] to rewind it with
+/// [`JsonLexer::restore`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint<'a> {
+    byte_offset: usize,
+    peeked: Option<(Token<'a>, usize, usize)>,
+    stats: LexerStats,
 }
 
 impl<'a> JsonLexer<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self::with_options(input, LexerOptions::default())
+    }
+
+    pub fn with_options(input: &'a str, options: LexerOptions) -> Self {
+        JsonLexer {
+            input,
+            byte_offset: 0,
+            options,
+            peeked: None,
+            stats: LexerStats::default(),
+        }
+    }
+
     pub fn next_token(&mut self) -> Result<Option<Token<'a>>, LexError> {
-        let mut chars = self.input[self.byte_offset..].chars();
-        let mut c;
-        loop {
-            if let Some(ch) = chars.next() {
-                c = ch;
-            } else {
-                return Ok(None);
-            }
+        self.skip_whitespace();
+        if let Some((token, _, end)) = self.take_peeked() {
+            self.byte_offset = end;
+            return Ok(Some(token));
+        }
+        self.lex()
+    }
 
-            if !c.is_whitespace() {
-                break;
+    /// Lexes the token at `byte_offset`, checking the limits of the options.
+    fn lex(&mut self) -> Result<Option<Token<'a>>, LexError> {
+        let start = self.byte_offset;
+        let token = self.lex_token()?;
+        let exceeded = match &token {
+            None => None,
+            _ if self
+                .options
+                .max_tokens
+                .is_some_and(|max| self.stats.tokens() >= max) =>
+            {
+                Some("too many tokens")
+            }
+            Some(Token::Number(n, _))
+                if self.options.max_number_len.is_some_and(|max| n.len() > max) =>
+            {
+                Some("number too long")
             }
-            self.byte_offset += 1;
+            Some(_) => None,
+        };
+        if let Some(message) = exceeded {
+            self.byte_offset = start;
+            return Err(self.error_kind(ErrorKind::LimitExceeded, message));
+        }
+        if let Some(token) = &token {
+            self.stats.record(token);
+        }
+        Ok(token)
+    }
+
+    fn lex_token(&mut self) -> Result<Option<Token<'a>>, LexError> {
+        let Some(c) = self.input[self.byte_offset..].chars().next() else {
+            return Ok(None);
+        };
+        if self.options.dialect == Dialect::Json5
+            && let Some(token) = self.lex_json5(c)
+        {
+            return token;
+        }
+        if self.options.js_object_literals && json5::is_identifier_start(c) {
+            return self.lex_identifier();
         }
 
         match c {
@@ -74,173 +442,583 @@ impl<'a> JsonLexer<'a> {
                 self.byte_offset += 1;
                 Ok(Some(Token::Colon))
             }
-            't' => self.lex_match(4, |s| {
-                if TRUE_REGEX.is_match(s) {
-                    Some(Token::True)
-                } else {
-                    None
-                }
-            }),
-            'f' => self.lex_match(5, |s| {
-                if FALSE_REGEX.is_match(s) {
-                    Some(Token::False)
-                } else {
-                    None
-                }
-            }),
-            'n' => self.lex_match(4, |s| {
-                if NULL_REGEX.is_match(s) {
-                    Some(Token::Null)
-                } else {
-                    None
-                }
-            }),
-            '"' => self.lex_string(chars),
-            n @ '-' | n if n.is_ascii_digit() => self.lex_number(chars, n),
-            c => Err(LexError(format!("unable to parse token from char {c}"))),
-        }
-    }
-
-    fn lex_number(&mut self, chars: Chars<'_>, first: char) -> Result<Option<Token<'a>>, LexError> {
-        let mut chars = chars.peekable();
-        let first_digit = if first == '-' {
-            match chars.peek() {
-                Some(n) if n.is_ascii_digit() => chars.next().unwrap(),
-                _ => {
-                    return Err(LexError(
-                        "invalid number literal, expected digit after `-`".into(),
-                    ));
-                }
+            't' => self.lex_keyword("true", Token::True),
+            'f' => self.lex_keyword("false", Token::False),
+            'n' => self.lex_keyword("null", Token::Null),
+            '"' => self.lex_string(),
+            '/' if self.comment_mode() != CommentMode::Reject => self.lex_comment(),
+            '\'' if self.options.python_literals => self.lex_json5_string('\''),
+            'T' if self.options.python_literals => self.lex_keyword("True", Token::True),
+            'F' if self.options.python_literals => self.lex_keyword("False", Token::False),
+            'N' if self.options.python_literals => self.lex_keyword("None", Token::Null),
+            '+' | '-' | '.' | '0'..='9' if self.options.strictness == Strictness::Lenient => {
+                self.lex_lenient_number()
             }
-        } else {
-            first
+            '-' | '0'..='9' => self.lex_number(),
+            c => Err(self.error(format!("unable to parse token from char {c}"))),
+        }
+    }
+
+    /// Returns the next token without consuming it: `byte_offset` is left at its start, and the
+    /// next call to [`JsonLexer::next_token`] returns it again. Errors are not cached, and are
+    /// returned by every call until `byte_offset` is moved, e.g. by [`JsonLexer::resync`].
+    pub fn peek_token(&mut self) -> Result<Option<Token<'a>>, LexError> {
+        self.skip_whitespace();
+        if let Some((token, start, end)) = self.take_peeked() {
+            self.peeked = Some((token.clone(), start, end));
+            return Ok(Some(token));
+        }
+        let start = self.byte_offset;
+        let token = self.lex()?;
+        let end = core::mem::replace(&mut self.byte_offset, start);
+        if let Some(token) = &token {
+            self.peeked = Some((token.clone(), start, end));
+        }
+        Ok(token)
+    }
+
+    /// Consumes the token returned by the last [`JsonLexer::peek_token`]. Does nothing if no
+    /// token was peeked since the last one was consumed.
+    pub fn advance(&mut self) {
+        if let Some((_, _, end)) = self.take_peeked() {
+            self.byte_offset = end;
+        }
+    }
+
+    /// Saves the position of the lexer, for speculative lexing that may need to backtrack.
+    /// Nothing is copied, so checkpoints are cheap to take.
+    pub fn checkpoint(&self) -> Checkpoint<'a> {
+        Checkpoint {
+            byte_offset: self.byte_offset,
+            peeked: self.peeked.clone(),
+            stats: self.stats,
+        }
+    }
+
+    /// Rewinds the lexer to a checkpoint taken from it, or moves it forward again to one taken
+    /// later. The tokens lexed since then count no more against [`LexerOptions::max_tokens`].
+    pub fn restore(&mut self, checkpoint: Checkpoint<'a>) {
+        self.byte_offset = checkpoint.byte_offset;
+        self.peeked = checkpoint.peeked;
+        self.stats = checkpoint.stats;
+    }
+
+    /// Counts of what was lexed so far. Tokens lexed by [`JsonLexer::peek_token`] are counted,
+    /// and the bytes of the input up to `byte_offset`.
+    pub fn stats(&self) -> LexerStats {
+        self.stats.consumed(self.byte_offset)
+    }
+
+    /// How comments are handled: JSON5 accepts them even when they are rejected in JSON.
+    fn comment_mode(&self) -> CommentMode {
+        match self.options.comments {
+            CommentMode::Reject if self.options.dialect == Dialect::Json5 => CommentMode::Skip,
+            mode => mode,
+        }
+    }
+
+    /// How control chars in strings are handled: lenient lexing accepts them even when they are
+    /// rejected in strict lexing.
+    fn control_chars(&self) -> ControlChars {
+        match self.options.control_chars {
+            ControlChars::Reject if self.options.strictness == Strictness::Lenient => {
+                ControlChars::Preserve
+            }
+            policy => policy,
+        }
+    }
+
+    /// Takes the peeked token, unless `byte_offset` was moved away from its start since.
+    fn take_peeked(&mut self) -> Option<(Token<'a>, usize, usize)> {
+        self.peeked
+            .take()
+            .filter(|(_, start, _)| *start == self.byte_offset)
+    }
+
+    /// Advances `byte_offset` past any whitespace, and comments in [`CommentMode::Skip`], so that
+    /// it points at the start of the next token. Strict JSON only has space, tab, LF and CR as
+    /// whitespace; JSON5 and [`Strictness::Lenient`] also skip Unicode whitespace.
+    pub fn skip_whitespace(&mut self) {
+        let json5 = self.options.dialect == Dialect::Json5;
+        let unicode = json5 || self.options.strictness == Strictness::Lenient;
+        loop {
+            let rest = &self.input[self.byte_offset..];
+            // whitespace is mostly ASCII, which is skipped bytewise before looking at chars
+            let ascii = rest
+                .bytes()
+                .position(|b| !matches!(b, b' ' | b'\t' | b'\n' | b'\r'))
+                .unwrap_or(rest.len());
+            let rest = &rest[ascii..];
+            let trimmed = rest.trim_start_matches(|c: char| {
+                (unicode && c.is_whitespace()) || (json5 && c == '\u{feff}')
+            });
+            self.byte_offset += ascii + rest.len() - trimmed.len();
+            // an unclosed comment is left for `next_token` to report
+            if self.comment_mode() != CommentMode::Skip
+                || !self.input[self.byte_offset..].starts_with('/')
+                || self.lex_comment().is_err()
+            {
+                return;
+            }
+        }
+    }
+
+    /// Recovers from a [`LexError`] by skipping the char at `byte_offset` and everything up to
+    /// the next structural char (`{}[],:`), so that tokenizing can continue, e.g. to report
+    /// more than one error. Returns the offset of that char, or `None` if the input ended first.
+    pub fn resync(&mut self) -> Option<usize> {
+        let rest = &self.input[self.byte_offset..];
+        let skipped = rest.chars().next().map_or(0, char::len_utf8);
+        match rest[skipped..].find(['{', '}', '[', ']', ',', ':']) {
+            Some(i) => {
+                self.byte_offset += skipped + i;
+                Some(self.byte_offset)
+            }
+            None => {
+                self.byte_offset = self.input.len();
+                None
+            }
+        }
+    }
+
+    /// Whether a string whose raw text is `len` bytes long so far exceeds
+    /// [`LexerOptions::max_string_len`], checked while it is lexed so that the end of an
+    /// overlong string isn't waited for.
+    pub(crate) fn string_too_long(&self, len: usize) -> bool {
+        self.options.max_string_len.is_some_and(|max| len > max)
+    }
+
+    fn error(&self, message: impl Into<String>) -> LexError {
+        self.error_kind(ErrorKind::Syntax, message)
+    }
+
+    fn error_kind(&self, kind: ErrorKind, message: impl Into<String>) -> LexError {
+        LexError {
+            kind,
+            message: message.into(),
+            offset: self.byte_offset,
+        }
+    }
+
+    /// Lexes a number as RFC 8259 defines it, bytewise since it is all ASCII.
+    fn lex_number(&mut self) -> Result<Option<Token<'a>>, LexError> {
+        let bytes = &self.input.as_bytes()[self.byte_offset..];
+        let digits = |from: usize| {
+            from + bytes[from..]
+                .iter()
+                .take_while(|b| b.is_ascii_digit())
+                .count()
         };
 
         // integer part
-        let mut len = 1;
-        match chars.peek() {
-            Some('0') if first_digit == '0' => {
-                return Err(LexError(
-                    "invalid number literal, no leading zeroes allowed".into(),
-                ));
+        let mut len = usize::from(bytes[0] == b'-');
+        match bytes.get(len) {
+            Some(b'0') if bytes.get(len + 1).is_some_and(u8::is_ascii_digit) => {
+                return Err(self.error("invalid number literal, no leading zeroes allowed"));
             }
-            Some(d) if d.is_ascii_digit() => {
-                while matches!(chars.peek(), Some(d) if d.is_ascii_digit()) {
-                    len += 1;
-                    chars.next();
-                }
+            Some(b'0') => len += 1,
+            Some(d) if d.is_ascii_digit() => len = digits(len),
+            Some(_) => return Err(self.error("invalid number literal, expected digit after `-`")),
+            None => {
+                return Err(self.error_kind(
+                    ErrorKind::UnexpectedEof,
+                    "invalid number literal, expected digit after `-`",
+                ));
             }
-            _ => {}
         }
 
         // fractional part
-        if matches!(chars.peek(), Some('.')) {
+        let mut kind = NumberKind::Integer;
+        if bytes.get(len) == Some(&b'.') {
+            kind = NumberKind::Float;
             len += 1;
-            chars.next(); // skip over dot
-            while matches!(chars.peek(), Some(d) if d.is_ascii_digit()) {
-                len += 1;
-                chars.next();
-            }
+            self.expect_digit(bytes.get(len), len, "expected digit after `.`")?;
+            len = digits(len);
         }
 
         // exponent
-        if matches!(chars.peek(), Some('e') | Some('E')) {
+        if let Some(b'e' | b'E') = bytes.get(len) {
+            kind = NumberKind::Exponent;
             len += 1;
-            chars.next(); // skip over e
-            if let Some('-') | Some('+') = chars.peek() {
-                len += 1;
-                chars.next(); // skip over (optional) sign in exponent
-            }
-
-            while matches!(chars.peek(), Some(d) if d.is_ascii_digit()) {
+            if let Some(b'+' | b'-') = bytes.get(len) {
                 len += 1;
-                chars.next();
             }
+            self.expect_digit(bytes.get(len), len, "expected digit in exponent")?;
+            len = digits(len);
         }
 
-        let number = Token::Number(&self.input[self.byte_offset..self.byte_offset + len]);
+        let number = Token::Number(&self.input[self.byte_offset..self.byte_offset + len], kind);
         self.byte_offset += len;
         Ok(Some(number))
     }
 
-    fn lex_match<T: FnOnce(&str) -> Option<Token>>(
+    /// Fails unless `next`, found `len` bytes into a number, is a digit.
+    fn expect_digit(&self, next: Option<&u8>, len: usize, message: &str) -> Result<(), LexError> {
+        let kind = match next {
+            Some(d) if d.is_ascii_digit() => return Ok(()),
+            Some(_) => ErrorKind::Syntax,
+            None => ErrorKind::UnexpectedEof,
+        };
+        Err(LexError {
+            kind,
+            message: format!("invalid number literal, {message}"),
+            offset: self.byte_offset + len,
+        })
+    }
+
+    /// Lexes a number as accepted by [`Strictness::Lenient`].
+    fn lex_lenient_number(&mut self) -> Result<Option<Token<'a>>, LexError> {
+        let rest = &self.input[self.byte_offset..];
+        // a sign, dot or exponent at the end of the input may be followed by digits
+        let eof = || self.error_kind(ErrorKind::UnexpectedEof, "invalid number literal");
+        let sign = usize::from(rest.starts_with(['+', '-']));
+        let Some((len, kind)) = decimal_len(&rest.as_bytes()[sign..], true) else {
+            return Err(match rest {
+                "+" | "-" | "." | "+." | "-." => eof(),
+                _ => self.error("invalid number literal"),
+            });
+        };
+        let len = sign + len;
+        let after = &rest[len..];
+        if matches!(after, "e" | "E" | "e+" | "e-" | "E+" | "E-") {
+            return Err(eof());
+        }
+        if after.starts_with(|c: char| c.is_alphanumeric() || c == '.') {
+            return Err(self.error("invalid number literal"));
+        }
+        self.byte_offset += len;
+        Ok(Some(Token::Number(&rest[..len], kind)))
+    }
+
+    fn lex_comment(&mut self) -> Result<Option<Token<'a>>, LexError> {
+        let rest = &self.input[self.byte_offset..];
+        let len = if rest.starts_with("//") {
+            let line = &rest[..rest.find('\n').unwrap_or(rest.len())];
+            line.trim_end_matches('\r').len()
+        } else if let Some(body) = rest.strip_prefix("/*") {
+            match body.find("*/") {
+                Some(end) => end + 4,
+                None => {
+                    return Err(self.error_kind(ErrorKind::UnexpectedEof, "unclosed block comment"));
+                }
+            }
+        } else if rest == "/" {
+            return Err(self.error_kind(ErrorKind::UnexpectedEof, "unfinished comment"));
+        } else {
+            return Err(self.error("unable to parse token from char /"));
+        };
+
+        let comment = Token::Comment(&rest[..len]);
+        self.byte_offset += len;
+        Ok(Some(comment))
+    }
+
+    /// Lexes `keyword`, which must not be followed by a word char, as in `nullable`.
+    fn lex_keyword(
         &mut self,
-        len: usize,
-        factory: T,
+        keyword: &str,
+        token: Token<'a>,
     ) -> Result<Option<Token<'a>>, LexError> {
-        let end = (self.byte_offset + len).min(self.input.len());
-        let slice = &self.input[self.byte_offset..end];
-        match factory(slice) {
-            Some(token) => {
-                self.byte_offset += len;
+        let rest = &self.input[self.byte_offset..];
+        match rest.strip_prefix(keyword) {
+            Some(after) if !after.starts_with(|c: char| c.is_alphanumeric() || c == '_') => {
+                self.byte_offset += keyword.len();
                 Ok(Some(token))
             }
-            None => Err(LexError("unexpected token".to_string())),
+            None if keyword.starts_with(rest) => {
+                Err(self.error_kind(ErrorKind::UnexpectedEof, "unexpected token"))
+            }
+            _ => Err(self.error("unexpected token")),
         }
     }
 
-    fn lex_string(&mut self, mut chars: Chars<'_>) -> Result<Option<Token<'a>>, LexError> {
+    fn lex_string(&mut self) -> Result<Option<Token<'a>>, LexError> {
         self.byte_offset += 1; // skip opening quote
         let start = self.byte_offset;
-        let mut byte_len = 0;
-
-        while let Some(c) = chars.next() {
-            if c == '"' {
-                let end = start + byte_len;
-                let result = Ok(Some(Token::String(&self.input[start..end])));
-                self.byte_offset = end + 1; // skip closing quote
-                return result;
+        let bytes = self.input.as_bytes();
+        let reject_controls = self.control_chars() == ControlChars::Reject;
+        let mut end = start;
+
+        loop {
+            // the text up to the next quote or escape sequence needs no char-by-char look
+            let run = &bytes[end..];
+            let len = find_quote_or_escape(run).unwrap_or(run.len());
+            if reject_controls && run[..len].iter().any(|b| *b < 0x20) {
+                return Err(self.error("invalid control char in string"));
+            }
+            end += len;
+            if self.string_too_long(end - start) {
+                return Err(self.error_kind(ErrorKind::LimitExceeded, "string too long"));
+            }
+            match bytes.get(end) {
+                Some(b'"') => {
+                    self.byte_offset = end + 1; // skip closing quote
+                    return Ok(Some(Token::String(&self.input[start..end])));
+                }
+                Some(_) => match self.lex_escape(&self.input[end + 1..])? {
+                    Some(len) => end += 1 + len,
+                    None => break,
+                },
+                None => break,
             }
+        }
+
+        Err(self.error_kind(ErrorKind::UnexpectedEof, "unclosed string literal"))
+    }
 
-            if c.is_control() {
-                return Err(LexError("invalid control char in string".to_string()));
+    /// Returns the length of the escape sequence at the start of `rest`, which follows a
+    /// backslash, or `None` if the input ends in the middle of it.
+    fn lex_escape(&self, rest: &str) -> Result<Option<usize>, LexError> {
+        let lenient = self.options.strictness == Strictness::Lenient;
+        let Some(e) = rest.chars().next() else {
+            return Ok(None);
+        };
+        let (digits, message) = match e {
+            '"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't' => return Ok(Some(1)),
+            'u' => (4, "invalid unicode escape sequence"),
+            'x' if lenient => (2, "invalid hex escape sequence"),
+            // any other char escapes itself
+            e if lenient => return Ok(Some(e.len_utf8())),
+            _ => return Err(self.error("invalid escape sequence")),
+        };
+        for h in (1..=digits).map(|i| rest.as_bytes().get(i)) {
+            match h {
+                Some(h) if h.is_ascii_hexdigit() => {}
+                Some(_) => return Err(self.error(message)),
+                None => return Ok(None),
             }
+        }
+        Ok(Some(1 + digits))
+    }
+}
 
-            if c == '\\' {
-                byte_len += c.len_utf8();
+/// Position of the first `"` or `\\` in `bytes`.
+#[cfg(feature = "memchr")]
+fn find_quote_or_escape(bytes: &[u8]) -> Option<usize> {
+    memchr::memchr2(b'"', b'\\', bytes)
+}
 
-                match chars.next() {
-                    None => break,
-                    Some(e) => {
-                        byte_len += e.len_utf8();
-
-                        match e {
-                            '"' | '\\' | '/' | 'f' | 'n' | 'r' | 't' => continue,
-                            'u' => {
-                                for _ in 0..4 {
-                                    if let Some(h) = chars.next() {
-                                        if !h.is_ascii_hexdigit() {
-                                            return Err(LexError(
-                                                "invalid unicode escape sequence".to_string(),
-                                            ));
-                                        }
-                                        byte_len += h.len_utf8();
-                                    } else {
-                                        break;
-                                    }
-                                }
-                            }
-                            _ => return Err(LexError("invalid escape sequence".to_string())),
-                        }
-                    }
-                }
+/// Length of the unsigned decimal number at the start of `bytes`, an integer part and a fraction
+/// either of which may be empty, but not both, and an exponent. The integer part may only start
+/// with zeros if `leading_zeros` is set. Returns the kind of the number along with its length.
+pub(crate) fn decimal_len(bytes: &[u8], leading_zeros: bool) -> Option<(usize, NumberKind)> {
+    let digits = |bytes: &[u8]| bytes.iter().take_while(|b| b.is_ascii_digit()).count();
+    let int = match bytes.first() {
+        Some(b'0') if !leading_zeros => 1,
+        _ => digits(bytes),
+    };
+    let (mut len, mut kind) = (int, NumberKind::Integer);
+    if bytes.get(len) == Some(&b'.') {
+        let fraction = digits(&bytes[len + 1..]);
+        if int == 0 && fraction == 0 {
+            return None;
+        }
+        len += 1 + fraction;
+        kind = NumberKind::Float;
+    } else if int == 0 {
+        return None;
+    }
+    if let Some(b'e' | b'E') = bytes.get(len) {
+        let sign = usize::from(matches!(bytes.get(len + 1), Some(b'+' | b'-')));
+        match digits(&bytes[len + 1 + sign..]) {
+            0 => {}
+            exponent => {
+                len += 1 + sign + exponent;
+                kind = NumberKind::Exponent;
+            }
+        }
+    }
+    Some((len, kind))
+}
+
+/// Position of the first `"` or `\\` in `bytes`.
+#[cfg(not(feature = "memchr"))]
+fn find_quote_or_escape(bytes: &[u8]) -> Option<usize> {
+    bytes.iter().position(|b| matches!(b, b'"' | b'\\'))
+}
+
+impl<'a> TokenSource<'a> for JsonLexer<'a> {
+    fn input(&self) -> &'a str {
+        self.input
+    }
+
+    fn next_spanned(&mut self) -> Result<Option<(Token<'a>, Range<usize>)>, LexError> {
+        self.skip_whitespace();
+        let start = self.byte_offset;
+        let token = self.next_token()?;
+        Ok(token.map(|t| (t, start..self.byte_offset)))
+    }
+
+    fn options(&self) -> LexerOptions {
+        self.options.clone()
+    }
+}
 
-                continue;
+/// Returns the 1-based line and column (counted in chars) of a byte offset into `input`.
+pub fn line_column(input: &str, offset: usize) -> (usize, usize) {
+    let before = &input[..offset.min(input.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let line = before.matches('\n').count() + 1;
+    let column = before[line_start..].chars().count() + 1;
+    (line, column)
+}
+
+/// Lists the tokens of `input` one per line, with their byte range and kind, for debugging
+/// malformed documents. Lexing stops at the first error, which ends the list.
+pub fn dump_tokens(input: &str) -> String {
+    use core::fmt::Write;
+
+    let mut lexer = JsonLexer::new(input);
+    let mut dump = String::new();
+    loop {
+        match lexer.next_spanned() {
+            Ok(Some((token, span))) => {
+                let span = format!("{}..{}", span.start, span.end);
+                let kind = format!("{:?}", token.kind());
+                writeln!(dump, "{span:<10}{kind:<10}{token}").unwrap();
             }
+            Ok(None) => return dump,
+            Err(e) => {
+                writeln!(dump, "{:<10}error: {e}", e.offset).unwrap();
+                return dump;
+            }
+        }
+    }
+}
+
+/// Decodes the escape sequences in the raw text of a string literal, as held by
+/// [`Token::String`], including surrogate pairs of `\uXXXX` escapes. Unpaired surrogates, which
+/// no char can hold, are replaced with U+FFFD. Text without escapes is returned as is.
+///
+/// The escapes of JSON5 and lenient strings are decoded too: `\xXX`, `\v`, `\0`, line
+/// continuations, which decode to nothing, and any other char escaping itself, as in `\'`.
+pub fn unescape(raw: &str) -> Cow<'_, str> {
+    if !raw.contains('\\') {
+        return Cow::Borrowed(raw);
+    }
+    let unit = |s: &str| {
+        let hex = s
+            .get(..4)
+            .filter(|h| h.bytes().all(|b| b.is_ascii_hexdigit()))?;
+        u16::from_str_radix(hex, 16).ok()
+    };
 
-            byte_len += c.len_utf8();
+    let mut decoded = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(i) = rest.find('\\') {
+        decoded.push_str(&rest[..i]);
+        let escape = &rest[i + 1..];
+        let Some(e) = escape.chars().next() else {
+            break;
+        };
+        rest = &escape[e.len_utf8()..];
+        match e {
+            'b' => decoded.push('\u{8}'),
+            'f' => decoded.push('\u{c}'),
+            'n' => decoded.push('\n'),
+            'r' => decoded.push('\r'),
+            't' => decoded.push('\t'),
+            'u' => {
+                let Some(high) = unit(rest) else {
+                    decoded.push(char::REPLACEMENT_CHARACTER);
+                    continue;
+                };
+                rest = &rest[4..];
+                let low = rest.strip_prefix("\\u").and_then(unit);
+                match (high, low) {
+                    (0xD800..=0xDBFF, Some(low @ 0xDC00..=0xDFFF)) => {
+                        rest = &rest[6..];
+                        let c = 0x10000 + ((high as u32 - 0xD800) << 10) + (low as u32 - 0xDC00);
+                        decoded.push(char::from_u32(c).unwrap());
+                    }
+                    _ => decoded
+                        .push(char::from_u32(high as u32).unwrap_or(char::REPLACEMENT_CHARACTER)),
+                }
+            }
+            'v' => decoded.push('\u{b}'),
+            '0' => decoded.push('\0'),
+            'x' => match rest
+                .get(..2)
+                .filter(|h| h.bytes().all(|b| b.is_ascii_hexdigit()))
+            {
+                Some(hex) => {
+                    rest = &rest[2..];
+                    decoded.push(char::from(u8::from_str_radix(hex, 16).unwrap()));
+                }
+                None => decoded.push(char::REPLACEMENT_CHARACTER),
+            },
+            // line continuations
+            '\r' => rest = rest.strip_prefix('\n').unwrap_or(rest),
+            '\n' | '\u{2028}' | '\u{2029}' => {}
+            // `"`, `\` and `/` stand for themselves, as does any other char in JSON5
+            e => decoded.push(e),
         }
+    }
+    decoded.push_str(rest);
+    Cow::Owned(decoded)
+}
+
+/// Broad category of an error, so callers can decide how to react to it without matching on
+/// messages.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The input is not valid JSON.
+    Syntax,
+    /// The input ended in the middle of a value; more input could make it valid.
+    UnexpectedEof,
+    /// A configured limit was exceeded.
+    LimitExceeded,
+    /// An object contains the same key more than once.
+    DuplicateKey,
+    /// The input is not valid UTF-8.
+    InvalidUtf8,
+    /// Reading the input failed.
+    Io,
+    /// The operation was cancelled by the caller.
+    Cancelled,
+}
 
-        Err(LexError("unclosed string literal".to_string()))
+impl ErrorKind {
+    /// Stable `snake_case` identifier of this kind, e.g. `"unexpected_eof"`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrorKind::Syntax => "syntax",
+            ErrorKind::UnexpectedEof => "unexpected_eof",
+            ErrorKind::LimitExceeded => "limit_exceeded",
+            ErrorKind::DuplicateKey => "duplicate_key",
+            ErrorKind::InvalidUtf8 => "invalid_utf8",
+            ErrorKind::Io => "io",
+            ErrorKind::Cancelled => "cancelled",
+        }
     }
 }
 
-#[derive(Debug, PartialEq)]
-pub struct LexError(String);
+#[derive(Debug, PartialEq, Clone)]
+pub struct LexError {
+    kind: ErrorKind,
+    message: String,
+    offset: usize,
+}
+
+impl LexError {
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Byte offset into the input at which lexing failed.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
 
 impl Display for LexError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.message)
     }
 }
 
@@ -248,19 +1026,128 @@ impl Error for LexError {}
 
 #[cfg(test)]
 mod test {
-    use crate::{JsonLexer, Token};
+    use crate::{
+        CommentMode, ControlChars, Dialect, ErrorKind, JsonLexer, LexerOptions, NumberKind,
+        Strictness, Token, dump_tokens, unescape,
+    };
 
     #[test]
-    fn lex_token_sequence() {
-        let mut lexer = JsonLexer {
-            input: "{ \"asdf\": 1, \"🗻∈🌏\": true, \"🗻\": 42 }",
-            byte_offset: 0,
+    fn display_tokens() {
+        let input = r#"{"a\"b": [-1.5e3, true, false, null]}"#;
+        let mut lexer = JsonLexer::new(input);
+        let mut text = String::new();
+        while let Some(token) = lexer.next_token().unwrap() {
+            text.push_str(&token.to_string());
+        }
+        assert_eq!(input.replace(' ', ""), text);
+
+        let options = LexerOptions {
+            dialect: Dialect::Json5,
+            ..Default::default()
         };
+        let mut lexer = JsonLexer::with_options("{a: 1} // c", options.clone());
+        let tokens: Vec<String> = core::iter::from_fn(|| lexer.next_token().unwrap())
+            .map(|token| token.to_string())
+            .collect();
+        assert_eq!(vec!["{", "a", ":", "1", "}"], tokens);
+
+        // single-quoted strings and JSON5 escapes become JSON
+        let input = "['say \"hi\"\\'s', '\\x41\\v\\0\\\nb']";
+        let mut lexer = JsonLexer::with_options(input, options);
+        let tokens: Vec<String> = core::iter::from_fn(|| lexer.next_token().unwrap())
+            .map(|token| token.to_string())
+            .collect();
+        assert_eq!(
+            vec![
+                "[",
+                r#""say \"hi\"'s""#,
+                ",",
+                r#""\u0041\u000b\u0000b""#,
+                "]"
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    fn dump_token_stream() {
+        assert_eq!(
+            "0..1      LBracket  [\n\
+             1..6      String    \"a b\"\n\
+             6..7      Comma     ,\n\
+             8..12     Number    -1.5\n\
+             12..13    RBracket  ]\n",
+            dump_tokens(r#"["a b", -1.5]"#)
+        );
+        assert_eq!(
+            "0..4      True      true\n5         error: unable to parse token from char x\n",
+            dump_tokens("true x")
+        );
+        assert_eq!("", dump_tokens(" "));
+    }
+
+    #[test]
+    fn tokens_borrow_from_input() {
+        let input = r#"["a\"b", -1.5e3]"#;
+        let mut lexer = JsonLexer::new(input);
+        let range = input.as_bytes().as_ptr_range();
+        let mut borrowed = 0;
+        while let Some(token) = lexer.next_token().unwrap() {
+            if let Token::String(s) | Token::Number(s, _) = token {
+                assert!(range.contains(&s.as_ptr()), "{s}");
+                borrowed += 1;
+            }
+        }
+        assert_eq!(2, borrowed);
+    }
+
+    #[test]
+    fn unescape_strings() {
+        assert!(matches!(
+            unescape("plain"),
+            std::borrow::Cow::Borrowed("plain")
+        ));
+        assert_eq!("as\"df\n\t/\\", unescape(r#"as\"df\n\t\/\\"#));
+        assert_eq!("é😀", unescape(r"\u00e9\ud83d\ude00"));
+        assert_eq!("\u{FFFD}x\u{FFFD}", unescape(r"\ud83dx\ude00"));
+        // JSON5
+        assert_eq!(
+            "A\u{b}\0'b\u{2028}c",
+            unescape("\\x41\\v\\0\\'\\\r\nb\u{2028}\\\u{2028}c")
+        );
+        assert_eq!("ab", unescape("a\\\nb"));
+    }
+
+    #[test]
+    fn lex_long_strings() {
+        let text = format!("{}\\\"é\\u00e9{}\\n", "a".repeat(100), "b ".repeat(100));
+        let input = format!("  \n\t \"{text}\"  ");
+        let mut lexer = JsonLexer::new(&input);
+        assert_eq!(Ok(Some(Token::String(&text))), lexer.next_token());
+        assert_eq!(Ok(None), lexer.next_token());
+        assert_eq!(input.len(), lexer.byte_offset);
+
+        for invalid in ["\"abc\ndef\"", "\"abc\\qdef\"", "\"abc\\u12x4\""] {
+            let err = JsonLexer::new(invalid).next_token().unwrap_err();
+            assert_eq!(ErrorKind::Syntax, err.kind(), "{invalid}");
+        }
+        for unclosed in ["\"abc", "\"abc\\", "\"abc\\u12"] {
+            let err = JsonLexer::new(unclosed).next_token().unwrap_err();
+            assert_eq!(ErrorKind::UnexpectedEof, err.kind(), "{unclosed}");
+        }
+    }
+
+    #[test]
+    fn lex_token_sequence() {
+        let mut lexer = JsonLexer::new("{ \"asdf\": 1, \"🗻∈🌏\": true, \"🗻\": 42 }");
 
         assert_eq!(Ok(Some(Token::LBrace)), lexer.next_token());
         assert_eq!(Ok(Some(Token::String("asdf"))), lexer.next_token());
         assert_eq!(Ok(Some(Token::Colon)), lexer.next_token());
-        assert_eq!(Ok(Some(Token::Number("1"))), lexer.next_token());
+        assert_eq!(
+            Ok(Some(Token::Number("1", NumberKind::Integer))),
+            lexer.next_token()
+        );
         assert_eq!(Ok(Some(Token::Comma)), lexer.next_token());
         assert_eq!(Ok(Some(Token::String("🗻∈🌏"))), lexer.next_token());
         assert_eq!(Ok(Some(Token::Colon)), lexer.next_token());
@@ -268,187 +1155,474 @@ mod test {
         assert_eq!(Ok(Some(Token::Comma)), lexer.next_token());
         assert_eq!(Ok(Some(Token::String("🗻"))), lexer.next_token());
         assert_eq!(Ok(Some(Token::Colon)), lexer.next_token());
-        assert_eq!(Ok(Some(Token::Number("42"))), lexer.next_token());
+        assert_eq!(
+            Ok(Some(Token::Number("42", NumberKind::Integer))),
+            lexer.next_token()
+        );
         assert_eq!(Ok(Some(Token::RBrace)), lexer.next_token());
     }
 
     #[test]
     fn lex_single_tokens() {
+        assert_eq!(Ok(Some(Token::True)), JsonLexer::new("true").next_token());
+        assert_eq!(Ok(Some(Token::False)), JsonLexer::new("false").next_token());
+        assert_eq!(Ok(Some(Token::Null)), JsonLexer::new("null").next_token());
+        assert_eq!(Ok(Some(Token::Null)), JsonLexer::new("null,").next_token());
+        for (input, kind) in [
+            ("nullable", ErrorKind::Syntax),
+            ("true_", ErrorKind::Syntax),
+            ("falsé", ErrorKind::Syntax),
+            ("nul", ErrorKind::UnexpectedEof),
+            ("nil", ErrorKind::Syntax),
+        ] {
+            let err = JsonLexer::new(input).next_token().unwrap_err();
+            assert_eq!(kind, err.kind(), "{input}");
+        }
+        assert_eq!(Ok(Some(Token::Comma)), JsonLexer::new(",").next_token());
+        assert_eq!(Ok(Some(Token::Colon)), JsonLexer::new(":").next_token());
+        assert_eq!(Ok(Some(Token::LBrace)), JsonLexer::new("{").next_token());
+        assert_eq!(Ok(Some(Token::RBrace)), JsonLexer::new("}").next_token());
+        assert_eq!(Ok(Some(Token::LBracket)), JsonLexer::new("[").next_token());
+        assert_eq!(Ok(Some(Token::RBracket)), JsonLexer::new("]").next_token());
         assert_eq!(
-            Ok(Some(Token::True)),
-            JsonLexer {
-                input: "true",
-                byte_offset: 0
-            }
-            .next_token()
+            Ok(Some(Token::String("asdf"))),
+            JsonLexer::new("\"asdf\"").next_token()
         );
         assert_eq!(
-            Ok(Some(Token::False)),
-            JsonLexer {
-                input: "false",
-                byte_offset: 0
-            }
-            .next_token()
+            Ok(Some(Token::String(r#"as\"df"#))),
+            JsonLexer::new(r#""as\"df""#).next_token()
         );
         assert_eq!(
-            Ok(Some(Token::Null)),
-            JsonLexer {
-                input: "null",
-                byte_offset: 0
-            }
-            .next_token()
+            Ok(Some(Token::String(r#"as\uFFFFdf"#))),
+            JsonLexer::new(r#""as\uFFFFdf""#).next_token()
         );
         assert_eq!(
-            Ok(Some(Token::Comma)),
-            JsonLexer {
-                input: ",",
-                byte_offset: 0
-            }
-            .next_token()
+            Ok(Some(Token::Number("1", NumberKind::Integer))),
+            JsonLexer::new("1").next_token()
         );
         assert_eq!(
-            Ok(Some(Token::Colon)),
-            JsonLexer {
-                input: ":",
-                byte_offset: 0
-            }
-            .next_token()
+            Ok(Some(Token::Number("0", NumberKind::Integer))),
+            JsonLexer::new("0").next_token()
         );
         assert_eq!(
-            Ok(Some(Token::LBrace)),
-            JsonLexer {
-                input: "{",
-                byte_offset: 0
-            }
-            .next_token()
+            Ok(Some(Token::Number("10", NumberKind::Integer))),
+            JsonLexer::new("10").next_token()
         );
         assert_eq!(
-            Ok(Some(Token::RBrace)),
-            JsonLexer {
-                input: "}",
-                byte_offset: 0
-            }
-            .next_token()
+            Ok(Some(Token::Number("1.2", NumberKind::Float))),
+            JsonLexer::new("1.2").next_token()
         );
         assert_eq!(
-            Ok(Some(Token::LBracket)),
-            JsonLexer {
-                input: "[",
-                byte_offset: 0
-            }
-            .next_token()
+            Ok(Some(Token::Number("1.2E2", NumberKind::Exponent))),
+            JsonLexer::new("1.2E2").next_token()
         );
         assert_eq!(
-            Ok(Some(Token::RBracket)),
-            JsonLexer {
-                input: "]",
-                byte_offset: 0
-            }
-            .next_token()
+            Ok(Some(Token::Number("1.2E-2", NumberKind::Exponent))),
+            JsonLexer::new("1.2E-2").next_token()
         );
         assert_eq!(
-            Ok(Some(Token::String("asdf"))),
-            JsonLexer {
-                input: "\"asdf\"",
-                byte_offset: 0
-            }
-            .next_token()
+            Ok(Some(Token::Number("1.2E+2", NumberKind::Exponent))),
+            JsonLexer::new("1.2E+2").next_token()
         );
         assert_eq!(
-            Ok(Some(Token::String(r#"as\"df"#))),
-            JsonLexer {
-                input: r#""as\"df""#,
-                byte_offset: 0
-            }
-            .next_token()
+            Ok(Some(Token::Number("1.2e2", NumberKind::Exponent))),
+            JsonLexer::new("1.2e2").next_token()
         );
         assert_eq!(
-            Ok(Some(Token::String(r#"as\uFFFFdf"#))),
-            JsonLexer {
-                input: r#""as\uFFFFdf""#,
-                byte_offset: 0
-            }
-            .next_token()
+            Ok(Some(Token::Number("1.2e-2", NumberKind::Exponent))),
+            JsonLexer::new("1.2e-2").next_token()
         );
         assert_eq!(
-            Ok(Some(Token::Number("1"))),
-            JsonLexer {
-                input: "1",
-                byte_offset: 0
-            }
-            .next_token()
+            Ok(Some(Token::Number("1.2e+2", NumberKind::Exponent))),
+            JsonLexer::new("1.2e+2").next_token()
         );
         assert_eq!(
-            Ok(Some(Token::Number("0"))),
-            JsonLexer {
-                input: "0",
-                byte_offset: 0
-            }
-            .next_token()
+            Ok(Some(Token::Number("-1.2", NumberKind::Float))),
+            JsonLexer::new("-1.2").next_token()
+        );
+    }
+
+    #[test]
+    fn lex_comments() {
+        let mut lexer = JsonLexer::with_options(
+            "// line\n[1, /* block\n */ 2] // end",
+            LexerOptions {
+                comments: CommentMode::Emit,
+                ..Default::default()
+            },
         );
+        assert_eq!(Ok(Some(Token::Comment("// line"))), lexer.next_token());
+        assert_eq!(Ok(Some(Token::LBracket)), lexer.next_token());
         assert_eq!(
-            Ok(Some(Token::Number("10"))),
-            JsonLexer {
-                input: "10",
-                byte_offset: 0
-            }
-            .next_token()
+            Ok(Some(Token::Number("1", NumberKind::Integer))),
+            lexer.next_token()
         );
+        assert_eq!(Ok(Some(Token::Comma)), lexer.next_token());
         assert_eq!(
-            Ok(Some(Token::Number("1.2"))),
-            JsonLexer {
-                input: "1.2",
-                byte_offset: 0
-            }
-            .next_token()
+            Ok(Some(Token::Comment("/* block\n */"))),
+            lexer.next_token()
         );
         assert_eq!(
-            Ok(Some(Token::Number("1.2E2"))),
-            JsonLexer {
-                input: "1.2E2",
-                byte_offset: 0
-            }
-            .next_token()
+            Ok(Some(Token::Number("2", NumberKind::Integer))),
+            lexer.next_token()
         );
+        assert_eq!(Ok(Some(Token::RBracket)), lexer.next_token());
+        assert_eq!(Ok(Some(Token::Comment("// end"))), lexer.next_token());
+        assert_eq!(Ok(None), lexer.next_token());
+
+        assert!(JsonLexer::new("// line").next_token().is_err());
+        let mut lexer = JsonLexer::with_options(
+            "// line\n[1, /* block */ /* another */ 2] // end",
+            LexerOptions {
+                comments: CommentMode::Skip,
+                ..Default::default()
+            },
+        );
+        let tokens: Vec<_> = std::iter::from_fn(|| lexer.next_token().unwrap()).collect();
         assert_eq!(
-            Ok(Some(Token::Number("1.2E-2"))),
-            JsonLexer {
-                input: "1.2E-2",
-                byte_offset: 0
-            }
-            .next_token()
+            vec![
+                Token::LBracket,
+                Token::Number("1", NumberKind::Integer),
+                Token::Comma,
+                Token::Number("2", NumberKind::Integer),
+                Token::RBracket
+            ],
+            tokens
         );
+        let mut lexer = JsonLexer::with_options(
+            "[1 /* open",
+            LexerOptions {
+                comments: CommentMode::Skip,
+                ..Default::default()
+            },
+        );
+        lexer.next_token().unwrap();
+        lexer.next_token().unwrap();
         assert_eq!(
-            Ok(Some(Token::Number("1.2E+2"))),
-            JsonLexer {
-                input: "1.2E+2",
-                byte_offset: 0
-            }
-            .next_token()
+            Err(crate::ErrorKind::UnexpectedEof),
+            lexer.next_token().map_err(|e| e.kind())
+        );
+        let mut lexer = JsonLexer::with_options(
+            "[/",
+            LexerOptions {
+                comments: CommentMode::Emit,
+                ..Default::default()
+            },
         );
+        assert_eq!(Ok(Some(Token::LBracket)), lexer.next_token());
         assert_eq!(
-            Ok(Some(Token::Number("1.2e2"))),
-            JsonLexer {
-                input: "1.2e2",
-                byte_offset: 0
-            }
-            .next_token()
+            Err(crate::ErrorKind::UnexpectedEof),
+            lexer.next_token().map_err(|e| e.kind())
         );
+    }
+
+    #[test]
+    fn lex_control_chars() {
+        let input = "\"a\tb\nc\u{1}\"";
+        assert!(JsonLexer::new(input).next_token().is_err());
+        for control_chars in [ControlChars::Preserve, ControlChars::Escape] {
+            let options = LexerOptions {
+                control_chars,
+                ..Default::default()
+            };
+            let mut lexer = JsonLexer::with_options(input, options);
+            assert_eq!(Ok(Some(Token::String("a\tb\nc\u{1}"))), lexer.next_token());
+        }
+    }
+
+    #[test]
+    fn strictness() {
+        let lex = |input, strictness| {
+            let options = LexerOptions {
+                strictness,
+                ..Default::default()
+            };
+            JsonLexer::with_options(input, options)
+                .next_token()
+                .map_err(|e| e.kind())
+        };
+        let (strict, lenient) = (Strictness::Strict, Strictness::Lenient);
+        // RFC 8259 allows the `\b` escape and unescaped control chars past U+001F
         assert_eq!(
-            Ok(Some(Token::Number("1.2e-2"))),
-            JsonLexer {
-                input: "1.2e-2",
-                byte_offset: 0
+            Ok(Some(Token::String("\\b\u{80}"))),
+            lex("\"\\b\u{80}\"", strict)
+        );
+        for deviation in [
+            "\"\t\"",
+            "\"\\'\"",
+            "\"\\x41\"",
+            "01",
+            "+1",
+            ".5",
+            "5.,",
+            "-01.e2",
+        ] {
+            assert_eq!(
+                Err(ErrorKind::Syntax),
+                lex(deviation, strict),
+                "{deviation}"
+            );
+            assert!(
+                lex(deviation, lenient).is_ok_and(|t| t.is_some()),
+                "{deviation}"
+            );
+        }
+        // only space, tab, LF and CR are whitespace in strict JSON
+        assert_eq!(Ok(Some(Token::Null)), lex(" \t\r\nnull", strict));
+        for whitespace in ["\u{a0}null", "\u{2028}null", "\u{3000}null"] {
+            assert_eq!(
+                Err(ErrorKind::Syntax),
+                lex(whitespace, strict),
+                "{whitespace:?}"
+            );
+            assert_eq!(
+                Ok(Some(Token::Null)),
+                lex(whitespace, lenient),
+                "{whitespace:?}"
+            );
+        }
+        for incomplete in ["1.", "1e", "-1E+"] {
+            assert_eq!(
+                Err(ErrorKind::UnexpectedEof),
+                lex(incomplete, strict),
+                "{incomplete}"
+            );
+        }
+        for incomplete in ["+", ".", "1e", "-1E+"] {
+            assert_eq!(
+                Err(ErrorKind::UnexpectedEof),
+                lex(incomplete, lenient),
+                "{incomplete}"
+            );
+        }
+        for invalid in ["1.x", "1ex", "\"\\xZ1\"", "1.2.3", "--1"] {
+            assert_eq!(Err(ErrorKind::Syntax), lex(invalid, lenient), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn enforce_limits() {
+        let options = LexerOptions {
+            max_string_len: Some(3),
+            max_number_len: Some(4),
+            max_tokens: Some(4),
+            ..Default::default()
+        };
+        let kinds = |input| {
+            let mut lexer = JsonLexer::with_options(input, options.clone());
+            let mut tokens = Vec::new();
+            loop {
+                match lexer.next_token() {
+                    Ok(Some(token)) => tokens.push(Ok(token)),
+                    Ok(None) => return tokens,
+                    Err(e) => {
+                        tokens.push(Err((e.kind(), e.offset())));
+                        return tokens;
+                    }
+                }
             }
-            .next_token()
+        };
+        let limit = crate::ErrorKind::LimitExceeded;
+        assert_eq!(
+            vec![
+                Ok(Token::LBracket),
+                Ok(Token::String("a\\n")),
+                Ok(Token::Comma),
+                Err((limit, 9))
+            ],
+            kinds(r#"["a\n", "abcd"]"#)
         );
         assert_eq!(
-            Ok(Some(Token::Number("1.2e+2"))),
-            JsonLexer {
-                input: "1.2e+2",
-                byte_offset: 0
-            }
-            .next_token()
+            vec![
+                Ok(Token::LBracket),
+                Ok(Token::Number("-1.5", NumberKind::Float)),
+                Ok(Token::Comma),
+                Err((limit, 7))
+            ],
+            kinds("[-1.5, 1e100]")
+        );
+        assert_eq!(
+            vec![
+                Ok(Token::LBracket),
+                Ok(Token::Number("1", NumberKind::Integer)),
+                Ok(Token::Comma),
+                Ok(Token::Number("2", NumberKind::Integer)),
+                Err((limit, 4))
+            ],
+            kinds("[1,2]")
+        );
+        // a peeked token is counted once
+        let mut lexer = JsonLexer::with_options("[1,2", options.clone());
+        for _ in 0..4 {
+            lexer.peek_token().unwrap();
+            lexer.next_token().unwrap();
+        }
+        assert_eq!(Ok(None), lexer.next_token());
+    }
+
+    #[test]
+    fn peek_and_advance() {
+        let mut lexer = JsonLexer::new(" [true, 12]");
+        assert_eq!(Ok(Some(Token::LBracket)), lexer.peek_token());
+        assert_eq!(Ok(Some(Token::LBracket)), lexer.peek_token());
+        assert_eq!(1, lexer.byte_offset);
+        lexer.advance();
+        assert_eq!(2, lexer.byte_offset);
+        assert_eq!(Ok(Some(Token::True)), lexer.peek_token());
+        assert_eq!(Ok(Some(Token::True)), lexer.next_token());
+        // advancing without a peeked token does nothing
+        lexer.advance();
+        assert_eq!(6, lexer.byte_offset);
+        assert_eq!(Ok(Some(Token::Comma)), lexer.next_token());
+        assert_eq!(
+            Ok(Some(Token::Number("12", NumberKind::Integer))),
+            lexer.peek_token()
+        );
+        // moving the offset drops the peeked token
+        lexer.byte_offset = 9;
+        assert_eq!(
+            Ok(Some(Token::Number("2", NumberKind::Integer))),
+            lexer.next_token()
+        );
+        lexer.advance();
+        assert_eq!(Ok(Some(Token::RBracket)), lexer.peek_token());
+        lexer.advance();
+        assert_eq!(Ok(None), lexer.peek_token());
+        lexer.advance();
+        assert_eq!(Ok(None), lexer.next_token());
+    }
+
+    #[test]
+    fn classify_numbers() {
+        use NumberKind::*;
+
+        let lenient = LexerOptions {
+            strictness: Strictness::Lenient,
+            ..Default::default()
+        };
+        let json5 = LexerOptions {
+            dialect: Dialect::Json5,
+            ..Default::default()
+        };
+        for (input, kind, options) in [
+            ("-12", Integer, &LexerOptions::default()),
+            ("0.5", Float, &LexerOptions::default()),
+            ("1e3", Exponent, &LexerOptions::default()),
+            ("-1.5E-2", Exponent, &LexerOptions::default()),
+            ("+.5", Float, &lenient),
+            ("007", Integer, &lenient),
+            ("5.e2", Exponent, &lenient),
+            ("0xFE", Integer, &json5),
+            ("-Infinity", Float, &json5),
+            ("NaN", Float, &json5),
+            ("5.", Float, &json5),
+        ] {
+            let token = JsonLexer::with_options(input, options.clone()).next_token();
+            assert_eq!(Ok(Some(Token::Number(input, kind))), token);
+            assert_eq!(kind, NumberKind::of(input), "{input}");
+        }
+    }
+
+    #[test]
+    fn lex_python_literals() {
+        let options = LexerOptions {
+            python_literals: true,
+            ..Default::default()
+        };
+        let mut lexer = JsonLexer::with_options(r"[True, False, None, 'a\'b', true]", options);
+        let mut tokens = Vec::new();
+        while let Some(token) = lexer.next_token().unwrap() {
+            tokens.push(token);
+        }
+        assert_eq!(
+            vec![
+                Token::LBracket,
+                Token::True,
+                Token::Comma,
+                Token::False,
+                Token::Comma,
+                Token::Null,
+                Token::Comma,
+                Token::String(r"a\'b"),
+                Token::Comma,
+                Token::True,
+                Token::RBracket,
+            ],
+            tokens
         );
+        let options = LexerOptions {
+            python_literals: true,
+            ..Default::default()
+        };
+        for invalid in ["Truth", "Non", "'a", "'\\x4'"] {
+            let mut lexer = JsonLexer::with_options(invalid, options.clone());
+            assert!(lexer.next_token().is_err(), "{invalid}");
+        }
+        assert!(JsonLexer::new("True").next_token().is_err());
+    }
+
+    #[test]
+    fn checkpoint_and_restore() {
+        let options = LexerOptions {
+            max_tokens: Some(3),
+            ..Default::default()
+        };
+        let mut lexer = JsonLexer::with_options(" [1, x]", options);
+        assert_eq!(Ok(Some(Token::LBracket)), lexer.peek_token());
+        let checkpoint = lexer.checkpoint();
+        assert_eq!(Ok(Some(Token::LBracket)), lexer.next_token());
+        assert_eq!(
+            Ok(Some(Token::Number("1", NumberKind::Integer))),
+            lexer.next_token()
+        );
+        let after = lexer.checkpoint();
+        assert_eq!(Ok(Some(Token::Comma)), lexer.next_token());
+        assert!(lexer.next_token().is_err());
+
+        // the peeked token and the token count are restored too
+        lexer.restore(checkpoint.clone());
+        assert_eq!(1, lexer.byte_offset);
+        lexer.advance();
+        assert_eq!(2, lexer.byte_offset);
+        assert_eq!(
+            Ok(Some(Token::Number("1", NumberKind::Integer))),
+            lexer.next_token()
+        );
+        assert_eq!(Ok(Some(Token::Comma)), lexer.next_token());
+
+        // and forward again
+        lexer.restore(after);
+        assert_eq!(3, lexer.byte_offset);
+        assert_eq!(Ok(Some(Token::Comma)), lexer.next_token());
+        lexer.restore(checkpoint);
+        assert_eq!(Ok(Some(Token::LBracket)), lexer.next_token());
+    }
+
+    #[test]
+    fn resync_after_error() {
+        let mut lexer = JsonLexer::new("[1, tru, \"a\nb\", 2]");
+        assert_eq!(Ok(Some(Token::LBracket)), lexer.next_token());
+        assert_eq!(
+            Ok(Some(Token::Number("1", NumberKind::Integer))),
+            lexer.next_token()
+        );
+        assert_eq!(Ok(Some(Token::Comma)), lexer.next_token());
+        let err = lexer.next_token().unwrap_err();
+        assert_eq!(4, err.offset());
+        assert_eq!(Some(7), lexer.resync());
+        assert_eq!(Ok(Some(Token::Comma)), lexer.next_token());
+        assert!(lexer.next_token().is_err());
+        assert_eq!(Some(14), lexer.resync());
+        assert_eq!(Ok(Some(Token::Comma)), lexer.next_token());
+        assert_eq!(
+            Ok(Some(Token::Number("2", NumberKind::Integer))),
+            lexer.next_token()
+        );
+        assert_eq!(Ok(Some(Token::RBracket)), lexer.next_token());
+
+        let mut lexer = JsonLexer::new("tru");
+        assert!(lexer.next_token().is_err());
+        assert_eq!(None, lexer.resync());
+        assert_eq!(Ok(None), lexer.next_token());
     }
 }
@@ -0,0 +1,156 @@
+//! Splitting of newline-delimited JSON (NDJSON) into its records, so that each can be handed to a
+//! parser on its own.
+
+use alloc::string::ToString;
+use core::ops::Range;
+
+use crate::{ErrorKind, JsonLexer, LexError, Token, TokenSource};
+
+/// Iterator over the byte ranges of the top-level values of an input, each of which must start on
+/// a new line. Blank lines and, when accepted, comments between records are skipped. Errors end
+/// the iteration.
+///
+/// Records may span several lines, so pretty-printed values are told apart too. Only their
+/// brackets are checked: a record whose brackets balance may still be invalid JSON.
+pub struct Lines<'a> {
+    lexer: JsonLexer<'a>,
+    /// End of the previous record, if any.
+    previous: Option<usize>,
+    failed: bool,
+}
+
+impl<'a> JsonLexer<'a> {
+    /// Returns an iterator over the byte ranges of the remaining records of an NDJSON input.
+    pub fn lines(self) -> Lines<'a> {
+        Lines {
+            lexer: self,
+            previous: None,
+            failed: false,
+        }
+    }
+}
+
+impl Lines<'_> {
+    fn next_record(&mut self) -> Result<Option<Range<usize>>, LexError> {
+        let mut start = None;
+        let mut depth = 0usize;
+        loop {
+            let Some((token, span)) = self.lexer.next_spanned()? else {
+                return match start {
+                    Some(_) => Err(LexError {
+                        kind: ErrorKind::UnexpectedEof,
+                        message: "record ended prematurely".to_string(),
+                        offset: self.lexer.byte_offset,
+                    }),
+                    None => Ok(None),
+                };
+            };
+            if matches!(token, Token::Comment(_)) {
+                continue;
+            }
+            let start = *start.get_or_insert(span.start);
+            if depth == 0
+                && let Some(previous) = self.previous
+                && !self.lexer.input[previous..span.start].contains('\n')
+            {
+                return Err(error("expected a line break between records", span.start));
+            }
+            match token {
+                Token::LBrace | Token::LBracket => depth += 1,
+                Token::RBrace | Token::RBracket if depth == 0 => {
+                    return Err(error("unexpected closing bracket", span.start));
+                }
+                Token::RBrace | Token::RBracket => depth -= 1,
+                Token::Comma | Token::Colon | Token::Identifier(_) if depth == 0 => {
+                    return Err(error("expected a record", span.start));
+                }
+                _ => {}
+            }
+            if depth == 0 {
+                self.previous = Some(span.end);
+                return Ok(Some(start..span.end));
+            }
+        }
+    }
+}
+
+fn error(message: &str, offset: usize) -> LexError {
+    LexError {
+        kind: ErrorKind::Syntax,
+        message: message.to_string(),
+        offset,
+    }
+}
+
+impl Iterator for Lines<'_> {
+    type Item = Result<Range<usize>, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let record = self.next_record();
+        self.failed = record.is_err();
+        record.transpose()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{CommentMode, ErrorKind, JsonLexer, LexerOptions};
+
+    fn records(input: &str, options: LexerOptions) -> Vec<Result<&str, (ErrorKind, usize)>> {
+        JsonLexer::with_options(input, options)
+            .lines()
+            .map(|r| {
+                r.map(|span| &input[span])
+                    .map_err(|e| (e.kind(), e.offset()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn split_records() {
+        let options = LexerOptions {
+            comments: CommentMode::Skip,
+            ..Default::default()
+        };
+        assert_eq!(
+            vec![
+                Ok(r#"{"a": [1, {"b": 2}]}"#),
+                Ok("3"),
+                Ok("\"x\""),
+                Ok("[\n  true\n]"),
+                Ok("null"),
+            ],
+            records(
+                "{\"a\": [1, {\"b\": 2}]}\n3\r\n\n\"x\" // c\n[\n  true\n]\n/* d */ null\n",
+                options.clone()
+            )
+        );
+        assert!(records(" \n", options).is_empty());
+
+        let default = LexerOptions::default;
+        assert_eq!(
+            vec![Ok("1"), Ok("2"), Err((ErrorKind::Syntax, 4))],
+            records("1\n2 3", default())
+        );
+        assert_eq!(
+            vec![Ok("{}"), Err((ErrorKind::Syntax, 2))],
+            records("{}{}", default())
+        );
+        assert_eq!(vec![Err((ErrorKind::Syntax, 0))], records("]", default()));
+        assert_eq!(
+            vec![Ok("1"), Err((ErrorKind::Syntax, 1))],
+            records("1,2", default())
+        );
+        assert_eq!(
+            vec![Ok("1"), Err((ErrorKind::UnexpectedEof, 7))],
+            records("1\n[2, 3", default())
+        );
+        assert_eq!(
+            vec![Ok("[1]"), Err((ErrorKind::Syntax, 5))],
+            records("[1]\n[x]\n[2]", default())
+        );
+    }
+}
@@ -0,0 +1,137 @@
+//! Observation of token streams, so that embedders can gather metrics or audit payloads as a
+//! document is lexed, without lexing it again.
+
+use core::ops::Range;
+
+use crate::{LexError, LexerOptions, Token, TokenSource};
+
+/// Callbacks called by [`Observed`] as tokens are produced.
+///
+/// Closures taking a token and its span are observers of the tokens only.
+pub trait TokenObserver<'a> {
+    /// Called with each token and its byte range in the input, before it is returned.
+    fn token(&mut self, token: &Token<'a>, span: Range<usize>);
+
+    /// Called with the error ending the stream, before it is returned.
+    fn error(&mut self, error: &LexError) {
+        let _ = error;
+    }
+}
+
+impl<'a, F: FnMut(&Token<'a>, Range<usize>)> TokenObserver<'a> for F {
+    fn token(&mut self, token: &Token<'a>, span: Range<usize>) {
+        self(token, span)
+    }
+}
+
+/// A [`TokenSource`] passing the tokens of another through an observer, e.g. a [`JsonLexer`]
+/// whose tokens are counted while a document is parsed from it.
+///
+/// [`JsonLexer`]: crate::JsonLexer
+#[derive(Debug, Clone)]
+pub struct Observed<S, O> {
+    source: S,
+    observer: O,
+}
+
+impl<S, O> Observed<S, O> {
+    pub fn new(source: S, observer: O) -> Self {
+        Observed { source, observer }
+    }
+
+    pub fn observer(&self) -> &O {
+        &self.observer
+    }
+
+    pub fn observer_mut(&mut self) -> &mut O {
+        &mut self.observer
+    }
+
+    /// Returns the source and the observer, e.g. to read the metrics gathered by the observer.
+    pub fn into_parts(self) -> (S, O) {
+        (self.source, self.observer)
+    }
+}
+
+impl<'a, S: TokenSource<'a>, O: TokenObserver<'a>> TokenSource<'a> for Observed<S, O> {
+    fn input(&self) -> &'a str {
+        self.source.input()
+    }
+
+    fn next_spanned(&mut self) -> Result<Option<(Token<'a>, Range<usize>)>, LexError> {
+        match self.source.next_spanned() {
+            Ok(Some((token, span))) => {
+                self.observer.token(&token, span.clone());
+                Ok(Some((token, span)))
+            }
+            Ok(None) => Ok(None),
+            Err(e) => {
+                self.observer.error(&e);
+                Err(e)
+            }
+        }
+    }
+
+    fn options(&self) -> LexerOptions {
+        self.source.options()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use crate::{
+        ErrorKind, JsonLexer, LexError, Token, TokenSource,
+        observe::{Observed, TokenObserver},
+    };
+
+    /// Counts object keys: strings followed by a colon.
+    #[derive(Default)]
+    struct KeyFrequency<'a> {
+        last_string: Option<&'a str>,
+        keys: BTreeMap<&'a str, usize>,
+        errors: Vec<ErrorKind>,
+    }
+
+    impl<'a> TokenObserver<'a> for KeyFrequency<'a> {
+        fn token(&mut self, token: &Token<'a>, _: core::ops::Range<usize>) {
+            match token {
+                Token::Colon => {
+                    if let Some(key) = self.last_string.take() {
+                        *self.keys.entry(key).or_default() += 1;
+                    }
+                }
+                Token::String(s) => self.last_string = Some(s),
+                _ => self.last_string = None,
+            }
+        }
+
+        fn error(&mut self, error: &LexError) {
+            self.errors.push(error.kind());
+        }
+    }
+
+    #[test]
+    fn observe_tokens() {
+        let input = r#"[{"id": 1, "name": "a"}, {"id": 2, "tags": ["id"]}, tru]"#;
+        let mut observed = Observed::new(JsonLexer::new(input), KeyFrequency::default());
+        while let Ok(Some(_)) = observed.next_spanned() {}
+        let (_, frequency) = observed.into_parts();
+        assert_eq!(
+            vec![("id", 2), ("name", 1), ("tags", 1)],
+            frequency.keys.into_iter().collect::<Vec<_>>()
+        );
+        assert_eq!(vec![ErrorKind::Syntax], frequency.errors);
+
+        let mut spans = Vec::new();
+        let mut observed =
+            Observed::new(JsonLexer::new("[1, 2]"), |_: &Token, span| spans.push(span));
+        assert_eq!(
+            Some((Token::LBracket, 0..1)),
+            observed.next_spanned().unwrap()
+        );
+        while observed.next_spanned().unwrap().is_some() {}
+        assert_eq!(vec![0..1, 1..2, 2..3, 4..5, 5..6], spans);
+    }
+}
@@ -0,0 +1,158 @@
+//! Recording of token streams, so that a document can be lexed once and parsed many times.
+
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::{Dialect, JsonLexer, LexError, LexerOptions, NumberKind, Token, TokenSource};
+
+/// The tokens of a document, stored as their spans in the input only.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recording<'a> {
+    input: &'a str,
+    options: LexerOptions,
+    spans: Vec<(usize, usize)>,
+    /// The error that ended the lexing, replayed after the last token.
+    error: Option<LexError>,
+}
+
+impl<'a> Recording<'a> {
+    /// Lexes all of the input of `lexer`, up to the end or to the first error.
+    pub fn record(mut lexer: JsonLexer<'a>) -> Self {
+        let mut spans = Vec::new();
+        let error = loop {
+            match lexer.next_spanned() {
+                Ok(Some((_, span))) => spans.push((span.start, span.end)),
+                Ok(None) => break None,
+                Err(e) => break Some(e),
+            }
+        };
+        Recording {
+            input: lexer.input,
+            options: lexer.options,
+            spans,
+            error,
+        }
+    }
+
+    /// Number of tokens recorded.
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    /// The error that ended the lexing, if any.
+    pub fn error(&self) -> Option<&LexError> {
+        self.error.as_ref()
+    }
+
+    /// Returns a source replaying the recorded tokens from the start.
+    pub fn replay(&self) -> Replayer<'_, 'a> {
+        Replayer {
+            recording: self,
+            position: 0,
+        }
+    }
+}
+
+/// Replays the tokens of a [`Recording`], then its error, if any.
+#[derive(Debug, Clone)]
+pub struct Replayer<'r, 'a> {
+    recording: &'r Recording<'a>,
+    position: usize,
+}
+
+impl<'a> TokenSource<'a> for Replayer<'_, 'a> {
+    fn input(&self) -> &'a str {
+        self.recording.input
+    }
+
+    fn next_spanned(&mut self) -> Result<Option<(Token<'a>, Range<usize>)>, LexError> {
+        let Some(&(start, end)) = self.recording.spans.get(self.position) else {
+            return match &self.recording.error {
+                Some(e) => Err(e.clone()),
+                None => Ok(None),
+            };
+        };
+        self.position += 1;
+        let text = &self.recording.input[start..end];
+        // the kind of a token is told by its first char
+        let token = match text.as_bytes()[0] {
+            b'{' => Token::LBrace,
+            b'}' => Token::RBrace,
+            b'[' => Token::LBracket,
+            b']' => Token::RBracket,
+            b',' => Token::Comma,
+            b':' => Token::Colon,
+            b'"' | b'\'' => Token::String(&text[1..text.len() - 1]),
+            b'/' => Token::Comment(text),
+            b'+' | b'-' | b'.' | b'0'..=b'9' => Token::Number(text, NumberKind::of(text)),
+            // which words are literals rather than identifiers depends on the options
+            _ => {
+                let options = &self.recording.options;
+                let python = options.python_literals;
+                match text {
+                    "true" => Token::True,
+                    "false" => Token::False,
+                    "null" => Token::Null,
+                    "True" if python => Token::True,
+                    "False" if python => Token::False,
+                    "None" if python => Token::Null,
+                    "Infinity" | "NaN" if options.dialect == Dialect::Json5 => {
+                        Token::Number(text, NumberKind::Float)
+                    }
+                    _ => Token::Identifier(text),
+                }
+            }
+        };
+        Ok(Some((token, start..end)))
+    }
+
+    fn options(&self) -> LexerOptions {
+        self.recording.options.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{CommentMode, ErrorKind, JsonLexer, LexerOptions, TokenSource, record::Recording};
+
+    fn tokens<'a>(mut source: impl TokenSource<'a>) -> Vec<String> {
+        let mut tokens = Vec::new();
+        loop {
+            match source.next_spanned() {
+                Ok(Some((token, span))) => tokens.push(format!("{token:?} {span:?}")),
+                Ok(None) => return tokens,
+                Err(e) => {
+                    tokens.push(format!("{:?} {}", e.kind(), e.offset()));
+                    return tokens;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn replay_tokens() {
+        let input = r#" {"a\"": [1.5e3, true, false, null, "x"]} // end"#;
+        let options = LexerOptions {
+            comments: CommentMode::Emit,
+            ..Default::default()
+        };
+        let recording = Recording::record(JsonLexer::with_options(input, options.clone()));
+        assert_eq!(16, recording.len());
+        let expected = tokens(JsonLexer::with_options(input, options));
+        assert_eq!(expected, tokens(recording.replay()));
+        // replaying again starts over
+        assert_eq!(expected, tokens(recording.replay()));
+    }
+
+    #[test]
+    fn replay_error() {
+        let recording = Recording::record(JsonLexer::new("[1, x]"));
+        assert_eq!(3, recording.len());
+        assert_eq!(Some(ErrorKind::Syntax), recording.error().map(|e| e.kind()));
+        assert_eq!(tokens(JsonLexer::new("[1, x]")), tokens(recording.replay()));
+    }
+}
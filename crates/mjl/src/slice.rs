@@ -0,0 +1,92 @@
+//! Lexing into the kinds and text of tokens only, for tools such as syntax highlighters that need
+//! nothing else.
+
+use crate::{JsonLexer, LexError, TokenKind, TokenSource};
+
+/// Iterator over the tokens of an input as their kinds and whole text, quotes of strings
+/// included, borrowed from the input: nothing is allocated or copied. Errors end the iteration.
+///
+/// Whitespace and skipped comments are the text between tokens. The byte offset of a token is
+/// the one of its text in the input, `text.as_ptr() as usize - input.as_ptr() as usize`.
+pub struct SliceTokens<'a> {
+    lexer: JsonLexer<'a>,
+    failed: bool,
+}
+
+impl<'a> JsonLexer<'a> {
+    /// Returns an iterator over the kinds and text of the remaining tokens.
+    pub fn slices(self) -> SliceTokens<'a> {
+        SliceTokens {
+            lexer: self,
+            failed: false,
+        }
+    }
+}
+
+impl<'a> Iterator for SliceTokens<'a> {
+    type Item = Result<(TokenKind, &'a str), LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        match self.lexer.next_spanned() {
+            Ok(token) => token.map(|(token, span)| Ok((token.kind(), &self.lexer.input[span]))),
+            Err(e) => {
+                self.failed = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        CommentMode, ErrorKind, JsonLexer, LexerOptions,
+        TokenKind::{self, *},
+    };
+
+    #[test]
+    fn lex_slices() {
+        let options = LexerOptions {
+            comments: CommentMode::Emit,
+            ..Default::default()
+        };
+        let input = r#"{"a\n": [-1.5e3, true, null]} // end"#;
+        let slices: Vec<(TokenKind, &str)> = JsonLexer::with_options(input, options)
+            .slices()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            vec![
+                (LBrace, "{"),
+                (String, r#""a\n""#),
+                (Colon, ":"),
+                (LBracket, "["),
+                (Number, "-1.5e3"),
+                (Comma, ","),
+                (True, "true"),
+                (Comma, ","),
+                (Null, "null"),
+                (RBracket, "]"),
+                (RBrace, "}"),
+                (Comment, "// end"),
+            ],
+            slices
+        );
+        // the text is borrowed from the input
+        let (_, text) = slices[4];
+        assert_eq!(9, text.as_ptr() as usize - input.as_ptr() as usize);
+
+        let mut slices = JsonLexer::new("[1, x]").slices();
+        assert_eq!(3, slices.by_ref().take_while(Result::is_ok).count());
+        assert_eq!(None, slices.next());
+        let err = JsonLexer::new("[1, x]")
+            .slices()
+            .nth(3)
+            .unwrap()
+            .unwrap_err();
+        assert_eq!((ErrorKind::Syntax, 4), (err.kind(), err.offset()));
+    }
+}
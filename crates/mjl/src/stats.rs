@@ -0,0 +1,128 @@
+//! Counters of what a lexer has lexed, so that batch pipelines can log the shape of documents
+//! without a second pass over them.
+
+use crate::{Token, TokenKind};
+
+const KINDS: [TokenKind; 13] = [
+    TokenKind::LBrace,
+    TokenKind::RBrace,
+    TokenKind::LBracket,
+    TokenKind::RBracket,
+    TokenKind::Comma,
+    TokenKind::Colon,
+    TokenKind::True,
+    TokenKind::False,
+    TokenKind::Number,
+    TokenKind::String,
+    TokenKind::Null,
+    TokenKind::Identifier,
+    TokenKind::Comment,
+];
+
+/// Counts of the tokens lexed so far and of the input they were lexed from, returned by the
+/// `stats` method of the lexers. A token is counted once however many times it is peeked, and
+/// rewinding to a [`Checkpoint`](crate::Checkpoint) rewinds the counts too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LexerStats {
+    counts: [usize; KINDS.len()],
+    tokens: usize,
+    /// Bytes of input consumed, whitespace and skipped comments included.
+    pub bytes: usize,
+    /// Length in bytes of the longest string literal, between its quotes and escapes included.
+    pub max_string_len: usize,
+}
+
+impl LexerStats {
+    /// Number of tokens lexed, comments included.
+    pub fn tokens(&self) -> usize {
+        self.tokens
+    }
+
+    /// Number of tokens of `kind` lexed.
+    pub fn count(&self, kind: TokenKind) -> usize {
+        self.counts[kind as usize]
+    }
+
+    /// Number of tokens lexed of every kind, in the order [`TokenKind`] declares them.
+    pub fn counts(&self) -> impl Iterator<Item = (TokenKind, usize)> {
+        KINDS.into_iter().zip(self.counts)
+    }
+
+    /// These counts, with `bytes` of input consumed.
+    pub(crate) fn consumed(self, bytes: usize) -> Self {
+        LexerStats { bytes, ..self }
+    }
+
+    pub(crate) fn record(&mut self, token: &Token) {
+        self.counts[token.kind() as usize] += 1;
+        self.tokens += 1;
+        if let Token::String(s) = token {
+            self.max_string_len = self.max_string_len.max(s.len());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        ByteLexer, CommentMode, FeedLexer, JsonLexer, LexerOptions, TokenKind,
+        feed::Feed,
+        stats::{KINDS, LexerStats},
+    };
+
+    #[test]
+    fn kinds_in_order() {
+        for (i, kind) in KINDS.into_iter().enumerate() {
+            assert_eq!(i, kind as usize);
+        }
+    }
+
+    #[test]
+    fn count_tokens() {
+        let input = r#"{"a": [1, 2.5, "long\"er"], "b": null} // c"#;
+        let options = LexerOptions {
+            comments: CommentMode::Emit,
+            ..Default::default()
+        };
+        let mut lexer = JsonLexer::with_options(input, options.clone());
+        assert_eq!(LexerStats::default(), lexer.stats());
+        lexer.next_token().unwrap();
+        lexer.peek_token().unwrap();
+        lexer.peek_token().unwrap();
+        // the peeked token is counted once, but not consumed
+        assert_eq!((2, 1), (lexer.stats().tokens(), lexer.stats().bytes));
+        let checkpoint = lexer.checkpoint();
+        while lexer.next_token().unwrap().is_some() {}
+
+        let stats = lexer.stats();
+        assert_eq!(16, stats.tokens());
+        assert_eq!(input.len(), stats.bytes);
+        assert_eq!(8, stats.max_string_len);
+        assert_eq!(2, stats.count(TokenKind::Number));
+        assert_eq!(3, stats.count(TokenKind::String));
+        assert_eq!(1, stats.count(TokenKind::Comment));
+        assert_eq!(0, stats.count(TokenKind::True));
+        assert_eq!(
+            stats.tokens(),
+            stats.counts().map(|(_, count)| count).sum::<usize>()
+        );
+
+        lexer.restore(checkpoint);
+        assert_eq!(
+            (2, 1),
+            (lexer.stats().tokens(), lexer.stats().max_string_len)
+        );
+
+        let mut bytes = ByteLexer::with_options(input.as_bytes(), options.clone());
+        while bytes.next_token().unwrap().is_some() {}
+        assert_eq!(stats, bytes.stats());
+
+        let mut feed = FeedLexer::with_options(options);
+        feed.feed(&input[..10]);
+        while let Feed::Token(_) = feed.next_token().unwrap() {}
+        feed.feed(&input[10..]);
+        feed.finish();
+        while let Feed::Token(_) = feed.next_token().unwrap() {}
+        assert_eq!(stats, feed.stats());
+    }
+}
@@ -0,0 +1,147 @@
+//! Lossless lexing, keeping the whitespace and comments between tokens, for tools such as
+//! formatters that must reproduce the input exactly.
+
+use core::ops::Range;
+
+use crate::{CommentMode, JsonLexer, LexError, Token, TokenSource};
+
+/// A piece of the input: a token, or the trivia between tokens.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Element<'a> {
+    /// A token other than a comment.
+    Token(Token<'a>),
+    /// A run of whitespace, as the dialect defines it.
+    Whitespace(&'a str),
+    /// A `// line` or `/* block */` comment, delimiters included.
+    Comment(&'a str),
+}
+
+/// Iterator over the elements of an input with their byte ranges, which follow each other and
+/// cover the whole input, so that the input is the concatenation of their text. Errors end the
+/// iteration.
+pub struct TriviaTokens<'a> {
+    lexer: JsonLexer<'a>,
+    failed: bool,
+}
+
+impl<'a> JsonLexer<'a> {
+    /// Returns an iterator over the remaining tokens and the trivia between them. Comments are
+    /// returned in [`CommentMode::Skip`] as in [`CommentMode::Emit`], and are still errors in
+    /// [`CommentMode::Reject`].
+    pub fn trivia(mut self) -> TriviaTokens<'a> {
+        if self.options.comments == CommentMode::Skip {
+            self.options.comments = CommentMode::Emit;
+        }
+        TriviaTokens {
+            lexer: self,
+            failed: false,
+        }
+    }
+}
+
+impl<'a> Iterator for TriviaTokens<'a> {
+    type Item = Result<(Element<'a>, Range<usize>), LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        // comments are emitted, so only whitespace is skipped
+        let start = self.lexer.byte_offset;
+        self.lexer.skip_whitespace();
+        if self.lexer.byte_offset > start {
+            let whitespace = &self.lexer.input[start..self.lexer.byte_offset];
+            return Some(Ok((
+                Element::Whitespace(whitespace),
+                start..self.lexer.byte_offset,
+            )));
+        }
+        match self.lexer.next_spanned() {
+            Ok(token) => token.map(|(token, span)| {
+                let element = match token {
+                    Token::Comment(text) => Element::Comment(text),
+                    token => Element::Token(token),
+                };
+                Ok((element, span))
+            }),
+            Err(e) => {
+                self.failed = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        CommentMode, Dialect, ErrorKind, JsonLexer, LexerOptions, Token,
+        trivia::Element::{self, *},
+    };
+
+    fn elements<'a>(input: &'a str, options: &LexerOptions) -> Vec<Element<'a>> {
+        let elements = JsonLexer::with_options(input, options.clone()).trivia();
+        let mut text = String::new();
+        let mut all = Vec::new();
+        for element in elements {
+            let (element, span) = element.unwrap();
+            assert_eq!(text.len(), span.start);
+            text.push_str(&input[span]);
+            all.push(element);
+        }
+        // nothing is lost
+        assert_eq!(input, text);
+        all
+    }
+
+    #[test]
+    fn lex_trivia() {
+        let options = LexerOptions {
+            comments: CommentMode::Skip,
+            ..Default::default()
+        };
+        assert_eq!(
+            vec![
+                Whitespace(" \n"),
+                Token(Token::LBracket),
+                Comment("/* a */"),
+                Token(Token::True),
+                Token(Token::Comma),
+                Whitespace("\t"),
+                Comment("// b"),
+                Whitespace("\r\n "),
+                Token(Token::Null),
+                Token(Token::RBracket),
+                Whitespace(" "),
+            ],
+            elements(" \n[/* a */true,\t// b\r\n null] ", &options)
+        );
+        let json5 = LexerOptions {
+            dialect: Dialect::Json5,
+            ..Default::default()
+        };
+        assert_eq!(
+            vec![
+                Token(Token::LBrace),
+                Whitespace("\u{a0}\u{feff}"),
+                Token(Token::Identifier("a")),
+                Token(Token::Colon),
+                Whitespace(" "),
+                Token(Token::String("b")),
+                Token(Token::RBrace),
+            ],
+            elements("{\u{a0}\u{feff}a: 'b'}", &json5)
+        );
+        assert!(elements("", &options).is_empty());
+
+        let mut elements = JsonLexer::new("[1] // c").trivia();
+        assert_eq!(4, elements.by_ref().take_while(Result::is_ok).count());
+        assert_eq!(None, elements.next());
+        let err = JsonLexer::new("[1] // c")
+            .trivia()
+            .nth(4)
+            .unwrap()
+            .unwrap_err();
+        assert_eq!((ErrorKind::Syntax, 4), (err.kind(), err.offset()));
+    }
+}
@@ -0,0 +1,334 @@
+//! Analysis of the keys of arrays of records, such as database dumps or API listings, to
+//! discover the schema of unfamiliar data: which keys records have, how often, with values of
+//! which types, and how many distinct values each takes.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+use mjl::JsonLexer;
+
+use crate::{DuplicateKeyPolicy, ParseOptions, Value, parse_with};
+
+/// The keys of the records of an array, returned by [`Value::analyze_records`] and
+/// [`RecordAnalyzer::report`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordsReport {
+    /// Number of records, the elements that are objects. Other elements are ignored.
+    pub records: usize,
+    /// The keys of the records, in the order they were first seen.
+    pub keys: Vec<KeyReport>,
+}
+
+impl RecordsReport {
+    pub fn key(&self, key: &str) -> Option<&KeyReport> {
+        self.keys.iter().find(|k| k.key == key)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyReport {
+    /// The raw text of the key.
+    pub key: String,
+    /// Number of records with the key. Records repeating a key count once.
+    pub occurrences: usize,
+    /// Fraction of the records with the key, from 0 to 1.
+    pub rate: f64,
+    /// Number of values of each type, by name: `object`, `array`, `string`, `number`,
+    /// `boolean` or `null`.
+    pub types: BTreeMap<&'static str, usize>,
+    /// Estimated number of distinct values, compared by their compact text. Estimates are
+    /// exact for a few values and within about 3% for many.
+    pub cardinality: usize,
+}
+
+impl KeyReport {
+    /// Whether every record has the key.
+    pub fn is_required(&self) -> bool {
+        self.rate == 1.0
+    }
+}
+
+/// Analyzes records one at a time, e.g. the elements of
+/// [`stream_array`](crate::stream::stream_array), in memory proportional to the number of
+/// keys rather than of records.
+#[derive(Debug, Clone, Default)]
+pub struct RecordAnalyzer {
+    records: usize,
+    keys: Vec<KeyStats>,
+    positions: HashMap<String, usize>,
+}
+
+#[derive(Debug, Clone)]
+struct KeyStats {
+    key: String,
+    occurrences: usize,
+    types: BTreeMap<&'static str, usize>,
+    values: Sketch,
+}
+
+impl RecordAnalyzer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `record` to the analysis, ignored if it isn't an object.
+    pub fn add(&mut self, record: &Value) {
+        let pairs = match record {
+            Value::Object(pairs) => pairs,
+            Value::Raw(raw) => {
+                if let Some(record) = parse_raw(raw.get()) {
+                    self.add(&record);
+                }
+                return;
+            }
+            _ => return,
+        };
+        self.records += 1;
+        let mut seen = HashSet::new();
+        for pair in pairs {
+            let position = match self.positions.get(pair.key.as_ref()) {
+                Some(&position) => position,
+                None => {
+                    self.positions.insert(pair.key.to_string(), self.keys.len());
+                    self.keys.push(KeyStats {
+                        key: pair.key.to_string(),
+                        occurrences: 0,
+                        types: BTreeMap::new(),
+                        values: Sketch::default(),
+                    });
+                    self.keys.len() - 1
+                }
+            };
+            let stats = &mut self.keys[position];
+            if seen.insert(position) {
+                stats.occurrences += 1;
+            }
+            *stats.types.entry(type_name(&pair.value)).or_default() += 1;
+            let mut hasher = DefaultHasher::new();
+            pair.value.to_string().hash(&mut hasher);
+            stats.values.insert(hasher.finish());
+        }
+    }
+
+    /// The report of the records added so far.
+    pub fn report(&self) -> RecordsReport {
+        let keys = self
+            .keys
+            .iter()
+            .map(|stats| KeyReport {
+                key: stats.key.clone(),
+                occurrences: stats.occurrences,
+                rate: stats.occurrences as f64 / self.records as f64,
+                types: stats.types.clone(),
+                cardinality: stats.values.estimate(),
+            })
+            .collect();
+        RecordsReport {
+            records: self.records,
+            keys,
+        }
+    }
+}
+
+impl Value<'_> {
+    /// The keys of the records of this array, or `None` if this value isn't an array.
+    pub fn analyze_records(&self) -> Option<RecordsReport> {
+        let values = match self {
+            Value::Array(values) => values,
+            Value::Raw(raw) => return parse_raw(raw.get())?.analyze_records(),
+            _ => return None,
+        };
+        let mut analyzer = RecordAnalyzer::new();
+        for value in values {
+            analyzer.add(value);
+        }
+        Some(analyzer.report())
+    }
+}
+
+/// Parses the text of a raw value, keeping the members it may repeat.
+fn parse_raw(text: &str) -> Option<Value<'_>> {
+    let options = ParseOptions {
+        duplicate_keys: DuplicateKeyPolicy::KeepAll,
+        ..Default::default()
+    };
+    parse_with(JsonLexer::new(text), options)
+        .ok()
+        .map(|json| json.value)
+}
+
+/// Name of the type of `value`, raw values named after the value they hold.
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Object(_) => "object",
+        Value::Array(_) => "array",
+        Value::Str(_) => "string",
+        Value::Number(_) => "number",
+        Value::Boolean(_) => "boolean",
+        Value::Null => "null",
+        Value::Raw(raw) => match raw.get().trim_start().bytes().next() {
+            Some(b'{') => "object",
+            Some(b'[') => "array",
+            Some(b'"') => "string",
+            Some(b't' | b'f') => "boolean",
+            Some(b'n') => "null",
+            _ => "number",
+        },
+    }
+}
+
+/// Bits of a hash selecting a register of a [`Sketch`].
+const REGISTER_BITS: u32 = 10;
+const REGISTERS: usize = 1 << REGISTER_BITS;
+
+/// A HyperLogLog sketch of a set of hashes, estimating its size in constant memory.
+#[derive(Debug, Clone)]
+struct Sketch {
+    /// The most leading zeros plus one of the hashes of each register.
+    registers: Box<[u8; REGISTERS]>,
+}
+
+impl Default for Sketch {
+    fn default() -> Self {
+        Sketch {
+            registers: Box::new([0; REGISTERS]),
+        }
+    }
+}
+
+impl Sketch {
+    fn insert(&mut self, hash: u64) {
+        let register = (hash >> (64 - REGISTER_BITS)) as usize;
+        // the marker bit bounds the rank when the remaining bits are all zeros
+        let rest = (hash << REGISTER_BITS) | (1 << (REGISTER_BITS - 1));
+        let rank = rest.leading_zeros() as u8 + 1;
+        let current = &mut self.registers[register];
+        *current = (*current).max(rank);
+    }
+
+    fn estimate(&self) -> usize {
+        let m = REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|&r| 2f64.powi(-i32::from(r)))
+            .sum();
+        let raw = alpha * m * m / sum;
+        let empty = self.registers.iter().filter(|&&r| r == 0).count();
+        // linear counting is more accurate while many registers are empty
+        let estimate = if raw <= 2.5 * m && empty > 0 {
+            m * (m / empty as f64).ln()
+        } else {
+            raw
+        };
+        estimate.round() as usize
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::BTreeMap,
+        hash::{DefaultHasher, Hash, Hasher},
+    };
+
+    use mjl::JsonLexer;
+
+    use crate::{
+        DuplicateKeyPolicy, ParseOptions, Value,
+        analyze::{RecordAnalyzer, Sketch},
+        parse, parse_with,
+    };
+
+    #[test]
+    fn analyze_records() {
+        let input = r#"[
+            {"id": 1, "name": "a", "tags": ["x"]},
+            {"id": 2, "name": null, "id": 3},
+            {"id": 3, "name": "a", "extra": {"k": true}},
+            "not a record",
+            {"id": 4, "name": "b"}
+        ]"#;
+        let options = ParseOptions {
+            duplicate_keys: DuplicateKeyPolicy::KeepAll,
+            ..Default::default()
+        };
+        let report = parse_with(JsonLexer::new(input), options)
+            .unwrap()
+            .value
+            .analyze_records()
+            .unwrap();
+        assert_eq!(4, report.records);
+        assert_eq!(
+            vec!["id", "name", "tags", "extra"],
+            report
+                .keys
+                .iter()
+                .map(|k| k.key.as_str())
+                .collect::<Vec<_>>()
+        );
+
+        let id = report.key("id").unwrap();
+        assert_eq!(4, id.occurrences);
+        assert!(id.is_required());
+        assert_eq!(BTreeMap::from([("number", 5)]), id.types);
+        assert_eq!(4, id.cardinality);
+
+        let name = report.key("name").unwrap();
+        assert_eq!(BTreeMap::from([("null", 1), ("string", 3)]), name.types);
+        assert_eq!(3, name.cardinality);
+
+        let tags = report.key("tags").unwrap();
+        assert_eq!(1, tags.occurrences);
+        assert_eq!(0.25, tags.rate);
+        assert!(!tags.is_required());
+
+        // raw records are analyzed all the same, and raw members typed after the value they
+        // hold
+        let options = ParseOptions {
+            spill_above: Some(4),
+            duplicate_keys: DuplicateKeyPolicy::KeepAll,
+            ..Default::default()
+        };
+        let spilled = parse_with(JsonLexer::new(input), options).unwrap().value;
+        assert!(matches!(spilled, Value::Raw(_)));
+        assert_eq!(Some(report), spilled.analyze_records());
+        let options = ParseOptions {
+            spill_above: Some(8),
+            ..Default::default()
+        };
+        let record = parse_with(JsonLexer::new(r#"{"a": [1, 2, 3]}"#), options).unwrap();
+        assert!(matches!(record.value.get("a"), Some(Value::Raw(_))));
+        let mut analyzer = RecordAnalyzer::new();
+        analyzer.add(&record.value);
+        assert_eq!(
+            BTreeMap::from([("array", 1)]),
+            analyzer.report().key("a").unwrap().types
+        );
+
+        assert_eq!(
+            None,
+            parse(JsonLexer::new("{}")).unwrap().value.analyze_records()
+        );
+        let empty = RecordAnalyzer::new().report();
+        assert_eq!(0, empty.records);
+        assert!(empty.keys.is_empty());
+    }
+
+    #[test]
+    fn cardinality_estimates() {
+        let mut sketch = Sketch::default();
+        for i in 0..100_000u64 {
+            for _ in 0..2 {
+                let mut hasher = DefaultHasher::new();
+                i.hash(&mut hasher);
+                sketch.insert(hasher.finish());
+            }
+        }
+        let estimate = sketch.estimate() as f64;
+        assert!((estimate - 100_000.0).abs() < 10_000.0, "{estimate}");
+    }
+}
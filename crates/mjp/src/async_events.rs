@@ -0,0 +1,123 @@
+//! [`Event`]s of documents read from a tokio [`AsyncRead`], as a [`Stream`] that reads no more
+//! input than it needs to produce the next event.
+
+use futures_util::Stream;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::{
+    JsonParseError,
+    events::{Event, EventBuffer, READ_SIZE, Step},
+};
+
+/// Reads the [`Event`]s of the document in `reader`. Errors end the stream.
+///
+/// Like [`EventReader`](crate::events::EventReader), duplicate keys are not detected.
+pub fn event_stream<R: AsyncRead + Unpin>(
+    reader: R,
+) -> impl Stream<Item = Result<Event<'static>, JsonParseError>> {
+    futures_util::stream::unfold(AsyncEventReader::new(reader), |mut reader| async move {
+        let event = reader.next_event().await.transpose()?;
+        Some((event, reader))
+    })
+}
+
+struct AsyncEventReader<R> {
+    reader: R,
+    events: EventBuffer,
+}
+
+impl<R: AsyncRead + Unpin> AsyncEventReader<R> {
+    fn new(reader: R) -> Self {
+        AsyncEventReader {
+            reader,
+            events: EventBuffer::new(),
+        }
+    }
+
+    async fn next_event(&mut self) -> Result<Option<Event<'static>>, JsonParseError> {
+        loop {
+            match self.events.next_event()? {
+                Step::Event(event) => return Ok(Some(event)),
+                Step::End => return Ok(None),
+                Step::NeedInput => {
+                    let buffer = self.events.prepare_read(READ_SIZE);
+                    let result = self.reader.read_buf(buffer).await;
+                    self.events.finish_read(result)?;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        borrow::Cow,
+        pin::Pin,
+        task::{Context, Poll},
+    };
+
+    use futures_util::StreamExt;
+    use tokio::io::{AsyncRead, ReadBuf};
+
+    use crate::{ErrorKind, async_events::event_stream, events::Event};
+
+    /// Reader handing out one byte per read, to split every token.
+    struct Trickle(&'static [u8]);
+
+    impl AsyncRead for Trickle {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            if let Some((first, rest)) = self.0.split_first() {
+                buf.put_slice(&[*first]);
+                self.0 = rest;
+            }
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn stream_events() {
+        let events: Vec<_> = event_stream(Trickle("{\"ké\": [12, \"x\"]}\n".as_bytes()))
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(
+            vec![
+                Event::StartObject,
+                Event::Key(Cow::Borrowed("ké")),
+                Event::StartArray,
+                Event::Number(Cow::Borrowed("12")),
+                Event::Str(Cow::Borrowed("x")),
+                Event::EndArray,
+                Event::EndObject,
+            ],
+            events
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_errors() {
+        let results: Vec<_> = event_stream(Trickle(b"[1,\n 2 3]")).collect().await;
+        assert_eq!(4, results.len());
+        let err = results[3].as_ref().unwrap_err();
+        assert_eq!(ErrorKind::Syntax, err.kind());
+        assert_eq!((2, 4, 7), (err.line(), err.column(), err.offset()));
+        assert_eq!("/1", err.path());
+
+        let results: Vec<_> = event_stream(Trickle(b"[\"a\xFF\"]")).collect().await;
+        assert_eq!(
+            ErrorKind::InvalidUtf8,
+            results[1].as_ref().unwrap_err().kind()
+        );
+
+        let results: Vec<_> = event_stream(&b"[1"[..]).collect().await;
+        assert_eq!(
+            ErrorKind::UnexpectedEof,
+            results[2].as_ref().unwrap_err().kind()
+        );
+    }
+}
@@ -0,0 +1,294 @@
+//! Fluent construction of values.
+//!
+//! Each builder is typed after the place its value goes to, so that unbalanced nesting, such as
+//! ending an object twice or a key without a value, fails to compile:
+//!
+//! ```
+//! use mjp::builder::JsonBuilder;
+//!
+//! let value = JsonBuilder::new()
+//!     .object()
+//!     .key("name")
+//!     .string("mjd")
+//!     .key("versions")
+//!     .array()
+//!     .number(1)
+//!     .number(2.5)
+//!     .end()
+//!     .end();
+//! ```
+
+use std::{borrow::Cow, marker::PhantomData};
+
+use crate::{BooleanVal, Pair, Value, ser::escape};
+
+/// A place where a built value goes.
+pub trait Slot<'a>: Sized {
+    /// What building continues with once the value is in place.
+    type Next;
+
+    fn put(self, value: Value<'a>) -> Self::Next;
+}
+
+/// Builds a single value into `S`.
+#[must_use]
+pub struct JsonBuilder<'a, S> {
+    slot: S,
+    _value: PhantomData<Value<'a>>,
+}
+
+/// The slot of a builder made by [`JsonBuilder::new`], returning the built value.
+pub struct Root;
+
+impl<'a> Slot<'a> for Root {
+    type Next = Value<'a>;
+
+    fn put(self, value: Value<'a>) -> Value<'a> {
+        value
+    }
+}
+
+impl<'a> JsonBuilder<'a, Root> {
+    pub fn new() -> Self {
+        JsonBuilder::into(Root)
+    }
+}
+
+impl Default for JsonBuilder<'_, Root> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, S: Slot<'a>> JsonBuilder<'a, S> {
+    fn into(slot: S) -> Self {
+        JsonBuilder {
+            slot,
+            _value: PhantomData,
+        }
+    }
+
+    /// Puts a value built by other means, such as a parsed one.
+    pub fn value(self, value: Value<'a>) -> S::Next {
+        self.slot.put(value)
+    }
+
+    pub fn null(self) -> S::Next {
+        self.value(Value::Null)
+    }
+
+    pub fn boolean(self, b: bool) -> S::Next {
+        self.value(Value::Boolean(if b {
+            BooleanVal::True
+        } else {
+            BooleanVal::False
+        }))
+    }
+
+    /// Puts a number. Non-finite floats, which JSON can't represent, become `null`.
+    pub fn number(self, n: impl JsonNumber) -> S::Next {
+        match n.to_json() {
+            Some(n) => self.value(Value::Number(n.into())),
+            None => self.null(),
+        }
+    }
+
+    /// Puts a string, escaping it as needed.
+    pub fn string(self, s: &str) -> S::Next {
+        self.value(Value::Str(Cow::Owned(escape(s).into_owned())))
+    }
+
+    /// Starts an object, which [`ObjectBuilder::end`] puts in place.
+    pub fn object(self) -> ObjectBuilder<'a, S> {
+        ObjectBuilder {
+            slot: self.slot,
+            pairs: Vec::new(),
+        }
+    }
+
+    /// Starts an array, which [`ArrayBuilder::end`] puts in place.
+    pub fn array(self) -> ArrayBuilder<'a, S> {
+        ArrayBuilder {
+            slot: self.slot,
+            values: Vec::new(),
+        }
+    }
+}
+
+/// Builds the members of an object.
+#[must_use]
+pub struct ObjectBuilder<'a, S> {
+    slot: S,
+    pairs: Vec<Pair<'a>>,
+}
+
+/// The slot of the value of an object member.
+pub struct Member<'a, S> {
+    object: ObjectBuilder<'a, S>,
+    key: Cow<'a, str>,
+}
+
+impl<'a, S: Slot<'a>> Slot<'a> for Member<'a, S> {
+    type Next = ObjectBuilder<'a, S>;
+
+    fn put(mut self, value: Value<'a>) -> ObjectBuilder<'a, S> {
+        self.object.pairs.push(Pair {
+            key: self.key,
+            value,
+        });
+        self.object
+    }
+}
+
+impl<'a, S: Slot<'a>> ObjectBuilder<'a, S> {
+    /// Starts a member, escaping its key as needed.
+    pub fn key(self, key: &str) -> JsonBuilder<'a, Member<'a, S>> {
+        JsonBuilder::into(Member {
+            object: self,
+            key: Cow::Owned(escape(key).into_owned()),
+        })
+    }
+
+    /// Ends the object, putting it in place.
+    pub fn end(self) -> S::Next {
+        self.slot.put(Value::Object(self.pairs.into()))
+    }
+}
+
+/// Builds the elements of an array.
+#[must_use]
+pub struct ArrayBuilder<'a, S> {
+    slot: S,
+    values: Vec<Value<'a>>,
+}
+
+impl<'a, S: Slot<'a>> Slot<'a> for ArrayBuilder<'a, S> {
+    type Next = Self;
+
+    fn put(mut self, value: Value<'a>) -> Self {
+        self.values.push(value);
+        self
+    }
+}
+
+impl<'a, S: Slot<'a>> ArrayBuilder<'a, S> {
+    fn element(self) -> JsonBuilder<'a, Self> {
+        JsonBuilder::into(self)
+    }
+
+    pub fn value(self, value: Value<'a>) -> Self {
+        self.element().value(value)
+    }
+
+    pub fn null(self) -> Self {
+        self.element().null()
+    }
+
+    pub fn boolean(self, b: bool) -> Self {
+        self.element().boolean(b)
+    }
+
+    pub fn number(self, n: impl JsonNumber) -> Self {
+        self.element().number(n)
+    }
+
+    pub fn string(self, s: &str) -> Self {
+        self.element().string(s)
+    }
+
+    pub fn object(self) -> ObjectBuilder<'a, Self> {
+        self.element().object()
+    }
+
+    pub fn array(self) -> ArrayBuilder<'a, Self> {
+        self.element().array()
+    }
+
+    /// Ends the array, putting it in place.
+    pub fn end(self) -> S::Next {
+        self.slot.put(Value::Array(self.values))
+    }
+}
+
+/// Numbers accepted by the builders.
+pub trait JsonNumber {
+    /// The JSON text of this number, or `None` if it has none.
+    fn to_json(&self) -> Option<String>;
+}
+
+macro_rules! json_number {
+    ($($t:ty),*) => {
+        $(
+            impl JsonNumber for $t {
+                fn to_json(&self) -> Option<String> {
+                    Some(self.to_string())
+                }
+            }
+        )*
+    };
+}
+
+json_number!(
+    i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize
+);
+
+impl JsonNumber for f64 {
+    fn to_json(&self) -> Option<String> {
+        // `Display` never uses an exponent, which is unreadable for large or small numbers
+        self.is_finite().then(|| match self.abs() {
+            n if n != 0.0 && !(1e-6..1e21).contains(&n) => format!("{self:e}"),
+            _ => self.to_string(),
+        })
+    }
+}
+
+impl JsonNumber for f32 {
+    fn to_json(&self) -> Option<String> {
+        // through the shortest representation of the f32, not of its f64 widening
+        self.is_finite()
+            .then(|| self.to_string().parse::<f64>().ok()?.to_json())
+            .flatten()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mjl::JsonLexer;
+
+    use crate::{builder::JsonBuilder, parse};
+
+    #[test]
+    fn build_values() {
+        let value = JsonBuilder::new()
+            .object()
+            .key("name")
+            .string("say \"hi\"\n")
+            .key("sizes")
+            .array()
+            .number(1)
+            .number(-2.5)
+            .number(1e300)
+            .number(f64::NAN)
+            .number(0.1f32)
+            .object()
+            .end()
+            .array()
+            .boolean(true)
+            .end()
+            .end()
+            .key("none")
+            .null()
+            .end();
+        let expected = parse(JsonLexer::new(
+            r#"{"name": "say \"hi\"\n", "sizes": [1, -2.5, 1e300, null, 0.1, {}, [true]], "none": null}"#,
+        ))
+        .unwrap()
+        .value;
+        assert!(expected.eq_ordered(&value), "{value:?}");
+
+        assert_eq!(
+            crate::Value::Number("3".into()),
+            JsonBuilder::new().number(3u8)
+        );
+    }
+}
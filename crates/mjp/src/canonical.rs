@@ -0,0 +1,246 @@
+//! Canonical JSON text per the JSON Canonicalization Scheme (RFC 8785), so that equal values
+//! have byte-identical text to hash or sign, however they were formatted.
+
+use std::{
+    error::Error,
+    fmt::{Display, Write},
+};
+
+use mjl::JsonLexer;
+
+use crate::{Pair, PathSegment, Value, parse};
+
+/// A value that has no canonical text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CanonicalError {
+    /// The number at the pointer is not a finite IEEE 754 double.
+    Number(String),
+    /// The object at the pointer has several members with the same key, once escapes are
+    /// decoded.
+    DuplicateKey(String),
+    /// The raw value at the pointer is not valid JSON.
+    Raw(String),
+}
+
+impl Display for CanonicalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CanonicalError::Number(pointer) => write!(f, "number out of range at {pointer:?}"),
+            CanonicalError::DuplicateKey(pointer) => write!(f, "duplicate key at {pointer:?}"),
+            CanonicalError::Raw(pointer) => write!(f, "invalid raw JSON at {pointer:?}"),
+        }
+    }
+}
+
+impl Error for CanonicalError {}
+
+impl Value<'_> {
+    /// This value as canonical JSON text: without whitespace, with the members of objects
+    /// ordered by the UTF-16 code units of their keys, strings escaped minimally and numbers
+    /// written as ECMAScript writes doubles, e.g. `1e+30` or `0.002`.
+    ///
+    /// Strings and keys hold the raw text of their literals. Unpaired surrogates are replaced
+    /// with U+FFFD, as by [`mjl::unescape`].
+    pub fn to_canonical_string(&self) -> Result<String, CanonicalError> {
+        let mut out = String::new();
+        write_canonical(self, &mut String::new(), &mut out)?;
+        Ok(out)
+    }
+}
+
+fn write_canonical(
+    value: &Value,
+    pointer: &mut String,
+    out: &mut String,
+) -> Result<(), CanonicalError> {
+    match value {
+        Value::Object(pairs) => {
+            let mut members: Vec<(Vec<u16>, &Pair)> = pairs
+                .iter()
+                .map(|p| (mjl::unescape(&p.key).encode_utf16().collect(), p))
+                .collect();
+            members.sort_by(|(a, _), (b, _)| a.cmp(b));
+            if let Some(pair) = members.windows(2).find(|w| w[0].0 == w[1].0) {
+                PathSegment::Key(&pair[1].1.key).push_to(pointer);
+                return Err(CanonicalError::DuplicateKey(pointer.clone()));
+            }
+            out.push('{');
+            for (i, (_, pair)) in members.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(&mjl::unescape(&pair.key), out);
+                out.push(':');
+                let len = pointer.len();
+                PathSegment::Key(&pair.key).push_to(pointer);
+                write_canonical(&pair.value, pointer, out)?;
+                pointer.truncate(len);
+            }
+            out.push('}');
+        }
+        Value::Array(values) => {
+            out.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                let len = pointer.len();
+                PathSegment::Index(i).push_to(pointer);
+                write_canonical(value, pointer, out)?;
+                pointer.truncate(len);
+            }
+            out.push(']');
+        }
+        Value::Str(s) => write_string(&mjl::unescape(s), out),
+        Value::Number(n) => {
+            let n = n
+                .as_f64()
+                .ok_or_else(|| CanonicalError::Number(pointer.clone()))?;
+            write_number(n, out);
+        }
+        Value::Boolean(b) => write!(out, "{b}").unwrap(),
+        Value::Null => out.push_str("null"),
+        Value::Raw(raw) => {
+            let value = parse(JsonLexer::new(raw.get()))
+                .map_err(|_| CanonicalError::Raw(pointer.clone()))?
+                .value;
+            write_canonical(&value, pointer, out)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes decoded text as a string literal, escaping only quotes, backslashes and control
+/// characters.
+fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{8}' => out.push_str("\\b"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\u{c}' => out.push_str("\\f"),
+            '\r' => out.push_str("\\r"),
+            c if c < ' ' => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Writes a finite double as ECMAScript's `Number.prototype.toString` does.
+fn write_number(n: f64, out: &mut String) {
+    if n == 0.0 {
+        // negative zero included
+        out.push('0');
+        return;
+    }
+    if n < 0.0 {
+        out.push('-');
+    }
+    // the shortest digits that round-trip, as in `1.2345e-7`
+    let scientific = format!("{:e}", n.abs());
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let digits = mantissa.replace('.', "");
+    let k = digits.len() as i32;
+    // the position of the decimal point after the first digits
+    let point = exponent.parse::<i32>().unwrap() + 1;
+    if k <= point && point <= 21 {
+        out.push_str(&digits);
+        out.extend(std::iter::repeat_n('0', (point - k) as usize));
+    } else if 0 < point && point <= 21 {
+        let (int, frac) = digits.split_at(point as usize);
+        write!(out, "{int}.{frac}").unwrap();
+    } else if -6 < point && point <= 0 {
+        out.push_str("0.");
+        out.extend(std::iter::repeat_n('0', -point as usize));
+        out.push_str(&digits);
+    } else {
+        let (first, rest) = digits.split_at(1);
+        out.push_str(first);
+        if !rest.is_empty() {
+            write!(out, ".{rest}").unwrap();
+        }
+        let sign = if point > 0 { '+' } else { '-' };
+        write!(out, "e{sign}{}", (point - 1).abs()).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mjl::JsonLexer;
+
+    use crate::{Value, canonical::CanonicalError, parse};
+
+    fn canonical(input: &str) -> Result<String, CanonicalError> {
+        parse(JsonLexer::new(input))
+            .unwrap()
+            .value
+            .to_canonical_string()
+    }
+
+    #[test]
+    fn rfc_8785_examples() {
+        let input = r#"{
+            "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+            "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+            "literals": [null, true, false]
+        }"#;
+        assert_eq!(
+            r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#,
+            canonical(input).unwrap()
+        );
+
+        let input = r#"{"\u20ac": 1, "\r": 2, "\ufb33": 3, "1": 4, "\ud83d\ude00": 5, "\u0080": 6,
+            "\u00f6": 7}"#;
+        let keys: Vec<String> = match parse(JsonLexer::new(&canonical(input).unwrap()))
+            .unwrap()
+            .value
+        {
+            Value::Object(pairs) => pairs.into_iter().map(|p| p.key.into_owned()).collect(),
+            _ => unreachable!(),
+        };
+        assert_eq!(vec!["\\r", "1", "\u{80}", "ö", "€", "😀", "\u{fb33}"], keys);
+    }
+
+    #[test]
+    fn numbers() {
+        let cases = [
+            ("0", "0"),
+            ("-0.0", "0"),
+            ("1", "1"),
+            ("-1.5", "-1.5"),
+            ("100", "100"),
+            ("1e20", "100000000000000000000"),
+            ("1e21", "1e+21"),
+            ("123e-8", "0.00000123"),
+            ("1e-7", "1e-7"),
+            ("-1.25e-10", "-1.25e-10"),
+            ("9007199254740993", "9007199254740992"),
+            ("5e-324", "5e-324"),
+            ("1.7976931348623157e308", "1.7976931348623157e+308"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(expected, canonical(input).unwrap(), "{input}");
+        }
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            Err(CanonicalError::Number("/a/1".to_string())),
+            canonical(r#"{"a": [1, 1e400]}"#)
+        );
+        assert_eq!(
+            Err(CanonicalError::DuplicateKey("/b".to_string())),
+            canonical(r#"{"a": 1, "\u0062": 2, "b": 3}"#)
+        );
+        // only control characters below U+0020 are escaped
+        assert_eq!(
+            "[\"\\u001f\u{7f}\u{2028}\"]",
+            canonical("[\"\\u001F\\u007f\u{2028}\"]").unwrap()
+        );
+    }
+}
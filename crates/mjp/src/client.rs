@@ -0,0 +1,189 @@
+//! Parsing of HTTP client responses, behind the `reqwest` and `ureq` features.
+//!
+//! Bodies are read up to [`ParseOptions::max_bytes`] and no further, so a hostile or broken
+//! server can't exhaust memory by sending an endless body.
+
+use std::{error::Error, fmt::Display};
+
+use crate::{FromJson, Json, JsonParseError, ParseOptions, from_json::FromJsonError};
+
+/// The limit on the size of bodies applied by the `json_mjd` methods, 10 MiB.
+pub const DEFAULT_MAX_BYTES: usize = 10 << 20;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResponseError {
+    /// The body could not be read, exceeded the limit or is not valid JSON.
+    Parse(JsonParseError),
+    /// The body could not be converted.
+    Convert(FromJsonError),
+}
+
+impl Display for ResponseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResponseError::Parse(e) => e.fmt(f),
+            ResponseError::Convert(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for ResponseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ResponseError::Parse(e) => Some(e),
+            ResponseError::Convert(e) => Some(e),
+        }
+    }
+}
+
+impl From<JsonParseError> for ResponseError {
+    fn from(e: JsonParseError) -> Self {
+        ResponseError::Parse(e)
+    }
+}
+
+/// The options of the `json_mjd` methods.
+fn default_options() -> ParseOptions {
+    ParseOptions {
+        max_bytes: Some(DEFAULT_MAX_BYTES),
+        ..Default::default()
+    }
+}
+
+fn convert<T: FromJson>(json: Result<Json, JsonParseError>) -> Result<T, ResponseError> {
+    T::from_json(&json?.value).map_err(ResponseError::Convert)
+}
+
+#[cfg(feature = "reqwest")]
+pub mod reqwest {
+    use std::{future::Future, io};
+
+    use super::{ResponseError, convert, default_options};
+    use crate::{FromJson, ParseOptions, read_document, read_limit};
+
+    /// Parsing of the bodies of [`reqwest::Response`]s.
+    pub trait ResponseExt {
+        /// Parses the body and converts it into a `T`, reading at most
+        /// [`DEFAULT_MAX_BYTES`](super::DEFAULT_MAX_BYTES).
+        fn json_mjd<T: FromJson>(self) -> impl Future<Output = Result<T, ResponseError>> + Send;
+
+        /// Like [`ResponseExt::json_mjd`], with the given options and their limits.
+        fn json_mjd_with<T: FromJson>(
+            self,
+            options: ParseOptions,
+        ) -> impl Future<Output = Result<T, ResponseError>> + Send;
+    }
+
+    impl ResponseExt for ::reqwest::Response {
+        fn json_mjd<T: FromJson>(self) -> impl Future<Output = Result<T, ResponseError>> + Send {
+            self.json_mjd_with(default_options())
+        }
+
+        async fn json_mjd_with<T: FromJson>(
+            mut self,
+            options: ParseOptions,
+        ) -> Result<T, ResponseError> {
+            let limit = usize::try_from(read_limit(&options)).unwrap_or(usize::MAX);
+            let mut bytes = Vec::new();
+            let result = loop {
+                match self.chunk().await {
+                    Ok(Some(chunk)) => {
+                        bytes.extend_from_slice(&chunk);
+                        if bytes.len() >= limit {
+                            bytes.truncate(limit);
+                            break Ok(limit);
+                        }
+                    }
+                    Ok(None) => break Ok(bytes.len()),
+                    Err(e) => break Err(io::Error::other(e)),
+                }
+            };
+            convert(read_document(result, &bytes, options))
+        }
+    }
+}
+
+#[cfg(feature = "ureq")]
+pub mod ureq {
+    use ::ureq::{Body, http::Response};
+
+    use super::{ResponseError, convert, default_options};
+    use crate::{FromJson, ParseOptions, from_reader};
+
+    /// Parsing of the bodies of [`ureq`] responses.
+    pub trait ResponseExt {
+        /// Parses the body and converts it into a `T`, reading at most
+        /// [`DEFAULT_MAX_BYTES`](super::DEFAULT_MAX_BYTES).
+        fn json_mjd<T: FromJson>(self) -> Result<T, ResponseError>;
+
+        /// Like [`ResponseExt::json_mjd`], with the given options and their limits.
+        fn json_mjd_with<T: FromJson>(self, options: ParseOptions) -> Result<T, ResponseError>;
+    }
+
+    impl ResponseExt for Response<Body> {
+        fn json_mjd<T: FromJson>(self) -> Result<T, ResponseError> {
+            self.json_mjd_with(default_options())
+        }
+
+        fn json_mjd_with<T: FromJson>(self, options: ParseOptions) -> Result<T, ResponseError> {
+            convert(from_reader(self.into_body().into_reader(), options))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(feature = "reqwest")]
+    #[tokio::test]
+    async fn reqwest_response() {
+        use crate::{
+            ErrorKind, ParseOptions,
+            client::{ResponseError, reqwest::ResponseExt},
+        };
+
+        let response = |body: &'static str| reqwest::Response::from(http::Response::new(body));
+        assert_eq!(
+            vec![1, 2],
+            response("[1, 2]").json_mjd::<Vec<u8>>().await.unwrap()
+        );
+
+        let e = response(r#"[1, "2"]"#).json_mjd::<Vec<u8>>().await;
+        assert!(matches!(e, Err(ResponseError::Convert(_))));
+        let options = ParseOptions {
+            max_bytes: Some(4),
+            ..Default::default()
+        };
+        match response("[1, 2]").json_mjd_with::<Vec<u8>>(options).await {
+            Err(ResponseError::Parse(e)) => assert_eq!(ErrorKind::LimitExceeded, e.kind()),
+            r => panic!("not a parse error: {r:?}"),
+        }
+    }
+
+    #[cfg(feature = "ureq")]
+    #[test]
+    fn ureq_response() {
+        use ureq::{Body, http::Response};
+
+        use crate::{
+            ErrorKind, ParseOptions,
+            client::{ResponseError, ureq::ResponseExt},
+        };
+
+        let response = |body: &str| Response::new(Body::builder().data(body));
+        assert_eq!(
+            vec![1, 2],
+            response("[1, 2]").json_mjd::<Vec<u8>>().unwrap()
+        );
+
+        let e = response("[1,").json_mjd::<Vec<u8>>().unwrap_err();
+        assert!(matches!(e, ResponseError::Parse(_)));
+        let options = ParseOptions {
+            max_bytes: Some(4),
+            ..Default::default()
+        };
+        match response("[1, 2]").json_mjd_with::<Vec<u8>>(options) {
+            Err(ResponseError::Parse(e)) => assert_eq!(ErrorKind::LimitExceeded, e.kind()),
+            r => panic!("not a parse error: {r:?}"),
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Generation of the Rust code building a value, for turning captured documents, such as API
+//! responses, into test fixtures.
+
+use crate::{Number, Value, builder::JsonNumber};
+
+/// Returns a Rust expression building `value` with a [`JsonBuilder`](crate::builder::JsonBuilder),
+/// one call per line, as rustfmt lays out chains:
+///
+/// ```
+/// use mjl::JsonLexer;
+/// use mjp::{codegen::to_rust, parse};
+///
+/// let value = parse(JsonLexer::new(r#"{"id": 7, "tags": ["a"]}"#)).unwrap().value;
+/// assert_eq!(
+///     "mjp::builder::JsonBuilder::new()
+///     .object()
+///     .key(\"id\")
+///     .number(7)
+///     .key(\"tags\")
+///     .array()
+///     .string(\"a\")
+///     .end()
+///     .end()",
+///     to_rust(&value)
+/// );
+/// ```
+///
+/// Strings and keys are written decoded and escaped again by the builder, so an escape may be
+/// spelled differently in the built value, as `\u0041` becomes `A`. Numbers that no Rust literal
+/// spells the same, such as `1E5` or those too large for a `u64`, are written as their JSON text.
+pub fn to_rust(value: &Value) -> String {
+    let mut out = "mjp::builder::JsonBuilder::new()".to_string();
+    write_calls(value, &mut out);
+    out
+}
+
+fn write_calls(value: &Value, out: &mut String) {
+    let call = match value {
+        Value::Object(pairs) => {
+            out.push_str("\n    .object()");
+            for pair in pairs {
+                out.push_str(&format!("\n    .key({:?})", mjl::unescape(&pair.key)));
+                write_calls(&pair.value, out);
+            }
+            "end()".to_string()
+        }
+        Value::Array(values) => {
+            out.push_str("\n    .array()");
+            for value in values {
+                write_calls(value, out);
+            }
+            "end()".to_string()
+        }
+        Value::Str(s) => format!("string({:?})", mjl::unescape(s)),
+        Value::Number(n) => match literal(n) {
+            Some(literal) => format!("number({literal})"),
+            None => format!("value(mjp::Value::Number({n:?}.into()))"),
+        },
+        Value::Boolean(b) => format!("boolean({b})"),
+        Value::Null => "null()".to_string(),
+        Value::Raw(raw) => {
+            return write_calls(&raw.parse().expect("raw values are valid JSON"), out);
+        }
+    };
+    out.push_str("\n    .");
+    out.push_str(&call);
+}
+
+/// The Rust literal of a number, if one builds the same JSON text. Integer literals that don't
+/// fit the `i32` an unsuffixed one defaults to are suffixed.
+fn literal(n: &Number) -> Option<String> {
+    let text = n.as_str();
+    if n.is_i64() {
+        let i = n.as_i64()?;
+        let suffix = if i32::try_from(i).is_ok() { "" } else { "i64" };
+        return (i.to_string() == text).then(|| format!("{text}{suffix}"));
+    }
+    if n.is_u64() {
+        let u = n.as_u64()?;
+        return (u.to_string() == text).then(|| format!("{text}u64"));
+    }
+    // without a dot or an exponent, the literal would be an integer
+    let float = n.is_f64() && text.contains(['.', 'e']) && n.as_f64()?.to_json()? == text;
+    float.then(|| text.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use mjl::JsonLexer;
+
+    use crate::{codegen::to_rust, parse};
+
+    #[test]
+    fn generate_builders() {
+        let input = r#"{"name": "say \"hi\"\n", "n": [-3, 3000000000, 18446744073709551615, 2.5, 1e300, 1E5, -0, 0.10, 99999999999999999999], "ok": true, "none": null, "\u00e9": {}}"#;
+        let value = parse(JsonLexer::new(input)).unwrap().value;
+        let code = to_rust(&value);
+        assert_eq!(
+            r#"mjp::builder::JsonBuilder::new()
+    .object()
+    .key("name")
+    .string("say \"hi\"\n")
+    .key("n")
+    .array()
+    .number(-3)
+    .number(3000000000i64)
+    .number(18446744073709551615u64)
+    .number(2.5)
+    .number(1e300)
+    .value(mjp::Value::Number("1E5".into()))
+    .value(mjp::Value::Number("-0".into()))
+    .value(mjp::Value::Number("0.10".into()))
+    .value(mjp::Value::Number("99999999999999999999".into()))
+    .end()
+    .key("ok")
+    .boolean(true)
+    .key("none")
+    .null()
+    .key("é")
+    .object()
+    .end()
+    .end()"#,
+            code
+        );
+
+        // the generated code builds the value, up to the spelling of escapes
+        let built = crate::builder::JsonBuilder::new()
+            .object()
+            .key("name")
+            .string("say \"hi\"\n")
+            .key("n")
+            .array()
+            .number(-3)
+            .number(3000000000i64)
+            .number(18446744073709551615u64)
+            .number(2.5)
+            .number(1e300)
+            .value(crate::Value::Number("1E5".into()))
+            .value(crate::Value::Number("-0".into()))
+            .value(crate::Value::Number("0.10".into()))
+            .value(crate::Value::Number("99999999999999999999".into()))
+            .end()
+            .key("ok")
+            .boolean(true)
+            .key("none")
+            .null()
+            .key("é")
+            .object()
+            .end()
+            .end();
+        let expected = input.replace(r"\u00e9", "é");
+        assert!(
+            parse(JsonLexer::new(&expected))
+                .unwrap()
+                .value
+                .eq_ordered(&built)
+        );
+    }
+}
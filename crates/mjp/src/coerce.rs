@@ -0,0 +1,116 @@
+//! Lenient numeric access to values, for data sources that stringify numbers or use booleans as
+//! flags.
+
+use crate::{BooleanVal, Number, Value, number::Decimal};
+
+/// Which values other than numbers the lenient numeric accessors convert.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Coercion {
+    /// Accept strings holding a number, such as `"42"` or `" -1.5e3 "`.
+    pub strings: bool,
+    /// Accept `true` as 1 and `false` as 0.
+    pub booleans: bool,
+}
+
+impl Coercion {
+    /// Accept numbers only.
+    pub const NONE: Coercion = Coercion {
+        strings: false,
+        booleans: false,
+    };
+    /// Accept numeric strings and booleans.
+    pub const ALL: Coercion = Coercion {
+        strings: true,
+        booleans: true,
+    };
+}
+
+impl Value<'_> {
+    /// Returns the nearest `f64` to this value, if it is a number or is accepted by `coercion`.
+    pub fn as_f64_lossy(&self, coercion: Coercion) -> Option<f64> {
+        match self.numeric_text(coercion)? {
+            Numeric::Number(n) => n.as_f64(),
+            Numeric::Text(n) => n.parse().ok(),
+            Numeric::Boolean(b) => Some(if b { 1.0 } else { 0.0 }),
+        }
+    }
+
+    /// Returns this value as an `i64`, if it is a number or is accepted by `coercion`, and is an
+    /// integer in range. Numbers written with a fraction or exponent, such as `42.0` or `4.2e1`,
+    /// are accepted when their value is integral.
+    pub fn as_i64_coerce(&self, coercion: Coercion) -> Option<i64> {
+        match self.numeric_text(coercion)? {
+            Numeric::Number(n) => n.as_i64(),
+            Numeric::Text(n) => n.parse().ok().or_else(|| Decimal::parse(n)?.to_i64()),
+            Numeric::Boolean(b) => Some(b as i64),
+        }
+    }
+
+    fn numeric_text(&self, coercion: Coercion) -> Option<Numeric<'_>> {
+        match self {
+            Value::Number(n) => Some(Numeric::Number(n)),
+            Value::Str(s) if coercion.strings => {
+                let s = s.trim();
+                is_number(s).then_some(Numeric::Text(s))
+            }
+            Value::Boolean(b) if coercion.booleans => {
+                Some(Numeric::Boolean(*b == BooleanVal::True))
+            }
+            _ => None,
+        }
+    }
+}
+
+enum Numeric<'s> {
+    Number(&'s Number<'s>),
+    Text(&'s str),
+    Boolean(bool),
+}
+
+/// Whether `s` is a JSON number, so that strings like `"inf"` or `"0x10"` are not accepted.
+fn is_number(s: &str) -> bool {
+    let mut lexer = mjl::JsonLexer::new(s);
+    matches!(lexer.next_token(), Ok(Some(mjl::Token::Number(..)))) && lexer.byte_offset == s.len()
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+
+    use crate::{BooleanVal, Value, coerce::Coercion};
+
+    fn string(s: &str) -> Value<'_> {
+        Value::Str(Cow::Borrowed(s))
+    }
+
+    #[test]
+    fn coerce_numbers() {
+        let number = Value::Number("4.2e1".into());
+        assert_eq!(Some(42.0), number.as_f64_lossy(Coercion::NONE));
+        assert_eq!(Some(42), number.as_i64_coerce(Coercion::NONE));
+        assert_eq!(
+            None,
+            Value::Number("1.5".into()).as_i64_coerce(Coercion::NONE)
+        );
+        assert_eq!(
+            None,
+            Value::Number("1e19".into()).as_i64_coerce(Coercion::NONE)
+        );
+        // exactly, beyond the integers an f64 holds
+        assert_eq!(
+            Some(9007199254740993),
+            string("9007199254740993.0").as_i64_coerce(Coercion::ALL)
+        );
+
+        assert_eq!(None, string("42").as_i64_coerce(Coercion::NONE));
+        assert_eq!(Some(42), string(" 42 ").as_i64_coerce(Coercion::ALL));
+        assert_eq!(Some(-1500.0), string("-1.5e3").as_f64_lossy(Coercion::ALL));
+        assert_eq!(None, string("inf").as_f64_lossy(Coercion::ALL));
+        assert_eq!(None, string("0x10").as_i64_coerce(Coercion::ALL));
+
+        let flag = Value::Boolean(BooleanVal::True);
+        assert_eq!(None, flag.as_i64_coerce(Coercion::NONE));
+        assert_eq!(Some(1), flag.as_i64_coerce(Coercion::ALL));
+        assert_eq!(None, Value::Null.as_f64_lossy(Coercion::ALL));
+    }
+}
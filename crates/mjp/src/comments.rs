@@ -0,0 +1,169 @@
+//! Comments of JSONC documents, attached to the nodes they document so they survive a round
+//! trip through the DOM.
+//!
+//! A comment leads the node that follows it, unless it starts on the line where the previous
+//! sibling ends, in which case it trails that sibling. Comments after the last child of a
+//! container trail that child, or the container itself if it is empty.
+
+use std::{borrow::Cow, collections::BTreeMap};
+
+use crate::{JsonParser, PathSegment};
+
+/// Comments attached to [`Json`](crate::Json) nodes, keyed by the JSON pointer of the node.
+pub type CommentMap<'a> = BTreeMap<String, Comments<'a>>;
+
+/// The comments attached to a node, including their delimiters (`// x`, `/* x */`).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Comments<'a> {
+    /// Comments on the lines before the node.
+    pub leading: Vec<Cow<'a, str>>,
+    /// Comments after the node, starting on the line it ends on.
+    pub trailing: Vec<Cow<'a, str>>,
+}
+
+pub(crate) fn into_owned(comments: CommentMap<'_>) -> CommentMap<'static> {
+    let owned = |texts: Vec<Cow<'_, str>>| {
+        texts
+            .into_iter()
+            .map(|t| Cow::Owned(t.into_owned()))
+            .collect()
+    };
+    comments
+        .into_iter()
+        .map(|(pointer, c)| {
+            let comments = Comments {
+                leading: owned(c.leading),
+                trailing: owned(c.trailing),
+            };
+            (pointer, comments)
+        })
+        .collect()
+}
+
+impl<'a> JsonParser<'a> {
+    /// Attaches the pending comments to the document root, before or after the root value.
+    pub(crate) fn attach_root_comments(&mut self, trailing: bool) {
+        if self.pending_comments.is_empty() {
+            return;
+        }
+        let comments = self.comments.entry(String::new()).or_default();
+        let list = if trailing {
+            &mut comments.trailing
+        } else {
+            &mut comments.leading
+        };
+        list.extend(
+            self.pending_comments
+                .drain(..)
+                .map(|(_, text)| Cow::Borrowed(text)),
+        );
+    }
+
+    /// Attaches the pending comments between two children of the current container: `previous`
+    /// with its end offset, and `next`, which is `None` at the closing bracket.
+    pub(crate) fn attach_comments(
+        &mut self,
+        previous: Option<(PathSegment<'a>, usize)>,
+        next: Option<PathSegment<'a>>,
+    ) {
+        if self.pending_comments.is_empty() {
+            return;
+        }
+        let container = self.pointer();
+        let child = |segment: PathSegment| {
+            let mut pointer = container.clone();
+            segment.push_to(&mut pointer);
+            pointer
+        };
+
+        for (offset, text) in std::mem::take(&mut self.pending_comments) {
+            let (pointer, trailing) = match (previous, next) {
+                (Some((segment, end)), _) if !self.lexer.input[end..offset].contains('\n') => {
+                    (child(segment), true)
+                }
+                (_, Some(segment)) => (child(segment), false),
+                (Some((segment, _)), None) => (child(segment), true),
+                (None, None) => (container.clone(), true),
+            };
+            let comments = self.comments.entry(pointer).or_default();
+            let list = if trailing {
+                &mut comments.trailing
+            } else {
+                &mut comments.leading
+            };
+            list.push(Cow::Borrowed(text));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+
+    use mjl::{CommentMode, JsonLexer, LexerOptions};
+
+    use crate::{Comments, ParseOptions, parse_with};
+
+    #[test]
+    fn attach_comments() {
+        let input = r#"// config
+{
+  // the name
+  "name": "x", // trailing
+  "list": [
+    1, /* one */
+    // two
+    2
+    // end of list
+  ],
+  "empty": {
+    // nothing
+  }
+} // done"#;
+        let lexer = JsonLexer::with_options(
+            input,
+            LexerOptions {
+                comments: CommentMode::Emit,
+                ..Default::default()
+            },
+        );
+        let options = ParseOptions {
+            attach_comments: true,
+            ..Default::default()
+        };
+        let json = parse_with(lexer, options).unwrap();
+
+        let comments = |leading: &[&'static str], trailing: &[&'static str]| Comments {
+            leading: leading.iter().copied().map(Cow::Borrowed).collect(),
+            trailing: trailing.iter().copied().map(Cow::Borrowed).collect(),
+        };
+        let attached: Vec<_> = json
+            .comments
+            .iter()
+            .map(|(p, c)| (p.as_str(), c.clone()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("", comments(&["// config"], &["// done"])),
+                ("/empty", comments(&[], &["// nothing"])),
+                ("/list/0", comments(&[], &["/* one */"])),
+                ("/list/1", comments(&["// two"], &["// end of list"])),
+                ("/name", comments(&["// the name"], &["// trailing"])),
+            ],
+            attached
+        );
+    }
+
+    #[test]
+    fn comments_are_skipped_by_default() {
+        let lexer = JsonLexer::with_options(
+            "[1 /* x */]",
+            LexerOptions {
+                comments: CommentMode::Emit,
+                ..Default::default()
+            },
+        );
+        let json = parse_with(lexer, ParseOptions::default()).unwrap();
+        assert!(json.comments.is_empty());
+    }
+}
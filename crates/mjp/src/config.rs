@@ -0,0 +1,324 @@
+//! Layering of configuration sources, such as defaults, a file, and settings derived from the
+//! environment and the command line, remembering which source each setting came from.
+
+use std::collections::BTreeMap;
+
+use mjl::JsonLexer;
+
+use crate::{Object, Pair, Value, builder::JsonBuilder, parse};
+
+/// A configuration merged from layers by [`layer`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layered<'a> {
+    pub value: Value<'a>,
+    /// The index of the layer each setting came from, keyed by JSON pointer.
+    sources: BTreeMap<String, usize>,
+}
+
+impl<'a> Layered<'a> {
+    /// The index of the layer the setting at `pointer` came from. Settings are the values of
+    /// `value` that aren't objects: objects are merged from several layers, while any other
+    /// value, arrays included, comes from a single one.
+    pub fn source(&self, pointer: &str) -> Option<usize> {
+        self.sources.get(pointer).copied()
+    }
+
+    /// The pointers to all settings with the index of the layer each came from, in the order of
+    /// their pointers.
+    pub fn sources(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.sources
+            .iter()
+            .map(|(pointer, i)| (pointer.as_str(), *i))
+    }
+}
+
+/// Merges `layers`, from lowest to highest precedence, with JSON Merge Patch (RFC 7386)
+/// semantics: each layer is applied as a merge patch over the previous ones, so objects are
+/// merged member by member, `null` removes a setting, and any other value replaces it.
+pub fn layer<'a>(layers: &[Value<'a>]) -> Layered<'a> {
+    let mut value = Value::Null;
+    for layer in layers {
+        merge_patch(&mut value, layer);
+    }
+    let mut settings = Vec::new();
+    collect_settings(&value, &mut String::new(), &mut settings);
+    // the last layer that set a setting is the one it came from: a later layer replacing one
+    // of its parents would have removed it
+    let sources = settings
+        .into_iter()
+        .filter_map(|pointer| {
+            let source = layers.iter().rposition(|layer| {
+                layer
+                    .pointer(&pointer)
+                    .is_some_and(|v| !matches!(v, Value::Object(_) | Value::Null))
+            })?;
+            Some((pointer, source))
+        })
+        .collect();
+    Layered { value, sources }
+}
+
+/// How the values of environment variables are typed by [`env_overlay`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EnvTypes {
+    /// Every value is a string.
+    Strings,
+    /// Values that are JSON booleans or numbers, such as `true` or `8080`, have that type, and
+    /// other values are strings.
+    #[default]
+    Scalars,
+    /// Values that are JSON documents, such as `[1, 2]` or `null`, have that value, and other
+    /// values are strings. `null` removes a setting when the overlay is layered.
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvOptions {
+    /// Prefix of the names of the variables to read, such as `APP`. Only variables whose name
+    /// is the prefix and a separator followed by a path are read, and an empty prefix reads
+    /// every variable.
+    pub prefix: String,
+    /// Separator of the segments of the path of a setting in variable names.
+    pub separator: String,
+    /// Whether keys are lowercased, as `APP__SERVER__PORT` sets `/server/port`.
+    pub lowercase: bool,
+    pub types: EnvTypes,
+}
+
+impl Default for EnvOptions {
+    fn default() -> Self {
+        EnvOptions {
+            prefix: String::new(),
+            separator: "__".to_string(),
+            lowercase: true,
+            types: EnvTypes::default(),
+        }
+    }
+}
+
+/// Builds the overlay of the settings set by environment variables `vars`, given as pairs of a
+/// name and a value, for [`layer`]: `APP__SERVER__PORT=8080` becomes
+/// `{"server": {"port": 8080}}` with the prefix `APP`.
+///
+/// Variables are applied in the order given, so a later variable setting `/a` replaces the
+/// settings below `/a` of earlier ones. Variables with an empty segment in their path are
+/// ignored.
+pub fn env_overlay<K: AsRef<str>, V: AsRef<str>>(
+    vars: impl IntoIterator<Item = (K, V)>,
+    options: &EnvOptions,
+) -> Value<'static> {
+    let mut overlay = Value::Object(Object::new());
+    for (name, text) in vars {
+        let name = name.as_ref();
+        let path = if options.prefix.is_empty() {
+            Some(name)
+        } else {
+            name.strip_prefix(options.prefix.as_str())
+                .and_then(|rest| rest.strip_prefix(options.separator.as_str()))
+        };
+        let Some(path) = path else {
+            continue;
+        };
+        let segments: Vec<&str> = path.split(options.separator.as_str()).collect();
+        if segments.iter().any(|segment| segment.is_empty()) {
+            continue;
+        }
+        let mut patch = env_value(text.as_ref(), options.types);
+        for segment in segments.into_iter().rev() {
+            let key = if options.lowercase {
+                segment.to_lowercase()
+            } else {
+                segment.to_string()
+            };
+            patch = JsonBuilder::new().object().key(&key).value(patch).end();
+        }
+        merge_patch(&mut overlay, &patch);
+    }
+    overlay
+}
+
+/// Like [`env_overlay`], reading the variables of the process, in the order of their names.
+pub fn env_overlay_from_process(options: &EnvOptions) -> Value<'static> {
+    let mut vars: Vec<(String, String)> = std::env::vars().collect();
+    vars.sort();
+    env_overlay(vars, options)
+}
+
+fn env_value(text: &str, types: EnvTypes) -> Value<'static> {
+    let parsed = match types {
+        EnvTypes::Strings => None,
+        _ => parse(JsonLexer::new(text)).ok().map(|json| json.value),
+    };
+    match parsed {
+        Some(value @ (Value::Boolean(_) | Value::Number(_))) => value.into_owned(),
+        Some(value) if types == EnvTypes::Json => value.into_owned(),
+        _ => JsonBuilder::new().string(text),
+    }
+}
+
+/// Applies `patch` to `target` as a JSON merge patch.
+fn merge_patch<'a>(target: &mut Value<'a>, patch: &Value<'a>) {
+    let Value::Object(members) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !matches!(target, Value::Object(_)) {
+        *target = Value::Object(Object::new());
+    }
+    let Value::Object(pairs) = target else {
+        unreachable!()
+    };
+    for member in members {
+        let existing = pairs.position(&member.key);
+        match (existing, &member.value) {
+            (Some(i), Value::Null) => {
+                pairs.remove(i);
+            }
+            (None, Value::Null) => {}
+            (Some(i), value) => merge_patch(&mut pairs[i].value, value),
+            (None, value) => {
+                let mut new = Value::Null;
+                merge_patch(&mut new, value);
+                pairs.push(Pair {
+                    key: member.key.clone(),
+                    value: new,
+                });
+            }
+        }
+    }
+}
+
+/// Pushes the pointers to the values of `value` that aren't objects.
+fn collect_settings(value: &Value, path: &mut String, settings: &mut Vec<String>) {
+    match value {
+        Value::Object(pairs) => {
+            for pair in pairs {
+                let len = path.len();
+                path.push('/');
+                path.push_str(&pair.key.replace('~', "~0").replace('/', "~1"));
+                collect_settings(&pair.value, path, settings);
+                path.truncate(len);
+            }
+        }
+        _ => settings.push(path.clone()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mjl::JsonLexer;
+
+    use crate::{
+        Value,
+        config::{EnvOptions, EnvTypes, env_overlay, layer},
+        parse,
+    };
+
+    fn value(input: &str) -> Value<'_> {
+        parse(JsonLexer::new(input)).unwrap().value
+    }
+
+    #[test]
+    fn layer_sources() {
+        let defaults = value(
+            r#"{"server": {"host": "localhost", "port": 80, "tls": {"cert": "a.pem"}},
+                "log": {"level": "info", "targets": ["stderr"]}, "a/b": 1}"#,
+        );
+        let file =
+            value(r#"{"server": {"port": 8080, "tls": null}, "log": {"targets": ["file"]}}"#);
+        let env = value(r#"{"server": {"host": "0.0.0.0", "tls": {"key": "k.pem"}}}"#);
+        let cli = value(r#"{"log": {"level": "debug"}, "a/b": null, "extra": {}}"#);
+        let config = layer(&[defaults, file, env, cli]);
+
+        assert_eq!(
+            value(
+                r#"{"server": {"host": "0.0.0.0", "port": 8080, "tls": {"key": "k.pem"}},
+                    "log": {"level": "debug", "targets": ["file"]}, "extra": {}}"#
+            ),
+            config.value
+        );
+        assert_eq!(
+            vec![
+                ("/log/level", 3),
+                ("/log/targets", 1),
+                ("/server/host", 2),
+                ("/server/port", 1),
+                ("/server/tls/key", 2),
+            ],
+            config.sources().collect::<Vec<_>>()
+        );
+        assert_eq!(Some(0), layer(&[value(r#"{"a~b": 1}"#)]).source("/a~0b"));
+        assert_eq!(None, config.source("/server"));
+
+        // a layer that isn't an object replaces everything below it
+        let config = layer(&[
+            value(r#"{"a": 1}"#),
+            value("[2]"),
+            value(r#"{"b": {"c": null}}"#),
+        ]);
+        assert_eq!(value(r#"{"b": {}}"#), config.value);
+        assert_eq!(0, config.sources().count());
+        assert_eq!(Value::Null, layer(&[]).value);
+    }
+
+    #[test]
+    fn overlay_env_vars() {
+        let vars = [
+            ("APP__SERVER__PORT", "8080"),
+            ("APP__SERVER__HOST", "0.0.0.0"),
+            ("APP__LOG__JSON", "true"),
+            ("APP__LOG__TARGETS", r#"["file"]"#),
+            ("APP__NAME", "say \"hi\""),
+            ("APPLICATION__X", "1"),
+            ("APP__", "1"),
+            ("APP__A____B", "1"),
+            ("HOME", "/root"),
+        ];
+        let options = EnvOptions {
+            prefix: "APP".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            value(
+                r#"{"server": {"port": 8080, "host": "0.0.0.0"},
+                    "log": {"json": true, "targets": "[\"file\"]"}, "name": "say \"hi\""}"#
+            ),
+            env_overlay(vars, &options)
+        );
+
+        let json = EnvOptions {
+            types: EnvTypes::Json,
+            lowercase: false,
+            ..options.clone()
+        };
+        assert_eq!(
+            value(r#"{"LOG": {"TARGETS": ["file"]}, "SERVER": "x"}"#),
+            env_overlay(
+                [
+                    ("APP__LOG__TARGETS", r#"["file"]"#),
+                    ("APP__SERVER__PORT", "1"),
+                    ("APP__SERVER", "x"),
+                ],
+                &json
+            )
+        );
+        let strings = EnvOptions {
+            types: EnvTypes::Strings,
+            ..options
+        };
+        assert_eq!(
+            value(r#"{"port": "8080"}"#),
+            env_overlay([("APP__PORT", "8080")], &strings)
+        );
+
+        // twelve-factor configuration: the environment overrides the file
+        let file = value(r#"{"server": {"port": 80, "host": "localhost"}}"#);
+        let env = env_overlay([("SERVER__PORT", "8080")], &EnvOptions::default());
+        let config = layer(&[file, env]);
+        assert_eq!(
+            value(r#"{"server": {"port": 8080, "host": "localhost"}}"#),
+            config.value
+        );
+        assert_eq!(Some(1), config.source("/server/port"));
+    }
+}
@@ -0,0 +1,225 @@
+//! Rendering of the differences between two values, for assertion failures.
+
+use std::io::IsTerminal;
+
+use crate::{
+    PathSegment, Value,
+    ser::{PrettyOptions, WriteOptions, write_value},
+};
+
+/// Lines of unchanged context shown around each change.
+const CONTEXT: usize = 3;
+
+/// Asserts that two [`Value`]s are equal, like `assert_eq!`, comparing objects regardless of
+/// member order. On failure, the panic message shows a unified diff of the differing subtrees
+/// only, rather than both documents.
+#[macro_export]
+macro_rules! assert_json_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if left != right {
+                    panic!(
+                        "assertion `left == right` failed\n{}",
+                        $crate::diff::render_diff(left, right, $crate::diff::use_color())
+                    );
+                }
+            }
+        }
+    };
+}
+
+/// Renders the differences between `left` and `right` as unified diffs of the smallest differing
+/// subtrees, each under the JSON pointer to it, with `-` lines for `left` and `+` lines for
+/// `right`. Removed and added lines are colored red and green with `color`.
+pub fn render_diff(left: &Value, right: &Value, color: bool) -> String {
+    let mut diffs = Vec::new();
+    collect(Some(left), Some(right), &mut String::new(), &mut diffs);
+
+    let mut out = String::new();
+    for (path, left, right) in diffs {
+        let path = if path.is_empty() { "(root)" } else { &path };
+        out.push_str(&format!("at {path}:\n"));
+        render_lines(&lines(left), &lines(right), color, &mut out);
+    }
+    out
+}
+
+/// Whether [`assert_json_eq!`] colors its diffs: when stderr is a terminal and `NO_COLOR` isn't
+/// set.
+pub fn use_color() -> bool {
+    std::env::var_os("NO_COLOR").is_none() && std::io::stderr().is_terminal()
+}
+
+type Difference<'v, 'a> = (String, Option<&'v Value<'a>>, Option<&'v Value<'a>>);
+
+fn collect<'v, 'a>(
+    left: Option<&'v Value<'a>>,
+    right: Option<&'v Value<'a>>,
+    path: &mut String,
+    diffs: &mut Vec<Difference<'v, 'a>>,
+) {
+    if left == right {
+        return;
+    }
+    let parent = path.len();
+    match (left, right) {
+        (Some(Value::Object(left)), Some(Value::Object(right))) => {
+            let keys = left
+                .iter()
+                .chain(right.iter().filter(|p| !left.contains_key(&p.key)))
+                .map(|p| &p.key);
+            for key in keys {
+                PathSegment::Key(key).push_to(path);
+                collect(left.get(key), right.get(key), path, diffs);
+                path.truncate(parent);
+            }
+        }
+        (Some(Value::Array(l)), Some(Value::Array(r))) if l.len() == r.len() => {
+            for (i, (l, r)) in l.iter().zip(r).enumerate() {
+                PathSegment::Index(i).push_to(path);
+                collect(Some(l), Some(r), path, diffs);
+                path.truncate(parent);
+            }
+        }
+        _ => diffs.push((path.clone(), left, right)),
+    }
+}
+
+/// The pretty-printed lines of `value`, or none if it is missing.
+fn lines(value: Option<&Value>) -> Vec<String> {
+    let Some(value) = value else {
+        return Vec::new();
+    };
+    let options = WriteOptions {
+        pretty: Some(PrettyOptions::default()),
+        ..Default::default()
+    };
+    let mut text = String::new();
+    write_value(value, &options, &mut text).expect("writing to a String can't fail");
+    text.lines().map(str::to_string).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Line {
+    Same(usize),
+    Removed(usize),
+    Added(usize),
+}
+
+/// Writes the line diff of `left` and `right`, keeping [`CONTEXT`] unchanged lines around each
+/// change.
+fn render_lines(left: &[String], right: &[String], color: bool, out: &mut String) {
+    let diff = diff_lines(left, right);
+    let changed: Vec<_> = diff
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Line::Same(_)))
+        .map(|(i, _)| i)
+        .collect();
+    let shown = |i: usize| changed.iter().any(|c| c.abs_diff(i) <= CONTEXT);
+
+    let mut skipped = false;
+    for (i, line) in diff.into_iter().enumerate() {
+        if !shown(i) {
+            skipped = true;
+            continue;
+        }
+        if std::mem::take(&mut skipped) {
+            out.push_str("  ...\n");
+        }
+        let (prefix, text, ansi) = match line {
+            Line::Same(l) => (' ', &left[l], None),
+            Line::Removed(l) => ('-', &left[l], Some("31")),
+            Line::Added(r) => ('+', &right[r], Some("32")),
+        };
+        match ansi.filter(|_| color) {
+            Some(ansi) => out.push_str(&format!("\x1b[{ansi}m{prefix} {text}\x1b[0m\n")),
+            None => out.push_str(&format!("{prefix} {text}\n")),
+        }
+    }
+    if skipped {
+        out.push_str("  ...\n");
+    }
+}
+
+/// Diffs lines by longest common subsequence.
+fn diff_lines(left: &[String], right: &[String]) -> Vec<Line> {
+    // lengths of the longest common subsequences of the suffixes
+    let mut lcs = vec![vec![0; right.len() + 1]; left.len() + 1];
+    for l in (0..left.len()).rev() {
+        for r in (0..right.len()).rev() {
+            lcs[l][r] = if left[l] == right[r] {
+                lcs[l + 1][r + 1] + 1
+            } else {
+                lcs[l + 1][r].max(lcs[l][r + 1])
+            };
+        }
+    }
+
+    let (mut l, mut r) = (0, 0);
+    let mut diff = Vec::new();
+    while l < left.len() || r < right.len() {
+        if l < left.len() && r < right.len() && left[l] == right[r] {
+            diff.push(Line::Same(l));
+            l += 1;
+            r += 1;
+        } else if r == right.len() || (l < left.len() && lcs[l + 1][r] >= lcs[l][r + 1]) {
+            diff.push(Line::Removed(l));
+            l += 1;
+        } else {
+            diff.push(Line::Added(r));
+            r += 1;
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod test {
+    use mjl::JsonLexer;
+
+    use crate::{Value, diff::render_diff, parse};
+
+    fn value(input: &str) -> Value<'_> {
+        parse(JsonLexer::new(input)).unwrap().value
+    }
+
+    #[test]
+    fn render_differing_subtrees() {
+        let left =
+            value(r#"{"a": {"b": 1, "c": "x"}, "d": [1, 2, 3, 4, 5, 6, 7, 8, 9], "e": true}"#);
+        let right = value(
+            r#"{"e": true, "d": [1, 2, 3, 4, 5, 6, 7, 9], "a": {"b": 2, "c": "x"}, "f": null}"#,
+        );
+        assert_eq!(
+            "at /a/b:\n\
+             - 1\n\
+             + 2\n\
+             at /d:\n  \
+             ...\n    \
+             5,\n    \
+             6,\n    \
+             7,\n\
+             -   8,\n    \
+             9\n  \
+             ]\n\
+             at /f:\n\
+             + null\n",
+            render_diff(&left, &right, false)
+        );
+        assert_eq!(
+            "at (root):\n\x1b[31m- 1\x1b[0m\n\x1b[32m+ 2\x1b[0m\n",
+            render_diff(&value("1"), &value("2"), true)
+        );
+    }
+
+    #[test]
+    fn assert_equal_values() {
+        crate::assert_json_eq!(value(r#"{"a": 1, "b": 2}"#), value(r#"{"b": 2, "a": 1}"#));
+        let failure = std::panic::catch_unwind(|| {
+            crate::assert_json_eq!(value("[1]"), value("[2]"));
+        });
+        assert!(failure.is_err());
+    }
+}
@@ -0,0 +1,105 @@
+//! Targeted edits of JSON text that leave everything but the edited value untouched, for tools
+//! that change a field in files owned by users.
+
+use std::{error::Error, fmt::Display};
+
+use mjl::{CommentMode, JsonLexer, LexerOptions};
+
+use crate::{JsonParseError, JsonParser, Value, ser};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditError {
+    /// The input is not a valid document.
+    Parse(JsonParseError),
+    /// No value exists at the pointer.
+    NotFound(String),
+}
+
+impl Display for EditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EditError::Parse(e) => e.fmt(f),
+            EditError::NotFound(pointer) => write!(f, "no value at {pointer:?}"),
+        }
+    }
+}
+
+impl Error for EditError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EditError::Parse(e) => Some(e),
+            EditError::NotFound(_) => None,
+        }
+    }
+}
+
+impl From<JsonParseError> for EditError {
+    fn from(e: JsonParseError) -> Self {
+        EditError::Parse(e)
+    }
+}
+
+/// Replaces the value at `pointer` in `input` with `new_value`, written compactly. Every other
+/// byte of `input`, including whitespace and comments, is kept as is.
+///
+/// `pointer` is a JSON pointer (RFC 6901) whose keys are matched against the raw text of object
+/// keys, escapes included.
+pub fn edit(input: &str, pointer: &str, new_value: &Value) -> Result<String, EditError> {
+    let lexer = JsonLexer::with_options(
+        input,
+        LexerOptions {
+            comments: CommentMode::Emit,
+            ..Default::default()
+        },
+    );
+    let mut parser = JsonParser::new(lexer);
+    parser.span_target = Some(pointer.to_string());
+    parser.parse_json()?;
+    let span = parser
+        .span
+        .ok_or_else(|| EditError::NotFound(pointer.to_string()))?;
+
+    let mut out = String::with_capacity(input.len());
+    out.push_str(&input[..span.start]);
+    ser::write_value(new_value, &Default::default(), &mut out).unwrap();
+    out.push_str(&input[span.end..]);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+
+    use crate::{
+        ErrorKind, Value,
+        edit::{EditError, edit},
+    };
+
+    #[test]
+    fn edit_keeps_other_bytes() {
+        let input = "{\n  // port\n  \"port\" :  8080 ,\n  \"hosts\": [ \"a\",\t\"b\" ]\n}\n";
+        let value = Value::Str(Cow::Borrowed("c"));
+        assert_eq!(
+            "{\n  // port\n  \"port\" :  8080 ,\n  \"hosts\": [ \"a\",\t\"c\" ]\n}\n",
+            edit(input, "/hosts/1", &value).unwrap()
+        );
+        let value = Value::Array(vec![Value::Number("1".into())]);
+        assert_eq!(
+            "{\n  // port\n  \"port\" :  [1] ,\n  \"hosts\": [ \"a\",\t\"b\" ]\n}\n",
+            edit(input, "/port", &value).unwrap()
+        );
+        assert_eq!(" null\n", edit(" {\"a\": 1}\n", "", &Value::Null).unwrap());
+    }
+
+    #[test]
+    fn edit_errors() {
+        assert_eq!(
+            EditError::NotFound("/b".to_string()),
+            edit("{\"a\": 1}", "/b", &Value::Null).unwrap_err()
+        );
+        match edit("{\"a\": }", "/a", &Value::Null).unwrap_err() {
+            EditError::Parse(e) => assert_eq!(ErrorKind::Syntax, e.kind()),
+            e => panic!("unexpected error {e:?}"),
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! Parsing of JSON documents embedded in string values, a common sight in log records.
+
+use std::borrow::Cow;
+
+use mjl::JsonLexer;
+
+use crate::{Value, parse, ser::escape};
+
+impl<'a> Value<'a> {
+    /// Replaces the string values matching any of `patterns` that hold a JSON object or array
+    /// with the value they hold, and returns the number of strings replaced. Patterns are JSON
+    /// pointers with wildcards, as for [`Value::matches`].
+    ///
+    /// Replaced values are matched against the patterns again, so `/**` unwraps documents
+    /// embedded at any depth, while `/payload` unwraps only one level. Strings holding other JSON
+    /// values, such as `"12"`, are left as they are.
+    ///
+    /// Strings are expected to hold raw text, as parsed without
+    /// [`ParseOptions::decode_escapes`](crate::ParseOptions::decode_escapes).
+    pub fn parse_embedded_json(&mut self, patterns: &[&str]) -> usize {
+        let mut replaced = 0;
+        loop {
+            let mut embedded: Vec<(String, Value<'static>)> = Vec::new();
+            for pattern in patterns {
+                for (pointer, value) in self.matches(pattern) {
+                    if embedded.iter().any(|(p, _)| *p == pointer) {
+                        continue;
+                    }
+                    if let Value::Str(s) = value
+                        && let Some(value) = parse_container(s)
+                    {
+                        embedded.push((pointer, value));
+                    }
+                }
+            }
+            if embedded.is_empty() {
+                return replaced;
+            }
+            replaced += embedded.len();
+            for (pointer, value) in embedded {
+                // replacing a string never moves the other ones
+                *self.pointer_mut(&pointer).unwrap() = value;
+            }
+        }
+    }
+
+    /// Replaces the value at `pointer` with a string holding its compact JSON text, the inverse
+    /// of [`Value::parse_embedded_json`], for consumers that expect double-encoded fields.
+    /// Returns `false` if there is no value at `pointer`.
+    pub fn stringify_at(&mut self, pointer: &str) -> bool {
+        let Some(value) = self.pointer_mut(pointer) else {
+            return false;
+        };
+        *value = Value::Str(Cow::Owned(escape(&value.to_string()).into_owned()));
+        true
+    }
+}
+
+/// Parses the raw text of a string literal, if it holds an object or array.
+fn parse_container(raw: &str) -> Option<Value<'static>> {
+    if !raw.trim_start().starts_with(['{', '[']) {
+        return None;
+    }
+    let text = mjl::unescape(raw);
+    let value = parse(JsonLexer::new(&text)).ok()?.value;
+    Some(value.into_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use mjl::JsonLexer;
+
+    use crate::{Value, parse};
+
+    fn json(input: &str) -> Value<'_> {
+        parse(JsonLexer::new(input)).unwrap().value
+    }
+
+    #[test]
+    fn parse_embedded_documents() {
+        let input = r#"{"msg": "{\"user\": \"a\", \"ctx\": \"[1, {\\\"b\\\": 2}]\"}", "n": "12", "bad": "{x"}"#;
+        let mut value = json(input);
+        assert_eq!(1, value.clone().parse_embedded_json(&["/msg"]));
+        assert_eq!(2, value.parse_embedded_json(&["/**"]));
+        assert_eq!(
+            json(r#"{"msg": {"user": "a", "ctx": [1, {"b": 2}]}, "n": "12", "bad": "{x"}"#),
+            value
+        );
+        assert_eq!(0, value.parse_embedded_json(&["/**"]));
+    }
+
+    #[test]
+    fn stringify_subtrees() {
+        let mut value = json(r#"{"msg": {"user": "a\"b", "ctx": [1, 2]}}"#);
+        let original = value.clone();
+        assert!(value.stringify_at("/msg/ctx"));
+        assert!(value.stringify_at("/msg"));
+        assert!(!value.stringify_at("/nope"));
+        assert_eq!(
+            json(r#"{"msg": "{\"user\":\"a\\\"b\",\"ctx\":\"[1,2]\"}"}"#),
+            value
+        );
+        value.parse_embedded_json(&["/**"]);
+        assert_eq!(original, value);
+    }
+}
@@ -0,0 +1,77 @@
+//! Equality of values.
+
+use crate::{Pair, Value};
+
+impl PartialEq for Value<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        eq(self, other, false)
+    }
+}
+
+impl Value<'_> {
+    /// Compares values like `==`, except that objects are only equal if their members are in the
+    /// same order.
+    pub fn eq_ordered(&self, other: &Value) -> bool {
+        eq(self, other, true)
+    }
+}
+
+fn eq(a: &Value, b: &Value, ordered: bool) -> bool {
+    match (a, b) {
+        (Value::Object(a), Value::Object(b)) => {
+            let same_order = |a: &[Pair], b: &[Pair]| a.iter().zip(b).all(|(a, b)| a.key == b.key);
+            if a.len() != b.len() {
+                false
+            } else if ordered || same_order(a, b) {
+                a.iter()
+                    .zip(b)
+                    .all(|(a, b)| a.key == b.key && eq(&a.value, &b.value, ordered))
+            } else {
+                sorted(a)
+                    .into_iter()
+                    .zip(sorted(b))
+                    .all(|(a, b)| a.key == b.key && eq(&a.value, &b.value, ordered))
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| eq(a, b, ordered))
+        }
+        (Value::Str(a), Value::Str(b)) => a == b,
+        (Value::Number(a), Value::Number(b)) => a == b,
+        (Value::Boolean(a), Value::Boolean(b)) => a == b,
+        (Value::Null, Value::Null) => true,
+        (Value::Raw(a), Value::Raw(b)) if a == b => true,
+        (Value::Raw(raw), other) | (other, Value::Raw(raw)) => {
+            raw.parse().is_ok_and(|value| eq(&value, other, ordered))
+        }
+        _ => false,
+    }
+}
+
+fn sorted<'p, 'a>(pairs: &'p [Pair<'a>]) -> Vec<&'p Pair<'a>> {
+    let mut sorted: Vec<_> = pairs.iter().collect();
+    sorted.sort_by(|a, b| a.key.cmp(&b.key));
+    sorted
+}
+
+#[cfg(test)]
+mod test {
+    use mjl::JsonLexer;
+
+    use crate::{Value, parse};
+
+    fn value(input: &str) -> Value<'_> {
+        parse(JsonLexer::new(input)).unwrap().value
+    }
+
+    #[test]
+    fn member_order() {
+        let a = value(r#"{"a": 1, "b": [{"c": 2, "d": 3}]}"#);
+        let b = value(r#"{"b": [{"d": 3, "c": 2}], "a": 1}"#);
+        assert_eq!(a, b);
+        assert!(!a.eq_ordered(&b));
+        assert!(a.eq_ordered(&a.clone()));
+        assert_ne!(a, value(r#"{"a": 1, "b": [{"c": 2, "d": 4}]}"#));
+        assert_ne!(value("[1, 2]"), value("[2, 1]"));
+    }
+}
@@ -0,0 +1,578 @@
+//! Parsing documents into a stream of events instead of a tree, in memory proportional to their
+//! nesting depth.
+
+use std::{borrow::Cow, io};
+
+use mjl::{JsonLexer, Token};
+
+use crate::{BooleanVal, ErrorKind, JsonParseError, PathSegment};
+
+/// A step through a document. Strings and keys hold the raw text of their literal, like
+/// [`Value`](crate::Value).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<'a> {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    /// The key of the next member of the enclosing object, followed by the events of its value.
+    Key(Cow<'a, str>),
+    Str(Cow<'a, str>),
+    Number(Cow<'a, str>),
+    Boolean(BooleanVal),
+    Null,
+}
+
+impl Event<'_> {
+    /// Converts this event into one that owns its strings.
+    pub fn into_owned(self) -> Event<'static> {
+        match self {
+            Event::StartObject => Event::StartObject,
+            Event::EndObject => Event::EndObject,
+            Event::StartArray => Event::StartArray,
+            Event::EndArray => Event::EndArray,
+            Event::Key(k) => Event::Key(Cow::Owned(k.into_owned())),
+            Event::Str(s) => Event::Str(Cow::Owned(s.into_owned())),
+            Event::Number(n) => Event::Number(Cow::Owned(n.into_owned())),
+            Event::Boolean(b) => Event::Boolean(b),
+            Event::Null => Event::Null,
+        }
+    }
+}
+
+/// Iterator over the [`Event`]s of a document. Errors end the iteration.
+///
+/// Unlike [`parse`](crate::parse), duplicate keys are not detected, as that would require
+/// remembering every key of the objects being read.
+pub struct EventReader<'a> {
+    lexer: JsonLexer<'a>,
+    machine: EventMachine<&'a str>,
+}
+
+impl<'a> EventReader<'a> {
+    pub fn new(lexer: JsonLexer<'a>) -> Self {
+        EventReader {
+            lexer,
+            machine: EventMachine::new(),
+        }
+    }
+
+    fn next_event(&mut self) -> Result<Option<Event<'a>>, JsonParseError> {
+        loop {
+            self.lexer.skip_whitespace();
+            let offset = self.lexer.byte_offset;
+            let token = self
+                .lexer
+                .next_token()
+                .map_err(|e| self.error((e.kind(), e.message().to_string()), e.offset()))?;
+            let result = match token {
+                Some(token) => self.machine.feed(token, |key| key),
+                None => {
+                    return self
+                        .machine
+                        .finish()
+                        .map(|_| None)
+                        .map_err(|e| self.error(e, offset));
+                }
+            };
+            match result {
+                Ok(Some(event)) => return Ok(Some(event)),
+                Ok(None) => {}
+                Err(e) => return Err(self.error(e, offset)),
+            }
+        }
+    }
+
+    fn error(&mut self, (kind, message): (ErrorKind, String), offset: usize) -> JsonParseError {
+        self.machine.fail();
+        let (line, column) = mjl::line_column(self.lexer.input, offset);
+        JsonParseError::new(kind, message, offset, (line, column), self.machine.path())
+            .with_hint(self.lexer.input)
+    }
+}
+
+impl<'a> Iterator for EventReader<'a> {
+    type Item = Result<Event<'a>, JsonParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.machine.is_failed() {
+            return None;
+        }
+        self.next_event().transpose()
+    }
+}
+
+/// Reads the [`Event`]s of the document in `reader`, reading no more input than it needs to
+/// produce the next event, so that memory stays proportional to the nesting depth and the
+/// longest token rather than to the document. Errors end the iteration.
+///
+/// Like [`EventReader`], duplicate keys are not detected.
+pub fn read_events<R: io::Read>(reader: R) -> ReaderEvents<R> {
+    ReaderEvents {
+        reader,
+        events: EventBuffer::new(),
+    }
+}
+
+/// Iterator over the [`Event`]s of a document read from an [`io::Read`], returned by
+/// [`read_events`].
+pub struct ReaderEvents<R> {
+    reader: R,
+    events: EventBuffer,
+}
+
+impl<R: io::Read> ReaderEvents<R> {
+    fn next_event(&mut self) -> Result<Option<Event<'static>>, JsonParseError> {
+        loop {
+            match self.events.next_event()? {
+                Step::Event(event) => return Ok(Some(event)),
+                Step::End => return Ok(None),
+                Step::NeedInput => {
+                    let buffer = self.events.prepare_read(READ_SIZE);
+                    let len = buffer.len();
+                    buffer.resize(len + READ_SIZE, 0);
+                    let result = loop {
+                        match self.reader.read(&mut buffer[len..]) {
+                            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                            result => break result,
+                        }
+                    };
+                    buffer.truncate(len + result.as_ref().map_or(0, |read| *read));
+                    self.events.finish_read(result)?;
+                }
+            }
+        }
+    }
+}
+
+impl<R: io::Read> Iterator for ReaderEvents<R> {
+    type Item = Result<Event<'static>, JsonParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+/// Number of bytes requested from readers when more input is needed.
+pub(crate) const READ_SIZE: usize = 8 * 1024;
+
+/// The input of an event parser reading from a reader: the bytes read but not lexed yet, and
+/// where they start in the input.
+pub(crate) struct EventBuffer {
+    /// The input read so far, validated as UTF-8 once as it is read.
+    text: String,
+    /// Bytes read after `text` that aren't valid UTF-8 yet, such as a sequence split across
+    /// reads.
+    pending: Vec<u8>,
+    /// Whether `pending` starts with an invalid sequence, which no read can complete.
+    invalid_utf8: bool,
+    /// Bytes at the start of `text` that were lexed.
+    consumed: usize,
+    eof: bool,
+    machine: EventMachine<String>,
+    /// Byte offset, line and column of the start of `text` in the input.
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+pub(crate) enum Step {
+    Event(Event<'static>),
+    NeedInput,
+    End,
+}
+
+impl EventBuffer {
+    pub(crate) fn new() -> Self {
+        EventBuffer {
+            text: String::new(),
+            pending: Vec::new(),
+            invalid_utf8: false,
+            consumed: 0,
+            eof: false,
+            machine: EventMachine::new(),
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    /// Produces the next event from the buffered input, or asks for more. Errors stop the
+    /// parser, which ends afterwards.
+    pub(crate) fn next_event(&mut self) -> Result<Step, JsonParseError> {
+        if self.machine.is_failed() {
+            return Ok(Step::End);
+        }
+        self.step().inspect_err(|_| self.machine.fail())
+    }
+
+    /// Lexes the next token from the buffered input, unless it may continue past the buffer.
+    fn step(&mut self) -> Result<Step, JsonParseError> {
+        let invalid_utf8 = self.invalid_utf8 || (self.eof && !self.pending.is_empty());
+        let more_input = !self.eof && !invalid_utf8;
+        let text = &self.text[self.consumed..];
+        let valid = text.len();
+
+        let mut lexer = JsonLexer::new(text);
+        lexer.skip_whitespace();
+        let start = lexer.byte_offset;
+        let token = match lexer.next_token() {
+            Ok(Some(Token::Number(..))) if lexer.byte_offset == text.len() && more_input => {
+                // the number may continue in the next read
+                return Ok(Step::NeedInput);
+            }
+            Ok(Some(token)) => token,
+            Ok(None) if more_input => return Ok(Step::NeedInput),
+            Ok(None) if invalid_utf8 => {
+                return Err(self.error(ErrorKind::InvalidUtf8, "invalid UTF-8 in input", valid));
+            }
+            Ok(None) => {
+                return match self.machine.finish() {
+                    Ok(()) => Ok(Step::End),
+                    Err((kind, message)) => Err(self.error(kind, message, start)),
+                };
+            }
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof && more_input => {
+                return Ok(Step::NeedInput);
+            }
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof && invalid_utf8 => {
+                return Err(self.error(ErrorKind::InvalidUtf8, "invalid UTF-8 in input", valid));
+            }
+            Err(e) => return Err(self.error(e.kind(), e.message(), e.offset())),
+        };
+        let end = lexer.byte_offset;
+
+        let event = match self.machine.feed(token, str::to_string) {
+            Ok(event) => event.map(Event::into_owned),
+            Err((kind, message)) => return Err(self.error(kind, message, start)),
+        };
+        self.consumed += end;
+        Ok(match event {
+            Some(event) => Step::Event(event),
+            None => self.step()?,
+        })
+    }
+
+    /// Drops the lexed input from the buffer, returning the buffer to read at least `size` more
+    /// bytes into, after its end.
+    pub(crate) fn prepare_read(&mut self, size: usize) -> &mut Vec<u8> {
+        for c in self.text[..self.consumed].chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+        self.offset += self.consumed;
+        self.text.drain(..self.consumed);
+        self.consumed = 0;
+        self.pending.reserve(size);
+        &mut self.pending
+    }
+
+    /// Records the outcome of a read into the buffer returned by [`EventBuffer::prepare_read`].
+    pub(crate) fn finish_read(&mut self, result: io::Result<usize>) -> Result<(), JsonParseError> {
+        match result {
+            Ok(0) => self.eof = true,
+            Ok(_) if self.invalid_utf8 => self.pending.clear(),
+            Ok(_) => {
+                let valid = match std::str::from_utf8(&self.pending) {
+                    Ok(_) => self.pending.len(),
+                    // an incomplete sequence at the end may be completed by the next read
+                    Err(e) => {
+                        self.invalid_utf8 = e.error_len().is_some();
+                        e.valid_up_to()
+                    }
+                };
+                self.text
+                    .push_str(std::str::from_utf8(&self.pending[..valid]).unwrap());
+                self.pending.drain(..valid);
+            }
+            Err(e) => {
+                let offset = self.text.len() - self.consumed;
+                let e = self.error(ErrorKind::Io, e.to_string(), offset);
+                self.machine.fail();
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Builds an error at `offset`, relative to the unlexed input.
+    fn error(&self, kind: ErrorKind, message: impl Into<String>, offset: usize) -> JsonParseError {
+        let offset = self.consumed + offset;
+        let before = &self.text[..offset];
+        let (line, column) = match mjl::line_column(before, before.len()) {
+            (1, column) => (self.line, self.column + column - 1),
+            (line, column) => (self.line + line - 1, column),
+        };
+        JsonParseError::new(
+            kind,
+            message,
+            self.offset + offset,
+            (line, column),
+            self.machine.path(),
+        )
+    }
+}
+
+/// The state of an event parser between two tokens, with the keys of the open objects stored as
+/// `K`.
+pub(crate) struct EventMachine<K> {
+    expect: Expect,
+    stack: Vec<Frame<K>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Expect {
+    Value,
+    ValueOrEnd,
+    Key,
+    KeyOrEnd,
+    Colon,
+    CommaOrEnd,
+    /// The root value was read.
+    Eof,
+    /// An error was returned.
+    Nothing,
+}
+
+enum Frame<K> {
+    /// With the number of elements started.
+    Array(usize),
+    /// With the key of the current member.
+    Object(Option<K>),
+}
+
+impl<K: AsRef<str>> EventMachine<K> {
+    pub(crate) fn new() -> Self {
+        EventMachine {
+            expect: Expect::Value,
+            stack: Vec::new(),
+        }
+    }
+
+    /// Advances past `token`, returning the event it produces, if any. `key` stores the text of
+    /// keys.
+    pub(crate) fn feed<'t>(
+        &mut self,
+        token: Token<'t>,
+        key: impl FnOnce(&'t str) -> K,
+    ) -> Result<Option<Event<'t>>, (ErrorKind, String)> {
+        let event = match (self.expect, token) {
+            (_, Token::Comment(_)) => return Ok(None),
+            (Expect::Colon, Token::Colon) => {
+                self.expect = Expect::Value;
+                return Ok(None);
+            }
+            (Expect::CommaOrEnd, Token::Comma) => {
+                self.expect = match self.stack.last() {
+                    Some(Frame::Array(_)) => Expect::Value,
+                    _ => Expect::Key,
+                };
+                return Ok(None);
+            }
+            (Expect::ValueOrEnd | Expect::CommaOrEnd, Token::RBracket)
+                if matches!(self.stack.last(), Some(Frame::Array(_))) =>
+            {
+                self.stack.pop();
+                self.value_done();
+                Event::EndArray
+            }
+            (Expect::KeyOrEnd | Expect::CommaOrEnd, Token::RBrace)
+                if matches!(self.stack.last(), Some(Frame::Object(_))) =>
+            {
+                self.stack.pop();
+                self.value_done();
+                Event::EndObject
+            }
+            (Expect::Key | Expect::KeyOrEnd, Token::String(k)) => {
+                if let Some(Frame::Object(current)) = self.stack.last_mut() {
+                    *current = Some(key(k));
+                }
+                self.expect = Expect::Colon;
+                Event::Key(Cow::Borrowed(k))
+            }
+            (Expect::Value | Expect::ValueOrEnd, token) if starts_value(&token) => {
+                if let Some(Frame::Array(started)) = self.stack.last_mut() {
+                    *started += 1;
+                }
+                self.value(token)
+            }
+            (_, token) => return Err((ErrorKind::Syntax, format!("unexpected {token:?}"))),
+        };
+        Ok(Some(event))
+    }
+
+    /// Checks that the input may end here.
+    pub(crate) fn finish(&self) -> Result<(), (ErrorKind, String)> {
+        match self.expect {
+            Expect::Eof => Ok(()),
+            _ => Err((
+                ErrorKind::UnexpectedEof,
+                "input ended prematurely".to_string(),
+            )),
+        }
+    }
+
+    /// Stops the machine after an error.
+    pub(crate) fn fail(&mut self) {
+        self.expect = Expect::Nothing;
+    }
+
+    pub(crate) fn is_failed(&self) -> bool {
+        self.expect == Expect::Nothing
+    }
+
+    /// JSON pointer to the value being read.
+    pub(crate) fn path(&self) -> String {
+        let mut path = String::new();
+        for frame in &self.stack {
+            let segment = match frame {
+                Frame::Array(0) | Frame::Object(None) => continue,
+                Frame::Array(started) => PathSegment::Index(started - 1),
+                Frame::Object(Some(key)) => PathSegment::Key(key.as_ref()),
+            };
+            segment.push_to(&mut path);
+        }
+        path
+    }
+
+    fn value<'t>(&mut self, token: Token<'t>) -> Event<'t> {
+        let event = match token {
+            Token::LBrace => {
+                self.stack.push(Frame::Object(None));
+                self.expect = Expect::KeyOrEnd;
+                return Event::StartObject;
+            }
+            Token::LBracket => {
+                self.stack.push(Frame::Array(0));
+                self.expect = Expect::ValueOrEnd;
+                return Event::StartArray;
+            }
+            Token::String(s) => Event::Str(Cow::Borrowed(s)),
+            Token::Number(n, _) => Event::Number(Cow::Borrowed(n)),
+            Token::True => Event::Boolean(BooleanVal::True),
+            Token::False => Event::Boolean(BooleanVal::False),
+            Token::Null => Event::Null,
+            _ => unreachable!("checked by starts_value"),
+        };
+        self.value_done();
+        event
+    }
+
+    fn value_done(&mut self) {
+        self.expect = if self.stack.is_empty() {
+            Expect::Eof
+        } else {
+            Expect::CommaOrEnd
+        };
+    }
+}
+
+fn starts_value(token: &Token) -> bool {
+    use Token::*;
+    matches!(
+        token,
+        LBrace | LBracket | String(_) | Number(..) | True | False | Null
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        borrow::Cow,
+        io::{self, Read},
+    };
+
+    use mjl::JsonLexer;
+
+    use crate::{
+        BooleanVal, ErrorKind,
+        events::{Event, EventReader},
+    };
+
+    #[test]
+    fn read_events() {
+        let events: Vec<_> = EventReader::new(JsonLexer::new(r#"{"a": [1, true], "b": {}}"#))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            vec![
+                Event::StartObject,
+                Event::Key(Cow::Borrowed("a")),
+                Event::StartArray,
+                Event::Number(Cow::Borrowed("1")),
+                Event::Boolean(BooleanVal::True),
+                Event::EndArray,
+                Event::Key(Cow::Borrowed("b")),
+                Event::StartObject,
+                Event::EndObject,
+                Event::EndObject,
+            ],
+            events
+        );
+    }
+
+    #[test]
+    fn errors_end_the_stream() {
+        let mut events = EventReader::new(JsonLexer::new(r#"{"a": [1 2]}"#));
+        let err = events.find_map(Result::err).unwrap();
+        assert_eq!(ErrorKind::Syntax, err.kind());
+        assert_eq!("/a/0", err.path());
+        assert_eq!(10, err.column());
+        assert!(events.next().is_none());
+
+        let mut events = EventReader::new(JsonLexer::new("[1,"));
+        let err = events.find_map(Result::err).unwrap();
+        assert_eq!(ErrorKind::UnexpectedEof, err.kind());
+    }
+
+    /// Reader handing out one byte per read, to split every token.
+    struct Trickle(&'static [u8]);
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let Some((first, rest)) = self.0.split_first() else {
+                return Ok(0);
+            };
+            buf[0] = *first;
+            self.0 = rest;
+            Ok(1)
+        }
+    }
+
+    #[test]
+    fn events_from_reader() {
+        let events: Vec<_> =
+            crate::events::read_events(Trickle("{\"ké\": [12, \"x\"]}\n".as_bytes()))
+                .collect::<Result<_, _>>()
+                .unwrap();
+        assert_eq!(
+            vec![
+                Event::StartObject,
+                Event::Key(Cow::Borrowed("ké")),
+                Event::StartArray,
+                Event::Number(Cow::Borrowed("12")),
+                Event::Str(Cow::Borrowed("x")),
+                Event::EndArray,
+                Event::EndObject,
+            ],
+            events
+        );
+
+        let mut events = crate::events::read_events(Trickle(b"[1,\n 2 3]"));
+        let err = events.find_map(Result::err).unwrap();
+        assert_eq!((2, 4, 7), (err.line(), err.column(), err.offset()));
+        assert_eq!("/1", err.path());
+        assert!(events.next().is_none());
+
+        for (input, offset) in [(&b"[1, \xFF 2]"[..], 4), (b"[\"\xC3", 2)] {
+            let err = crate::events::read_events(Trickle(input))
+                .find_map(Result::err)
+                .unwrap();
+            assert_eq!((ErrorKind::InvalidUtf8, offset), (err.kind(), err.offset()));
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! Conversion of values into Rust types.
+
+use std::{error::Error, fmt::Display};
+
+use crate::{BooleanVal, Value};
+
+/// Types that can be built from a [`Value`].
+pub trait FromJson: Sized {
+    fn from_json(value: &Value) -> Result<Self, FromJsonError>;
+}
+
+/// A value that does not have the shape of the requested type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FromJsonError {
+    message: String,
+    path: String,
+}
+
+impl FromJsonError {
+    pub fn new(message: impl Into<String>) -> Self {
+        FromJsonError {
+            message: message.into(),
+            path: String::new(),
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// JSON pointer to the offending value, relative to the converted one.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn within(mut self, index: usize) -> Self {
+        self.path = format!("/{index}{}", self.path);
+        self
+    }
+}
+
+impl Display for FromJsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{} at {}", self.message, self.path)
+        }
+    }
+}
+
+impl Error for FromJsonError {}
+
+fn mismatch(expected: &str, value: &Value) -> FromJsonError {
+    let found = match value {
+        Value::Object(_) => "object",
+        Value::Array(_) => "array",
+        Value::Str(_) => "string",
+        Value::Number(_) => "number",
+        Value::Boolean(_) => "boolean",
+        Value::Null => "null",
+        Value::Raw(_) => "raw JSON",
+    };
+    FromJsonError::new(format!("expected {expected}, found {found}"))
+}
+
+impl FromJson for Value<'static> {
+    fn from_json(value: &Value) -> Result<Self, FromJsonError> {
+        Ok(value.clone().into_owned())
+    }
+}
+
+impl FromJson for bool {
+    fn from_json(value: &Value) -> Result<Self, FromJsonError> {
+        match value {
+            Value::Boolean(b) => Ok(*b == BooleanVal::True),
+            v => Err(mismatch("a boolean", v)),
+        }
+    }
+}
+
+/// Strings are converted to the text they hold, which is raw unless they were parsed with
+/// [`ParseOptions::decode_escapes`](crate::ParseOptions::decode_escapes).
+impl FromJson for String {
+    fn from_json(value: &Value) -> Result<Self, FromJsonError> {
+        match value {
+            Value::Str(s) => Ok(s.to_string()),
+            v => Err(mismatch("a string", v)),
+        }
+    }
+}
+
+macro_rules! from_json_number {
+    ($($t:ty),* ; $($float:ty),*) => {
+        $(
+            /// Numbers written with a fraction or an exponent, such as `1.0`, aren't integers, as
+            /// for [`Number::is_i64`](crate::Number::is_i64).
+            impl FromJson for $t {
+                fn from_json(value: &Value) -> Result<Self, FromJsonError> {
+                    match value {
+                        Value::Number(n) if n.as_str().contains(['.', 'e', 'E']) => {
+                            Err(not_an_integer(n.as_str()))
+                        }
+                        Value::Number(n) => n
+                            .as_str()
+                            .parse()
+                            .map_err(|_| does_not_fit(n.as_str(), stringify!($t))),
+                        v => Err(mismatch("a number", v)),
+                    }
+                }
+            }
+        )*
+        $(
+            /// Numbers beyond the largest finite value, such as `1e400`, don't fit rather than
+            /// converting to infinity.
+            impl FromJson for $float {
+                fn from_json(value: &Value) -> Result<Self, FromJsonError> {
+                    match value {
+                        Value::Number(n) => n
+                            .as_str()
+                            .parse()
+                            .ok()
+                            .filter(|f: &$float| f.is_finite())
+                            .ok_or_else(|| does_not_fit(n.as_str(), stringify!($float))),
+                        v => Err(mismatch("a number", v)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_json_number!(
+    i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize; f32, f64
+);
+
+fn does_not_fit(n: &str, t: &str) -> FromJsonError {
+    FromJsonError::new(format!("number {n} does not fit in {t}"))
+}
+
+fn not_an_integer(n: &str) -> FromJsonError {
+    FromJsonError::new(format!("number {n} is not an integer"))
+}
+
+/// `null` converts to `None`.
+impl<T: FromJson> FromJson for Option<T> {
+    fn from_json(value: &Value) -> Result<Self, FromJsonError> {
+        match value {
+            Value::Null => Ok(None),
+            v => T::from_json(v).map(Some),
+        }
+    }
+}
+
+impl<T: FromJson> FromJson for Vec<T> {
+    fn from_json(value: &Value) -> Result<Self, FromJsonError> {
+        match value {
+            Value::Array(values) => values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| T::from_json(v).map_err(|e| e.within(i)))
+                .collect(),
+            v => Err(mismatch("an array", v)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mjl::JsonLexer;
+
+    use crate::{from_json::FromJson, parse};
+
+    fn convert<T: FromJson>(input: &str) -> Result<T, String> {
+        let json = parse(JsonLexer::new(input)).unwrap();
+        T::from_json(&json.value).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn convert_values() {
+        assert_eq!(
+            Ok(vec![Some(1), None]),
+            convert::<Vec<Option<u8>>>("[1, null]")
+        );
+        assert_eq!(Ok(-1.5), convert::<f64>("-1.5"));
+        assert_eq!(
+            Err("number 1e39 does not fit in f32".to_string()),
+            convert::<f32>("1e39")
+        );
+        assert_eq!(Ok(true), convert::<bool>("true"));
+        assert_eq!(
+            Err("number 300 does not fit in u8 at /1".to_string()),
+            convert::<Vec<u8>>("[1, 300]")
+        );
+        assert_eq!(
+            Err("number 1.0 is not an integer".to_string()),
+            convert::<i32>("1.0")
+        );
+        assert_eq!(
+            Err("number 5e-1 is not an integer at /0".to_string()),
+            convert::<Vec<u64>>("[5e-1]")
+        );
+        assert_eq!(
+            Err("expected a string, found object".to_string()),
+            convert::<String>("{}")
+        );
+    }
+}
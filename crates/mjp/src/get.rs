@@ -0,0 +1,253 @@
+//! Extraction of a single value from a large document, without parsing the rest of it.
+
+use std::{error::Error, fmt::Display};
+
+use mjl::{JsonLexer, LexError, Token};
+
+use crate::{
+    ErrorKind, JsonParseError, JsonParser,
+    from_json::{FromJson, FromJsonError},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GetError {
+    Parse(JsonParseError),
+    /// The value at the pointer could not be converted.
+    Convert(FromJsonError),
+}
+
+impl Display for GetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GetError::Parse(e) => e.fmt(f),
+            GetError::Convert(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for GetError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GetError::Parse(e) => Some(e),
+            GetError::Convert(e) => Some(e),
+        }
+    }
+}
+
+impl From<JsonParseError> for GetError {
+    fn from(e: JsonParseError) -> Self {
+        GetError::Parse(e)
+    }
+}
+
+/// Converts the value at `pointer` in `input` into a `T`, or returns `None` if there is no such
+/// value.
+///
+/// Only the addressed value is parsed: the containers on the way to it are read up to the
+/// addressed member or element, and their other children are skipped by matching brackets, so
+/// syntax errors outside of the addressed value may go unnoticed. Keys of `pointer` are matched
+/// against the raw text of object keys, escapes included.
+pub fn get_at<T: FromJson>(input: &str, pointer: &str) -> Result<Option<T>, GetError> {
+    let mut segments = pointer.split('/');
+    if segments.next() != Some("") {
+        return Ok(None);
+    }
+
+    let mut scanner = Scanner {
+        lexer: JsonLexer::new(input),
+        path: String::new(),
+    };
+    if !pointer.is_empty() {
+        for segment in segments {
+            if !scanner.enter(&segment.replace("~1", "/").replace("~0", "~"))? {
+                return Ok(None);
+            }
+            scanner.path.push('/');
+            scanner.path.push_str(segment);
+        }
+    }
+
+    let mut parser = JsonParser::new(scanner.lexer);
+    let value = parser
+        .parse_value()
+        .map_err(|e| e.relocate(0, (1, 1), pointer))?;
+    T::from_json(&value).map(Some).map_err(GetError::Convert)
+}
+
+struct Scanner<'a> {
+    lexer: JsonLexer<'a>,
+    /// Pointer to the value at the lexer's position.
+    path: String,
+}
+
+impl<'a> Scanner<'a> {
+    /// Moves to the child named `segment` of the value at the current position, returning
+    /// whether it exists.
+    fn enter(&mut self, segment: &str) -> Result<bool, JsonParseError> {
+        match self.next()? {
+            Some(Token::LBrace) => loop {
+                match self.next()? {
+                    Some(Token::String(key)) => {
+                        self.expect(Token::Colon)?;
+                        if key == segment {
+                            return Ok(true);
+                        }
+                        self.skip_value()?;
+                    }
+                    Some(Token::RBrace) => return Ok(false),
+                    t => return Err(self.unexpected(t)),
+                }
+                match self.next()? {
+                    Some(Token::Comma) => {}
+                    Some(Token::RBrace) => return Ok(false),
+                    t => return Err(self.unexpected(t)),
+                }
+            },
+            Some(Token::LBracket) => {
+                let Ok(index) = segment.parse::<usize>() else {
+                    return Ok(false);
+                };
+                for _ in 0..index {
+                    if self.at_array_end()? {
+                        return Ok(false);
+                    }
+                    self.skip_value()?;
+                    match self.next()? {
+                        Some(Token::Comma) => {}
+                        Some(Token::RBracket) => return Ok(false),
+                        t => return Err(self.unexpected(t)),
+                    }
+                }
+                Ok(!self.at_array_end()?)
+            }
+            Some(_) => Ok(false),
+            None => Err(self.unexpected(None)),
+        }
+    }
+
+    fn at_array_end(&mut self) -> Result<bool, JsonParseError> {
+        let offset = self.lexer.byte_offset;
+        let end = self.next()? == Some(Token::RBracket);
+        self.lexer.byte_offset = offset;
+        Ok(end)
+    }
+
+    /// Skips the value at the current position, matching brackets without lexing the contents of
+    /// arrays and objects.
+    fn skip_value(&mut self) -> Result<(), JsonParseError> {
+        if !matches!(self.next()?, Some(Token::LBrace | Token::LBracket)) {
+            return Ok(());
+        }
+        let bytes = self.lexer.input.as_bytes();
+        let mut depth = 1;
+        let mut in_string = false;
+        let mut escaped = false;
+        for (i, &b) in bytes.iter().enumerate().skip(self.lexer.byte_offset) {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' if in_string => escaped = true,
+                b'"' => in_string = !in_string,
+                _ if in_string => {}
+                b'[' | b'{' => depth += 1,
+                b']' | b'}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.lexer.byte_offset = i + 1;
+                        return Ok(());
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.lexer.byte_offset = bytes.len();
+        Err(self.error(ErrorKind::UnexpectedEof, "unclosed container"))
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), JsonParseError> {
+        match self.next()? {
+            Some(t) if t == expected => Ok(()),
+            t => Err(self.unexpected(t)),
+        }
+    }
+
+    fn next(&mut self) -> Result<Option<Token<'a>>, JsonParseError> {
+        self.lexer.skip_whitespace();
+        let offset = self.lexer.byte_offset;
+        self.lexer.next_token().map_err(|e: LexError| {
+            self.lexer.byte_offset = offset;
+            self.error_at(e.kind(), e.message(), e.offset())
+        })
+    }
+
+    fn unexpected(&self, token: Option<Token>) -> JsonParseError {
+        match token {
+            Some(t) => self.error(ErrorKind::Syntax, format!("unexpected {t:?}")),
+            None => self.error(ErrorKind::UnexpectedEof, "input ended prematurely"),
+        }
+    }
+
+    fn error(&self, kind: ErrorKind, message: impl Into<String>) -> JsonParseError {
+        self.error_at(kind, message, self.lexer.byte_offset)
+    }
+
+    fn error_at(
+        &self,
+        kind: ErrorKind,
+        message: impl Into<String>,
+        offset: usize,
+    ) -> JsonParseError {
+        let (line, column) = mjl::line_column(self.lexer.input, offset);
+        JsonParseError::new(kind, message, offset, (line, column), self.path.clone())
+            .with_hint(self.lexer.input)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        ErrorKind, Value,
+        get::{GetError, get_at},
+    };
+
+    const INPUT: &str = r#"{
+        "meta": {"skipped": [1, "]}", {"x": "\"}"}]},
+        "data": {"items": [10, [20], 30, 40], "a/b": true}
+    }"#;
+
+    #[test]
+    fn get_values() {
+        assert_eq!(Some(40), get_at::<u32>(INPUT, "/data/items/3").unwrap());
+        assert_eq!(
+            Some(vec![20]),
+            get_at::<Vec<u8>>(INPUT, "/data/items/1").unwrap()
+        );
+        assert_eq!(Some(true), get_at::<bool>(INPUT, "/data/a~1b").unwrap());
+        assert_eq!(None, get_at::<u32>(INPUT, "/data/items/4").unwrap());
+        assert_eq!(None, get_at::<u32>(INPUT, "/data/missing").unwrap());
+        assert_eq!(None, get_at::<u32>(INPUT, "/data/items/x").unwrap());
+        assert_eq!(None, get_at::<u32>("[]", "/0").unwrap());
+        assert!(matches!(
+            get_at::<Value>("[1]", "").unwrap(),
+            Some(Value::Array(_))
+        ));
+    }
+
+    #[test]
+    fn get_errors() {
+        match get_at::<u32>(INPUT, "/data/a~1b").unwrap_err() {
+            GetError::Convert(e) => assert_eq!("expected a number, found boolean", e.message()),
+            e => panic!("unexpected error {e:?}"),
+        }
+        match get_at::<u32>(r#"{"a": [1, 2"#, "/b").unwrap_err() {
+            GetError::Parse(e) => assert_eq!(ErrorKind::UnexpectedEof, e.kind()),
+            e => panic!("unexpected error {e:?}"),
+        }
+        match get_at::<Value>(r#"{"a": {"b": [1 2]}}"#, "/a/b").unwrap_err() {
+            GetError::Parse(e) => {
+                assert_eq!(ErrorKind::Syntax, e.kind());
+                assert_eq!("/a/b", e.path());
+            }
+            e => panic!("unexpected error {e:?}"),
+        }
+    }
+}
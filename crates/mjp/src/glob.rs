@@ -0,0 +1,115 @@
+//! Resolution of JSON pointers with wildcards.
+
+use crate::{PathSegment, Value};
+
+impl<'a> Value<'a> {
+    /// Returns the pointer and value of every descendant matching `pattern`, in document order.
+    ///
+    /// The pattern is a JSON pointer in which a `*` segment matches any member or element, and a
+    /// `**` segment matches any number of levels, none included. For instance, `/users/*/email`
+    /// matches the `email` of every user, and `/**/password` every `password` member. Keys are
+    /// matched against the raw text of object keys, escapes included.
+    pub fn matches(&self, pattern: &str) -> Vec<(String, &Value<'a>)> {
+        let mut matches = Vec::new();
+        let segments: Vec<_> = match pattern.strip_prefix('/') {
+            _ if pattern.is_empty() => Vec::new(),
+            Some(pattern) => pattern.split('/').collect(),
+            None => return matches,
+        };
+        walk(self, &segments, &mut String::new(), &mut matches);
+        matches
+    }
+}
+
+fn walk<'v, 'a>(
+    value: &'v Value<'a>,
+    segments: &[&str],
+    path: &mut String,
+    matches: &mut Vec<(String, &'v Value<'a>)>,
+) {
+    let Some((&segment, rest)) = segments.split_first() else {
+        matches.push((path.clone(), value));
+        return;
+    };
+    let key =
+        (!matches!(segment, "*" | "**")).then(|| segment.replace("~1", "/").replace("~0", "~"));
+    // children are matched against the rest of the pattern, or against all of it after `**`
+    let next = if segment == "**" {
+        walk(value, rest, path, matches);
+        segments
+    } else {
+        rest
+    };
+
+    let parent = path.len();
+    let mut visit = |child, segment: PathSegment, path: &mut String| {
+        segment.push_to(path);
+        walk(child, next, path, matches);
+        path.truncate(parent);
+    };
+    match value {
+        Value::Object(pairs) => {
+            for pair in pairs {
+                if key.as_deref().is_none_or(|key| key == pair.key) {
+                    visit(&pair.value, PathSegment::Key(&pair.key), path);
+                }
+            }
+        }
+        Value::Array(values) => {
+            for (i, child) in values.iter().enumerate() {
+                if key.as_deref().is_none_or(|key| key == i.to_string()) {
+                    visit(child, PathSegment::Index(i), path);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mjl::JsonLexer;
+
+    use crate::{Value, parse};
+
+    fn paths(value: &Value, pattern: &str) -> Vec<String> {
+        value
+            .matches(pattern)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect()
+    }
+
+    #[test]
+    fn match_wildcards() {
+        let input = r#"{
+            "users": [
+                {"name": "a", "email": "a@x", "auth": {"password": "1"}},
+                {"name": "b"},
+                {"name": "c", "email": "c@x"}
+            ],
+            "admin": {"password": "2"},
+            "a/b": 1
+        }"#;
+        let value = parse(JsonLexer::new(input)).unwrap().value;
+
+        let emails = value.matches("/users/*/email");
+        assert_eq!(
+            vec![
+                ("/users/0/email".to_string(), &Value::Str("a@x".into())),
+                ("/users/2/email".to_string(), &Value::Str("c@x".into())),
+            ],
+            emails
+        );
+        assert_eq!(
+            vec!["/users/0/auth/password", "/admin/password"],
+            paths(&value, "/**/password")
+        );
+        assert_eq!(vec!["/users/1/name"], paths(&value, "/users/1/name"));
+        assert_eq!(vec!["/a~1b"], paths(&value, "/a~1b"));
+        assert_eq!(vec![""], paths(&value, ""));
+        assert_eq!(3, paths(&value, "/*").len());
+        assert!(paths(&value, "/users/*/missing").is_empty());
+        assert!(paths(&value, "users").is_empty());
+    }
+}
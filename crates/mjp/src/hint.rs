@@ -0,0 +1,99 @@
+//! Hints for the mistakes commonly made writing JSON by hand, such as in configuration files.
+
+/// Returns a hint for an error at `offset` in `input`, if the text there looks like a common
+/// mistake: a single-quoted string, a Python literal such as `True` or `None`, a trailing comma
+/// or an unquoted key.
+pub(crate) fn suggest(input: &str, offset: usize) -> Option<String> {
+    let rest = input.get(offset..)?;
+    let before = input[..offset].trim_end();
+    if let Some(string) = rest.strip_prefix('\'') {
+        return Some(match string.split_once(['\'', '\n']) {
+            Some((text, _)) if string.as_bytes()[text.len()] == b'\'' => {
+                format!("did you mean {}?", quoted(text))
+            }
+            _ => "strings are quoted with double quotes".to_string(),
+        });
+    }
+    if rest.starts_with([']', '}']) && before.ends_with(',') {
+        return Some("remove the trailing comma".to_string());
+    }
+
+    let word = &rest[..rest
+        .find(|c: char| !c.is_alphanumeric() && c != '_' && c != '$')
+        .unwrap_or(rest.len())];
+    if word.is_empty() {
+        return None;
+    }
+    let literal = match word.to_ascii_lowercase().as_str() {
+        "true" => "true",
+        "false" => "false",
+        "null" | "none" | "nil" => "null",
+        _ => "",
+    };
+    if !literal.is_empty() {
+        return Some(format!("did you mean \"{literal}\"?"));
+    }
+    let key = before.ends_with(['{', ',']) && rest[word.len()..].trim_start().starts_with(':');
+    key.then(|| format!("keys are quoted: did you mean {}?", quoted(word)))
+}
+
+/// `text` as a JSON string, with the quotes and backslashes it holds escaped.
+fn quoted(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod test {
+    use mjl::JsonLexer;
+
+    use crate::{events::EventReader, parse};
+
+    fn hint(input: &str) -> Option<String> {
+        let e = parse(JsonLexer::new(input)).unwrap_err();
+        e.hint().map(str::to_string)
+    }
+
+    #[test]
+    fn hint_common_mistakes() {
+        assert_eq!(
+            Some("did you mean \"a \\\"b\\\"\"?".to_string()),
+            hint(r#"{"k": 'a "b"'}"#)
+        );
+        assert_eq!(
+            Some("strings are quoted with double quotes".to_string()),
+            hint("['a\n']")
+        );
+        assert_eq!(Some("did you mean \"true\"?".to_string()), hint("[True]"));
+        assert_eq!(Some("did you mean \"false\"?".to_string()), hint("[FALSE]"));
+        assert_eq!(
+            Some("did you mean \"null\"?".to_string()),
+            hint("{\"a\": None}")
+        );
+        assert_eq!(
+            Some("remove the trailing comma".to_string()),
+            hint("[1, 2,\n]")
+        );
+        assert_eq!(
+            Some("remove the trailing comma".to_string()),
+            hint(r#"{"a": 1,}"#)
+        );
+        assert_eq!(
+            Some("keys are quoted: did you mean \"name\"?".to_string()),
+            hint(r#"{"id": 1, name : "x"}"#)
+        );
+        // other mistakes get no hint
+        assert_eq!(None, hint("[1 2]"));
+        assert_eq!(None, hint("[abc]"));
+        assert_eq!(None, hint("{\"a\": 1"));
+
+        let e = parse(JsonLexer::new("[True]")).unwrap_err();
+        assert_eq!(
+            "unable to parse token from char T at line 1, column 2; did you mean \"true\"?",
+            e.to_string()
+        );
+        let e = EventReader::new(JsonLexer::new("{a: 1}"))
+            .find_map(Result::err)
+            .unwrap();
+        assert_eq!(Some("keys are quoted: did you mean \"a\"?"), e.hint());
+    }
+}
@@ -0,0 +1,119 @@
+//! Sharing of repeated strings between values, for workloads that keep many documents with the
+//! same keys and strings in memory.
+
+use std::{borrow::Cow, collections::HashSet};
+
+use crate::{Pair, RawValue, Value};
+
+/// A set of strings that values can borrow from, so that each distinct key, string or number is
+/// stored once.
+#[derive(Debug, Clone, Default)]
+pub struct StringPool(HashSet<Box<str>>);
+
+impl StringPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of distinct strings in the pool.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Adds the keys, strings and numbers of `value` to the pool.
+    pub fn add(&mut self, value: &Value) {
+        match value {
+            Value::Object(pairs) => {
+                for pair in pairs {
+                    self.insert(&pair.key);
+                    self.add(&pair.value);
+                }
+            }
+            Value::Array(values) => values.iter().for_each(|v| self.add(v)),
+            Value::Str(s) => self.insert(s),
+            Value::Number(n) => self.insert(n.as_str()),
+            Value::Raw(raw) => self.insert(&raw.0),
+            Value::Boolean(_) | Value::Null => {}
+        }
+    }
+
+    /// Returns a copy of `value` borrowing its keys, strings and numbers from the pool. Those
+    /// missing from the pool are copied.
+    pub fn intern<'p>(&'p self, value: &Value) -> Value<'p> {
+        match value {
+            Value::Object(pairs) => Value::Object(
+                pairs
+                    .iter()
+                    .map(|p| Pair {
+                        key: self.get(&p.key),
+                        value: self.intern(&p.value),
+                    })
+                    .collect(),
+            ),
+            Value::Array(values) => Value::Array(values.iter().map(|v| self.intern(v)).collect()),
+            Value::Str(s) => Value::Str(self.get(s)),
+            Value::Number(n) => Value::Number(n.with_text(self.get(n.as_str()))),
+            Value::Boolean(b) => Value::Boolean(*b),
+            Value::Null => Value::Null,
+            Value::Raw(raw) => Value::Raw(RawValue(self.get(&raw.0))),
+        }
+    }
+
+    fn insert(&mut self, s: &str) {
+        if !self.0.contains(s) {
+            self.0.insert(s.into());
+        }
+    }
+
+    fn get(&self, s: &str) -> Cow<'_, str> {
+        match self.0.get(s) {
+            Some(s) => Cow::Borrowed(s),
+            None => Cow::Owned(s.to_string()),
+        }
+    }
+}
+
+impl Value<'_> {
+    /// Returns a copy of this value in which equal keys, strings and numbers share their storage
+    /// in `pool`. Only strings are shared, not subtrees.
+    pub fn dedup_strings<'p>(&self, pool: &'p mut StringPool) -> Value<'p> {
+        pool.add(self);
+        let pool: &'p StringPool = pool;
+        pool.intern(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+
+    use mjl::JsonLexer;
+
+    use crate::{Value, intern::StringPool, parse};
+
+    #[test]
+    fn share_strings() {
+        let input = r#"[{"name": "x", "port": 80}, {"name": "x", "port": 80}]"#;
+        let value = parse(JsonLexer::new(input)).unwrap().value.into_owned();
+        let mut pool = StringPool::new();
+        let deduped = value.dedup_strings(&mut pool);
+        assert_eq!(value, deduped);
+
+        let Value::Array(values) = &deduped else {
+            panic!("unexpected value {deduped:?}");
+        };
+        let (Value::Object(a), Value::Object(b)) = (&values[0], &values[1]) else {
+            panic!("unexpected value {deduped:?}");
+        };
+        let (Cow::Borrowed(a), Cow::Borrowed(b)) = (&a[0].key, &b[0].key) else {
+            panic!("keys are not borrowed");
+        };
+        assert!(std::ptr::eq(*a, *b));
+        // "name", "x", "port" and "80"
+        assert_eq!(4, pool.len());
+    }
+}
@@ -0,0 +1,148 @@
+//! Conversion of JSON5 literals, and of those accepted by
+//! [`Strictness::Lenient`](mjl::Strictness::Lenient), into JSON, so that such documents parse
+//! into the same values as their JSON equivalents.
+
+use std::borrow::Cow;
+
+/// Converts the raw text of a string literal quoted with `quote` into the raw text of a JSON
+/// string literal. Control chars are escaped if `controls` is set, and kept as is otherwise.
+pub(crate) fn string(raw: &str, quote: char, controls: bool) -> Cow<'_, str> {
+    let needs_quote = |c: char| c == '"' && quote == '\'';
+    let needs_escape = |c: char| controls && c.is_ascii_control();
+    if !raw.contains(|c: char| c == '\\' || needs_escape(c) || needs_quote(c)) {
+        return Cow::Borrowed(raw);
+    }
+    let mut json = String::with_capacity(raw.len());
+    let mut chars = raw.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => json.push_str("\\\""),
+            c if needs_escape(c) => json.push_str(&format!("\\u{:04x}", c as u32)),
+            '\\' => match chars.next() {
+                Some(e @ ('"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't' | 'u')) => {
+                    json.push('\\');
+                    json.push(e);
+                }
+                Some('x') => {
+                    json.push_str("\\u00");
+                    json.extend(chars.by_ref().take(2));
+                }
+                Some('v') => json.push_str("\\u000b"),
+                Some('0') => json.push_str("\\u0000"),
+                // line continuations
+                Some('\r') => {
+                    chars.next_if_eq(&'\n');
+                }
+                Some('\n' | '\u{2028}' | '\u{2029}') => {}
+                // any other char escapes itself
+                Some(c) => {
+                    let mut buf = [0; 4];
+                    json.push_str(&string(c.encode_utf8(&mut buf), '\'', true));
+                }
+                None => {}
+            },
+            c => json.push(c),
+        }
+    }
+    Cow::Owned(json)
+}
+
+/// Converts a JSON5 number into JSON: `Infinity` and `NaN` are kept, having no JSON
+/// equivalent.
+pub(crate) fn number(text: &str) -> Cow<'_, str> {
+    let unsigned = text.strip_prefix('+').unwrap_or(text);
+    let (sign, digits) = match unsigned.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", unsigned),
+    };
+    if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        return Cow::Owned(format!("{sign}{}", hex_to_decimal(hex)));
+    }
+    let (mantissa, exponent) = match digits.find(['e', 'E']) {
+        Some(i) => digits.split_at(i),
+        None => (digits, ""),
+    };
+    let (int, frac) = match mantissa.split_once('.') {
+        Some((int, frac)) => (int, Some(frac)),
+        None => (mantissa, None),
+    };
+    // leading zeros are dropped, and a leading or trailing dot gets its zero
+    let normalized = match int.trim_start_matches('0') {
+        "" => "0",
+        int => int,
+    };
+    if normalized == int && frac != Some("") {
+        return Cow::Borrowed(unsigned);
+    }
+    let frac = match frac {
+        Some(frac) if !frac.is_empty() => format!(".{frac}"),
+        _ => String::new(),
+    };
+    Cow::Owned(format!("{sign}{normalized}{frac}{exponent}"))
+}
+
+/// Converts hexadecimal digits into decimal ones, whatever their number.
+fn hex_to_decimal(hex: &str) -> String {
+    // decimal digits, least significant first
+    let mut decimal = vec![0u8];
+    for h in hex.chars() {
+        let mut carry = h.to_digit(16).unwrap();
+        for d in decimal.iter_mut() {
+            let n = u32::from(*d) * 16 + carry;
+            *d = (n % 10) as u8;
+            carry = n / 10;
+        }
+        while carry > 0 {
+            decimal.push((carry % 10) as u8);
+            carry /= 10;
+        }
+    }
+    while decimal.len() > 1 && decimal.last() == Some(&0) {
+        decimal.pop();
+    }
+    decimal.iter().rev().map(|d| char::from(b'0' + d)).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::json5::{number, string};
+
+    #[test]
+    fn convert_strings() {
+        assert_eq!("plain", string("plain", '\'', true));
+        assert_eq!("it's \\\"x\\\"", string("it\\'s \"x\"", '\'', true));
+        assert_eq!("\\\"", string("\\\"", '"', true));
+        assert_eq!(
+            "\\u0041\\u000b\\u0000\\n\\u0009ab",
+            string("\\x41\\v\\0\\n\ta\\\r\nb", '"', true)
+        );
+        assert_eq!("a\\u0009", string("\\a\\\t", '"', true));
+    }
+
+    #[test]
+    fn convert_numbers() {
+        for (json5, json) in [
+            ("1", "1"),
+            ("+1.5e3", "1.5e3"),
+            ("-.5", "-0.5"),
+            ("5.", "5"),
+            ("5.E-2", "5E-2"),
+            ("0xFF", "255"),
+            ("-0x0", "-0"),
+            (
+                "0x10000000000000000000000000000000",
+                "21267647932558653966460912964485513216",
+            ),
+            ("+Infinity", "Infinity"),
+            ("NaN", "NaN"),
+            ("007", "7"),
+            ("-00.50", "-0.50"),
+            ("00", "0"),
+        ] {
+            assert_eq!(json, number(json5), "{json5}");
+        }
+    }
+}
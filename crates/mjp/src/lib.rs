@@ -1,29 +1,224 @@
-use std::{error::Error, fmt::Display};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, hash_map::Entry},
+    error::Error,
+    fmt::Display,
+    hash::{BuildHasher, DefaultHasher, Hasher, RandomState},
+    str::FromStr,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
 
-use mjl::{JsonLexer, LexError, Token};
+use mjl::{ControlChars, Dialect, JsonLexer, Strictness, Token, TokenSource, encoding::Encoding};
 
-#[derive(Debug)]
+pub use comments::{CommentMap, Comments};
+pub use from_json::FromJson;
+pub use get::get_at;
+pub use media::{parse_media, parse_media_with};
+pub use mjl::ErrorKind;
+pub use number::Number;
+pub use object::Object;
+pub use raw::RawValue;
+pub use stream::{stream_array, stream_array_with};
+pub use utf8::Utf8Policy;
+
+/// Emits a trace-level `tracing` event with the `tracing` feature, and nothing without it.
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::trace!(target: "mjp::parse", $($arg)*);
+    };
+}
+
+pub mod analyze;
+#[cfg(feature = "tokio")]
+pub mod async_events;
+pub mod builder;
+#[cfg(feature = "canonical")]
+pub mod canonical;
+#[cfg(any(feature = "reqwest", feature = "ureq"))]
+pub mod client;
+#[cfg(feature = "codegen")]
+pub mod codegen;
+pub mod coerce;
+pub mod comments;
+#[cfg(feature = "config")]
+pub mod config;
+#[cfg(feature = "diff")]
+pub mod diff;
+pub mod edit;
+pub mod embedded;
+pub mod eq;
+pub mod events;
+pub mod from_json;
+pub mod get;
+pub mod glob;
+mod hint;
+pub mod intern;
+mod json5;
+pub mod log;
+pub mod lookup;
+pub mod media;
+#[cfg(feature = "merge")]
+pub mod merge;
+pub mod ndjson;
+pub mod number;
+pub mod object;
+pub mod order;
+#[cfg(feature = "patch")]
+pub mod patch;
+pub mod pipeline;
+#[cfg(feature = "pseudonym")]
+pub mod pseudonym;
+pub mod raw;
+pub mod sample;
+pub mod sanitize;
+#[cfg(feature = "schema")]
+pub mod schema;
+pub mod ser;
+pub mod shard;
+pub mod shared;
+#[cfg(feature = "sign")]
+pub mod sign;
+pub mod spanned;
+pub mod split;
+pub mod stats;
+pub mod stream;
+pub mod truncate;
+pub mod utf8;
+#[cfg(any(feature = "axum", feature = "actix"))]
+pub mod web;
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Json<'a> {
     pub value: Value<'a>,
+    /// Comments found in the input, if [`ParseOptions::attach_comments`] was set.
+    pub comments: CommentMap<'a>,
+    /// Keys found more than once in an object, unless [`ParseOptions::duplicate_keys`] fails the
+    /// parse on them, in the order their second occurrence was found.
+    pub duplicate_keys: Vec<DuplicateKey>,
+    pub metadata: Metadata,
+}
+
+/// Where a document came from and how its parse went, filled in by the entry points for
+/// diagnostics and logs.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Metadata {
+    /// Name of the source, such as a file name or URI, from [`ParseOptions::source`].
+    pub source: Option<String>,
+    /// Length of the input in bytes.
+    pub byte_length: usize,
+    /// Time spent parsing.
+    pub duration: Duration,
+    /// Number of values, nested ones included.
+    pub values: usize,
+    /// Nesting depth of the deepest value, 0 when the root value is a scalar.
+    pub max_depth: usize,
+}
+
+/// How the parser handles objects with several members with the same key, which RFC 8259 leaves
+/// to implementations. Keys are compared by their raw text, or by their decoded text with
+/// [`ParseOptions::decode_escapes`], so `"a"` and `"\u0061"` are only duplicates then.
+///
+/// Unless the parse fails, the duplicates are listed in [`Json::duplicate_keys`]. Values kept
+/// [raw](Value::Raw) keep every member.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeyPolicy {
+    /// Fail the parse with [`ErrorKind::DuplicateKey`].
+    #[default]
+    Error,
+    /// Keep the first member with each key.
+    FirstWins,
+    /// Keep the value of the last member with each key, where the first one was.
+    LastWins,
+    /// Keep every member.
+    KeepAll,
+}
+
+/// A key found more than once in the same object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateKey {
+    /// JSON pointer to the member.
+    pub path: String,
+    /// Line and column of each occurrence of the key, in order.
+    pub occurrences: Vec<(usize, usize)>,
+}
+
+impl Json<'_> {
+    /// Converts this document into one that owns all of its strings, see [`Value::into_owned`].
+    pub fn into_owned(self) -> Json<'static> {
+        Json {
+            value: self.value.into_owned(),
+            comments: comments::into_owned(self.comments),
+            duplicate_keys: self.duplicate_keys,
+            metadata: self.metadata,
+        }
+    }
+}
+
+/// Parses a document that owns its strings, as in `"[1, 2]".parse::<Json>()`. Use [`parse_str`]
+/// to borrow them from the input instead.
+impl FromStr for Json<'static> {
+    type Err = JsonParseError;
+
+    fn from_str(input: &str) -> Result<Self, JsonParseError> {
+        parse_str(input).map(Json::into_owned)
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Pair<'a> {
-    pub key: &'a str,
+    pub key: Cow<'a, str>,
     pub value: Value<'a>,
 }
 
-#[derive(Debug)]
+/// Strings and keys hold the raw text of their literal, escapes included, unless parsed with
+/// [`ParseOptions::decode_escapes`].
+///
+/// Values compare structurally: the order of object members does not matter, see
+/// [`Value::eq_ordered`] for a comparison where it does.
+#[derive(Debug, Clone)]
 pub enum Value<'a> {
-    Object(Vec<Pair<'a>>),
+    Object(Object<'a>),
     Array(Vec<Value<'a>>),
-    Str(&'a str),
-    Number(&'a str),
+    Str(Cow<'a, str>),
+    Number(Number<'a>),
     Boolean(BooleanVal),
     Null,
+    /// A value kept as its JSON text, see [`ParseOptions::spill_above`].
+    Raw(RawValue<'a>),
 }
 
-#[derive(Debug)]
+impl Value<'_> {
+    /// Converts this value into one that owns all of its strings, detaching it from the input it
+    /// was parsed from.
+    pub fn into_owned(self) -> Value<'static> {
+        match self {
+            Value::Object(pairs) => Value::Object(
+                pairs
+                    .into_iter()
+                    .map(|p| Pair {
+                        key: Cow::Owned(p.key.into_owned()),
+                        value: p.value.into_owned(),
+                    })
+                    .collect(),
+            ),
+            Value::Array(values) => {
+                Value::Array(values.into_iter().map(Value::into_owned).collect())
+            }
+            Value::Str(s) => Value::Str(Cow::Owned(s.into_owned())),
+            Value::Number(n) => Value::Number(n.into_owned()),
+            Value::Boolean(b) => Value::Boolean(b),
+            Value::Null => Value::Null,
+            Value::Raw(raw) => Value::Raw(raw.into_owned()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum BooleanVal {
     True,
     False,
@@ -38,196 +233,1546 @@ impl Display for BooleanVal {
     }
 }
 
+/// Number of tokens lexed between two checks of [`ParseOptions::cancellation`] and reports to
+/// [`ParseOptions::progress`].
+const CHECK_INTERVAL: usize = 1024;
+
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// Aborts the parse with [`ErrorKind::Cancelled`] once cancelled. Checked periodically while
+    /// the input is lexed.
+    pub cancellation: Option<CancellationToken>,
+    /// Called with the [`Progress`] of the parse periodically while the input is lexed, and once
+    /// it is parsed, e.g. to show a progress bar.
+    pub progress: Option<ProgressHook>,
+    /// How invalid UTF-8 is handled by the entry points that take bytes or readers.
+    pub invalid_utf8: Utf8Policy,
+    /// Records the comments produced by the lexer (see [`mjl::LexerOptions::comments`]) in
+    /// [`Json::comments`], attached to the nodes they document. Otherwise comments are skipped.
+    pub attach_comments: bool,
+    /// How objects with duplicate keys are handled.
+    pub duplicate_keys: DuplicateKeyPolicy,
+    /// Name of the input, such as a file name or URI, recorded in [`Metadata::source`].
+    pub source: Option<String>,
+    /// Decodes the escape sequences of strings and keys (see [`mjl::unescape`]), so that
+    /// [`Value::Str`] and [`Pair::key`] hold their actual text rather than their raw literal. The
+    /// keys of pointers, in paths and errors, stay raw. Such values are written back with
+    /// [`WriteOptions::decoded`](ser::WriteOptions::decoded).
+    pub decode_escapes: bool,
+    /// Hashes keys to detect duplicates. The default resists collision attacks from untrusted
+    /// input.
+    pub key_hasher: KeyHasher,
+    /// Keeps values whose text is longer than this many bytes as [`Value::Raw`], so that a large
+    /// subtree costs no more memory than its text. The values kept raw inside a container don't
+    /// count towards its length, so that a container isn't kept raw just for holding a large
+    /// value. Values kept raw are validated all the same.
+    pub spill_above: Option<usize>,
+    /// Largest document, in bytes, read by [`from_reader`] and `from_async_reader`. Reading
+    /// stops and fails with [`ErrorKind::LimitExceeded`] past it, so that an endless stream
+    /// can't exhaust memory.
+    pub max_bytes: Option<usize>,
+}
+
+/// The hasher used to index the keys of objects.
+///
+/// The default is SipHash with random keys, like [`HashMap`], so that inputs can't be crafted to
+/// make keys collide and slow down the parse. [`KeyHasher::Fast`] is quicker on short keys, for
+/// trusted input only.
+#[derive(Debug, Clone)]
+pub enum KeyHasher {
+    Random(RandomState),
+    /// FNV-1a, which has no key, so that colliding keys are easily found.
+    Fast,
+}
+
+impl Default for KeyHasher {
+    fn default() -> Self {
+        KeyHasher::Random(RandomState::new())
+    }
+}
+
+impl BuildHasher for KeyHasher {
+    type Hasher = KeyHashState;
+
+    fn build_hasher(&self) -> KeyHashState {
+        match self {
+            KeyHasher::Random(state) => KeyHashState::Random(state.build_hasher()),
+            KeyHasher::Fast => KeyHashState::Fast(0xcbf2_9ce4_8422_2325),
+        }
+    }
+}
+
+/// The state of a hasher built by [`KeyHasher`].
+#[derive(Debug, Clone)]
+pub enum KeyHashState {
+    Random(DefaultHasher),
+    Fast(u64),
+}
+
+impl Hasher for KeyHashState {
+    fn finish(&self) -> u64 {
+        match self {
+            KeyHashState::Random(hasher) => hasher.finish(),
+            KeyHashState::Fast(hash) => *hash,
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        match self {
+            KeyHashState::Random(hasher) => hasher.write(bytes),
+            KeyHashState::Fast(hash) => {
+                for b in bytes {
+                    *hash = (*hash ^ u64::from(*b)).wrapping_mul(0x100_0000_01b3);
+                }
+            }
+        }
+    }
+}
+
+/// How much of the input a parse went through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// Bytes of the input consumed so far.
+    pub consumed: usize,
+    /// Length of the input in bytes, if known.
+    pub total: Option<usize>,
+}
+
+/// The callback of [`ParseOptions::progress`].
+#[derive(Clone)]
+pub struct ProgressHook(Arc<dyn Fn(Progress) + Send + Sync>);
+
+impl ProgressHook {
+    pub fn new(hook: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        ProgressHook(Arc::new(hook))
+    }
+
+    pub fn report(&self, progress: Progress) {
+        (self.0)(progress)
+    }
+}
+
+impl std::fmt::Debug for ProgressHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ProgressHook")
+    }
+}
+
+/// Shared flag used to abort in-flight parses from another thread.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
 pub struct JsonParser<'a> {
     pub lexer: JsonLexer<'a>,
-    pub tokens: Vec<Token<'a>>,
-    pub position: usize,
+    pub options: ParseOptions,
+    /// Where tokens come from instead of `lexer`, which then only provides the input.
+    source: Option<Box<dyn TokenSource<'a> + 'a>>,
+    /// The token being looked at, lexed but not consumed yet, with its span.
+    current: Option<(Token<'a>, std::ops::Range<usize>)>,
+    /// Span of the last consumed token.
+    previous: std::ops::Range<usize>,
+    /// Number of tokens lexed, comments excluded.
+    lexed: usize,
+    path: Vec<PathSegment<'a>>,
+    /// Comments lexed since they were last attached, with their offsets.
+    pending_comments: Vec<(usize, &'a str)>,
+    comments: CommentMap<'a>,
+    /// Pointer of the value whose span is recorded in `span`.
+    span_target: Option<std::string::String>,
+    span: Option<std::ops::Range<usize>>,
+    /// The spans of every node, recorded for [`spanned::parse_spanned`].
+    spans: Option<spanned::SpanRecorder>,
+    /// The pointer to each duplicate key with the byte offsets of its occurrences, located once
+    /// parsing succeeds rather than for each occurrence.
+    duplicate_keys: Vec<(String, Vec<usize>)>,
+    /// Total length of the values kept raw, see [`ParseOptions::spill_above`].
+    spilled: usize,
+    values: usize,
+    max_depth: usize,
+}
+
+#[derive(Clone, Copy)]
+enum PathSegment<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+impl PathSegment<'_> {
+    fn push_to(&self, path: &mut std::string::String) {
+        match self {
+            PathSegment::Key(k) => {
+                path.push('/');
+                path.push_str(&k.replace('~', "~0").replace('/', "~1"));
+            }
+            PathSegment::Index(i) => path.push_str(&format!("/{i}")),
+        }
+    }
 }
 
 impl<'a> JsonParser<'a> {
-    fn parse_json(&mut self) -> Result<Json<'a>, Box<dyn Error>> {
+    pub fn new(lexer: JsonLexer<'a>) -> Self {
+        Self::with_options(lexer, ParseOptions::default())
+    }
+
+    pub fn with_options(lexer: JsonLexer<'a>, options: ParseOptions) -> Self {
+        JsonParser {
+            lexer,
+            options,
+            source: None,
+            current: None,
+            previous: 0..0,
+            lexed: 0,
+            path: Vec::new(),
+            pending_comments: Vec::new(),
+            comments: CommentMap::new(),
+            span_target: None,
+            span: None,
+            spans: None,
+            duplicate_keys: Vec::new(),
+            spilled: 0,
+            values: 0,
+            max_depth: 0,
+        }
+    }
+
+    /// Parses the tokens of `source`, such as a [replayed recording](mjl::Recording::replay),
+    /// instead of lexing its input.
+    pub fn from_source(source: impl TokenSource<'a> + 'a, options: ParseOptions) -> Self {
+        let mut parser = Self::with_options(JsonLexer::new(source.input()), options);
+        parser.lexer.options = source.options();
+        parser.source = Some(Box::new(source));
+        parser
+    }
+
+    fn parse_json(&mut self) -> Result<Json<'a>, JsonParseError> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!(target: "mjp::parse", "parse", source = self.options.source.as_deref())
+            .entered();
+        let result = self.parse_document();
+        #[cfg(feature = "tracing")]
+        match &result {
+            Ok(json) => {
+                trace!(
+                    values = json.metadata.values,
+                    max_depth = json.metadata.max_depth,
+                    duration = ?json.metadata.duration,
+                    "parsed"
+                );
+            }
+            Err(e) => {
+                trace!(offset = e.offset(), path = e.path(), error = %e, "failed");
+            }
+        }
+        result
+    }
+
+    fn parse_document(&mut self) -> Result<Json<'a>, JsonParseError> {
+        let started = Instant::now();
+        self.current()?;
+        self.attach_root_comments(false);
         let value = self.parse_value()?;
 
         if self.current()?.is_some() {
-            Err(Box::new(JsonParseError(
-                "unexpected content following root value".to_string(),
-            )))
+            Err(self.error(ErrorKind::Syntax, "unexpected content following root value"))
         } else {
-            Ok(Json { value })
+            self.attach_root_comments(true);
+            self.report_progress(self.lexer.input.len());
+            Ok(Json {
+                value,
+                comments: std::mem::take(&mut self.comments),
+                duplicate_keys: self.locate_duplicate_keys(),
+                metadata: Metadata {
+                    source: self.options.source.clone(),
+                    byte_length: self.lexer.input.len(),
+                    duration: started.elapsed(),
+                    values: self.values,
+                    max_depth: self.max_depth,
+                },
+            })
         }
     }
 
-    fn parse_value(&mut self) -> Result<Value<'a>, Box<dyn Error>> {
+    fn parse_value(&mut self) -> Result<Value<'a>, JsonParseError> {
+        self.values += 1;
+        self.max_depth = self.max_depth.max(self.path.len());
+        self.current()?;
+        let start = self.offset();
+        let opened = self.open_span(start);
+        let spilled = self.spilled;
+        let mut value = self.parse_token_value()?;
+        if !matches!(value, Value::Raw(_)) && self.spills(start, spilled) {
+            value = self.spill(start, spilled);
+        }
+        self.close_span(opened, matches!(value, Value::Raw(_)));
+        if self.span.is_none()
+            && self
+                .span_target
+                .as_ref()
+                .is_some_and(|t| *t == self.pointer())
+        {
+            self.span = Some(start..self.previous.end);
+        }
+        trace!(
+            offset = start,
+            end = self.previous.end,
+            path = %self.pointer(),
+            kind = kind_name(&value),
+            "value"
+        );
+        Ok(value)
+    }
+
+    fn parse_token_value(&mut self) -> Result<Value<'a>, JsonParseError> {
         use Token::*;
         use Value::*;
         if let Some(t) = self.current()? {
             let result = match t {
                 LBrace => self.parse_object()?,
                 String(s) => {
-                    self.position += 1;
-                    Str(s)
+                    self.advance();
+                    Str(self.string(s))
                 }
                 LBracket => self.parse_array()?,
                 True => {
-                    self.position += 1;
+                    self.advance();
                     Boolean(BooleanVal::True)
                 }
                 False => {
-                    self.position += 1;
+                    self.advance();
                     Boolean(BooleanVal::False)
                 }
-                Token::Number(n) => {
-                    self.position += 1;
-                    Value::Number(n)
+                Token::Number(n, kind) => {
+                    self.advance();
+                    let options = &self.lexer.options;
+                    Value::Number(
+                        if options.dialect == Dialect::Json5
+                            || options.strictness == Strictness::Lenient
+                        {
+                            // the kind of a converted number may differ, as `0x10` becomes `16`
+                            crate::Number::from(json5::number(n))
+                        } else {
+                            crate::Number::with_kind(Cow::Borrowed(n), kind)
+                        },
+                    )
                 }
                 Token::Null => {
-                    self.position += 1;
+                    self.advance();
                     Value::Null
                 }
                 t => {
-                    return Err(Box::new(JsonParseError(format!(
-                        "expected a value, but got {t:?}"
-                    ))));
+                    return Err(self.error(
+                        ErrorKind::Syntax,
+                        format!("expected a value, but got {t:?}"),
+                    ));
                 }
             };
             Ok(result)
         } else {
-            Err(Box::new(JsonParseError(
-                "expected value but input ended prematurely".to_string(),
-            )))
+            Err(self.error(
+                ErrorKind::UnexpectedEof,
+                "expected value but input ended prematurely",
+            ))
         }
     }
 
-    fn parse_array(&mut self) -> Result<Value<'a>, Box<dyn Error>> {
+    fn parse_array(&mut self) -> Result<Value<'a>, JsonParseError> {
         use Token::*;
         use Value::*;
-        self.position += 1; // skip over OpenSquareBracket
+        trace!(offset = self.offset(), path = %self.pointer(), "enter array");
+        let (start, spilled) = (self.offset(), self.spilled);
+        self.advance(); // skip over OpenSquareBracket
         let mut values = Vec::new();
+        // elements are still parsed, but no longer kept, once the array is to be kept raw
+        let (mut len, mut spilling) = (0, false);
+        let mut previous = None;
         loop {
             match self.current()? {
                 Some(RBracket) => {
-                    self.position += 1; // done with current array, skip over CloseSquareBracket
-                    return Ok(Array(values));
+                    self.attach_comments(previous, None);
+                    trace!(offset = self.offset(), elements = len, "exit array");
+                    self.advance(); // done with current array, skip over CloseSquareBracket
+                    return Ok(if spilling {
+                        self.spill(start, spilled)
+                    } else {
+                        Array(values)
+                    });
                 }
                 Some(_) => {
-                    if !values.is_empty() {
+                    if len > 0 {
                         self.expect_skip(&Comma)?;
+                        if self.trailing_comma(&RBracket)? {
+                            continue;
+                        }
                     }
-                    values.push(self.parse_value()?);
+                    let segment = PathSegment::Index(len);
+                    self.attach_comments(previous, Some(segment));
+                    self.path.push(segment);
+                    let value = self.parse_value()?;
+                    len += 1;
+                    if !spilling {
+                        values.push(value);
+                        spilling = self.spills(start, spilled);
+                        if spilling {
+                            values = Vec::new();
+                        }
+                    }
+                    self.path.pop();
+                    previous = Some((segment, self.previous.end));
                 }
                 None => {
-                    return Err(Box::new(JsonParseError(
-                        "unclosed array delimiter".to_string(),
-                    )));
+                    return Err(self.error(ErrorKind::UnexpectedEof, "unclosed array delimiter"));
                 }
             }
         }
     }
 
-    fn parse_object(&mut self) -> Result<Value<'a>, Box<dyn Error>> {
-        self.position += 1;
+    /// The duplicate keys found, with the line and column of their occurrences, from a single
+    /// scan of the input.
+    fn locate_duplicate_keys(&mut self) -> Vec<DuplicateKey> {
+        let reports = std::mem::take(&mut self.duplicate_keys);
+        let mut offsets: Vec<usize> = reports.iter().flat_map(|(_, o)| o).copied().collect();
+        offsets.sort_unstable();
+        offsets.dedup();
+        let input = self.lexer.input;
+        let (mut position, mut line, mut column) = (0, 1, 1);
+        let locations: Vec<(usize, usize)> = offsets
+            .iter()
+            .map(|&offset| {
+                let skipped = &input[position..offset];
+                match skipped.rfind('\n') {
+                    Some(i) => {
+                        line += skipped.matches('\n').count();
+                        column = skipped[i + 1..].chars().count() + 1;
+                    }
+                    None => column += skipped.chars().count(),
+                }
+                position = offset;
+                (line, column)
+            })
+            .collect();
+        let locate = |offset| locations[offsets.binary_search(&offset).unwrap()];
+        reports
+            .into_iter()
+            .map(|(path, occurrences)| DuplicateKey {
+                path,
+                occurrences: occurrences.into_iter().map(locate).collect(),
+            })
+            .collect()
+    }
+
+    fn parse_object(&mut self) -> Result<Value<'a>, JsonParseError> {
+        trace!(offset = self.offset(), path = %self.pointer(), "enter object");
+        let (start, spilled) = (self.offset(), self.spilled);
+        self.advance();
         let mut pairs = Vec::new();
-        let mut seen_keys = std::collections::HashSet::new();
+        // members are still parsed, but no longer kept, once the object is to be kept raw
+        let (mut len, mut spilling) = (0, false);
+        // with the offset and position of their first occurrence, and their index in
+        // `duplicate_keys`
+        let mut seen_keys = HashMap::with_hasher(self.options.key_hasher.clone());
+        let mut previous = None;
 
         loop {
             match self.current()? {
                 Some(Token::RBrace) => {
-                    self.position += 1;
-                    return Ok(Value::Object(pairs));
+                    self.attach_comments(previous, None);
+                    trace!(offset = self.offset(), members = len, "exit object");
+                    self.advance();
+                    return Ok(if spilling {
+                        self.spill(start, spilled)
+                    } else {
+                        Value::Object(pairs.into())
+                    });
                 }
                 Some(_) => {
-                    if !pairs.is_empty() {
+                    if len > 0 {
                         self.expect_skip(&Token::Comma)?;
+                        if self.trailing_comma(&Token::RBrace)? {
+                            continue;
+                        }
                     }
+                    // a missing key fails in `parse_pair` below
+                    let segment = match self.current()? {
+                        Some(Token::String(key) | Token::Identifier(key)) => {
+                            Some(PathSegment::Key(key))
+                        }
+                        _ => None,
+                    };
+                    self.attach_comments(previous, segment);
+                    let key_offset = self.offset();
                     let pair = self.parse_pair()?;
-                    if !seen_keys.insert(pair.key) {
-                        return Err(Box::new(JsonParseError(format!(
-                            "duplicate key: {}",
-                            pair.key
-                        ))));
+                    let policy = self.options.duplicate_keys;
+                    let mut duplicate_of = None;
+                    match seen_keys.entry(pair.key.clone()) {
+                        Entry::Vacant(e) => {
+                            e.insert((key_offset, pairs.len(), None));
+                        }
+                        Entry::Occupied(_) if policy == DuplicateKeyPolicy::Error => {
+                            return Err(self.error_at(
+                                ErrorKind::DuplicateKey,
+                                format!("duplicate key: {}", pair.key),
+                                key_offset,
+                            ));
+                        }
+                        Entry::Occupied(mut e) => {
+                            let (first, position, report) = e.get_mut();
+                            duplicate_of = Some(*position);
+                            let index = *report.get_or_insert_with(|| {
+                                let mut path = self.pointer();
+                                PathSegment::Key(&pair.key).push_to(&mut path);
+                                self.duplicate_keys.push((path, vec![*first]));
+                                self.duplicate_keys.len() - 1
+                            });
+                            self.duplicate_keys[index].1.push(key_offset);
+                        }
+                    }
+                    len += 1;
+                    if !spilling {
+                        match (duplicate_of, policy) {
+                            (Some(_), DuplicateKeyPolicy::FirstWins) => {}
+                            (Some(i), DuplicateKeyPolicy::LastWins) => pairs[i].value = pair.value,
+                            _ => pairs.push(pair),
+                        }
+                        spilling = self.spills(start, spilled);
+                        if spilling {
+                            pairs = Vec::new();
+                        }
                     }
-                    pairs.push(pair);
+                    previous = segment.map(|s| (s, self.previous.end));
                 }
-                None => return Err(Box::new(JsonParseError("unclosed object".to_string()))),
+                None => return Err(self.error(ErrorKind::UnexpectedEof, "unclosed object")),
             }
         }
     }
 
-    fn expect_string(&mut self) -> Result<&'a str, Box<dyn Error>> {
+    /// Whether the current token closes a container after a comma, which JSON5 and JavaScript
+    /// allow.
+    fn trailing_comma(&mut self, close: &Token) -> Result<bool, JsonParseError> {
+        let current = self.current()?;
+        let options = &self.lexer.options;
+        let allowed = options.dialect == Dialect::Json5 || options.js_object_literals;
+        Ok(allowed && current.as_ref() == Some(close))
+    }
+
+    /// Expects an object key: a string, or an identifier in JSON5.
+    fn expect_key(&mut self) -> Result<&'a str, JsonParseError> {
         use Token::*;
         match self.current()? {
-            Some(String(s)) => {
-                self.position += 1;
+            Some(String(s) | Identifier(s)) => {
+                self.advance();
                 Ok(s)
             }
-            Some(t) => Err(Box::new(JsonParseError(format!(
-                "expected string, but got {:?}",
-                t
-            )))),
-            None => Err(Box::new(JsonParseError(
-                "expected string, but input ended prematurely".to_string(),
-            ))),
+            Some(t) => Err(self.error(
+                ErrorKind::Syntax,
+                format!("expected string, but got {:?}", t),
+            )),
+            None => Err(self.error(
+                ErrorKind::UnexpectedEof,
+                "expected string, but input ended prematurely",
+            )),
         }
     }
 
-    fn expect_skip(&mut self, expected: &Token) -> Result<(), Box<dyn Error>> {
+    fn expect_skip(&mut self, expected: &Token) -> Result<(), JsonParseError> {
         use std::mem::discriminant;
         if let Some(t) = self.current()? {
             if discriminant(&t) == discriminant(expected) {
-                self.position += 1;
+                self.advance();
                 Ok(())
             } else {
-                Err(Box::new(JsonParseError(format!(
-                    "expected {expected:?}, but got {t:?}"
-                ))))
+                Err(self.error(
+                    ErrorKind::Syntax,
+                    format!("expected {expected:?}, but got {t:?}"),
+                ))
             }
         } else {
-            Err(Box::new(JsonParseError(format!(
-                "expected {expected:?}, but input ended prematurely"
-            ))))
+            Err(self.error(
+                ErrorKind::UnexpectedEof,
+                format!("expected {expected:?}, but input ended prematurely"),
+            ))
         }
     }
 
-    fn current(&mut self) -> Result<Option<Token<'a>>, LexError> {
-        let t = self.tokens.get(self.position);
-        if let Some(t) = t {
-            Ok(Some(t.clone()))
-        } else {
-            let t = self.lexer.next_token()?;
-            if let Some(u) = &t {
-                self.tokens.push(u.clone());
-                Ok(t)
-            } else {
-                Ok(None)
+    fn current(&mut self) -> Result<Option<Token<'a>>, JsonParseError> {
+        if let Some((t, _)) = &self.current {
+            return Ok(Some(t.clone()));
+        }
+        loop {
+            if self.lexed.is_multiple_of(CHECK_INTERVAL) {
+                if self
+                    .options
+                    .cancellation
+                    .as_ref()
+                    .is_some_and(|c| c.is_cancelled())
+                {
+                    return Err(self.error(ErrorKind::Cancelled, "parse cancelled"));
+                }
+                self.report_progress(self.previous.end);
+            }
+            let t = match &mut self.source {
+                Some(source) => source.next_spanned(),
+                None => self.lexer.next_spanned(),
+            }
+            .map_err(|e| self.error_at(e.kind(), e.message(), e.offset()))?;
+            let Some((t, span)) = t else {
+                if self.source.is_some() {
+                    self.lexer.byte_offset = self.lexer.input.len();
+                }
+                return Ok(None);
+            };
+            // the lexer tracks the position in the input for errors and metadata
+            self.lexer.byte_offset = span.end;
+            let offset = span.start;
+            match t {
+                Token::Comment(text) => {
+                    if self.options.attach_comments {
+                        self.pending_comments.push((offset, text));
+                    }
+                }
+                u => {
+                    self.lexed += 1;
+                    self.current = Some((u.clone(), span));
+                    return Ok(Some(u));
+                }
             }
         }
     }
 
-    fn parse_pair(&mut self) -> Result<Pair<'a>, Box<dyn Error>> {
-        let key = self.expect_string()?;
+    fn report_progress(&self, consumed: usize) {
+        if let Some(progress) = &self.options.progress {
+            progress.report(Progress {
+                consumed,
+                total: Some(self.lexer.input.len()),
+            });
+        }
+    }
+
+    fn parse_pair(&mut self) -> Result<Pair<'a>, JsonParseError> {
+        let raw = self.expect_key()?;
+        trace!(offset = self.previous.start, key = raw, "key");
+        self.record_key_span();
+        let key = self.string(raw);
         self.expect_skip(&Token::Colon)?;
+        self.path.push(PathSegment::Key(raw));
         let value = self.parse_value()?;
+        self.path.pop();
         Ok(Pair { key, value })
     }
+
+    /// The text of the string literal or identifier just consumed, converted to JSON and
+    /// decoded per the options.
+    fn string(&self, raw: &'a str) -> Cow<'a, str> {
+        let options = &self.lexer.options;
+        let escape = options.control_chars == ControlChars::Escape;
+        let json = match options.dialect {
+            Dialect::Json
+                if options.python_literals
+                    && self.lexer.input[self.previous.start..].starts_with('\'') =>
+            {
+                json5::string(raw, '\'', true)
+            }
+            Dialect::Json if options.strictness == Strictness::Lenient => {
+                json5::string(raw, '"', escape)
+            }
+            Dialect::Json if escape => ser::escape_control_chars(raw),
+            Dialect::Json => Cow::Borrowed(raw),
+            Dialect::Json5 => match self.lexer.input[self.previous.start..].chars().next() {
+                Some(quote @ ('"' | '\'')) => json5::string(raw, quote, true),
+                _ => Cow::Borrowed(raw),
+            },
+        };
+        match json {
+            Cow::Borrowed(json) if self.options.decode_escapes => mjl::unescape(json),
+            // the converted text is reused when it holds no escape sequence
+            Cow::Owned(json) if self.options.decode_escapes => match mjl::unescape(&json) {
+                Cow::Owned(decoded) => Cow::Owned(decoded),
+                Cow::Borrowed(_) => Cow::Owned(json),
+            },
+            json => json,
+        }
+    }
+
+    /// Whether the value from `start` to the last consumed token is longer than
+    /// [`ParseOptions::spill_above`], not counting the values kept raw since `spilled` was the
+    /// total length of those.
+    fn spills(&self, start: usize, spilled: usize) -> bool {
+        self.options
+            .spill_above
+            .is_some_and(|limit| self.previous.end - start - (self.spilled - spilled) > limit)
+    }
+
+    /// Keeps the value from `start` to the last consumed token raw.
+    fn spill(&mut self, start: usize, spilled: usize) -> Value<'a> {
+        self.spilled = spilled + self.previous.end - start;
+        Value::Raw(RawValue(Cow::Borrowed(
+            &self.lexer.input[start..self.previous.end],
+        )))
+    }
+
+    /// Consumes the current token.
+    fn advance(&mut self) {
+        if let Some((_, span)) = self.current.take() {
+            self.previous = span;
+        }
+    }
+
+    /// Byte offset of the current token, which may not have been lexed yet.
+    fn offset(&self) -> usize {
+        self.current
+            .as_ref()
+            .map(|(_, span)| span.start)
+            .unwrap_or_else(|| {
+                let rest = &self.lexer.input[self.lexer.byte_offset..];
+                self.lexer.byte_offset + rest.len() - rest.trim_start().len()
+            })
+    }
+
+    fn error(&self, kind: ErrorKind, message: impl Into<String>) -> JsonParseError {
+        self.error_at(kind, message, self.offset())
+    }
+
+    fn error_at(
+        &self,
+        kind: ErrorKind,
+        message: impl Into<String>,
+        offset: usize,
+    ) -> JsonParseError {
+        let (line, column) = mjl::line_column(self.lexer.input, offset);
+        JsonParseError::new(kind, message, offset, (line, column), self.pointer())
+            .with_hint(self.lexer.input)
+    }
+
+    /// JSON pointer to the value being parsed.
+    fn pointer(&self) -> std::string::String {
+        let mut path = std::string::String::new();
+        for segment in &self.path {
+            segment.push_to(&mut path);
+        }
+        path
+    }
 }
 
-pub fn parse(lexer: JsonLexer) -> Result<Json, Box<dyn Error>> {
-    let mut p = JsonParser {
-        lexer,
-        tokens: Vec::new(),
-        position: 0,
-    };
+/// Name of the kind of `value`, for trace events.
+#[cfg(feature = "tracing")]
+fn kind_name(value: &Value) -> &'static str {
+    match value {
+        Value::Object(_) => "object",
+        Value::Array(_) => "array",
+        Value::Str(_) => "string",
+        Value::Number(_) => "number",
+        Value::Boolean(_) => "boolean",
+        Value::Null => "null",
+        Value::Raw(_) => "raw",
+    }
+}
+
+pub fn parse(lexer: JsonLexer) -> Result<Json, JsonParseError> {
+    JsonParser::new(lexer).parse_json()
+}
+
+pub fn parse_with(lexer: JsonLexer, options: ParseOptions) -> Result<Json, JsonParseError> {
+    JsonParser::with_options(lexer, options).parse_json()
+}
+
+/// Parses a document from text, borrowing its strings from `input`.
+pub fn parse_str(input: &str) -> Result<Json<'_>, JsonParseError> {
+    parse(JsonLexer::new(input))
+}
+
+/// Parses the tokens of `source`, e.g. to parse a [recorded](mjl::Recording) document many
+/// times without lexing it again.
+pub fn parse_source<'a>(
+    source: impl TokenSource<'a> + 'a,
+    options: ParseOptions,
+) -> Result<Json<'a>, JsonParseError> {
+    JsonParser::from_source(source, options).parse_json()
+}
 
-    p.parse_json()
+/// Parses a document from bytes, handling invalid UTF-8 according to
+/// [`ParseOptions::invalid_utf8`].
+///
+/// UTF-16 and UTF-32 input, told apart by [`mjl::detect_encoding`], is decoded first, and must
+/// be valid. Error offsets then count bytes of its UTF-8 text.
+pub fn parse_bytes(bytes: &[u8], options: ParseOptions) -> Result<Json<'_>, JsonParseError> {
+    let input = decode_bytes(bytes, options.invalid_utf8)?;
+    let mut json = match input {
+        Cow::Borrowed(input) => parse_with(JsonLexer::new(input), options),
+        Cow::Owned(input) => parse_with(JsonLexer::new(&input), options).map(Json::into_owned),
+    }?;
+    // the decoded input differs in length when invalid UTF-8 was replaced
+    json.metadata.byte_length = bytes.len();
+    Ok(json)
 }
 
-#[derive(Debug)]
-pub struct JsonParseError(String);
+/// Decodes byte input as text, according to `policy` for UTF-8.
+pub(crate) fn decode_bytes(
+    bytes: &[u8],
+    policy: Utf8Policy,
+) -> Result<Cow<'_, str>, JsonParseError> {
+    if mjl::detect_encoding(bytes) != Encoding::Utf8 {
+        return mjl::encoding::decode(bytes).map_err(|e| {
+            // everything up to the error decodes
+            let valid = mjl::encoding::decode(&bytes[..e.offset()]).unwrap();
+            let at = mjl::line_column(&valid, valid.len());
+            JsonParseError::new(
+                e.kind(),
+                e.message(),
+                e.offset(),
+                at,
+                std::string::String::new(),
+            )
+        });
+    }
+    utf8::decode(bytes, policy).map_err(|e| {
+        let offset = e.valid_up_to();
+        JsonParseError::new(
+            ErrorKind::InvalidUtf8,
+            "invalid UTF-8 in input",
+            offset,
+            // everything up to the error is valid UTF-8
+            mjl::line_column(std::str::from_utf8(&bytes[..offset]).unwrap(), offset),
+            std::string::String::new(),
+        )
+    })
+}
+
+/// Reads `reader` to the end, or up to [`ParseOptions::max_bytes`], and parses its contents as
+/// a document.
+pub fn from_reader<R: std::io::Read>(
+    reader: R,
+    options: ParseOptions,
+) -> Result<Json<'static>, JsonParseError> {
+    use std::io::Read;
+
+    let mut bytes = Vec::new();
+    let result = reader.take(read_limit(&options)).read_to_end(&mut bytes);
+    read_document(result, &bytes, options)
+}
+
+/// Reads `reader` to the end, or up to [`ParseOptions::max_bytes`], and parses its contents as
+/// a document.
+#[cfg(feature = "tokio")]
+pub async fn from_async_reader<R: tokio::io::AsyncRead + Unpin>(
+    reader: R,
+    options: ParseOptions,
+) -> Result<Json<'static>, JsonParseError> {
+    use tokio::io::AsyncReadExt;
+
+    let mut bytes = Vec::new();
+    let result = reader
+        .take(read_limit(&options))
+        .read_to_end(&mut bytes)
+        .await;
+    read_document(result, &bytes, options)
+}
+
+/// Number of bytes to read: one past [`ParseOptions::max_bytes`], to tell whether it is
+/// exceeded.
+pub(crate) fn read_limit(options: &ParseOptions) -> u64 {
+    options
+        .max_bytes
+        .map_or(u64::MAX, |max| (max as u64).saturating_add(1))
+}
+
+/// Parses the `bytes` read by a reader-based entry point, given the result of the read.
+pub(crate) fn read_document(
+    result: std::io::Result<usize>,
+    bytes: &[u8],
+    options: ParseOptions,
+) -> Result<Json<'static>, JsonParseError> {
+    if let Err(e) = result {
+        return Err(JsonParseError::new(
+            ErrorKind::Io,
+            e.to_string(),
+            bytes.len(),
+            (1, 1),
+            std::string::String::new(),
+        ));
+    }
+    if let Some(max) = options.max_bytes
+        && bytes.len() > max
+    {
+        let before = String::from_utf8_lossy(&bytes[..max]);
+        return Err(JsonParseError::new(
+            ErrorKind::LimitExceeded,
+            format!("document exceeds the limit of {max} bytes"),
+            max,
+            mjl::line_column(&before, before.len()),
+            std::string::String::new(),
+        ));
+    }
+    parse_bytes(bytes, options).map(Json::into_owned)
+}
+
+/// An error raised while parsing a document.
+///
+/// With the `serde` feature, this serializes to a stable shape meant to be returned directly in
+/// API responses: `{ "code", "message", "line", "column", "path" }`, where `path` is a JSON
+/// pointer to the value being parsed when the error occurred, and `"hint"` when there is one.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct JsonParseError {
+    #[cfg_attr(
+        feature = "serde",
+        serde(rename = "code", serialize_with = "serialize_kind")
+    )]
+    kind: ErrorKind,
+    message: String,
+    line: usize,
+    column: usize,
+    path: String,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    hint: Option<String>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    offset: usize,
+}
+
+impl JsonParseError {
+    pub(crate) fn new(
+        kind: ErrorKind,
+        message: impl Into<String>,
+        offset: usize,
+        (line, column): (usize, usize),
+        path: String,
+    ) -> Self {
+        JsonParseError {
+            kind,
+            message: message.into(),
+            line,
+            column,
+            path,
+            hint: None,
+            offset,
+        }
+    }
+
+    /// Adds a hint for the mistake made at the error's offset in `input`, if it is a common one.
+    pub(crate) fn with_hint(self, input: &str) -> Self {
+        JsonParseError {
+            hint: hint::suggest(input, self.offset),
+            ..self
+        }
+    }
+
+    /// Moves an error raised while parsing a fragment of a larger input so it is reported
+    /// relative to that input. `offset`, `(line, column)` and `path` locate the fragment.
+    pub(crate) fn relocate(
+        self,
+        offset: usize,
+        (line, column): (usize, usize),
+        path: &str,
+    ) -> Self {
+        JsonParseError {
+            column: if self.line == 1 {
+                column + self.column - 1
+            } else {
+                self.column
+            },
+            line: line + self.line - 1,
+            offset: offset + self.offset,
+            path: format!("{path}{}", self.path),
+            ..self
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Stable identifier of the error's kind, see [`ErrorKind::code`].
+    pub fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Suggestion for fixing a common mistake, such as `did you mean "true"?` for a Python
+    /// `True`.
+    pub fn hint(&self) -> Option<&str> {
+        self.hint.as_deref()
+    }
+
+    /// 1-based line of the input at which the error occurred.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// 1-based column, counted in chars, at which the error occurred.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// JSON pointer to the value that was being parsed, `""` for the root value.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Byte offset into the input at which the error occurred.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
 
 impl Display for JsonParseError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        write!(
+            f,
+            "{} at line {}, column {}",
+            self.message, self.line, self.column
+        )?;
+        match &self.hint {
+            Some(hint) => write!(f, "; {hint}"),
+            None => Ok(()),
+        }
     }
 }
 
 impl Error for JsonParseError {}
+
+#[cfg(feature = "serde")]
+fn serialize_kind<S: serde::Serializer>(
+    kind: &ErrorKind,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(kind.code())
+}
+
+#[cfg(test)]
+mod test {
+    use mjl::JsonLexer;
+
+    use crate::{
+        CancellationToken, DuplicateKey, DuplicateKeyPolicy, ErrorKind, Json, ParseOptions, Value,
+        parse, parse_source, parse_str, parse_with,
+    };
+
+    #[test]
+    fn error_reports_location_and_path() {
+        let err = parse(JsonLexer::new("{\n  \"a\": [1, {\"b/c\": tru}]\n}")).unwrap_err();
+        assert_eq!(ErrorKind::Syntax, err.kind());
+        assert_eq!((2, 20), (err.line(), err.column()));
+        assert_eq!("/a/1/b~1c", err.path());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize_errors() {
+        let err = parse(JsonLexer::new("{\n  \"a\": [1, {\"b/c\": tru}]\n}")).unwrap_err();
+        assert_eq!(
+            serde_json::json!({
+                "code": err.kind().code(),
+                "message": err.message(),
+                "line": 2,
+                "column": 20,
+                "path": "/a/1/b~1c",
+            }),
+            serde_json::to_value(&err).unwrap()
+        );
+
+        let err = parse(JsonLexer::new("{'a': 1}")).unwrap_err();
+        let hint = err.hint().expect("a hint about single quotes");
+        assert_eq!(
+            serde_json::json!({
+                "code": err.kind().code(),
+                "message": err.message(),
+                "line": 1,
+                "column": 2,
+                "path": "",
+                "hint": hint,
+            }),
+            serde_json::to_value(&err).unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_from_str() {
+        let input = String::from(r#"{"a": [1]}"#);
+        assert_eq!(
+            parse(JsonLexer::new(&input)).unwrap().value,
+            parse_str(&input).unwrap().value
+        );
+        let json: Json = input.parse().unwrap();
+        assert_eq!(Value::Number("1".into()), json.value["a"][0]);
+        assert_eq!(
+            ErrorKind::Syntax,
+            "[1,]".parse::<Json>().unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn duplicate_key_points_at_key() {
+        let err = parse(JsonLexer::new(r#"{"a": 1, "a": 2}"#)).unwrap_err();
+        assert_eq!(ErrorKind::DuplicateKey, err.kind());
+        assert_eq!((1, 10), (err.line(), err.column()));
+        assert_eq!("", err.path());
+    }
+
+    #[test]
+    fn report_duplicate_keys() {
+        let options = ParseOptions {
+            duplicate_keys: DuplicateKeyPolicy::KeepAll,
+            ..Default::default()
+        };
+        let input = "{\"a\": 1, \"b\": [{\"c\": 1,\n\"c\": 2, \"c\": 3}], \"a\": 4}";
+        let json = parse_with(JsonLexer::new(input), options).unwrap();
+        assert_eq!(
+            vec![
+                DuplicateKey {
+                    path: "/b/0/c".to_string(),
+                    occurrences: vec![(1, 17), (2, 1), (2, 9)],
+                },
+                DuplicateKey {
+                    path: "/a".to_string(),
+                    occurrences: vec![(1, 2), (2, 19)],
+                },
+            ],
+            json.duplicate_keys
+        );
+        match json.value {
+            crate::Value::Object(pairs) => assert_eq!(3, pairs.len()),
+            v => panic!("unexpected value {v:?}"),
+        }
+    }
+
+    #[test]
+    fn duplicate_key_policies() {
+        let input = r#"{"a": 1, "b": {"c": 1, "c": 2}, "a": 3, "d": 4, "a": 5}"#;
+        let parse = |policy| {
+            let options = ParseOptions {
+                duplicate_keys: policy,
+                ..Default::default()
+            };
+            parse_with(JsonLexer::new(input), options).unwrap()
+        };
+        let json = parse(DuplicateKeyPolicy::FirstWins);
+        assert_eq!(
+            parse_str(r#"{"a": 1, "b": {"c": 1}, "d": 4}"#)
+                .unwrap()
+                .value,
+            json.value
+        );
+        assert_eq!(2, json.duplicate_keys.len());
+        assert_eq!(
+            parse_str(r#"{"a": 5, "b": {"c": 2}, "d": 4}"#)
+                .unwrap()
+                .value,
+            parse(DuplicateKeyPolicy::LastWins).value
+        );
+        assert_eq!(
+            ErrorKind::DuplicateKey,
+            parse_str(r#"{"a": 5, "a": 6}"#).unwrap_err().kind()
+        );
+        // escaped keys are duplicates once decoded
+        let input = r#"{"a": 1, "\u0061": 2}"#;
+        assert!(parse_str(input).is_ok());
+        let options = ParseOptions {
+            decode_escapes: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            ErrorKind::DuplicateKey,
+            parse_with(JsonLexer::new(input), options)
+                .unwrap_err()
+                .kind()
+        );
+    }
+
+    #[test]
+    fn fast_key_hasher() {
+        let options = ParseOptions {
+            key_hasher: crate::KeyHasher::Fast,
+            ..Default::default()
+        };
+        parse_with(JsonLexer::new(r#"{"a": 1, "b": 2}"#), options.clone()).unwrap();
+        let err = parse_with(JsonLexer::new(r#"{"a": 1, "b": 2, "a": 3}"#), options).unwrap_err();
+        assert_eq!(ErrorKind::DuplicateKey, err.kind());
+    }
+
+    #[test]
+    fn parse_recorded_tokens() {
+        let input = "{\"a\": [1, true], // c\n \"b\": null}";
+        let lexer = JsonLexer::with_options(
+            input,
+            mjl::LexerOptions {
+                comments: mjl::CommentMode::Emit,
+                ..Default::default()
+            },
+        );
+        let recording = mjl::Recording::record(lexer);
+        let options = ParseOptions {
+            attach_comments: true,
+            ..Default::default()
+        };
+        let expected = parse_with(
+            JsonLexer::with_options(
+                input,
+                mjl::LexerOptions {
+                    comments: mjl::CommentMode::Emit,
+                    ..Default::default()
+                },
+            ),
+            options.clone(),
+        )
+        .unwrap();
+        for _ in 0..2 {
+            let json = parse_source(recording.replay(), options.clone()).unwrap();
+            assert_eq!(expected.value, json.value);
+            assert_eq!(expected.comments, json.comments);
+        }
+
+        let recording = mjl::Recording::record(JsonLexer::new("[1,\n 2 3]"));
+        let err = parse_source(recording.replay(), ParseOptions::default()).unwrap_err();
+        assert_eq!(ErrorKind::Syntax, err.kind());
+        assert_eq!((2, 4), (err.line(), err.column()));
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn trace_events() {
+        use std::{
+            fmt::Debug,
+            sync::{Arc, Mutex},
+        };
+
+        use tracing::{
+            Event, Id, Metadata, Subscriber,
+            field::{Field, Visit},
+            span::{Attributes, Record},
+        };
+
+        /// Collects the fields of events as `name=value` text.
+        #[derive(Clone, Default)]
+        struct Collect(Arc<Mutex<Vec<std::string::String>>>);
+
+        struct Fields(std::string::String);
+
+        impl Visit for Fields {
+            fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+                self.0 += &format!(" {}={value:?}", field.name());
+            }
+        }
+
+        impl Subscriber for Collect {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, _: &Attributes<'_>) -> Id {
+                Id::from_u64(1)
+            }
+
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+            fn event(&self, event: &Event<'_>) {
+                let mut fields = Fields(std::string::String::new());
+                event.record(&mut fields);
+                self.0.lock().unwrap().push(fields.0);
+            }
+
+            fn enter(&self, _: &Id) {}
+
+            fn exit(&self, _: &Id) {}
+        }
+
+        let collect = Collect::default();
+        tracing::subscriber::with_default(collect.clone(), || {
+            parse(JsonLexer::new(r#"{"a": [1]}"#)).unwrap();
+            parse(JsonLexer::new("[1 2]")).unwrap_err();
+        });
+        let events = collect.0.lock().unwrap();
+        assert_eq!(
+            &events[..8],
+            [
+                " message=enter object offset=0 path=",
+                " message=key offset=1 key=\"a\"",
+                " message=enter array offset=6 path=/a",
+                " message=value offset=7 end=8 path=/a/0 kind=\"number\"",
+                " message=exit array offset=8 elements=1",
+                " message=value offset=6 end=9 path=/a kind=\"array\"",
+                " message=exit object offset=9 members=1",
+                " message=value offset=0 end=10 path= kind=\"object\"",
+            ]
+        );
+        assert!(events[8].starts_with(" message=parsed values=3 max_depth=2"));
+        assert!(
+            events
+                .last()
+                .unwrap()
+                .starts_with(" message=failed offset=3 path=\"\" error=")
+        );
+    }
+
+    #[test]
+    fn parse_json5() {
+        let input = "// config\n{unquoted: 'it\\'s \"x\"', hex: 0x1F, n: [+1, .5, 5., Infinity,],\n \
+                     'multi': 'a\\\nb', nested: {t: true,},}";
+        let options = mjl::LexerOptions {
+            dialect: mjl::Dialect::Json5,
+            ..Default::default()
+        };
+        let json5 = parse(JsonLexer::with_options(input, options.clone())).unwrap();
+        let json = r#"{"unquoted": "it's \"x\"", "hex": 31, "n": [1, 0.5, 5, Infinity],
+                       "multi": "ab", "nested": {"t": true}}"#;
+        // Infinity has no JSON equivalent, so the lexer would reject it
+        let json = json.replace("Infinity", "1");
+        let mut expected = parse(JsonLexer::new(&json)).unwrap().value;
+        *expected.pointer_mut("/n/3").unwrap() = crate::Value::Number("Infinity".into());
+        assert_eq!(expected, json5.value);
+
+        let recording = mjl::Recording::record(JsonLexer::with_options(input, options.clone()));
+        let replayed = parse_source(recording.replay(), ParseOptions::default()).unwrap();
+        assert_eq!(expected, replayed.value);
+
+        for invalid in ["[1,,]", "[,]", "{a: 1,,}", "{,}", "[a]"] {
+            let lexer = JsonLexer::with_options(invalid, options.clone());
+            assert!(parse(lexer).is_err(), "{invalid}");
+        }
+        assert!(parse(JsonLexer::new("[1,]")).is_err());
+    }
+
+    #[test]
+    fn parse_lenient() {
+        let options = ParseOptions {
+            decode_escapes: true,
+            ..Default::default()
+        };
+        let input = "[\"it\\'s\", \"tab\there\", \"\\x41\", 007, +1, .5, 5., -0.10e1]";
+        let lexer = JsonLexer::with_options(
+            input,
+            mjl::LexerOptions {
+                strictness: mjl::Strictness::Lenient,
+                ..Default::default()
+            },
+        );
+        let lenient = parse_with(lexer, options.clone()).unwrap();
+        let json = r#"["it's", "tab\there", "A", 7, 1, 0.5, 5, -0.10e1]"#;
+        let expected = parse_with(JsonLexer::new(json), options).unwrap();
+        assert_eq!(expected.value, lenient.value);
+        assert!(parse(JsonLexer::new(input)).is_err());
+    }
+
+    #[test]
+    fn parse_python_literals() {
+        let input = r#"{'name': 'it\'s "x"', 'ok': True, "none": None, 'n': [False, 1.5]}"#;
+        let json = r#"{"name": "it's \"x\"", "ok": true, "none": null, "n": [false, 1.5]}"#;
+        let expected = parse(JsonLexer::new(json)).unwrap().value;
+        for dialect in [mjl::Dialect::Json, mjl::Dialect::Json5] {
+            let options = mjl::LexerOptions {
+                python_literals: true,
+                dialect,
+                ..Default::default()
+            };
+            let python = parse(JsonLexer::with_options(input, options)).unwrap();
+            assert_eq!(expected, python.value);
+        }
+        assert!(parse(JsonLexer::new(input)).is_err());
+        let json5 = mjl::LexerOptions {
+            dialect: mjl::Dialect::Json5,
+            ..Default::default()
+        };
+        // `True` is a key in JSON5, but not a value
+        assert!(parse(JsonLexer::with_options("[True]", json5)).is_err());
+    }
+
+    #[test]
+    fn parse_js_object_literals() {
+        let options = mjl::LexerOptions {
+            js_object_literals: true,
+            ..Default::default()
+        };
+        let input = "{id: 1, $type: \"user\", tags: [\"a\", null,], nested: {ok: true,},}";
+        let json = r#"{"id": 1, "$type": "user", "tags": ["a", null], "nested": {"ok": true}}"#;
+        let expected = parse(JsonLexer::new(json)).unwrap().value;
+        let js = parse(JsonLexer::with_options(input, options.clone())).unwrap();
+        assert_eq!(expected, js.value);
+        // recorded tokens replay the same
+        let recording = mjl::Recording::record(JsonLexer::with_options(input, options.clone()));
+        let replayed = parse_source(recording.replay(), ParseOptions::default()).unwrap();
+        assert_eq!(expected, replayed.value);
+
+        // the rest of JSON5 is still rejected
+        for invalid in [
+            "{a: 'b'}",
+            "[0x1F]",
+            "[Infinity]",
+            "[.5]",
+            "{a: 1} // c",
+            "[a]",
+        ] {
+            let lexer = JsonLexer::with_options(invalid, options.clone());
+            assert!(parse(lexer).is_err(), "{invalid}");
+        }
+        assert!(parse(JsonLexer::new("{a: 1}")).is_err());
+    }
+
+    #[test]
+    fn decode_escapes() {
+        let options = ParseOptions {
+            decode_escapes: true,
+            ..Default::default()
+        };
+        let input = r#"{"a\u0062": "as\"df\n\ud83d\ude00"}"#;
+        let json = parse_with(JsonLexer::new(input), options).unwrap();
+        match &json.value {
+            crate::Value::Object(pairs) => {
+                assert_eq!("ab", pairs[0].key);
+                assert_eq!(crate::Value::Str("as\"df\n😀".into()), pairs[0].value);
+            }
+            v => panic!("unexpected value {v:?}"),
+        }
+
+        let options = crate::ser::WriteOptions {
+            decoded: true,
+            ..Default::default()
+        };
+        let mut out = String::new();
+        crate::ser::write_value(&json.value, &options, &mut out).unwrap();
+        assert_eq!(r#"{"ab":"as\"df\n😀"}"#, out);
+    }
+
+    #[test]
+    fn control_chars() {
+        let input = "{\"a\tkey\": \"line\nbreak\"}";
+        assert!(parse(JsonLexer::new(input)).is_err());
+        let write = |value: &crate::Value| {
+            let mut out = String::new();
+            crate::ser::write_value(value, &Default::default(), &mut out).unwrap();
+            out
+        };
+        let lexer = |control_chars| {
+            let options = mjl::LexerOptions {
+                control_chars,
+                ..Default::default()
+            };
+            JsonLexer::with_options(input, options)
+        };
+
+        let preserved = parse(lexer(mjl::ControlChars::Preserve)).unwrap();
+        assert_eq!("{\"a\tkey\":\"line\nbreak\"}", write(&preserved.value));
+
+        let escaped = parse(lexer(mjl::ControlChars::Escape)).unwrap();
+        assert_eq!(r#"{"a\u0009key":"line\u000abreak"}"#, write(&escaped.value));
+        let options = ParseOptions {
+            decode_escapes: true,
+            ..Default::default()
+        };
+        let decoded = parse_with(lexer(mjl::ControlChars::Escape), options).unwrap();
+        assert_eq!(
+            Some(&crate::Value::Str("line\nbreak".into())),
+            decoded.value.pointer("/a\tkey")
+        );
+    }
+
+    #[test]
+    fn metadata() {
+        let options = ParseOptions {
+            source: Some("data.json".to_string()),
+            ..Default::default()
+        };
+        let json = crate::parse_bytes(br#"{"a": [1, {"b": null}]}"#, options).unwrap();
+        assert_eq!(Some("data.json"), json.metadata.source.as_deref());
+        assert_eq!(23, json.metadata.byte_length);
+        assert_eq!(5, json.metadata.values);
+        assert_eq!(3, json.metadata.max_depth);
+    }
+
+    #[test]
+    fn limit_document_size() {
+        let options = |max_bytes| ParseOptions {
+            max_bytes,
+            ..Default::default()
+        };
+        let input = "[1,\n 2]";
+        let json = crate::from_reader(input.as_bytes(), options(Some(7))).unwrap();
+        assert_eq!(7, json.metadata.byte_length);
+        let err = crate::from_reader(input.as_bytes(), options(Some(6))).unwrap_err();
+        assert_eq!(ErrorKind::LimitExceeded, err.kind());
+        assert_eq!((6, 2, 3), (err.offset(), err.line(), err.column()));
+        // an endless stream is read no further than the limit
+        let endless = std::io::repeat(b' ');
+        let err = crate::from_reader(endless, options(Some(1 << 20))).unwrap_err();
+        assert_eq!(ErrorKind::LimitExceeded, err.kind());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn limit_async_document_size() {
+        let options = ParseOptions {
+            max_bytes: Some(1 << 20),
+            ..Default::default()
+        };
+        let json = crate::from_async_reader(&b"[true]"[..], options.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            crate::Value::Array(vec![crate::Value::Boolean(crate::BooleanVal::True)]),
+            json.value
+        );
+        let err = crate::from_async_reader(tokio::io::repeat(b' '), options)
+            .await
+            .unwrap_err();
+        assert_eq!(ErrorKind::LimitExceeded, err.kind());
+    }
+
+    #[test]
+    fn truncated_input_is_unexpected_eof() {
+        for input in ["[1, ", "{\"a\": tr", "\"abc", "-", "[1"] {
+            let err = parse(JsonLexer::new(input)).unwrap_err();
+            assert_eq!(ErrorKind::UnexpectedEof, err.kind(), "{input}");
+        }
+    }
+
+    #[test]
+    fn cancelled_parse_aborts() {
+        let input = format!("[{}0]", "0, ".repeat(5000));
+        let cancellation = CancellationToken::new();
+        let options = ParseOptions {
+            cancellation: Some(cancellation.clone()),
+            ..Default::default()
+        };
+        assert!(parse_with(JsonLexer::new(&input), options.clone(),).is_ok());
+
+        cancellation.cancel();
+        let err = parse_with(JsonLexer::new(&input), options).unwrap_err();
+        assert_eq!(ErrorKind::Cancelled, err.kind());
+    }
+
+    #[test]
+    fn report_progress() {
+        let input = format!("[{}0]", "0, ".repeat(5000));
+        let reports = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = reports.clone();
+        let options = ParseOptions {
+            progress: Some(crate::ProgressHook::new(move |p| {
+                sink.lock().unwrap().push(p)
+            })),
+            ..Default::default()
+        };
+        parse_with(JsonLexer::new(&input), options).unwrap();
+        let reports = reports.lock().unwrap();
+        // every 1024 of the 10003 tokens, then at the end
+        assert_eq!(11, reports.len());
+        assert_eq!(0, reports[0].consumed);
+        assert!(reports.windows(2).all(|w| w[0].consumed < w[1].consumed));
+        assert!(reports.iter().all(|p| p.total == Some(input.len())));
+        assert_eq!(input.len(), reports[10].consumed);
+    }
+}
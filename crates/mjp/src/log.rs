@@ -0,0 +1,96 @@
+//! Recording values as `tracing`/`log` fields.
+//!
+//! A value can be recorded as a single compact JSON string through its `Display`,
+//! `tracing::info!(payload = %value)`, or, with the `valuable` feature, as a structured field,
+//! `tracing::info!(payload = tracing::field::valuable(&value))`.
+
+#[cfg(feature = "valuable")]
+mod valuable_impl {
+    use valuable::{Listable, Mappable, Valuable, Visit};
+
+    use crate::{BooleanVal, Value};
+
+    impl Valuable for Value<'_> {
+        fn as_value(&self) -> valuable::Value<'_> {
+            match self {
+                Value::Object(_) => valuable::Value::Mappable(self),
+                Value::Array(_) => valuable::Value::Listable(self),
+                Value::Str(s) => valuable::Value::String(s),
+                Value::Number(n) => {
+                    if n.is_i64()
+                        && let Some(i) = n.as_i64()
+                    {
+                        valuable::Value::I64(i)
+                    } else if n.is_u64()
+                        && let Some(u) = n.as_u64()
+                    {
+                        valuable::Value::U64(u)
+                    } else {
+                        n.as_f64()
+                            .map(valuable::Value::F64)
+                            .unwrap_or(valuable::Value::String(n.as_str()))
+                    }
+                }
+                Value::Boolean(b) => valuable::Value::Bool(matches!(b, BooleanVal::True)),
+                Value::Null => valuable::Value::Unit,
+                Value::Raw(raw) => valuable::Value::String(raw.get()),
+            }
+        }
+
+        fn visit(&self, visit: &mut dyn Visit) {
+            match self {
+                Value::Object(pairs) => {
+                    for pair in pairs {
+                        visit
+                            .visit_entry(valuable::Value::String(&pair.key), pair.value.as_value());
+                    }
+                }
+                Value::Array(values) => {
+                    for value in values {
+                        visit.visit_value(value.as_value());
+                    }
+                }
+                v => visit.visit_value(v.as_value()),
+            }
+        }
+    }
+
+    impl Mappable for Value<'_> {
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            let len = match self {
+                Value::Object(pairs) => pairs.len(),
+                _ => 0,
+            };
+            (len, Some(len))
+        }
+    }
+
+    impl Listable for Value<'_> {
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            let len = match self {
+                Value::Array(values) => values.len(),
+                _ => 0,
+            };
+            (len, Some(len))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mjl::JsonLexer;
+
+    use crate::parse;
+
+    #[test]
+    fn compact_strips_whitespace() {
+        let json = parse(JsonLexer::new(
+            "{ \"a\": [1, true, null], \"b\\n\": \"🗻\\u00e9\" }",
+        ))
+        .unwrap();
+        assert_eq!(
+            "{\"a\":[1,true,null],\"b\\n\":\"🗻\\u00e9\"}",
+            json.value.to_string()
+        );
+    }
+}
@@ -0,0 +1,327 @@
+//! Lookup of object members by key, of array elements by position, and of descendants by JSON
+//! pointer.
+//!
+//! Keys are matched against the raw text of object keys, escapes included. When an object has
+//! several members matching a key, the first one is returned. Exact keys are looked up through
+//! the index of [`Object`](crate::Object), in constant time.
+
+use std::ops::{Index, IndexMut};
+
+use crate::{Pair, Value};
+
+/// A key or position looked up by [`Value::get`] and by indexing: a string is the key of an
+/// object member, and a `usize` the position of an array element.
+pub trait ValueIndex {
+    fn index_into<'v, 'a>(&self, value: &'v Value<'a>) -> Option<&'v Value<'a>>;
+
+    fn index_into_mut<'v, 'a>(&self, value: &'v mut Value<'a>) -> Option<&'v mut Value<'a>>;
+}
+
+impl ValueIndex for str {
+    fn index_into<'v, 'a>(&self, value: &'v Value<'a>) -> Option<&'v Value<'a>> {
+        match value {
+            Value::Object(pairs) => pairs.get(self),
+            _ => None,
+        }
+    }
+
+    fn index_into_mut<'v, 'a>(&self, value: &'v mut Value<'a>) -> Option<&'v mut Value<'a>> {
+        match value {
+            Value::Object(pairs) => pairs.get_mut(self),
+            _ => None,
+        }
+    }
+}
+
+impl ValueIndex for String {
+    fn index_into<'v, 'a>(&self, value: &'v Value<'a>) -> Option<&'v Value<'a>> {
+        self.as_str().index_into(value)
+    }
+
+    fn index_into_mut<'v, 'a>(&self, value: &'v mut Value<'a>) -> Option<&'v mut Value<'a>> {
+        self.as_str().index_into_mut(value)
+    }
+}
+
+impl ValueIndex for usize {
+    fn index_into<'v, 'a>(&self, value: &'v Value<'a>) -> Option<&'v Value<'a>> {
+        match value {
+            Value::Array(values) => values.get(*self),
+            _ => None,
+        }
+    }
+
+    fn index_into_mut<'v, 'a>(&self, value: &'v mut Value<'a>) -> Option<&'v mut Value<'a>> {
+        match value {
+            Value::Array(values) => values.get_mut(*self),
+            _ => None,
+        }
+    }
+}
+
+impl<T: ValueIndex + ?Sized> ValueIndex for &T {
+    fn index_into<'v, 'a>(&self, value: &'v Value<'a>) -> Option<&'v Value<'a>> {
+        (**self).index_into(value)
+    }
+
+    fn index_into_mut<'v, 'a>(&self, value: &'v mut Value<'a>) -> Option<&'v mut Value<'a>> {
+        (**self).index_into_mut(value)
+    }
+}
+
+static NULL: Value<'static> = Value::Null;
+
+/// `value["key"]` and `value[0]`, which are `null` when there is no such member or element, so
+/// that lookups can be chained as in `value["items"][0]["id"]`.
+impl<'a, I: ValueIndex> Index<I> for Value<'a> {
+    type Output = Value<'a>;
+
+    fn index(&self, index: I) -> &Value<'a> {
+        index.index_into(self).unwrap_or(&NULL)
+    }
+}
+
+/// Mutable `value["key"]` and `value[0]`, which panic when there is no such member or element.
+impl<I: ValueIndex> IndexMut<I> for Value<'_> {
+    fn index_mut(&mut self, index: I) -> &mut Self {
+        match index.index_into_mut(self) {
+            Some(value) => value,
+            None => panic!("no such member or element"),
+        }
+    }
+}
+
+impl<'a> Value<'a> {
+    /// Returns the value of the member `index`, if this is an object that has one, or of the
+    /// element at `index`, if this is an array that long.
+    pub fn get<I: ValueIndex>(&self, index: I) -> Option<&Value<'a>> {
+        index.index_into(self)
+    }
+
+    pub fn get_mut<I: ValueIndex>(&mut self, index: I) -> Option<&mut Value<'a>> {
+        index.index_into_mut(self)
+    }
+
+    /// Like [`Value::get`], with ASCII letters matching regardless of case, as for HTTP header
+    /// names: `get_ignore_ascii_case("Content-Type")` finds a `content-type` member.
+    pub fn get_ignore_ascii_case(&self, key: &str) -> Option<&Value<'a>> {
+        self.find(|k| k.eq_ignore_ascii_case(key))
+    }
+
+    pub fn get_ignore_ascii_case_mut(&mut self, key: &str) -> Option<&mut Value<'a>> {
+        self.find_mut(|k| k.eq_ignore_ascii_case(key))
+    }
+
+    /// Returns a view of this object whose lookups ignore the case of ASCII letters, if this is
+    /// an object.
+    pub fn as_case_insensitive(&self) -> Option<CaseInsensitive<'_, 'a>> {
+        match self {
+            Value::Object(pairs) => Some(CaseInsensitive(pairs)),
+            _ => None,
+        }
+    }
+
+    /// Returns the value at `pointer`, if any.
+    pub fn pointer(&self, pointer: &str) -> Option<&Value<'a>> {
+        let mut value = self;
+        for token in pointer_tokens(pointer)? {
+            value = match value {
+                Value::Array(values) => values.get(array_index(&token)?)?,
+                value => value.get(&token)?,
+            };
+        }
+        Some(value)
+    }
+
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Value<'a>> {
+        let mut value = self;
+        for token in pointer_tokens(pointer)? {
+            value = match value {
+                Value::Array(values) => values.get_mut(array_index(&token)?)?,
+                value => value.get_mut(&token)?,
+            };
+        }
+        Some(value)
+    }
+
+    /// Returns a copy of the value at `pointer`, cloning nothing else.
+    pub fn subtree(&self, pointer: &str) -> Option<Value<'a>> {
+        self.pointer(pointer).cloned()
+    }
+
+    /// Removes the value at `pointer` and returns it, shifting the following elements of an
+    /// array. The root, at the pointer `""`, is replaced with `null`.
+    pub fn split_off(&mut self, pointer: &str) -> Option<Value<'a>> {
+        let Some((parent, last)) = pointer.rsplit_once('/') else {
+            return pointer
+                .is_empty()
+                .then(|| std::mem::replace(self, Value::Null));
+        };
+        let last = pointer_tokens(&format!("/{last}"))?.pop()?;
+        match self.pointer_mut(parent)? {
+            Value::Object(pairs) => {
+                let i = pairs.position(&last)?;
+                Some(pairs.remove(i).value)
+            }
+            Value::Array(values) => {
+                let i = array_index(&last).filter(|i| *i < values.len())?;
+                Some(values.remove(i))
+            }
+            _ => None,
+        }
+    }
+
+    fn find(&self, mut matches: impl FnMut(&str) -> bool) -> Option<&Value<'a>> {
+        match self {
+            Value::Object(pairs) => pairs.iter().find(|p| matches(&p.key)).map(|p| &p.value),
+            _ => None,
+        }
+    }
+
+    fn find_mut(&mut self, mut matches: impl FnMut(&str) -> bool) -> Option<&mut Value<'a>> {
+        match self {
+            Value::Object(pairs) => pairs
+                .iter_mut()
+                .find(|p| matches(&p.key))
+                .map(|p| &mut p.value),
+            _ => None,
+        }
+    }
+}
+
+/// The reference tokens of `pointer`, unescaped, or `None` if it isn't a JSON pointer.
+pub(crate) fn pointer_tokens(pointer: &str) -> Option<Vec<String>> {
+    match pointer.strip_prefix('/') {
+        _ if pointer.is_empty() => Some(Vec::new()),
+        Some(rest) => Some(
+            rest.split('/')
+                .map(|t| t.replace("~1", "/").replace("~0", "~"))
+                .collect(),
+        ),
+        None => None,
+    }
+}
+
+/// Parses an array index, which has no sign or leading zeros.
+pub(crate) fn array_index(token: &str) -> Option<usize> {
+    let canonical = token == "0" || !token.starts_with(['0', '+']);
+    canonical.then(|| token.parse().ok()).flatten()
+}
+
+/// An object whose keys are looked up regardless of the case of ASCII letters, while keeping
+/// their original spelling.
+#[derive(Debug, Clone, Copy)]
+pub struct CaseInsensitive<'v, 'a>(&'v [Pair<'a>]);
+
+impl<'v, 'a> CaseInsensitive<'v, 'a> {
+    pub fn get(&self, key: &str) -> Option<&'v Value<'a>> {
+        self.get_key_value(key).map(|(_, value)| value)
+    }
+
+    /// Returns the member matching `key`, with its key as spelled in the object.
+    pub fn get_key_value(&self, key: &str) -> Option<(&'v str, &'v Value<'a>)> {
+        self.0
+            .iter()
+            .find(|p| p.key.eq_ignore_ascii_case(key))
+            .map(|p| (p.key.as_ref(), &p.value))
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get_key_value(key).is_some()
+    }
+
+    /// The members of the object, in order.
+    pub fn pairs(&self) -> &'v [Pair<'a>] {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mjl::JsonLexer;
+
+    use crate::{Value, parse};
+
+    #[test]
+    fn lookup_keys() {
+        let input = r#"{"Content-Type": "text/plain", "content-length": 3, "ÄB": 1}"#;
+        let mut value = parse(JsonLexer::new(input)).unwrap().value;
+        assert_eq!(
+            Some(&Value::Number("3".into())),
+            value.get("content-length")
+        );
+        assert_eq!(None, value.get("content-type"));
+        assert_eq!(
+            Some(&Value::Str("text/plain".into())),
+            value.get_ignore_ascii_case("CONTENT-TYPE")
+        );
+        // only ASCII letters are folded
+        assert_eq!(None, value.get_ignore_ascii_case("äb"));
+        *value.get_ignore_ascii_case_mut("Content-Length").unwrap() = Value::Null;
+        assert_eq!(Some(&Value::Null), value.get("content-length"));
+
+        let headers = value.as_case_insensitive().unwrap();
+        assert_eq!(
+            Some(("Content-Type", &Value::Str("text/plain".into()))),
+            headers.get_key_value("content-type")
+        );
+        assert!(headers.contains_key("CONTENT-LENGTH"));
+        assert!(Value::Null.as_case_insensitive().is_none());
+    }
+
+    #[test]
+    fn pointers() {
+        let input = r#"{"a": [{"b~/c": 1}, 2, 3], "d": null}"#;
+        let mut value = parse(JsonLexer::new(input)).unwrap().value;
+        assert_eq!(
+            Some(&Value::Number("1".into())),
+            value.pointer("/a/0/b~0~1c")
+        );
+        assert_eq!(None, value.pointer("/a/01"));
+        assert_eq!(None, value.pointer("a"));
+        assert_eq!(Some(value.clone()), value.subtree(""));
+
+        assert_eq!(Some(Value::Number("2".into())), value.split_off("/a/1"));
+        assert_eq!(Some(&Value::Number("3".into())), value.pointer("/a/1"));
+        assert_eq!(Some(Value::Null), value.split_off("/d"));
+        assert_eq!(None, value.split_off("/d"));
+        assert_eq!(
+            parse(JsonLexer::new(r#"{"a": [{"b~/c": 1}, 3]}"#))
+                .unwrap()
+                .value,
+            value
+        );
+    }
+
+    #[test]
+    fn index() {
+        let input = r#"{"items": [{"id": 1}, null], "a": 2}"#;
+        let mut value = parse(JsonLexer::new(input)).unwrap().value;
+        assert_eq!(Value::Number("1".into()), value["items"][0]["id"]);
+        assert_eq!(Value::Null, value["items"][5]["id"]);
+        assert_eq!(Value::Null, value["a"]["b"]);
+        assert_eq!(Some(&Value::Null), value["items"].get(1));
+        assert_eq!(None, value["items"].get(2));
+        assert_eq!(None, value.get(0));
+        assert_eq!(
+            Some(&Value::Number("2".into())),
+            value.get(String::from("a"))
+        );
+
+        value["items"][1] = Value::Number("3".into());
+        *value.get_mut("a").unwrap() = Value::Null;
+        assert_eq!(
+            parse(JsonLexer::new(r#"{"items": [{"id": 1}, 3], "a": null}"#))
+                .unwrap()
+                .value,
+            value
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn index_mut_missing() {
+        let mut value = parse(JsonLexer::new("[]")).unwrap().value;
+        value[0] = Value::Null;
+    }
+}
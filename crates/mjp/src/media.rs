@@ -0,0 +1,433 @@
+//! Parsing request and response bodies according to their `Content-Type`, so that HTTP servers
+//! can funnel every body through one function whatever the format it was sent in.
+//!
+//! The formats holding several documents, JSON text sequences and NDJSON, are parsed into an
+//! array of their documents. CBOR and MessagePack, behind the `cbor` and `msgpack` features, are
+//! converted to the value JSON would hold: byte strings become arrays of their bytes, tags and
+//! extension types are dropped for their content, non-finite floats become `null` and keys that
+//! are not strings become their JSON text.
+
+use std::{borrow::Cow, ops::Range};
+
+use mjl::JsonLexer;
+
+use crate::{
+    ErrorKind, JsonParseError, ParseOptions, Value, decode_bytes, parse_bytes, parse_with,
+};
+
+/// A format of body understood by [`parse_media`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaType {
+    /// `application/json`, and the types with a `+json` suffix such as `application/ld+json`.
+    Json,
+    /// JSON text sequences (RFC 7464), `application/json-seq`, where each document is preceded
+    /// by an ASCII record separator.
+    JsonSeq,
+    /// Newline-delimited JSON, `application/x-ndjson` or `application/jsonl` among others.
+    Ndjson,
+    /// `application/cbor`, and the types with a `+cbor` suffix.
+    #[cfg(feature = "cbor")]
+    Cbor,
+    /// `application/msgpack`, or its unregistered `application/x-msgpack`.
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+}
+
+impl MediaType {
+    /// The format named by the value of a `Content-Type` header, whose parameters such as
+    /// `charset` are ignored, or `None` if it isn't supported. Servers can check it up front to
+    /// answer 415 Unsupported Media Type rather than 400 Bad Request.
+    pub fn from_content_type(content_type: &str) -> Option<MediaType> {
+        let essence = content_type.split(';').next()?.trim().to_ascii_lowercase();
+        let (kind, subtype) = essence.split_once('/')?;
+        if !matches!(kind, "application" | "text") {
+            return None;
+        }
+        match subtype {
+            "json-seq" => return Some(MediaType::JsonSeq),
+            "x-ndjson" | "ndjson" | "jsonl" | "x-jsonl" | "jsonlines" | "x-jsonlines" => {
+                return Some(MediaType::Ndjson);
+            }
+            #[cfg(feature = "msgpack")]
+            "msgpack" | "x-msgpack" | "vnd.msgpack" => return Some(MediaType::MessagePack),
+            _ => {}
+        }
+        match subtype
+            .rsplit_once('+')
+            .map_or(subtype, |(_, suffix)| suffix)
+        {
+            "json" => Some(MediaType::Json),
+            "json-seq" => Some(MediaType::JsonSeq),
+            #[cfg(feature = "cbor")]
+            "cbor" => Some(MediaType::Cbor),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a body in the format named by `content_type`, see [`MediaType::from_content_type`].
+pub fn parse_media<'a>(bytes: &'a [u8], content_type: &str) -> Result<Value<'a>, JsonParseError> {
+    parse_media_with(bytes, content_type, ParseOptions::default())
+}
+
+/// Like [`parse_media`], parsing JSON documents with the given options. Only
+/// [`ParseOptions::decode_escapes`] applies to CBOR and MessagePack.
+pub fn parse_media_with<'a>(
+    bytes: &'a [u8],
+    content_type: &str,
+    options: ParseOptions,
+) -> Result<Value<'a>, JsonParseError> {
+    let Some(media) = MediaType::from_content_type(content_type) else {
+        let message = format!("unsupported media type {content_type:?}");
+        return Err(error(ErrorKind::Syntax, message, 0));
+    };
+    match media {
+        MediaType::Json => parse_bytes(bytes, options).map(|json| json.value),
+        MediaType::JsonSeq | MediaType::Ndjson => {
+            match decode_bytes(bytes, options.invalid_utf8)? {
+                Cow::Borrowed(input) => records(input, media, options),
+                Cow::Owned(input) => records(&input, media, options).map(Value::into_owned),
+            }
+        }
+        #[cfg(feature = "cbor")]
+        MediaType::Cbor => cbor::parse(bytes, options.decode_escapes),
+        #[cfg(feature = "msgpack")]
+        MediaType::MessagePack => msgpack::parse(bytes, options.decode_escapes),
+    }
+}
+
+/// Parses the documents of a JSON text sequence or NDJSON input into an array.
+fn records(
+    input: &str,
+    media: MediaType,
+    options: ParseOptions,
+) -> Result<Value<'_>, JsonParseError> {
+    let spans = match media {
+        MediaType::JsonSeq => sequence(input)?,
+        _ => JsonLexer::new(input)
+            .lines()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| {
+                let at = mjl::line_column(input, e.offset());
+                JsonParseError::new(e.kind(), e.message(), e.offset(), at, String::new())
+            })?,
+    };
+    let values = spans.into_iter().enumerate().map(|(i, span)| {
+        parse_with(JsonLexer::new(&input[span.clone()]), options.clone())
+            .map(|json| json.value)
+            .map_err(|e| {
+                // only on error, as it scans the input from its start
+                let at = mjl::line_column(input, span.start);
+                e.relocate(span.start, at, &format!("/{i}"))
+            })
+    });
+    values.collect::<Result<_, _>>().map(Value::Array)
+}
+
+/// The ranges of the records of a JSON text sequence, without their separators. Blank records
+/// are skipped, as RFC 7464 recommends.
+fn sequence(input: &str) -> Result<Vec<Range<usize>>, JsonParseError> {
+    let mut pieces = input.split('\u{1e}');
+    let first = pieces.next().unwrap_or_default();
+    if !first.trim().is_empty() {
+        let offset = first.len() - first.trim_start().len();
+        let at = mjl::line_column(input, offset);
+        let message = "expected a record separator";
+        return Err(JsonParseError::new(
+            ErrorKind::Syntax,
+            message,
+            offset,
+            at,
+            String::new(),
+        ));
+    }
+    let mut start = first.len() + 1;
+    let mut spans = Vec::new();
+    for piece in pieces {
+        if !piece.trim().is_empty() {
+            spans.push(start..start + piece.len());
+        }
+        start += piece.len() + 1;
+    }
+    Ok(spans)
+}
+
+/// An error in a binary body, whose position has no line or column.
+fn error(kind: ErrorKind, message: impl Into<String>, offset: usize) -> JsonParseError {
+    JsonParseError::new(kind, message, offset, (1, 1), String::new())
+}
+
+/// Helpers shared by the conversions from binary formats.
+#[cfg(any(feature = "cbor", feature = "msgpack"))]
+mod convert {
+    use std::borrow::Cow;
+
+    use crate::{
+        Pair, Value,
+        builder::JsonBuilder,
+        ser::{WriteOptions, escape, write_value},
+    };
+
+    pub(super) fn string(s: String, decoded: bool) -> Value<'static> {
+        Value::Str(text(s, decoded))
+    }
+
+    /// The text held for a string, escaped unless strings are `decoded`.
+    fn text(s: String, decoded: bool) -> Cow<'static, str> {
+        if decoded {
+            Cow::Owned(s)
+        } else {
+            Cow::Owned(escape(&s).into_owned())
+        }
+    }
+
+    pub(super) fn bytes(bytes: &[u8]) -> Value<'static> {
+        Value::Array(
+            bytes
+                .iter()
+                .map(|b| JsonBuilder::new().number(*b))
+                .collect(),
+        )
+    }
+
+    pub(super) fn pair(key: Value<'static>, value: Value<'static>, decoded: bool) -> Pair<'static> {
+        let key = match key {
+            Value::Str(key) => key,
+            key => {
+                let options = WriteOptions {
+                    decoded,
+                    ..Default::default()
+                };
+                let mut json = String::new();
+                write_value(&key, &options, &mut json).unwrap();
+                text(json, decoded)
+            }
+        };
+        Pair { key, value }
+    }
+}
+
+#[cfg(feature = "cbor")]
+mod cbor {
+    use ciborium::{Value as Cbor, de::Error};
+
+    use super::{
+        convert::{bytes, pair, string},
+        error,
+    };
+    use crate::{ErrorKind, JsonParseError, Value, builder::JsonBuilder};
+
+    pub(super) fn parse(input: &[u8], decoded: bool) -> Result<Value<'static>, JsonParseError> {
+        let mut rest = input;
+        let value: Cbor = ciborium::from_reader(&mut rest).map_err(|e| match e {
+            Error::Io(e) => error(ErrorKind::UnexpectedEof, e.to_string(), input.len()),
+            Error::Syntax(offset) => error(ErrorKind::Syntax, "invalid CBOR", offset),
+            Error::Semantic(offset, message) => {
+                error(ErrorKind::Syntax, message, offset.unwrap_or_default())
+            }
+            Error::RecursionLimitExceeded => {
+                error(ErrorKind::LimitExceeded, "CBOR nested too deeply", 0)
+            }
+        })?;
+        if !rest.is_empty() {
+            let offset = input.len() - rest.len();
+            return Err(error(
+                ErrorKind::Syntax,
+                "trailing data after CBOR item",
+                offset,
+            ));
+        }
+        Ok(convert(value, decoded))
+    }
+
+    fn convert(value: Cbor, decoded: bool) -> Value<'static> {
+        match value {
+            Cbor::Integer(n) => JsonBuilder::new().number(i128::from(n)),
+            Cbor::Float(f) => JsonBuilder::new().number(f),
+            Cbor::Text(s) => string(s, decoded),
+            Cbor::Bytes(b) => bytes(&b),
+            Cbor::Bool(b) => JsonBuilder::new().boolean(b),
+            Cbor::Tag(_, value) => convert(*value, decoded),
+            Cbor::Array(values) => {
+                Value::Array(values.into_iter().map(|v| convert(v, decoded)).collect())
+            }
+            Cbor::Map(pairs) => Value::Object(
+                pairs
+                    .into_iter()
+                    .map(|(k, v)| pair(convert(k, decoded), convert(v, decoded), decoded))
+                    .collect(),
+            ),
+            _ => Value::Null,
+        }
+    }
+}
+
+#[cfg(feature = "msgpack")]
+mod msgpack {
+    use rmpv::{Value as MessagePack, decode::Error};
+
+    use super::{
+        convert::{bytes, pair, string},
+        error,
+    };
+    use crate::{ErrorKind, JsonParseError, Value, builder::JsonBuilder};
+
+    pub(super) fn parse(input: &[u8], decoded: bool) -> Result<Value<'static>, JsonParseError> {
+        let mut rest = input;
+        let value = rmpv::decode::read_value(&mut rest).map_err(|e| {
+            let offset = input.len() - rest.len();
+            match e {
+                Error::InvalidMarkerRead(e) | Error::InvalidDataRead(e) => {
+                    error(ErrorKind::UnexpectedEof, e.to_string(), offset)
+                }
+                Error::DepthLimitExceeded => error(
+                    ErrorKind::LimitExceeded,
+                    "MessagePack nested too deeply",
+                    offset,
+                ),
+            }
+        })?;
+        if !rest.is_empty() {
+            let offset = input.len() - rest.len();
+            return Err(error(
+                ErrorKind::Syntax,
+                "trailing data after MessagePack value",
+                offset,
+            ));
+        }
+        Ok(convert(value, decoded))
+    }
+
+    /// Strings that are not valid UTF-8 get their invalid sequences replaced.
+    fn convert(value: MessagePack, decoded: bool) -> Value<'static> {
+        match value {
+            MessagePack::Nil => Value::Null,
+            MessagePack::Boolean(b) => JsonBuilder::new().boolean(b),
+            MessagePack::Integer(n) => Value::Number(n.to_string().into()),
+            MessagePack::F32(f) => JsonBuilder::new().number(f),
+            MessagePack::F64(f) => JsonBuilder::new().number(f),
+            MessagePack::String(s) => {
+                let s = String::from_utf8_lossy(s.as_bytes()).into_owned();
+                string(s, decoded)
+            }
+            MessagePack::Binary(b) | MessagePack::Ext(_, b) => bytes(&b),
+            MessagePack::Array(values) => {
+                Value::Array(values.into_iter().map(|v| convert(v, decoded)).collect())
+            }
+            MessagePack::Map(pairs) => Value::Object(
+                pairs
+                    .into_iter()
+                    .map(|(k, v)| pair(convert(k, decoded), convert(v, decoded), decoded))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mjl::JsonLexer;
+
+    use crate::{
+        ErrorKind, Value,
+        media::{MediaType, parse_media},
+        parse,
+    };
+
+    fn json(input: &str) -> Value<'_> {
+        parse(JsonLexer::new(input)).unwrap().value
+    }
+
+    #[test]
+    fn media_types() {
+        let cases = [
+            ("application/json", Some(MediaType::Json)),
+            ("Application/JSON; charset=utf-8", Some(MediaType::Json)),
+            ("application/problem+json", Some(MediaType::Json)),
+            ("text/json", Some(MediaType::Json)),
+            ("application/json-seq", Some(MediaType::JsonSeq)),
+            ("application/geo+json-seq", Some(MediaType::JsonSeq)),
+            ("application/x-ndjson", Some(MediaType::Ndjson)),
+            ("application/jsonl ;charset=utf-8", Some(MediaType::Ndjson)),
+            ("image/json", None),
+            ("application/xml", None),
+            ("json", None),
+            ("", None),
+        ];
+        for (content_type, media) in cases {
+            assert_eq!(
+                media,
+                MediaType::from_content_type(content_type),
+                "{content_type}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_json_media() {
+        assert_eq!(
+            json(r#"{"a": [1, 2]}"#),
+            parse_media(br#"{"a": [1, 2]}"#, "application/json").unwrap()
+        );
+        assert_eq!(
+            json(r#"[{"a": 1}, [2], "three"]"#),
+            parse_media(
+                b"\x1e{\"a\": 1}\n\x1e[2]\n\x1e \n\x1e\"three\"\n",
+                "application/json-seq"
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            json(r#"[{"a": 1}, [2]]"#),
+            parse_media(b"{\"a\": 1}\n\n[2]\n", "application/x-ndjson").unwrap()
+        );
+        assert_eq!(
+            json("[]"),
+            parse_media(b"", "application/x-ndjson").unwrap()
+        );
+
+        // errors are located in the whole body, under the index of their record
+        let e = parse_media(b"1\n[2,\n", "application/x-ndjson").unwrap_err();
+        assert_eq!((ErrorKind::UnexpectedEof, 6), (e.kind(), e.offset()));
+        let e = parse_media(b"\x1e1\n\x1e[2 3]\n", "application/json-seq").unwrap_err();
+        assert_eq!(
+            (ErrorKind::Syntax, 7, "/1"),
+            (e.kind(), e.offset(), e.path())
+        );
+        let e = parse_media(b"1\n\x1e2\n", "application/json-seq").unwrap_err();
+        assert_eq!((ErrorKind::Syntax, 0), (e.kind(), e.offset()));
+        let e = parse_media(b"{}", "text/html").unwrap_err();
+        assert_eq!("unsupported media type \"text/html\"", e.message());
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn parse_cbor_media() {
+        // {"a": [1, -2, 1.5, h'0102', true, null], 3: "x\"y"}
+        let body = b"\xa2\x61a\x86\x01\x21\xf9\x3e\x00\x42\x01\x02\xf5\xf6\x03\x63x\"y";
+        assert_eq!(
+            json(r#"{"a": [1, -2, 1.5, [1, 2], true, null], "3": "x\"y"}"#),
+            parse_media(body, "application/cbor").unwrap()
+        );
+        let e = parse_media(b"\x82\x01", "application/cbor").unwrap_err();
+        assert_eq!(ErrorKind::UnexpectedEof, e.kind());
+        let e = parse_media(b"\x01\x02", "application/cbor").unwrap_err();
+        assert_eq!((ErrorKind::Syntax, 1), (e.kind(), e.offset()));
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn parse_msgpack_media() {
+        // {"a": [1, -2, 1.5, bin 0102, true, nil], 3: "x"}
+        let body =
+            b"\x82\xa1a\x96\x01\xfe\xcb\x3f\xf8\0\0\0\0\0\0\xc4\x02\x01\x02\xc3\xc0\x03\xa1x";
+        assert_eq!(
+            json(r#"{"a": [1, -2, 1.5, [1, 2], true, null], "3": "x"}"#),
+            parse_media(body, "application/msgpack").unwrap()
+        );
+        let e = parse_media(b"\x92\x01", "application/x-msgpack").unwrap_err();
+        assert_eq!(ErrorKind::UnexpectedEof, e.kind());
+        let e = parse_media(b"\x01\x02", "application/msgpack").unwrap_err();
+        assert_eq!((ErrorKind::Syntax, 1), (e.kind(), e.offset()));
+    }
+}
@@ -0,0 +1,153 @@
+//! Three-way merge of documents changed independently from a common base.
+
+use crate::{Object, Pair, PathSegment, Value};
+
+/// The outcome of [`merge3`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Merge<'a> {
+    /// The merged document, holding our side of each conflict.
+    pub value: Value<'a>,
+    pub conflicts: Vec<Conflict<'a>>,
+}
+
+/// A value changed differently on both sides. `None` stands for a member that is missing, e.g.
+/// because it was removed on that side.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict<'a> {
+    /// JSON pointer to the value.
+    pub path: String,
+    pub base: Option<Value<'a>>,
+    pub ours: Option<Value<'a>>,
+    pub theirs: Option<Value<'a>>,
+}
+
+/// Merges the changes made to `base` in `ours` and in `theirs`.
+///
+/// A change made on one side only is taken as is. Objects changed on both sides are merged
+/// member by member, keeping the member order of `ours` and appending members only added in
+/// `theirs`. Any other value changed differently on both sides, arrays included, is a conflict.
+pub fn merge3<'a>(base: &Value<'a>, ours: &Value<'a>, theirs: &Value<'a>) -> Merge<'a> {
+    let mut conflicts = Vec::new();
+    let mut path = String::new();
+    let value = merge_member(
+        Some(base),
+        Some(ours),
+        Some(theirs),
+        &mut path,
+        &mut conflicts,
+    );
+    Merge {
+        value: value.unwrap_or(Value::Null),
+        conflicts,
+    }
+}
+
+fn merge_member<'a>(
+    base: Option<&Value<'a>>,
+    ours: Option<&Value<'a>>,
+    theirs: Option<&Value<'a>>,
+    path: &mut String,
+    conflicts: &mut Vec<Conflict<'a>>,
+) -> Option<Value<'a>> {
+    if ours == theirs || theirs == base {
+        return ours.cloned();
+    }
+    if ours == base {
+        return theirs.cloned();
+    }
+    if let (Some(Value::Object(base)), Some(Value::Object(ours)), Some(Value::Object(theirs))) =
+        (base, ours, theirs)
+    {
+        return Some(merge_objects(base, ours, theirs, path, conflicts));
+    }
+
+    conflicts.push(Conflict {
+        path: path.clone(),
+        base: base.cloned(),
+        ours: ours.cloned(),
+        theirs: theirs.cloned(),
+    });
+    ours.cloned()
+}
+
+fn merge_objects<'a>(
+    base: &Object<'a>,
+    ours: &Object<'a>,
+    theirs: &Object<'a>,
+    path: &mut String,
+    conflicts: &mut Vec<Conflict<'a>>,
+) -> Value<'a> {
+    let keys = ours
+        .iter()
+        .chain(theirs.iter().filter(|p| !ours.contains_key(&p.key)))
+        .map(|p| p.key.clone());
+
+    let mut pairs = Vec::new();
+    for key in keys {
+        let parent = path.len();
+        PathSegment::Key(&key).push_to(path);
+        let merged = merge_member(
+            base.get(&key),
+            ours.get(&key),
+            theirs.get(&key),
+            path,
+            conflicts,
+        );
+        path.truncate(parent);
+        if let Some(value) = merged {
+            pairs.push(Pair { key, value });
+        }
+    }
+    Value::Object(pairs.into())
+}
+
+#[cfg(test)]
+mod test {
+    use mjl::JsonLexer;
+
+    use crate::{
+        Value,
+        merge::{Conflict, merge3},
+        parse,
+    };
+
+    fn value(input: &str) -> Value<'_> {
+        parse(JsonLexer::new(input)).unwrap().value
+    }
+
+    #[test]
+    fn merge_independent_changes() {
+        let base = value(r#"{"name": "a", "port": 80, "tags": [1], "old": true}"#);
+        let ours = value(r#"{"name": "b", "port": 80, "tags": [1]}"#);
+        let theirs = value(r#"{"name": "a", "port": 81, "tags": [1], "old": true, "new": 1}"#);
+        let merge = merge3(&base, &ours, &theirs);
+        assert_eq!(
+            value(r#"{"name": "b", "port": 81, "tags": [1], "new": 1}"#),
+            merge.value
+        );
+        assert!(merge.value.eq_ordered(&value(
+            r#"{"name": "b", "port": 81, "tags": [1], "new": 1}"#
+        )));
+        assert!(merge.conflicts.is_empty());
+    }
+
+    #[test]
+    fn report_conflicts() {
+        let base = value(r#"{"a": {"b": 1, "c": [1]}, "d": 1}"#);
+        let ours = value(r#"{"a": {"b": 2, "c": [1, 2]}}"#);
+        let theirs = value(r#"{"a": {"b": 3, "c": [0]}, "d": 2}"#);
+        let merge = merge3(&base, &ours, &theirs);
+        assert_eq!(value(r#"{"a": {"b": 2, "c": [1, 2]}}"#), merge.value);
+        let conflicts: Vec<_> = merge.conflicts.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(vec!["/a/b", "/a/c", "/d"], conflicts);
+        assert_eq!(
+            Conflict {
+                path: "/d".to_string(),
+                base: Some(value("1")),
+                ours: None,
+                theirs: Some(value("2")),
+            },
+            merge.conflicts[2]
+        );
+    }
+}
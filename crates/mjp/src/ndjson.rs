@@ -0,0 +1,137 @@
+//! Reading JSON Lines (newline-delimited JSON) input, where every line holds a complete document.
+//!
+//! Blank lines are skipped. Errors are reported with the line number and byte offset of the
+//! whole input.
+
+use std::io::{BufRead, BufReader, Read};
+
+use mjl::JsonLexer;
+
+use crate::{ErrorKind, JsonParseError, ParseOptions, Value, parse_with, utf8};
+
+/// Iterator over the documents of a JSON Lines input, created by [`read_lines`].
+///
+/// Malformed lines are yielded as errors and iteration carries on with the next line; reading
+/// the input failing ends the iteration.
+pub struct JsonLines<R> {
+    options: ParseOptions,
+    reader: R,
+    line: usize,
+    offset: usize,
+    done: bool,
+}
+
+pub fn read_lines<R: Read>(reader: R) -> JsonLines<BufReader<R>> {
+    read_lines_with(reader, ParseOptions::default())
+}
+
+/// Like [`read_lines`], parsing every line with the given options.
+pub fn read_lines_with<R: Read>(reader: R, options: ParseOptions) -> JsonLines<BufReader<R>> {
+    JsonLines {
+        options,
+        reader: BufReader::new(reader),
+        line: 0,
+        offset: 0,
+        done: false,
+    }
+}
+
+impl<R: BufRead> Iterator for JsonLines<R> {
+    type Item = Result<Value<'static>, JsonParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut bytes = Vec::new();
+        loop {
+            if self.done {
+                return None;
+            }
+
+            bytes.clear();
+            let offset = self.offset;
+            match self.reader.read_until(b'\n', &mut bytes) {
+                Ok(0) => {
+                    self.done = true;
+                    return None;
+                }
+                Ok(n) => {
+                    self.line += 1;
+                    self.offset += n;
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(self.error(ErrorKind::Io, e.to_string(), offset)));
+                }
+            }
+
+            let text = match utf8::decode(&bytes, self.options.invalid_utf8) {
+                Ok(text) => text,
+                Err(e) => {
+                    let message = "invalid UTF-8 in input";
+                    return Some(Err(self.error(
+                        ErrorKind::InvalidUtf8,
+                        message,
+                        offset + e.valid_up_to(),
+                    )));
+                }
+            };
+            let text = text.trim_end_matches(['\n', '\r']);
+            if text.trim().is_empty() {
+                continue;
+            }
+
+            return Some(
+                parse_with(JsonLexer::new(text), self.options.clone())
+                    .map(|json| json.value.into_owned())
+                    .map_err(|e| e.relocate(offset, (self.line, 1), "")),
+            );
+        }
+    }
+}
+
+impl<R> JsonLines<R> {
+    fn error(&self, kind: ErrorKind, message: impl Into<String>, offset: usize) -> JsonParseError {
+        JsonParseError::new(kind, message, offset, (self.line, 1), String::new())
+    }
+}
+
+/// Result of [`read_lines_tolerant`]: the documents that could be parsed and the errors of the
+/// lines that could not, keyed by 1-based line number.
+#[derive(Debug, Default)]
+pub struct LinesReport {
+    pub values: Vec<Value<'static>>,
+    pub errors: Vec<(usize, JsonParseError)>,
+}
+
+/// Reads every line of a JSON Lines input, recording malformed lines instead of failing on them.
+pub fn read_lines_tolerant<R: Read>(reader: R) -> LinesReport {
+    let mut report = LinesReport::default();
+    for result in read_lines(reader) {
+        match result {
+            Ok(value) => report.values.push(value),
+            Err(e) => report.errors.push((e.line(), e)),
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{ErrorKind, ndjson::read_lines_tolerant};
+
+    #[test]
+    fn tolerant_read_keeps_going() {
+        let input = b"{\"a\": 1}\n\n[1, 2\n\"ok\"\n\xff\ntrue\n";
+        let report = read_lines_tolerant(input.as_slice());
+        assert_eq!(3, report.values.len());
+        let lines: Vec<_> = report
+            .errors
+            .iter()
+            .map(|(line, e)| (*line, e.kind()))
+            .collect();
+        assert_eq!(
+            vec![(3, ErrorKind::UnexpectedEof), (5, ErrorKind::InvalidUtf8)],
+            lines
+        );
+        assert_eq!(15, report.errors[0].1.offset());
+    }
+}
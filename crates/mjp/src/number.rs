@@ -0,0 +1,663 @@
+//! Numbers as held by values, and their exact arithmetic and comparison, for aggregations that
+//! must not lose precision to `f64`.
+
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    fmt::{self, Display, Write},
+};
+
+use mjl::NumberKind;
+
+use crate::Value;
+
+/// Largest number of digits the exact result of [`Decimal::checked_add`] may span.
+const MAX_DIGITS: i128 = 4096;
+
+/// The exact decimal value of a JSON number, whatever its size or precision: integers beyond
+/// 64 bits and fractions such as `0.1` are held without rounding.
+///
+/// Numbers compare by value, so `1`, `1.0` and `10e-1` are equal.
+///
+/// Parsing and formatting never consult the process locale: the decimal separator is always
+/// `.` and no digit grouping is written or accepted.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Decimal {
+    negative: bool,
+    /// Decimal digits, most significant first, without leading or trailing zeros. Empty for 0.
+    digits: Vec<u8>,
+    /// Power of ten the digits are multiplied by.
+    exponent: i64,
+}
+
+impl Decimal {
+    /// Parses the text of a JSON number, or returns `None` if it isn't one or its exponent is
+    /// out of the range of `i64`.
+    pub fn parse(text: &str) -> Option<Decimal> {
+        let (negative, rest) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+        let (mantissa, exponent) = match rest.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => {
+                let digits = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+                if !is_digits(digits) {
+                    return None;
+                }
+                (mantissa, exponent.parse::<i64>().ok()?)
+            }
+            None => (rest, 0),
+        };
+        let (int, frac) = match mantissa.split_once('.') {
+            Some((int, frac)) if is_digits(frac) => (int, frac),
+            Some(_) => return None,
+            None => (mantissa, ""),
+        };
+        if !is_digits(int) || (int.len() > 1 && int.starts_with('0')) {
+            return None;
+        }
+        let digits = int.bytes().chain(frac.bytes()).map(|b| b - b'0').collect();
+        let exponent = exponent.checked_sub(i64::try_from(frac.len()).ok()?)?;
+        Decimal::new(negative, digits, exponent)
+    }
+
+    /// Builds the number `digits × 10^exponent`, dropping the zeros that don't change it.
+    fn new(negative: bool, mut digits: Vec<u8>, mut exponent: i64) -> Option<Decimal> {
+        let trailing = digits.iter().rev().take_while(|d| **d == 0).count();
+        digits.truncate(digits.len() - trailing);
+        exponent = exponent.checked_add(i64::try_from(trailing).ok()?)?;
+        let leading = digits.iter().take_while(|d| **d == 0).count();
+        digits.drain(..leading);
+        if digits.is_empty() {
+            return Some(Decimal {
+                negative: false,
+                digits,
+                exponent: 0,
+            });
+        }
+        Some(Decimal {
+            negative,
+            digits,
+            exponent,
+        })
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    /// Whether this number has no fractional part.
+    pub fn is_integer(&self) -> bool {
+        self.exponent >= 0
+    }
+
+    /// Returns this number as an `i64`, or `None` if it has a fractional part or is out of
+    /// range. `4.2e1` is 42.
+    pub fn to_i64(&self) -> Option<i64> {
+        i64::try_from(self.to_i128()?).ok()
+    }
+
+    /// Returns this number as a `u64`, or `None` if it has a fractional part or is out of range.
+    pub fn to_u64(&self) -> Option<u64> {
+        u64::try_from(self.to_i128()?).ok()
+    }
+
+    /// Returns the nearest `f64` to this number, or `None` if its magnitude is beyond the
+    /// largest finite `f64`, as `1e400` is. Numbers too small for an `f64` are rounded to 0.
+    pub fn to_f64(&self) -> Option<f64> {
+        self.to_string()
+            .parse::<f64>()
+            .ok()
+            .filter(|f| f.is_finite())
+    }
+
+    /// This integer as an `i128`, if it is one that fits.
+    fn to_i128(&self) -> Option<i128> {
+        if !self.is_integer() || self.top() > 39 {
+            return None;
+        }
+        let mut n: i128 = 0;
+        let zeros = std::iter::repeat_n(&0, self.exponent as usize);
+        for d in self.digits.iter().chain(zeros) {
+            n = n.checked_mul(10)?.checked_add(i128::from(*d))?;
+        }
+        Some(if self.negative { -n } else { n })
+    }
+
+    /// Returns the exact sum, or `None` if it spans more than 4096 digits, as `1e5000 + 1`
+    /// would.
+    pub fn checked_add(&self, other: &Decimal) -> Option<Decimal> {
+        if self.is_zero() {
+            return Some(other.clone());
+        } else if other.is_zero() {
+            return Some(self.clone());
+        }
+        let low = self.exponent.min(other.exponent);
+        let width = self.top().max(other.top()) - i128::from(low);
+        if width > MAX_DIGITS {
+            return None;
+        }
+        // digits of both numbers aligned on `low`, least significant first
+        let width = width as usize + 1;
+        let (a, b) = (self.aligned(low, width), other.aligned(low, width));
+        let (negative, digits) = if self.negative == other.negative {
+            (self.negative, add(&a, &b))
+        } else if magnitude_cmp(self, other) == Ordering::Less {
+            (other.negative, sub(&b, &a))
+        } else {
+            (self.negative, sub(&a, &b))
+        };
+        Decimal::new(negative, digits.into_iter().rev().collect(), low)
+    }
+
+    /// Returns the exact difference, or `None` if it spans more than 4096 digits.
+    pub fn checked_sub(&self, other: &Decimal) -> Option<Decimal> {
+        self.checked_add(&-other.clone())
+    }
+
+    /// Position of the most significant digit, plus one.
+    fn top(&self) -> i128 {
+        self.digits.len() as i128 + i128::from(self.exponent)
+    }
+
+    /// The digits of this number in `width` places of `10^low` and up, least significant first.
+    fn aligned(&self, low: i64, width: usize) -> Vec<u8> {
+        let shift = (self.exponent - low) as usize;
+        let mut aligned = vec![0; width];
+        for (i, d) in self.digits.iter().rev().enumerate() {
+            aligned[shift + i] = *d;
+        }
+        aligned
+    }
+}
+
+fn is_digits(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn add(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let mut carry = 0;
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| {
+            let sum = a + b + carry;
+            carry = sum / 10;
+            sum % 10
+        })
+        .collect()
+}
+
+/// Subtracts `b` from `a`, which must not be smaller.
+fn sub(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let mut borrow = 0;
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| {
+            let (difference, next) = match a.checked_sub(b + borrow) {
+                Some(d) => (d, 0),
+                None => (a + 10 - b - borrow, 1),
+            };
+            borrow = next;
+            difference
+        })
+        .collect()
+}
+
+fn magnitude_cmp(a: &Decimal, b: &Decimal) -> Ordering {
+    match (a.is_zero(), b.is_zero()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        // digits past the end of the shorter number are nonzero, so compare as greater
+        (false, false) => a.top().cmp(&b.top()).then_with(|| a.digits.cmp(&b.digits)),
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => magnitude_cmp(self, other),
+            (true, true) => magnitude_cmp(other, self),
+        }
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl std::ops::Neg for Decimal {
+    type Output = Decimal;
+
+    fn neg(mut self) -> Decimal {
+        self.negative = !self.negative && !self.is_zero();
+        self
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(n: i64) -> Self {
+        Decimal::parse(&n.to_string()).unwrap()
+    }
+}
+
+impl From<u64> for Decimal {
+    fn from(n: u64) -> Self {
+        Decimal::parse(&n.to_string()).unwrap()
+    }
+}
+
+/// Writes plain notation for integers below 10^21 and for fractions down to 10^-6, and exponent
+/// notation otherwise, as JavaScript does.
+impl Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_char('0');
+        }
+        if self.negative {
+            f.write_char('-')?;
+        }
+        let digit = |d: &u8| char::from(b'0' + d);
+        let point = self.top();
+        let len = self.digits.len() as i128;
+        if self.exponent >= 0 && point <= 21 {
+            self.digits
+                .iter()
+                .try_for_each(|d| f.write_char(digit(d)))?;
+            (0..self.exponent).try_for_each(|_| f.write_char('0'))
+        } else if point > 0 && point < len {
+            let (int, frac) = self.digits.split_at(point as usize);
+            int.iter().try_for_each(|d| f.write_char(digit(d)))?;
+            f.write_char('.')?;
+            frac.iter().try_for_each(|d| f.write_char(digit(d)))
+        } else if point <= 0 && point > -6 {
+            f.write_str("0.")?;
+            (0..-point).try_for_each(|_| f.write_char('0'))?;
+            self.digits.iter().try_for_each(|d| f.write_char(digit(d)))
+        } else {
+            f.write_char(digit(&self.digits[0]))?;
+            if len > 1 {
+                f.write_char('.')?;
+                self.digits[1..]
+                    .iter()
+                    .try_for_each(|d| f.write_char(digit(d)))?;
+            }
+            write!(f, "e{}", point - 1)
+        }
+    }
+}
+
+impl From<Decimal> for Value<'static> {
+    fn from(n: Decimal) -> Self {
+        Value::Number(Number::from(n.to_string()))
+    }
+}
+
+/// A JSON number: its text, kept as written, and the value it holds as the first of `i64`,
+/// `u64` and `f64` that holds it, told once when the number is parsed.
+///
+/// Numbers beyond those types, such as `1e400` or integers past 64 bits, keep only their text,
+/// which [`to_decimal`](Number::to_decimal) reads exactly. So do JSON5's `Infinity` and `NaN`.
+///
+/// Numbers compare by their text, so `1` and `1.0` differ, as do the values holding them.
+#[derive(Clone)]
+pub struct Number<'a> {
+    text: Cow<'a, str>,
+    repr: Repr,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Repr {
+    I64(i64),
+    U64(u64),
+    F64(f64),
+    Text,
+}
+
+impl<'a> Number<'a> {
+    /// The number with `text`, a number literal of kind `kind`.
+    pub(crate) fn with_kind(text: Cow<'a, str>, kind: NumberKind) -> Self {
+        let repr = match kind {
+            NumberKind::Integer => match text.parse::<i64>() {
+                Ok(i) => Repr::I64(i),
+                Err(_) => text.parse::<u64>().map_or(Repr::Text, Repr::U64),
+            },
+            NumberKind::Float | NumberKind::Exponent => text
+                .parse::<f64>()
+                .ok()
+                .filter(|f| f.is_finite())
+                .map_or(Repr::Text, Repr::F64),
+        };
+        Number { text, repr }
+    }
+
+    /// This number with its text replaced by `text`, the same text from elsewhere.
+    pub(crate) fn with_text<'b>(&self, text: Cow<'b, str>) -> Number<'b> {
+        Number {
+            text,
+            repr: self.repr,
+        }
+    }
+
+    /// The text of this number, as written.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// This number with its text copied, so that it no longer borrows the input.
+    pub fn into_owned(self) -> Number<'static> {
+        Number {
+            text: Cow::Owned(self.text.into_owned()),
+            repr: self.repr,
+        }
+    }
+
+    /// Whether this number is an integer that an `i64` holds, as written without a fraction or
+    /// an exponent.
+    pub fn is_i64(&self) -> bool {
+        matches!(self.repr, Repr::I64(_))
+    }
+
+    /// Whether this number is an integer beyond `i64` that a `u64` holds, as written without a
+    /// fraction or an exponent.
+    pub fn is_u64(&self) -> bool {
+        matches!(self.repr, Repr::U64(_))
+    }
+
+    /// Whether this number has a fraction or an exponent, and is within the range of `f64`.
+    pub fn is_f64(&self) -> bool {
+        matches!(self.repr, Repr::F64(_))
+    }
+
+    /// Returns this number as an `i64`, if it is an integer in range, whether it is written
+    /// `42`, `42.0` or `4.2e1`.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self.repr {
+            Repr::I64(i) => Some(i),
+            Repr::U64(_) => None,
+            Repr::F64(_) | Repr::Text => self.to_decimal()?.to_i64(),
+        }
+    }
+
+    /// Returns this number as a `u64`, if it is an integer in range.
+    pub fn as_u64(&self) -> Option<u64> {
+        match self.repr {
+            Repr::I64(i) => u64::try_from(i).ok(),
+            Repr::U64(u) => Some(u),
+            Repr::F64(_) | Repr::Text => self.to_decimal()?.to_u64(),
+        }
+    }
+
+    /// Returns the nearest `f64` to this number, unless it is too large for one.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.repr {
+            Repr::I64(i) => Some(i as f64),
+            Repr::U64(u) => Some(u as f64),
+            Repr::F64(f) => Some(f),
+            Repr::Text => self.text.parse().ok().filter(|f: &f64| f.is_finite()),
+        }
+    }
+
+    /// Whether this number has no fractional part, whatever its size.
+    pub fn is_integer(&self) -> bool {
+        match self.repr {
+            Repr::I64(_) | Repr::U64(_) => true,
+            Repr::F64(_) | Repr::Text => self.to_decimal().is_some_and(|d| d.is_integer()),
+        }
+    }
+
+    /// The exact value of this number, unless its text isn't a JSON number, as JSON5's
+    /// `Infinity` isn't.
+    pub fn to_decimal(&self) -> Option<Decimal> {
+        Decimal::parse(&self.text)
+    }
+}
+
+/// The text is taken to be a number literal, as the parser would have lexed it.
+impl<'a> From<Cow<'a, str>> for Number<'a> {
+    fn from(text: Cow<'a, str>) -> Self {
+        let kind = NumberKind::of(&text);
+        Number::with_kind(text, kind)
+    }
+}
+
+impl<'a> From<&'a str> for Number<'a> {
+    fn from(text: &'a str) -> Self {
+        Number::from(Cow::Borrowed(text))
+    }
+}
+
+impl From<String> for Number<'static> {
+    fn from(text: String) -> Self {
+        Number::from(Cow::Owned(text))
+    }
+}
+
+impl PartialEq for Number<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.text == other.text
+    }
+}
+
+/// Writes the text of the number, as in a value's `Number("1.50")`.
+impl fmt::Debug for Number<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.text, f)
+    }
+}
+
+impl Display for Number<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl<'a> Value<'a> {
+    /// Returns this value as a number, if it is one.
+    pub fn as_number(&self) -> Option<&Number<'a>> {
+        match self {
+            Value::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// Returns the exact value of this number, if it is one.
+    pub fn as_decimal(&self) -> Option<Decimal> {
+        self.as_number()?.to_decimal()
+    }
+
+    /// See [`Number::as_i64`].
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_number()?.as_i64()
+    }
+
+    /// See [`Number::as_u64`].
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_number()?.as_u64()
+    }
+
+    /// See [`Number::as_f64`]. Use [`as_decimal`](Value::as_decimal) for the exact value.
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number()?.as_f64()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{borrow::Cow, cmp::Ordering};
+
+    use proptest::prelude::*;
+
+    use crate::{
+        Value,
+        builder::JsonNumber,
+        number::{Decimal, Number},
+    };
+
+    fn n(text: &str) -> Decimal {
+        Decimal::parse(text).unwrap()
+    }
+
+    fn sum(a: &str, b: &str) -> String {
+        n(a).checked_add(&n(b)).unwrap().to_string()
+    }
+
+    #[test]
+    fn parse_and_format() {
+        for invalid in [
+            "",
+            "-",
+            "01",
+            "1.",
+            ".5",
+            "1e",
+            "1e+",
+            "+1",
+            "1.5x",
+            "0x10",
+            "1e99999999999999999999",
+        ] {
+            assert_eq!(None, Decimal::parse(invalid), "{invalid}");
+        }
+        let cases = [
+            ("0", "0"),
+            ("-0.0e5", "0"),
+            ("1.50", "1.5"),
+            ("12e3", "12000"),
+            ("1e21", "1e21"),
+            ("-1.25e-3", "-0.00125"),
+            ("1.5e-7", "1.5e-7"),
+            // no digit is lost, unlike with f64
+            (
+                "123456789012345678901234567890",
+                "1.2345678901234567890123456789e29",
+            ),
+        ];
+        for (text, formatted) in cases {
+            assert_eq!(formatted, n(text).to_string(), "{text}");
+        }
+    }
+
+    #[test]
+    fn exact_arithmetic() {
+        assert_eq!("9007199254740994", sum("9007199254740993", "1"));
+        assert_eq!("0.3", sum("0.1", "0.2"));
+        assert_eq!("18446744073709551615", sum("18446744073709551616", "-1"));
+        assert_eq!("0", sum("1e400", "-1e400"));
+        assert_eq!("-0.5", sum("1.5", "-2"));
+        assert_eq!("1e400", sum("1e400", "0"));
+        assert_eq!(None, n("1e5000").checked_add(&n("1")));
+        assert_eq!("-3", n("-1").checked_sub(&n("2")).unwrap().to_string());
+        assert_eq!(Decimal::from(i64::MIN), n("-9223372036854775808"));
+    }
+
+    #[test]
+    fn convert() {
+        assert_eq!(Some(42), n("4.2e1").to_i64());
+        assert_eq!(Some(i64::MIN), n("-9223372036854775808").to_i64());
+        assert_eq!(None, n("9223372036854775808").to_i64());
+        assert_eq!(Some(9223372036854775808), n("9223372036854775808").to_u64());
+        assert_eq!(None, n("-1").to_u64());
+        assert_eq!(None, n("1.5").to_i64());
+        assert_eq!(None, n("1e400").to_u64());
+        assert_eq!(Some(0), n("-0.0").to_u64());
+        assert_eq!(Some(1e300), n("1e300").to_f64());
+        assert_eq!(None, n("-1e400").to_f64());
+        assert_eq!(Some(0.0), n("1e-400").to_f64());
+
+        let value = |text: &'static str| Value::Number(text.into());
+        assert_eq!(Some(9007199254740993), value("9007199254740993.0").as_i64());
+        assert_eq!(Some(u64::MAX), value("18446744073709551615").as_u64());
+        assert_eq!(None, value("18446744073709551616").as_u64());
+        assert_eq!(None, value("1e400").as_f64());
+        assert_eq!(Some(-1.5), value("-1.5").as_f64());
+        assert_eq!(None, Value::Null.as_i64());
+    }
+
+    #[test]
+    fn number_values() {
+        let number = |text| Number::from(Cow::Borrowed(text));
+        assert!(number("-42").is_i64());
+        assert_eq!(Some(-42.0), number("-42").as_f64());
+        assert_eq!(None, number("-42").as_u64());
+        let big = number("18446744073709551615");
+        assert!(big.is_u64() && !big.is_i64());
+        assert_eq!(None, big.as_i64());
+        assert!(number("1.5e3").is_f64());
+        assert_eq!(Some(1500), number("1.5e3").as_u64());
+
+        // beyond 64 bits, only the text is kept
+        let huge = number("123456789012345678901234567890");
+        assert!(!huge.is_i64() && !huge.is_u64() && !huge.is_f64());
+        assert!(huge.is_integer());
+        assert_eq!(Some(1.2345678901234568e29), huge.as_f64());
+        assert_eq!("123456789012345678901234567890", huge.as_str());
+        assert_eq!(None, number("1e400").as_f64());
+        assert!(!number("Infinity").is_f64());
+        assert_eq!(None, number("Infinity").to_decimal());
+
+        // the text is kept as written
+        assert_eq!("1.50", number("1.50").to_string());
+        assert_ne!(number("1"), number("1.0"));
+        assert_eq!(
+            "Number(\"1.50\")",
+            format!("{:?}", Value::Number(number("1.50")))
+        );
+    }
+
+    #[test]
+    fn compare_values() {
+        assert_eq!(n("1"), n("1.0"));
+        assert_eq!(n("1"), n("10e-1"));
+        assert_eq!(n("-0"), n("0"));
+        assert_eq!(Ordering::Greater, n("1e2").cmp(&n("99.5")));
+        assert_eq!(Ordering::Less, n("0.12").cmp(&n("0.125")));
+        assert_eq!(Ordering::Less, n("-2").cmp(&n("-1.5")));
+        assert_eq!(Ordering::Less, n("-1").cmp(&n("0")));
+        assert_eq!(
+            Ordering::Greater,
+            n("9223372036854775808").cmp(&n("9223372036854775807.5"))
+        );
+    }
+
+    fn any_number() -> impl Strategy<Value = Decimal> {
+        let digits = prop::collection::vec(0u8..10, 0..40);
+        (any::<bool>(), digits, -400i64..400)
+            .prop_filter_map("not normalized", |(negative, digits, exponent)| {
+                Decimal::new(negative, digits, exponent)
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn round_trip(number in any_number()) {
+            let text = number.to_string();
+            prop_assert!(!text.contains(','), "{text}");
+            prop_assert_eq!(Some(number), Decimal::parse(&text));
+        }
+
+        #[test]
+        fn round_trip_f64(f in any::<f64>().prop_filter("finite", |f| f.is_finite())) {
+            let text = f.to_json().unwrap();
+            let number = Decimal::parse(&text).unwrap();
+            prop_assert_eq!(Some(f), number.to_string().parse::<f64>().ok());
+        }
+
+        #[test]
+        fn reject_locale_separators(int in 1u64.., frac in 0u32..1000) {
+            // as written with a decimal comma or with digit grouping
+            prop_assert_eq!(None, Decimal::parse(&format!("{int},{frac}")));
+            prop_assert_eq!(None, Decimal::parse(&format!("{int}.{frac:03}.000")));
+            prop_assert_eq!(None, Decimal::parse(&format!("{int} {frac:03}")));
+            prop_assert_eq!(None, Decimal::parse(&format!("{int}\u{a0}{frac:03}")));
+        }
+    }
+}
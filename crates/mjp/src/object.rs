@@ -0,0 +1,227 @@
+//! Objects: their members in document order, with an index of their keys for lookups in
+//! constant time.
+
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    fmt,
+    hash::BuildHasher,
+    ops::{Deref, DerefMut},
+    sync::OnceLock,
+};
+
+use crate::{KeyHasher, Pair, Value};
+
+/// The members of an object, in document order, duplicates included.
+///
+/// Lookups by key go through an index of the keys, built by the first lookup and kept until the
+/// members are next changed. Keys are matched against their raw text, escapes included, and
+/// when several members have a key, the first one is found.
+///
+/// The members are a `Vec` of [`Pair`]s, which the object dereferences to. Mutating them
+/// through it drops the index; mutating values through [`Object::get_mut`] keeps it.
+#[derive(Clone, Default)]
+pub struct Object<'a> {
+    pairs: Vec<Pair<'a>>,
+    index: OnceLock<Box<KeyIndex>>,
+}
+
+/// The position of the first member with each key, by the hash of the key.
+#[derive(Debug, Clone)]
+struct KeyIndex {
+    hasher: KeyHasher,
+    /// `None` for a hash shared by several keys, whose members are searched for in order.
+    positions: HashMap<u64, Option<usize>, KeyHasher>,
+}
+
+impl KeyIndex {
+    fn new(pairs: &[Pair]) -> Self {
+        let hasher = KeyHasher::default();
+        // the keys are hashed already, with random keys, so a fast hash of the hashes will do
+        let mut positions = HashMap::with_capacity_and_hasher(pairs.len(), KeyHasher::Fast);
+        for (i, pair) in pairs.iter().enumerate() {
+            match positions.entry(hasher.hash_one(pair.key.as_ref())) {
+                Entry::Vacant(e) => {
+                    e.insert(Some(i));
+                }
+                Entry::Occupied(mut e) => {
+                    if e.get().is_some_and(|first| pairs[first].key != pair.key) {
+                        e.insert(None);
+                    }
+                }
+            }
+        }
+        KeyIndex { hasher, positions }
+    }
+
+    fn position(&self, pairs: &[Pair], key: &str) -> Option<usize> {
+        match self.positions.get(&self.hasher.hash_one(key))? {
+            Some(i) => (pairs[*i].key == key).then_some(*i),
+            None => pairs.iter().position(|p| p.key == key),
+        }
+    }
+}
+
+impl<'a> Object<'a> {
+    pub fn new() -> Self {
+        Object::default()
+    }
+
+    /// Returns the value of the member `key`, if there is one.
+    pub fn get(&self, key: &str) -> Option<&Value<'a>> {
+        self.position(key).map(|i| &self.pairs[i].value)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value<'a>> {
+        let i = self.position(key)?;
+        Some(&mut self.pairs[i].value)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.position(key).is_some()
+    }
+
+    /// The position of the member `key` among the members of the object.
+    pub fn position(&self, key: &str) -> Option<usize> {
+        self.index
+            .get_or_init(|| Box::new(KeyIndex::new(&self.pairs)))
+            .position(&self.pairs, key)
+    }
+
+    /// The members of the object, in order.
+    pub fn into_vec(self) -> Vec<Pair<'a>> {
+        self.pairs
+    }
+}
+
+impl<'a> Deref for Object<'a> {
+    type Target = Vec<Pair<'a>>;
+
+    fn deref(&self) -> &Vec<Pair<'a>> {
+        &self.pairs
+    }
+}
+
+/// Drops the index, since the keys or their order may change.
+impl<'a> DerefMut for Object<'a> {
+    fn deref_mut(&mut self) -> &mut Vec<Pair<'a>> {
+        self.index.take();
+        &mut self.pairs
+    }
+}
+
+impl<'a> From<Vec<Pair<'a>>> for Object<'a> {
+    fn from(pairs: Vec<Pair<'a>>) -> Self {
+        Object {
+            pairs,
+            index: OnceLock::new(),
+        }
+    }
+}
+
+impl<'a> From<Object<'a>> for Value<'a> {
+    fn from(object: Object<'a>) -> Self {
+        Value::Object(object)
+    }
+}
+
+impl<'a> FromIterator<Pair<'a>> for Object<'a> {
+    fn from_iter<I: IntoIterator<Item = Pair<'a>>>(iter: I) -> Self {
+        Object::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl<'a> IntoIterator for Object<'a> {
+    type Item = Pair<'a>;
+    type IntoIter = std::vec::IntoIter<Pair<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.pairs.into_iter()
+    }
+}
+
+impl<'o, 'a> IntoIterator for &'o Object<'a> {
+    type Item = &'o Pair<'a>;
+    type IntoIter = std::slice::Iter<'o, Pair<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.pairs.iter()
+    }
+}
+
+impl<'o, 'a> IntoIterator for &'o mut Object<'a> {
+    type Item = &'o mut Pair<'a>;
+    type IntoIter = std::slice::IterMut<'o, Pair<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// Compares the members in order, as `Vec`s of them do.
+impl PartialEq for Object<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.pairs == other.pairs
+    }
+}
+
+/// Writes the members, as in a value's `Object([Pair { .. }])`.
+impl fmt::Debug for Object<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.pairs, f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+
+    use mjl::JsonLexer;
+
+    use crate::{DuplicateKeyPolicy, Pair, ParseOptions, Value, object::Object, parse_with};
+
+    fn pair<'a>(key: &'a str, value: &'a str) -> Pair<'a> {
+        Pair {
+            key: Cow::Borrowed(key),
+            value: Value::Number(value.into()),
+        }
+    }
+
+    #[test]
+    fn lookup_members() {
+        let input = r#"{"b": 1, "a": 2, "b": 3, "c\u0064": 4}"#;
+        let options = ParseOptions {
+            duplicate_keys: DuplicateKeyPolicy::KeepAll,
+            ..Default::default()
+        };
+        let value = parse_with(JsonLexer::new(input), options).unwrap().value;
+        let Value::Object(object) = value else {
+            panic!("not an object: {value:?}");
+        };
+        assert_eq!(Some(&Value::Number("1".into())), object.get("b"));
+        assert_eq!(Some(1), object.position("a"));
+        assert!(object.contains_key("c\\u0064"));
+        assert!(!object.contains_key("cd"));
+        assert_eq!(
+            vec!["b", "a", "b", "c\\u0064"],
+            object.iter().map(|p| p.key.as_ref()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn mutate_members() {
+        let mut object: Object = [pair("a", "1"), pair("b", "2")].into_iter().collect();
+        assert_eq!(Some(1), object.position("b"));
+        *object.get_mut("b").unwrap() = Value::Null;
+        assert_eq!(Some(&Value::Null), object.get("b"));
+
+        // the index is rebuilt once the members change
+        object.insert(0, pair("c", "3"));
+        object.remove(2);
+        assert_eq!(Some(0), object.position("c"));
+        assert_eq!(Some(1), object.position("a"));
+        assert_eq!(None, object.position("b"));
+        object[1].key = Cow::Borrowed("d");
+        assert!(!object.contains_key("a"));
+        assert!(object.contains_key("d"));
+    }
+}
@@ -0,0 +1,130 @@
+//! Canonical ordering of object members.
+
+use std::cmp::Ordering;
+
+use crate::{Pair, Value};
+
+/// How [`Value::sort_members`] orders the members of objects.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MemberOrder<'s, 'a> {
+    /// By key.
+    Lexical,
+    /// In the order the members are declared under `properties` in a JSON Schema, followed by
+    /// undeclared members by key. Nested values follow the subschemas of `properties`,
+    /// `additionalProperties`, `items` and `prefixItems`; values without a subschema are ordered
+    /// by key.
+    Schema(&'s Value<'a>),
+}
+
+impl Value<'_> {
+    /// Reorders the members of this object and of all nested objects. Keys are compared by their
+    /// raw text.
+    pub fn sort_members(&mut self, order: MemberOrder) {
+        let schema = match order {
+            MemberOrder::Lexical => None,
+            MemberOrder::Schema(schema) => Some(schema),
+        };
+        sort(self, schema);
+    }
+}
+
+fn sort(value: &mut Value, schema: Option<&Value>) {
+    match value {
+        Value::Object(pairs) => {
+            let properties = schema.and_then(|s| member(s, "properties"));
+            let declared = |key: &str| match properties {
+                Some(Value::Object(properties)) => properties.position(key),
+                _ => None,
+            };
+            pairs.sort_by(|a, b| match (declared(&a.key), declared(&b.key)) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => a.key.cmp(&b.key),
+            });
+            for Pair { key, value } in pairs {
+                let subschema = properties
+                    .and_then(|p| member(p, key))
+                    .or_else(|| schema.and_then(|s| member(s, "additionalProperties")));
+                sort(value, subschema);
+            }
+        }
+        Value::Array(values) => {
+            let prefix = schema.and_then(|s| member(s, "prefixItems"));
+            let items = schema.and_then(|s| member(s, "items"));
+            for (i, value) in values.iter_mut().enumerate() {
+                let subschema = match (prefix, items) {
+                    (Some(Value::Array(prefix)), _) if i < prefix.len() => Some(&prefix[i]),
+                    // draft 4 to 2019-09 tuples
+                    (_, Some(Value::Array(items))) => items.get(i),
+                    (_, items) => items,
+                };
+                sort(value, subschema);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The member `key` of `schema`, if it is an object schema.
+fn member<'s, 'a>(schema: &'s Value<'a>, key: &str) -> Option<&'s Value<'a>> {
+    match schema {
+        Value::Object(pairs) => pairs
+            .get(key)
+            .filter(|v| matches!(v, Value::Object(_) | Value::Array(_))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mjl::JsonLexer;
+
+    use crate::{Value, order::MemberOrder, parse};
+
+    fn value(input: &str) -> Value<'_> {
+        parse(JsonLexer::new(input)).unwrap().value
+    }
+
+    #[test]
+    fn sort_lexically() {
+        let mut doc = value(r#"{"b": 1, "a": [{"d": 1, "c": 2}]}"#);
+        doc.sort_members(MemberOrder::Lexical);
+        assert!(doc.eq_ordered(&value(r#"{"a": [{"c": 2, "d": 1}], "b": 1}"#)));
+    }
+
+    #[test]
+    fn sort_by_schema() {
+        let schema = value(
+            r#"{
+                "properties": {
+                    "name": {},
+                    "version": {},
+                    "dependencies": {"additionalProperties": {"properties": {"version": {}, "path": {}}}},
+                    "targets": {"items": {"properties": {"name": {}, "kind": {}}}}
+                }
+            }"#,
+        );
+        let mut doc = value(
+            r#"{
+                "targets": [{"kind": "lib", "name": "a", "doc": true}],
+                "extra": 1,
+                "dependencies": {"x": {"path": "..", "version": "1"}},
+                "authors": [{"z": 1, "y": 2}],
+                "version": "1.0",
+                "name": "a"
+            }"#,
+        );
+        doc.sort_members(MemberOrder::Schema(&schema));
+        assert!(doc.eq_ordered(&value(
+            r#"{
+                "name": "a",
+                "version": "1.0",
+                "dependencies": {"x": {"version": "1", "path": ".."}},
+                "targets": [{"name": "a", "kind": "lib", "doc": true}],
+                "authors": [{"y": 2, "z": 1}],
+                "extra": 1
+            }"#
+        )));
+    }
+}
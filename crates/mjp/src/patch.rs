@@ -0,0 +1,553 @@
+//! JSON Patch (RFC 6902): applying patches to values, and inverting them.
+//!
+//! Pointers are matched against the raw text of object keys, escapes included, like everywhere
+//! else in this crate.
+
+use std::{borrow::Cow, error::Error, fmt::Display};
+
+use crate::{
+    Pair, Value,
+    lookup::{array_index, pointer_tokens},
+};
+
+/// A sequence of operations, applied in order.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Patch<'a>(pub Vec<Operation<'a>>);
+
+/// A patch operation. Paths are JSON pointers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation<'a> {
+    Add { path: String, value: Value<'a> },
+    Remove { path: String },
+    Replace { path: String, value: Value<'a> },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value<'a> },
+}
+
+/// An operation that could not be read or applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchError {
+    index: usize,
+    message: String,
+}
+
+impl PatchError {
+    /// Index of the failed operation in the patch.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for PatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} in operation {}", self.message, self.index)
+    }
+}
+
+impl Error for PatchError {}
+
+impl<'a> Patch<'a> {
+    /// Reads a patch from its JSON form, an array of operation objects.
+    pub fn from_value(value: &Value<'a>) -> Result<Self, PatchError> {
+        let Value::Array(operations) = value else {
+            return Err(error(0, "a patch must be an array"));
+        };
+        operations
+            .iter()
+            .enumerate()
+            .map(|(i, op)| Operation::from_value(op).map_err(|message| error(i, message)))
+            .collect::<Result<_, _>>()
+            .map(Patch)
+    }
+
+    /// Returns the JSON form of this patch.
+    pub fn to_value(&self) -> Value<'a> {
+        Value::Array(self.0.iter().map(Operation::to_value).collect())
+    }
+
+    /// Applies the operations to `doc` in order. On error, `doc` is left with the operations
+    /// before the failed one applied.
+    pub fn apply(&self, doc: &mut Value<'a>) -> Result<(), PatchError> {
+        for (i, op) in self.0.iter().enumerate() {
+            op.apply(doc).map_err(|message| error(i, message))?;
+        }
+        Ok(())
+    }
+
+    /// Returns the patch that undoes this one: applied to the result of applying this patch to
+    /// `original`, it gives back `original`.
+    pub fn invert(&self, original: &Value<'a>) -> Result<Patch<'a>, PatchError> {
+        let mut doc = original.clone();
+        let mut inverses = Vec::with_capacity(self.0.len());
+        for (i, op) in self.0.iter().enumerate() {
+            let inverse = op.invert(&mut doc).map_err(|message| error(i, message))?;
+            inverses.push(inverse);
+        }
+        Ok(Patch(inverses.into_iter().rev().flatten().collect()))
+    }
+}
+
+impl<'a> Operation<'a> {
+    fn from_value(value: &Value<'a>) -> Result<Self, String> {
+        let Value::Object(pairs) = value else {
+            return Err("an operation must be an object".to_string());
+        };
+        let member = |name: &str| pairs.get(name);
+        let string = |name: &str| match member(name) {
+            Some(Value::Str(s)) => Ok(s.to_string()),
+            _ => Err(format!("missing string member {name:?}")),
+        };
+        let value = || {
+            member("value")
+                .cloned()
+                .ok_or_else(|| "missing member \"value\"".to_string())
+        };
+
+        let operation = match string("op")?.as_str() {
+            "add" => Operation::Add {
+                path: string("path")?,
+                value: value()?,
+            },
+            "remove" => Operation::Remove {
+                path: string("path")?,
+            },
+            "replace" => Operation::Replace {
+                path: string("path")?,
+                value: value()?,
+            },
+            "move" => Operation::Move {
+                from: string("from")?,
+                path: string("path")?,
+            },
+            "copy" => Operation::Copy {
+                from: string("from")?,
+                path: string("path")?,
+            },
+            "test" => Operation::Test {
+                path: string("path")?,
+                value: value()?,
+            },
+            op => return Err(format!("unknown operation {op:?}")),
+        };
+        operation.check_pointers()?;
+        Ok(operation)
+    }
+
+    /// Checks that the pointers of this operation are valid, before any of them is followed.
+    fn check_pointers(&self) -> Result<(), String> {
+        let (from, path) = match self {
+            Operation::Move { from, path } | Operation::Copy { from, path } => (Some(from), path),
+            Operation::Add { path, .. }
+            | Operation::Remove { path }
+            | Operation::Replace { path, .. }
+            | Operation::Test { path, .. } => (None, path),
+        };
+        from.into_iter()
+            .chain([path])
+            .try_for_each(|p| tokens(p).map(drop))
+    }
+
+    fn to_value(&self) -> Value<'a> {
+        let member = |key: &'static str, value: Value<'a>| Pair {
+            key: Cow::Borrowed(key),
+            value,
+        };
+        let string = |key, s: &str| member(key, Value::Str(Cow::Owned(s.to_string())));
+        let pairs = match self {
+            Operation::Add { path, value } => {
+                vec![
+                    string("op", "add"),
+                    string("path", path),
+                    member("value", value.clone()),
+                ]
+            }
+            Operation::Remove { path } => vec![string("op", "remove"), string("path", path)],
+            Operation::Replace { path, value } => vec![
+                string("op", "replace"),
+                string("path", path),
+                member("value", value.clone()),
+            ],
+            Operation::Move { from, path } => {
+                vec![
+                    string("op", "move"),
+                    string("from", from),
+                    string("path", path),
+                ]
+            }
+            Operation::Copy { from, path } => {
+                vec![
+                    string("op", "copy"),
+                    string("from", from),
+                    string("path", path),
+                ]
+            }
+            Operation::Test { path, value } => {
+                vec![
+                    string("op", "test"),
+                    string("path", path),
+                    member("value", value.clone()),
+                ]
+            }
+        };
+        Value::Object(pairs.into())
+    }
+
+    fn apply(&self, doc: &mut Value<'a>) -> Result<(), String> {
+        self.check_pointers()?;
+        match self {
+            Operation::Add { path, value } => add(doc, path, value.clone()).map(drop),
+            Operation::Remove { path } => remove(doc, path).map(drop),
+            Operation::Replace { path, value } => {
+                *get_mut(doc, path)? = value.clone();
+                Ok(())
+            }
+            Operation::Move { from, path } => move_value(doc, from, path).map(drop),
+            Operation::Copy { from, path } => {
+                let value = get_mut(doc, from)?.clone();
+                add(doc, path, value).map(drop)
+            }
+            Operation::Test { path, value } => {
+                if get_mut(doc, path)? == value {
+                    Ok(())
+                } else {
+                    Err(format!("test of {path:?} failed"))
+                }
+            }
+        }
+    }
+
+    /// Applies this operation to `doc`, returning the operations that undo it.
+    fn invert(&self, doc: &mut Value<'a>) -> Result<Vec<Operation<'a>>, String> {
+        self.check_pointers()?;
+        let inverse = match self {
+            Operation::Add { path, value } => {
+                let replaced = add(doc, path, value.clone())?;
+                vec![undo_add(doc, path, replaced)]
+            }
+            Operation::Copy { from, path } => {
+                let value = get_mut(doc, from)?.clone();
+                let replaced = add(doc, path, value)?;
+                vec![undo_add(doc, path, replaced)]
+            }
+            Operation::Remove { path } => vec![Operation::Add {
+                path: path.clone(),
+                value: remove(doc, path)?,
+            }],
+            Operation::Replace { path, value } => vec![Operation::Replace {
+                path: path.clone(),
+                value: std::mem::replace(get_mut(doc, path)?, value.clone()),
+            }],
+            Operation::Move { from, path } => {
+                let replaced = move_value(doc, from, path)?;
+                let path = concrete(doc, path);
+                let mut inverse = vec![Operation::Move {
+                    from: path.clone(),
+                    path: from.clone(),
+                }];
+                // `add` on an object member replaces it, and moving back leaves it missing
+                if let Some(value) = replaced {
+                    inverse.push(Operation::Add { path, value });
+                }
+                inverse
+            }
+            Operation::Test { .. } => {
+                self.apply(doc)?;
+                vec![self.clone()]
+            }
+        };
+        Ok(inverse)
+    }
+}
+
+fn error(index: usize, message: impl Into<String>) -> PatchError {
+    PatchError {
+        index,
+        message: message.into(),
+    }
+}
+
+/// The reference tokens of `pointer`, unescaped.
+fn tokens(pointer: &str) -> Result<Vec<String>, String> {
+    pointer_tokens(pointer).ok_or_else(|| format!("invalid pointer {pointer:?}"))
+}
+
+fn get_mut<'v, 'a>(doc: &'v mut Value<'a>, pointer: &str) -> Result<&'v mut Value<'a>, String> {
+    doc.pointer_mut(pointer)
+        .ok_or_else(|| format!("no value at {pointer:?}"))
+}
+
+/// The container holding the value at `pointer`, and the last token of the pointer.
+fn parent<'v, 'a>(
+    doc: &'v mut Value<'a>,
+    pointer: &str,
+) -> Result<(&'v mut Value<'a>, String), String> {
+    // a pointer that is not empty starts with `/`, as RFC 6901 requires
+    let (parent, last) = pointer
+        .rsplit_once('/')
+        .filter(|_| pointer.starts_with('/'))
+        .ok_or_else(|| format!("invalid pointer {pointer:?}"))?;
+    let last = tokens(&format!("/{last}"))?.remove(0);
+    Ok((get_mut(doc, parent)?, last))
+}
+
+/// Adds `value` at `pointer`, returning the value it replaced, if any.
+fn add<'a>(
+    doc: &mut Value<'a>,
+    pointer: &str,
+    value: Value<'a>,
+) -> Result<Option<Value<'a>>, String> {
+    if pointer.is_empty() {
+        return Ok(Some(std::mem::replace(doc, value)));
+    }
+    let (parent, last) = parent(doc, pointer)?;
+    match parent {
+        Value::Object(pairs) => match pairs.get_mut(&last) {
+            Some(old) => Ok(Some(std::mem::replace(old, value))),
+            None => {
+                pairs.push(Pair {
+                    key: Cow::Owned(last),
+                    value,
+                });
+                Ok(None)
+            }
+        },
+        Value::Array(values) => {
+            let i = insert_index(values, &last, pointer)?;
+            values.insert(i, value);
+            Ok(None)
+        }
+        _ => Err(format!("no container at {pointer:?}")),
+    }
+}
+
+/// Checks that [`add`] at `pointer` would succeed, without adding anything.
+fn check_add(doc: &mut Value, pointer: &str) -> Result<(), String> {
+    if pointer.is_empty() {
+        return Ok(());
+    }
+    match parent(doc, pointer)? {
+        (Value::Object(_), _) => Ok(()),
+        (Value::Array(values), last) => insert_index(values, &last, pointer).map(drop),
+        _ => Err(format!("no container at {pointer:?}")),
+    }
+}
+
+/// The position an add at `pointer`, whose last token is `last`, inserts at in `values`.
+fn insert_index(values: &[Value], last: &str, pointer: &str) -> Result<usize, String> {
+    match last {
+        "-" => Ok(values.len()),
+        token => array_index(token)
+            .filter(|i| *i <= values.len())
+            .ok_or_else(|| format!("invalid array index at {pointer:?}")),
+    }
+}
+
+fn remove<'a>(doc: &mut Value<'a>, pointer: &str) -> Result<Value<'a>, String> {
+    remove_at(doc, pointer).map(|(value, _)| value)
+}
+
+/// Removes the value at `pointer`, returning it with its position among its siblings.
+fn remove_at<'a>(doc: &mut Value<'a>, pointer: &str) -> Result<(Value<'a>, usize), String> {
+    if pointer.is_empty() {
+        return Err("cannot remove the root".to_string());
+    }
+    let (parent, last) = parent(doc, pointer)?;
+    let missing = || format!("no value at {pointer:?}");
+    match parent {
+        Value::Object(pairs) => {
+            let i = pairs.position(&last).ok_or_else(missing)?;
+            Ok((pairs.remove(i).value, i))
+        }
+        Value::Array(values) => {
+            let i = array_index(&last)
+                .filter(|i| *i < values.len())
+                .ok_or_else(missing)?;
+            Ok((values.remove(i), i))
+        }
+        _ => Err(missing()),
+    }
+}
+
+/// Moves the value at `from` to `path`, returning the value it replaced, if any. On error,
+/// `doc` is left unchanged.
+fn move_value<'a>(
+    doc: &mut Value<'a>,
+    from: &str,
+    path: &str,
+) -> Result<Option<Value<'a>>, String> {
+    if path.starts_with(&format!("{from}/")) {
+        return Err(format!("cannot move {from:?} into itself"));
+    }
+    let (value, i) = remove_at(doc, from)?;
+    // the target is resolved once the value is removed, which may shift array elements
+    if let Err(message) = check_add(doc, path) {
+        match parent(doc, from) {
+            Ok((Value::Object(pairs), key)) => pairs.insert(
+                i,
+                Pair {
+                    key: Cow::Owned(key),
+                    value,
+                },
+            ),
+            Ok((Value::Array(values), _)) => values.insert(i, value),
+            _ => unreachable!("the value was removed from a container"),
+        }
+        return Err(message);
+    }
+    add(doc, path, value)
+}
+
+/// The operation undoing an add at `pointer`, given the value it replaced.
+fn undo_add<'a>(doc: &mut Value<'a>, pointer: &str, replaced: Option<Value<'a>>) -> Operation<'a> {
+    let path = concrete(doc, pointer);
+    match replaced {
+        Some(value) => Operation::Replace { path, value },
+        None => Operation::Remove { path },
+    }
+}
+
+/// `pointer`, with a final `-` replaced by the index of the last element, after an add to the
+/// end of an array.
+fn concrete(doc: &mut Value, pointer: &str) -> String {
+    match pointer.strip_suffix("/-") {
+        Some(parent) => match get_mut(doc, parent) {
+            Ok(Value::Array(values)) => format!("{parent}/{}", values.len() - 1),
+            _ => pointer.to_string(),
+        },
+        None => pointer.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mjl::JsonLexer;
+
+    use crate::{
+        Value, parse,
+        patch::{Operation, Patch},
+    };
+
+    fn value(input: &str) -> Value<'_> {
+        parse(JsonLexer::new(input)).unwrap().value
+    }
+
+    #[test]
+    fn apply_patch() {
+        let patch = Patch::from_value(&value(
+            r#"[
+                {"op": "add", "path": "/a/-", "value": 3},
+                {"op": "remove", "path": "/b"},
+                {"op": "replace", "path": "/c", "value": "x"},
+                {"op": "move", "from": "/a/0", "path": "/d"},
+                {"op": "copy", "from": "/d", "path": "/e~1f"},
+                {"op": "test", "path": "/a", "value": [2, 3]}
+            ]"#,
+        ))
+        .unwrap();
+        let mut doc = value(r#"{"a": [1, 2], "b": true, "c": null}"#);
+        patch.apply(&mut doc).unwrap();
+        assert!(doc.eq_ordered(&value(r#"{"a": [2, 3], "c": "x", "d": 1, "e/f": 1}"#)));
+        assert_eq!(patch, Patch::from_value(&patch.to_value()).unwrap());
+
+        let err = Patch::from_value(&value(r#"[{"op": "remove", "path": "/x"}]"#))
+            .unwrap()
+            .apply(&mut doc)
+            .unwrap_err();
+        assert_eq!(0, err.index());
+    }
+
+    #[test]
+    fn reject_relative_pointers() {
+        let err = Patch::from_value(&value(r#"[{"op": "remove", "path": "abc"}]"#)).unwrap_err();
+        assert_eq!("invalid pointer \"abc\"", err.message());
+
+        for op in [
+            Operation::Add {
+                path: "abc".to_string(),
+                value: Value::Null,
+            },
+            Operation::Replace {
+                path: "abc".to_string(),
+                value: Value::Null,
+            },
+            Operation::Remove {
+                path: "abc".to_string(),
+            },
+            Operation::Move {
+                from: "/".to_string(),
+                path: "abc".to_string(),
+            },
+        ] {
+            let mut doc = value(r#"{"": 0}"#);
+            let patch = Patch(vec![op]);
+            let err = patch.apply(&mut doc).unwrap_err();
+            assert_eq!("invalid pointer \"abc\"", err.message());
+            assert_eq!(value(r#"{"": 0}"#), doc);
+            assert!(patch.invert(&doc).is_err());
+        }
+    }
+
+    #[test]
+    fn failed_move() {
+        for (doc, from, path, message) in [
+            (r#"{"a": 1, "b": 2}"#, "/a", "/x/y", "no value at \"/x\""),
+            // the array is one shorter once the element is removed
+            (
+                r#"{"a": [1, 2]}"#,
+                "/a/0",
+                "/a/2",
+                "invalid array index at \"/a/2\"",
+            ),
+        ] {
+            let patch = Patch(vec![Operation::Move {
+                from: from.to_string(),
+                path: path.to_string(),
+            }]);
+            let mut moved = value(doc);
+            assert_eq!(message, patch.apply(&mut moved).unwrap_err().message());
+            assert!(moved.eq_ordered(&value(doc)));
+            assert!(patch.invert(&value(doc)).is_err());
+        }
+    }
+
+    #[test]
+    fn invert_patch() {
+        let original = value(r#"{"a": [1, 2], "b": {"c": 1}, "d": "x"}"#);
+        let patch = Patch::from_value(&value(
+            r#"[
+                {"op": "add", "path": "/a/-", "value": 3},
+                {"op": "add", "path": "/a/0", "value": 0},
+                {"op": "add", "path": "/d", "value": "y"},
+                {"op": "remove", "path": "/b/c"},
+                {"op": "replace", "path": "/a/1", "value": 10},
+                {"op": "move", "from": "/a/2", "path": "/d"},
+                {"op": "move", "from": "/b", "path": "/a/-"},
+                {"op": "copy", "from": "/a/0", "path": "/e"},
+                {"op": "test", "path": "/e", "value": 0}
+            ]"#,
+        ))
+        .unwrap();
+        let inverse = patch.invert(&original).unwrap();
+
+        let mut doc = original.clone();
+        patch.apply(&mut doc).unwrap();
+        assert_ne!(original, doc);
+        inverse.apply(&mut doc).unwrap();
+        assert!(original.eq_ordered(&doc), "{doc:?}");
+
+        let root =
+            Patch::from_value(&value(r#"[{"op": "replace", "path": "", "value": 1}]"#)).unwrap();
+        assert_eq!(
+            Patch::from_value(&value(
+                r#"[{"op": "replace", "path": "", "value": [1, 2]}]"#
+            ))
+            .unwrap(),
+            root.invert(&value("[1, 2]")).unwrap()
+        );
+    }
+}
@@ -0,0 +1,242 @@
+//! Transformations of documents as streams of [`Event`]s, from an [`EventReader`] through
+//! composable filters into an [`EventWriter`], in memory proportional to their nesting depth.
+//!
+//! [`EventReader`]: crate::events::EventReader
+
+use std::{collections::HashSet, io};
+
+use crate::{
+    JsonParseError, PathSegment,
+    events::Event,
+    ser::{EventWriter, IoAdapter, WriteOptions},
+};
+
+/// A stage of a [`Pipeline`].
+pub trait Filter<'a> {
+    /// Handles `event`, pushing the events to pass on to the next stage to `out`.
+    ///
+    /// `pointer` is the JSON pointer of the value the event belongs to, as the events received
+    /// by this stage describe it; a [`Event::Key`] has the pointer of the member it names.
+    fn filter(&mut self, pointer: &str, event: Event<'a>, out: &mut Vec<Event<'a>>);
+}
+
+impl<'a, F: FnMut(&str, Event<'a>, &mut Vec<Event<'a>>)> Filter<'a> for F {
+    fn filter(&mut self, pointer: &str, event: Event<'a>, out: &mut Vec<Event<'a>>) {
+        self(pointer, event, out)
+    }
+}
+
+/// Renames object keys: the function receives the pointer of the member and its raw key, and
+/// returns the new raw key, if any.
+pub struct RenameKeys<F>(pub F);
+
+impl<'a, F: FnMut(&str, &str) -> Option<String>> Filter<'a> for RenameKeys<F> {
+    fn filter(&mut self, pointer: &str, event: Event<'a>, out: &mut Vec<Event<'a>>) {
+        out.push(match event {
+            Event::Key(key) => match (self.0)(pointer, &key) {
+                Some(renamed) => Event::Key(renamed.into()),
+                None => Event::Key(key),
+            },
+            event => event,
+        });
+    }
+}
+
+/// Rewrites scalar values: the function receives the pointer and event of each string, number,
+/// boolean and null, and returns the event to write instead.
+pub struct RewriteValues<F>(pub F);
+
+impl<'a, F: FnMut(&str, Event<'a>) -> Event<'a>> Filter<'a> for RewriteValues<F> {
+    fn filter(&mut self, pointer: &str, event: Event<'a>, out: &mut Vec<Event<'a>>) {
+        out.push(match event {
+            Event::Str(_) | Event::Number(_) | Event::Boolean(_) | Event::Null => {
+                (self.0)(pointer, event)
+            }
+            event => event,
+        });
+    }
+}
+
+/// Drops the values at the given pointers, along with their keys. Array elements after a dropped
+/// one keep their original indices in the pointers of this stage.
+pub struct DropPaths {
+    pointers: HashSet<String>,
+    /// Nesting depth inside the container being dropped.
+    depth: usize,
+}
+
+impl DropPaths {
+    pub fn new<I: IntoIterator<Item = S>, S: Into<String>>(pointers: I) -> Self {
+        DropPaths {
+            pointers: pointers.into_iter().map(Into::into).collect(),
+            depth: 0,
+        }
+    }
+}
+
+impl<'a> Filter<'a> for DropPaths {
+    fn filter(&mut self, pointer: &str, event: Event<'a>, out: &mut Vec<Event<'a>>) {
+        let dropped = self.depth > 0 || self.pointers.contains(pointer);
+        match event {
+            Event::StartObject | Event::StartArray if dropped => self.depth += 1,
+            Event::EndObject | Event::EndArray if dropped => self.depth -= 1,
+            _ if dropped => {}
+            event => out.push(event),
+        }
+    }
+}
+
+/// A chain of [`Filter`]s.
+#[derive(Default)]
+pub struct Pipeline<'a> {
+    stages: Vec<(Box<dyn Filter<'a> + 'a>, PointerTracker)>,
+    batch: Vec<Event<'a>>,
+    next_batch: Vec<Event<'a>>,
+}
+
+impl<'a> Pipeline<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a stage to the pipeline.
+    pub fn filter(mut self, filter: impl Filter<'a> + 'a) -> Self {
+        self.stages
+            .push((Box::new(filter), PointerTracker::default()));
+        self
+    }
+
+    /// Runs `event` through every stage, passing what comes out of the last one to `sink`.
+    pub fn process(&mut self, event: Event<'a>, mut sink: impl FnMut(Event<'a>)) {
+        self.batch.push(event);
+        for (filter, pointer) in &mut self.stages {
+            for event in self.batch.drain(..) {
+                pointer.track(&event);
+                filter.filter(&pointer.pointer, event, &mut self.next_batch);
+            }
+            std::mem::swap(&mut self.batch, &mut self.next_batch);
+        }
+        self.batch.drain(..).for_each(&mut sink);
+    }
+
+    /// Runs every event of `events` through the pipeline and writes the result as compact JSON
+    /// text to `writer`.
+    ///
+    /// Parse errors are reported as [`io::ErrorKind::InvalidData`] errors wrapping the
+    /// [`JsonParseError`].
+    pub fn run<I, W>(mut self, events: I, writer: &mut W, options: WriteOptions) -> io::Result<()>
+    where
+        I: IntoIterator<Item = Result<Event<'a>, JsonParseError>>,
+        W: io::Write,
+    {
+        let mut out = EventWriter::new(IoAdapter::new(writer), options);
+        let mut result = Ok(());
+        for event in events {
+            let event = event.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            self.process(event, |e| {
+                if result.is_ok() {
+                    result = out.write(&e);
+                }
+            });
+            if result.is_err() {
+                return Err(out.into_inner().into_error());
+            }
+        }
+        out.into_inner().flush()
+    }
+}
+
+/// Follows the JSON pointer of the events going through a stage.
+#[derive(Default)]
+struct PointerTracker {
+    pointer: String,
+    /// Pointer lengths of the open containers, with the number of elements started in arrays.
+    containers: Vec<(usize, Option<usize>)>,
+}
+
+impl PointerTracker {
+    fn track(&mut self, event: &Event) {
+        match event {
+            Event::Key(key) => {
+                self.truncate();
+                PathSegment::Key(key).push_to(&mut self.pointer);
+            }
+            Event::EndObject | Event::EndArray => {
+                self.truncate();
+                self.containers.pop();
+            }
+            _ => {
+                if let Some((_, Some(started))) = self.containers.last_mut() {
+                    let index = *started;
+                    *started += 1;
+                    self.truncate();
+                    PathSegment::Index(index).push_to(&mut self.pointer);
+                } else if self.containers.is_empty() {
+                    self.pointer.clear();
+                }
+                match event {
+                    Event::StartObject => self.containers.push((self.pointer.len(), None)),
+                    Event::StartArray => self.containers.push((self.pointer.len(), Some(0))),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Moves the pointer back to the innermost open container.
+    fn truncate(&mut self) {
+        let len = self.containers.last().map_or(0, |c| c.0);
+        self.pointer.truncate(len);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+
+    use mjl::JsonLexer;
+
+    use crate::{
+        events::{Event, EventReader},
+        pipeline::{DropPaths, Pipeline, RenameKeys, RewriteValues},
+    };
+
+    #[test]
+    fn transform_document() {
+        let input = r#"{"id": 1, "user": {"name": "a", "password": "x"}, "tags": ["t", "u", "v"]}"#;
+        let pipeline = Pipeline::new()
+            .filter(DropPaths::new(["/user/password", "/tags/1"]))
+            .filter(RenameKeys(|_: &str, key: &str| {
+                (key == "name").then(|| "login".to_string())
+            }))
+            .filter(RewriteValues(|pointer: &str, event| match pointer {
+                "/tags/1" => Event::Str(Cow::Borrowed("w")),
+                _ => event,
+            }));
+        let mut out = Vec::new();
+        pipeline
+            .run(
+                EventReader::new(JsonLexer::new(input)),
+                &mut out,
+                Default::default(),
+            )
+            .unwrap();
+        assert_eq!(
+            r#"{"id":1,"user":{"login":"a"},"tags":["t","w"]}"#,
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_errors_stop_the_pipeline() {
+        let mut out = Vec::new();
+        let err = Pipeline::new()
+            .run(
+                EventReader::new(JsonLexer::new("[1, }")),
+                &mut out,
+                Default::default(),
+            )
+            .unwrap_err();
+        assert_eq!(std::io::ErrorKind::InvalidData, err.kind());
+    }
+}
@@ -0,0 +1,107 @@
+//! Pseudonymization of values, for sharing datasets while debugging without disclosing their
+//! sensitive parts.
+//!
+//! Values are replaced with tokens computed by HMAC-SHA256 under a secret key, so equal values get
+//! equal tokens across documents, keeping references between them intact, while the values can't
+//! be recovered or guessed without the key.
+
+use std::borrow::Cow;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::Value;
+
+/// Prefix of the tokens replacing pseudonymized values.
+pub const TOKEN_PREFIX: &str = "anon_";
+
+impl<'a> Value<'a> {
+    /// Replaces every value matching one of `patterns`, as with [`Value::matches`], with a
+    /// string token derived from `key` and the value: `anon_` and 32 hexadecimal digits.
+    ///
+    /// Strings get the same token whatever the spelling of their escapes, and never the same as
+    /// a number or container, so `"1"` and `1` get different tokens.
+    pub fn pseudonymize(&mut self, key: &[u8], patterns: &[&str]) -> &mut Self {
+        let pointers: Vec<String> = patterns
+            .iter()
+            .flat_map(|pattern| self.matches(pattern))
+            .map(|(pointer, _)| pointer)
+            .collect();
+        for pointer in pointers {
+            if let Some(value) = self.pointer_mut(&pointer)
+                && !value.is_token()
+            {
+                *value = Value::Str(Cow::Owned(token(key, value)));
+            }
+        }
+        self
+    }
+
+    /// Whether this is a token, left as is when matched again by another pattern.
+    fn is_token(&self) -> bool {
+        matches!(self, Value::Str(s) if s.len() == TOKEN_PREFIX.len() + 32 && s.starts_with(TOKEN_PREFIX))
+    }
+}
+
+fn token(key: &[u8], value: &Value) -> String {
+    let mut message = Vec::new();
+    match value {
+        Value::Str(s) => {
+            message.push(b's');
+            message.extend_from_slice(mjl::unescape(s).as_bytes());
+        }
+        value => {
+            message.push(b'v');
+            message.extend_from_slice(value.to_string().as_bytes());
+        }
+    }
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(&message);
+    let mac = mac.finalize().into_bytes();
+    let mut token = TOKEN_PREFIX.to_string();
+    for byte in &mac[..16] {
+        token.push_str(&format!("{byte:02x}"));
+    }
+    token
+}
+
+#[cfg(test)]
+mod test {
+    use mjl::JsonLexer;
+
+    use crate::{Value, parse};
+
+    #[test]
+    fn pseudonymize_values() {
+        let mut value = parse(JsonLexer::new(
+            r#"{"users": [{"id": 1, "email": "a@example.com"}, {"id": "1", "email": "a\u0040example.com"}],
+                "orders": [{"user": 1}]}"#,
+        ))
+        .unwrap()
+        .value;
+        value.pseudonymize(b"secret", &["/users/*/id", "/**/email", "/orders/*/user"]);
+
+        let at = |pointer| match value.pointer(pointer) {
+            Some(Value::Str(s)) => s.to_string(),
+            v => panic!("not a token: {v:?}"),
+        };
+        // HMAC-SHA256 of `v1`, truncated
+        assert_eq!("anon_b774ec50777cf6a0542184bf8f3c539d", at("/users/0/id"));
+        // references are kept, and escapes don't matter
+        assert_eq!(at("/users/0/id"), at("/orders/0/user"));
+        assert_eq!(at("/users/0/email"), at("/users/1/email"));
+        // types are told apart
+        assert_ne!(at("/users/0/id"), at("/users/1/id"));
+
+        // the same key gives the same tokens, another one different tokens
+        let mut again = parse(JsonLexer::new(r#"[1]"#)).unwrap().value;
+        again.pseudonymize(b"secret", &["/0"]);
+        assert_eq!(
+            Some(&Value::Str(at("/users/0/id").into())),
+            again.pointer("/0")
+        );
+        let mut other = parse(JsonLexer::new(r#"[1]"#)).unwrap().value;
+        other.pseudonymize(b"other", &["/0"]);
+        assert_ne!(again, other);
+    }
+}
@@ -0,0 +1,85 @@
+//! Values kept as their JSON text rather than parsed into a tree, so that large subtrees cost
+//! no more memory than their text.
+
+use std::{borrow::Cow, fmt::Display};
+
+use mjl::JsonLexer;
+
+use crate::{JsonParseError, Value, parse};
+
+/// The JSON text of a value, borrowed from the input it was parsed from, as produced by
+/// [`ParseOptions::spill_above`](crate::ParseOptions::spill_above). The text is known to be valid
+/// JSON.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawValue<'a>(pub(crate) Cow<'a, str>);
+
+impl<'a> RawValue<'a> {
+    /// The JSON text of the value.
+    pub fn get(&self) -> &str {
+        &self.0
+    }
+
+    /// Parses the value, which borrows from this raw value.
+    pub fn parse(&self) -> Result<Value<'_>, JsonParseError> {
+        parse(JsonLexer::new(&self.0)).map(|json| json.value)
+    }
+
+    pub fn into_owned(self) -> RawValue<'static> {
+        RawValue(Cow::Owned(self.0.into_owned()))
+    }
+}
+
+impl Display for RawValue<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mjl::JsonLexer;
+
+    use crate::{ErrorKind, ParseOptions, Value, parse, parse_with, ser::WriteOptions};
+
+    fn spilled(input: &str, limit: usize) -> Result<Value<'_>, crate::JsonParseError> {
+        let options = ParseOptions {
+            spill_above: Some(limit),
+            ..Default::default()
+        };
+        parse_with(JsonLexer::new(input), options).map(|json| json.value)
+    }
+
+    #[test]
+    fn spill_large_subtrees() {
+        let data = "[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20]";
+        let input = format!(r#"{{"id": 1, "blob": {{"data": {data}}}, "name": "a"}}"#);
+        let value = spilled(&input, 50).unwrap();
+        // only the innermost large value is kept raw
+        match value.pointer("/blob/data") {
+            Some(Value::Raw(raw)) => assert_eq!(data, raw.get()),
+            v => panic!("unexpected value {v:?}"),
+        }
+        assert_eq!(Some(&Value::Number("1".into())), value.get("id"));
+        assert_eq!(parse(JsonLexer::new(&input)).unwrap().value, value);
+
+        let mut compact = String::new();
+        crate::ser::write_value(&value, &WriteOptions::default(), &mut compact).unwrap();
+        assert_eq!(
+            format!(r#"{{"id":1,"blob":{{"data":{data}}},"name":"a"}}"#),
+            compact
+        );
+
+        let value = spilled(r#"["a long string value", "b"]"#, 10).unwrap();
+        assert!(matches!(value.pointer("/0"), Some(Value::Raw(_))));
+        assert_eq!(Some(&Value::Str("b".into())), value.pointer("/1"));
+    }
+
+    #[test]
+    fn validate_spilled_values() {
+        let err = spilled(r#"{"blob": [1, 2, 3, 4, 5, {"a": 1, "a": 2}]}"#, 5).unwrap_err();
+        assert_eq!(ErrorKind::DuplicateKey, err.kind());
+        assert_eq!("/blob/5", err.path());
+        let err = spilled("[1, 2, 3, 4, 5 6]", 5).unwrap_err();
+        assert_eq!(ErrorKind::Syntax, err.kind());
+    }
+}
@@ -0,0 +1,142 @@
+//! Reduced copies of large arrays, for exploring and previewing documents quickly.
+
+use std::collections::BTreeSet;
+
+use crate::Value;
+
+/// How [`Value::sample_array`] picks the elements it keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// The first elements.
+    Head,
+    /// Elements picked at random, the same ones for the same seed.
+    Random { seed: u64 },
+    /// Evenly spaced elements: the array is split into as many runs as elements are kept, and
+    /// the middle element of each run is kept.
+    Stratified,
+}
+
+impl<'a> Value<'a> {
+    /// Returns a copy of this array with at most `n` of its elements, picked by `strategy` and
+    /// kept in their order. Other values are copied as they are.
+    ///
+    /// A [raw](Value::Raw) array is parsed first, so its sample owns its strings.
+    pub fn sample_array(&self, n: usize, strategy: Strategy) -> Value<'a> {
+        let values = match self {
+            Value::Array(values) => values,
+            Value::Raw(raw) => match raw.parse() {
+                Ok(value @ Value::Array(_)) => return value.sample_array(n, strategy).into_owned(),
+                _ => return self.clone(),
+            },
+            value => return value.clone(),
+        };
+        let len = values.len();
+        if len <= n {
+            return self.clone();
+        }
+        let indices: Vec<usize> = match strategy {
+            Strategy::Head => (0..n).collect(),
+            Strategy::Random { seed } => random_indices(len, n, seed),
+            // the middle of run `i`, (i + 1/2) * len / n
+            Strategy::Stratified => (0..n)
+                .map(|i| ((2 * i + 1) as u128 * len as u128 / (2 * n) as u128) as usize)
+                .collect(),
+        };
+        Value::Array(indices.into_iter().map(|i| values[i].clone()).collect())
+    }
+}
+
+/// `n` distinct indices below `len`, in increasing order, chosen uniformly by Floyd's algorithm.
+fn random_indices(len: usize, n: usize, seed: u64) -> Vec<usize> {
+    let mut rng = SplitMix64(seed);
+    let mut chosen = BTreeSet::new();
+    for j in len - n..len {
+        let t = rng.below(j + 1);
+        if !chosen.insert(t) {
+            chosen.insert(j);
+        }
+    }
+    chosen.into_iter().collect()
+}
+
+/// The SplitMix64 generator, which is small and good enough for sampling.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number below `bound`, with a negligible bias.
+    fn below(&mut self, bound: usize) -> usize {
+        ((u128::from(self.next()) * bound as u128) >> 64) as usize
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mjl::JsonLexer;
+
+    use crate::{ParseOptions, Value, parse, parse_with, sample::Strategy};
+
+    fn numbers(value: &Value) -> Vec<usize> {
+        let Value::Array(values) = value else {
+            panic!("not an array: {value:?}");
+        };
+        values
+            .iter()
+            .map(|v| match v {
+                Value::Number(n) => n.as_str().parse().unwrap(),
+                v => panic!("not a number: {v:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sample_arrays() {
+        let input = format!(
+            "[{}]",
+            (0..100)
+                .map(|i| i.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+        let array = parse(JsonLexer::new(&input)).unwrap().value;
+
+        assert_eq!(
+            vec![0, 1, 2],
+            numbers(&array.sample_array(3, Strategy::Head))
+        );
+        assert_eq!(
+            vec![10, 30, 50, 70, 90],
+            numbers(&array.sample_array(5, Strategy::Stratified))
+        );
+
+        let random = |seed| numbers(&array.sample_array(10, Strategy::Random { seed }));
+        let sample = random(7);
+        assert_eq!(10, sample.len());
+        assert!(sample.windows(2).all(|w| w[0] < w[1]), "{sample:?}");
+        assert_eq!(sample, random(7));
+        assert_ne!(sample, random(8));
+
+        // small arrays and other values are kept whole
+        assert_eq!(array, array.sample_array(100, Strategy::Stratified));
+        let object = parse(JsonLexer::new(r#"{"a": [1, 2]}"#)).unwrap().value;
+        assert_eq!(object, object.sample_array(1, Strategy::Head));
+
+        let options = ParseOptions {
+            spill_above: Some(10),
+            ..Default::default()
+        };
+        let raw = parse_with(JsonLexer::new(&input), options).unwrap().value;
+        assert!(matches!(raw, Value::Raw(_)));
+        assert_eq!(
+            vec![25, 75],
+            numbers(&raw.sample_array(2, Strategy::Stratified))
+        );
+    }
+}
@@ -0,0 +1,217 @@
+//! Cleanup passes for values, usually applied before persisting user-submitted documents.
+//!
+//! Every pass works in place and returns the value again, so passes can be chained, e.g.
+//! `value.drop_nulls().drop_empty().trim_strings()`.
+//!
+//! String values hold the raw (escaped) text of the literal, so only literal whitespace is
+//! trimmed; escape sequences such as `\n` are left untouched.
+
+use std::borrow::Cow;
+
+use crate::Value;
+
+/// What [`Value::normalize_unicode_with`] normalizes, and to which form.
+#[cfg(feature = "unicode")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Normalization {
+    pub form: NormalizationForm,
+    /// Normalize object keys. Members whose keys become equal are kept.
+    pub keys: bool,
+    /// Normalize string values.
+    pub strings: bool,
+}
+
+#[cfg(feature = "unicode")]
+impl Default for Normalization {
+    fn default() -> Self {
+        Normalization {
+            form: NormalizationForm::Nfc,
+            keys: false,
+            strings: true,
+        }
+    }
+}
+
+#[cfg(feature = "unicode")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NormalizationForm {
+    /// Canonical composition, as produced by most platforms.
+    #[default]
+    Nfc,
+    /// Canonical decomposition, as produced by macOS file names.
+    Nfd,
+}
+
+impl<'a> Value<'a> {
+    /// Removes object members whose value is `null`, recursively. Nulls inside arrays are kept,
+    /// since removing them would shift the position of the following elements.
+    pub fn drop_nulls(&mut self) -> &mut Self {
+        match self {
+            Value::Object(pairs) => {
+                pairs.retain(|p| !matches!(p.value, Value::Null));
+                for pair in pairs {
+                    pair.value.drop_nulls();
+                }
+            }
+            Value::Array(values) => {
+                for value in values {
+                    value.drop_nulls();
+                }
+            }
+            _ => {}
+        }
+        self
+    }
+
+    /// Removes object members and array elements that are empty objects or arrays, recursively.
+    ///
+    /// Containers are cleaned bottom-up, so a container that only held empty containers is
+    /// removed as well. The value this is called on is never removed, even if it ends up empty.
+    pub fn drop_empty(&mut self) -> &mut Self {
+        match self {
+            Value::Object(pairs) => {
+                for pair in pairs.iter_mut() {
+                    pair.value.drop_empty();
+                }
+                pairs.retain(|p| !p.value.is_empty_container());
+            }
+            Value::Array(values) => {
+                for value in values.iter_mut() {
+                    value.drop_empty();
+                }
+                values.retain(|v| !v.is_empty_container());
+            }
+            _ => {}
+        }
+        self
+    }
+
+    /// Trims leading and trailing whitespace from every string value, recursively. Keys are left
+    /// as they are.
+    pub fn trim_strings(&mut self) -> &mut Self {
+        self.for_each_str(&mut |s| match s {
+            Cow::Borrowed(b) => *b = b.trim(),
+            Cow::Owned(o) => {
+                let trimmed = o.trim();
+                if trimmed.len() != o.len() {
+                    *o = trimmed.to_string();
+                }
+            }
+        });
+        self
+    }
+
+    /// Normalizes every string value to Unicode Normalization Form C, recursively.
+    #[cfg(feature = "unicode")]
+    pub fn normalize_unicode(&mut self) -> &mut Self {
+        self.normalize_unicode_with(Normalization::default())
+    }
+
+    /// Normalizes string values and/or keys to the chosen form, recursively, so that documents
+    /// written on different platforms compare equal.
+    #[cfg(feature = "unicode")]
+    pub fn normalize_unicode_with(&mut self, normalization: Normalization) -> &mut Self {
+        use unicode_normalization::{
+            IsNormalized, UnicodeNormalization, is_nfc_quick, is_nfd_quick,
+        };
+
+        let mut normalize = |s: &mut Cow<'a, str>| match normalization.form {
+            NormalizationForm::Nfc if is_nfc_quick(s.chars()) != IsNormalized::Yes => {
+                *s = Cow::Owned(s.nfc().collect())
+            }
+            NormalizationForm::Nfd if is_nfd_quick(s.chars()) != IsNormalized::Yes => {
+                *s = Cow::Owned(s.nfd().collect())
+            }
+            _ => {}
+        };
+        self.for_each_text(normalization.keys, normalization.strings, &mut normalize);
+        self
+    }
+
+    fn is_empty_container(&self) -> bool {
+        match self {
+            Value::Object(pairs) => pairs.is_empty(),
+            Value::Array(values) => values.is_empty(),
+            _ => false,
+        }
+    }
+
+    fn for_each_str(&mut self, f: &mut impl FnMut(&mut Cow<'a, str>)) {
+        self.for_each_text(false, true, f);
+    }
+
+    /// Calls `f` on every key and/or string value, recursively.
+    fn for_each_text(&mut self, keys: bool, strings: bool, f: &mut impl FnMut(&mut Cow<'a, str>)) {
+        match self {
+            Value::Object(pairs) => {
+                for pair in pairs {
+                    if keys {
+                        f(&mut pair.key);
+                    }
+                    pair.value.for_each_text(keys, strings, f);
+                }
+            }
+            Value::Array(values) => {
+                for value in values {
+                    value.for_each_text(keys, strings, f);
+                }
+            }
+            Value::Str(s) if strings => f(s),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mjl::JsonLexer;
+
+    use crate::{Json, parse};
+
+    fn json(input: &str) -> Json<'_> {
+        parse(JsonLexer::new(input)).unwrap()
+    }
+
+    #[test]
+    fn drop_nulls_keeps_array_positions() {
+        let mut value = json(r#"{ "a": null, "b": [null, 1], "c": { "d": null } }"#).value;
+        value.drop_nulls();
+        assert_eq!(json(r#"{ "b": [null, 1], "c": {} }"#).value, value);
+    }
+
+    #[test]
+    fn drop_empty_cascades() {
+        let mut value = json(r#"{ "a": [[], {}], "b": { "c": {} }, "d": [1, []] }"#).value;
+        value.drop_empty();
+        assert_eq!(json(r#"{ "d": [1] }"#).value, value);
+    }
+
+    #[test]
+    fn passes_compose() {
+        let mut value = json(r#"{ "a": { "b": null }, "c": "  x  " }"#).value;
+        value.drop_nulls().drop_empty().trim_strings();
+        assert_eq!(json(r#"{ "c": "x" }"#).value, value);
+    }
+
+    #[cfg(feature = "unicode")]
+    #[test]
+    fn normalize_unicode_composes() {
+        let mut value = json("[\"e\u{301}\"]").value;
+        value.normalize_unicode();
+        assert_eq!(json("[\"\u{e9}\"]").value, value);
+    }
+
+    #[cfg(feature = "unicode")]
+    #[test]
+    fn normalize_unicode_keys() {
+        use crate::sanitize::{Normalization, NormalizationForm};
+
+        let mut value = json("{\"caf\u{e9}\": \"caf\u{e9}\"}").value;
+        value.normalize_unicode_with(Normalization {
+            form: NormalizationForm::Nfd,
+            keys: true,
+            strings: false,
+        });
+        assert_eq!(json("{\"cafe\u{301}\": \"caf\u{e9}\"}").value, value);
+    }
+}
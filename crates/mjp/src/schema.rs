@@ -0,0 +1,994 @@
+//! Validation against a subset of JSON Schema, checked event by event so that invalid documents
+//! are rejected without being read in full or built into a [`Value`].
+//!
+//! The supported keywords are `type`, `enum`, `const`, `minimum`, `maximum`, `exclusiveMinimum`,
+//! `exclusiveMaximum`, `required`, `properties`, `additionalProperties` and `items`, and `format`,
+//! which is only used by [`generate_example`]. Others are ignored.
+//!
+//! Property names are matched against the raw text of keys, and `enum` and `const` compare
+//! strings by their raw text and numbers as written, so `"\u0061"` doesn't match `"a"`, nor
+//! `1.0` match `1`.
+
+use std::{borrow::Cow, error::Error, fmt::Display};
+
+use mjl::JsonLexer;
+
+use crate::{
+    BooleanVal, JsonParseError, Pair, PathSegment, Value,
+    builder::JsonNumber,
+    events::{Event, EventReader},
+    ser::escape,
+    spanned::{Span, SpannedNode, SpannedValue},
+};
+
+/// A compiled schema. `true` and `{}` accept anything, `false` rejects everything.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Schema {
+    reject: bool,
+    types: Option<Vec<Type>>,
+    values: Option<Vec<Value<'static>>>,
+    minimum: Option<Bound>,
+    maximum: Option<Bound>,
+    required: Vec<String>,
+    properties: Vec<(String, Schema)>,
+    additional_properties: Option<Box<Schema>>,
+    items: Option<Box<Schema>>,
+    format: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Type {
+    Object,
+    Array,
+    String,
+    Number,
+    Integer,
+    Boolean,
+    Null,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Bound {
+    limit: f64,
+    exclusive: bool,
+}
+
+/// A schema that could not be compiled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaError {
+    path: String,
+    message: String,
+}
+
+impl SchemaError {
+    /// JSON pointer to the offending keyword in the schema.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {}", self.message, self.path)
+    }
+}
+
+impl Error for SchemaError {}
+
+/// A value rejected by a schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    path: String,
+    message: String,
+    span: Option<Span>,
+}
+
+impl ValidationError {
+    /// JSON pointer to the rejected value in the document.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Where the rejected value is in the input, when validated with [`validate_spanned`].
+    pub fn span(&self) -> Option<Span> {
+        self.span
+    }
+
+    /// Renders this error with the line of `input` holding the rejected value, which is
+    /// underlined, as compilers do:
+    ///
+    /// ```text
+    /// error: expected integer, found number at /id
+    ///  --> line 2, column 9
+    ///   |
+    /// 2 |   "id": 1.5,
+    ///   |         ^^^
+    /// ```
+    ///
+    /// `input` must be the one the error's span was recorded in. Without a span, only the
+    /// first line is rendered.
+    pub fn render(&self, input: &str) -> String {
+        let path = if self.path.is_empty() {
+            "(root)"
+        } else {
+            &self.path
+        };
+        let mut out = format!("error: {} at {path}\n", self.message);
+        let Some(span) = self.span else {
+            return out;
+        };
+        let line_start = input[..span.start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = input[span.start..]
+            .find('\n')
+            .map_or(input.len(), |i| span.start + i);
+        let line = input[line_start..line_end].trim_end_matches('\r');
+        // values spanning several lines are underlined up to the end of their first one
+        let width = input[span.start..span.end.min(line_end)].chars().count();
+
+        let number = span.line.to_string();
+        let margin = " ".repeat(number.len());
+        out.push_str(&format!(
+            "{margin}--> line {}, column {}\n",
+            span.line, span.column
+        ));
+        out.push_str(&format!("{margin} |\n{number} | {line}\n"));
+        out.push_str(&format!(
+            "{margin} | {}{}\n",
+            " ".repeat(span.column - 1),
+            "^".repeat(width.max(1))
+        ));
+        out
+    }
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {}", self.message, self.path)
+    }
+}
+
+impl Error for ValidationError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidateError {
+    Parse(JsonParseError),
+    Invalid(ValidationError),
+}
+
+impl Display for ValidateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidateError::Parse(e) => e.fmt(f),
+            ValidateError::Invalid(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for ValidateError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ValidateError::Parse(e) => Some(e),
+            ValidateError::Invalid(e) => Some(e),
+        }
+    }
+}
+
+impl From<JsonParseError> for ValidateError {
+    fn from(e: JsonParseError) -> Self {
+        ValidateError::Parse(e)
+    }
+}
+
+impl From<ValidationError> for ValidateError {
+    fn from(e: ValidationError) -> Self {
+        ValidateError::Invalid(e)
+    }
+}
+
+const ANY: Schema = Schema {
+    reject: false,
+    types: None,
+    values: None,
+    minimum: None,
+    maximum: None,
+    required: Vec::new(),
+    properties: Vec::new(),
+    additional_properties: None,
+    items: None,
+    format: None,
+};
+
+static UNCONSTRAINED: Schema = ANY;
+
+impl Schema {
+    pub fn from_value(schema: &Value) -> Result<Schema, SchemaError> {
+        let mut path = String::new();
+        compile(schema, &mut path)
+    }
+}
+
+fn compile(schema: &Value, path: &mut String) -> Result<Schema, SchemaError> {
+    let pairs = match schema {
+        Value::Object(pairs) => pairs,
+        Value::Boolean(b) => {
+            return Ok(Schema {
+                reject: *b == crate::BooleanVal::False,
+                ..ANY
+            });
+        }
+        _ => {
+            return Err(schema_error(
+                path,
+                "a schema must be an object or a boolean",
+            ));
+        }
+    };
+
+    let mut compiled = ANY;
+    for Pair { key, value } in pairs {
+        let parent = path.len();
+        PathSegment::Key(key).push_to(path);
+        match key.as_ref() {
+            "type" => {
+                let names = match value {
+                    Value::Array(values) => values.iter().collect(),
+                    value => vec![value],
+                };
+                let types = names.into_iter().map(|name| match name {
+                    Value::Str(name) => Type::from_name(name)
+                        .ok_or_else(|| schema_error(path, format!("unknown type {name:?}"))),
+                    _ => Err(schema_error(path, "a type must be a string")),
+                });
+                compiled.types = Some(types.collect::<Result<_, _>>()?);
+            }
+            "enum" => match value {
+                Value::Array(values) => {
+                    compiled.values = Some(values.iter().cloned().map(Value::into_owned).collect())
+                }
+                _ => return Err(schema_error(path, "expected an array")),
+            },
+            "const" => compiled.values = Some(vec![value.clone().into_owned()]),
+            "minimum" | "exclusiveMinimum" | "maximum" | "exclusiveMaximum" => {
+                let Some(limit) = number(value) else {
+                    return Err(schema_error(path, "expected a number"));
+                };
+                let exclusive = key.starts_with("exclusive");
+                let bound = Some(Bound { limit, exclusive });
+                if key.ends_with("inimum") {
+                    compiled.minimum = bound;
+                } else {
+                    compiled.maximum = bound;
+                }
+            }
+            "required" => match value {
+                Value::Array(names) => {
+                    for name in names {
+                        let Value::Str(name) = name else {
+                            return Err(schema_error(path, "a required member must be a string"));
+                        };
+                        compiled.required.push(name.to_string());
+                    }
+                }
+                _ => return Err(schema_error(path, "expected an array")),
+            },
+            "properties" => match value {
+                Value::Object(properties) => {
+                    for Pair { key, value } in properties {
+                        let parent = path.len();
+                        PathSegment::Key(key).push_to(path);
+                        compiled
+                            .properties
+                            .push((key.to_string(), compile(value, path)?));
+                        path.truncate(parent);
+                    }
+                }
+                _ => return Err(schema_error(path, "expected an object")),
+            },
+            "additionalProperties" => {
+                compiled.additional_properties = Some(Box::new(compile(value, path)?))
+            }
+            "items" => compiled.items = Some(Box::new(compile(value, path)?)),
+            "format" => match value {
+                Value::Str(format) => compiled.format = Some(format.to_string()),
+                _ => return Err(schema_error(path, "expected a string")),
+            },
+            _ => {}
+        }
+        path.truncate(parent);
+    }
+    Ok(compiled)
+}
+
+fn schema_error(path: &str, message: impl Into<String>) -> SchemaError {
+    SchemaError {
+        path: path.to_string(),
+        message: message.into(),
+    }
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        _ => None,
+    }
+}
+
+impl Type {
+    fn from_name(name: &str) -> Option<Type> {
+        Some(match name {
+            "object" => Type::Object,
+            "array" => Type::Array,
+            "string" => Type::String,
+            "number" => Type::Number,
+            "integer" => Type::Integer,
+            "boolean" => Type::Boolean,
+            "null" => Type::Null,
+            _ => return None,
+        })
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Type::Object => "object",
+            Type::Array => "array",
+            Type::String => "string",
+            Type::Number => "number",
+            Type::Integer => "integer",
+            Type::Boolean => "boolean",
+            Type::Null => "null",
+        }
+    }
+}
+
+/// Checks the events of a document against a [`Schema`] as they arrive.
+///
+/// Only the values of containers constrained by `enum` or `const` are kept in memory, to be
+/// compared once complete.
+pub struct StreamValidator<'s> {
+    root: &'s Schema,
+    containers: Vec<Container<'s>>,
+    /// Schema of the next member, set by its key.
+    member: Option<&'s Schema>,
+    path: String,
+}
+
+struct Container<'s> {
+    schema: &'s Schema,
+    /// Length of the validator's path at this container.
+    path_len: usize,
+    is_object: bool,
+    /// The required members not seen yet.
+    missing: Vec<&'s str>,
+    /// The number of elements started.
+    elements: usize,
+    /// The events of this container, when they are compared to `enum` or `const`.
+    events: Option<Vec<Event<'static>>>,
+}
+
+impl<'s> StreamValidator<'s> {
+    pub fn new(schema: &'s Schema) -> Self {
+        StreamValidator {
+            root: schema,
+            containers: Vec::new(),
+            member: None,
+            path: String::new(),
+        }
+    }
+
+    /// Checks the next event of the document.
+    pub fn feed(&mut self, event: &Event) -> Result<(), ValidationError> {
+        for container in &mut self.containers {
+            if let Some(events) = &mut container.events {
+                events.push(event.clone().into_owned());
+            }
+        }
+
+        let schema = match event {
+            Event::Key(key) => {
+                let Some(container) = self.containers.last_mut().filter(|c| c.is_object) else {
+                    return Err(self.error("key outside of an object"));
+                };
+                self.path.truncate(container.path_len);
+                PathSegment::Key(key).push_to(&mut self.path);
+                container.missing.retain(|name| name != key);
+                let schema = container.schema;
+                let member = schema
+                    .properties
+                    .iter()
+                    .find(|(name, _)| name == key)
+                    .map(|(_, schema)| schema)
+                    .or(schema.additional_properties.as_deref())
+                    .unwrap_or(&UNCONSTRAINED);
+                self.member = Some(member);
+                return Ok(());
+            }
+            Event::EndObject | Event::EndArray => {
+                let is_object = *event == Event::EndObject;
+                let Some(container) = self.containers.pop_if(|c| c.is_object == is_object) else {
+                    return Err(self.error("end of a container that was not started"));
+                };
+                self.path.truncate(container.path_len);
+                if let Some(name) = container.missing.first() {
+                    return Err(self.error(format!("missing required member {name:?}")));
+                }
+                if let Some(events) = container.events
+                    && let Some(value) = build(&mut events.into_iter())
+                {
+                    check_values(container.schema, &value).map_err(|e| self.error(e))?;
+                }
+                return Ok(());
+            }
+            _ => self.next_schema(),
+        };
+
+        if schema.reject {
+            return Err(self.error("no value is allowed"));
+        }
+        let (found, value) = match event {
+            Event::StartObject => (Type::Object, None),
+            Event::StartArray => (Type::Array, None),
+            Event::Str(s) => (Type::String, Some(Value::Str(s.clone()))),
+            Event::Number(n) => (Type::Number, Some(Value::Number(n.clone().into()))),
+            Event::Boolean(b) => (Type::Boolean, Some(Value::Boolean(*b))),
+            Event::Null => (Type::Null, Some(Value::Null)),
+            _ => unreachable!(),
+        };
+        if let Some(types) = &schema.types {
+            let accepted = |t: &Type| match (t, &value) {
+                (Type::Integer, Some(Value::Number(n))) => n.is_integer(),
+                (Type::Number, _) => found == Type::Number,
+                _ => *t == found,
+            };
+            if !types.iter().any(accepted) {
+                let expected: Vec<_> = types.iter().map(|t| t.name()).collect();
+                return Err(self.error(format!(
+                    "expected {}, found {}",
+                    expected.join(" or "),
+                    found.name()
+                )));
+            }
+        }
+
+        match value {
+            Some(value) => {
+                check_values(schema, &value).map_err(|e| self.error(e))?;
+                if let Value::Number(n) = &value {
+                    // beyond the range of `f64`, numbers are past any bound
+                    let f = n.as_f64().unwrap_or(if n.as_str().starts_with('-') {
+                        f64::NEG_INFINITY
+                    } else {
+                        f64::INFINITY
+                    });
+                    check_range(schema, f, n).map_err(|e| self.error(e))?;
+                }
+            }
+            None => self.containers.push(Container {
+                schema,
+                path_len: self.path.len(),
+                is_object: found == Type::Object,
+                missing: if found == Type::Object {
+                    schema.required.iter().map(String::as_str).collect()
+                } else {
+                    Vec::new()
+                },
+                elements: 0,
+                events: schema
+                    .values
+                    .is_some()
+                    .then(|| vec![event.clone().into_owned()]),
+            }),
+        }
+        Ok(())
+    }
+
+    /// The schema of the value starting now, with the path set to it.
+    fn next_schema(&mut self) -> &'s Schema {
+        match self.containers.last_mut() {
+            None => self.root,
+            Some(container) if !container.is_object => {
+                self.path.truncate(container.path_len);
+                PathSegment::Index(container.elements).push_to(&mut self.path);
+                container.elements += 1;
+                container.schema.items.as_deref().unwrap_or(&UNCONSTRAINED)
+            }
+            Some(_) => self.member.take().unwrap_or(&UNCONSTRAINED),
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> ValidationError {
+        ValidationError {
+            path: self.path.clone(),
+            message: message.into(),
+            span: None,
+        }
+    }
+}
+
+/// Validates a document read from `events`, stopping at the first parse error or invalid value.
+pub fn validate<'a>(
+    events: impl IntoIterator<Item = Result<Event<'a>, JsonParseError>>,
+    schema: &Schema,
+) -> Result<(), ValidateError> {
+    let mut validator = StreamValidator::new(schema);
+    for event in events {
+        validator.feed(&event?)?;
+    }
+    Ok(())
+}
+
+/// Validates a document parsed by [`parse_spanned`](crate::spanned::parse_spanned), so that
+/// errors carry the [span](ValidationError::span) of the rejected value along with its path.
+///
+/// The values inside a [raw](crate::Value::Raw) value have no spans of their own: errors in them
+/// point at the whole raw value.
+pub fn validate_spanned(value: &SpannedValue, schema: &Schema) -> Result<(), ValidationError> {
+    feed_spanned(&mut StreamValidator::new(schema), value)
+}
+
+fn feed_spanned(
+    validator: &mut StreamValidator,
+    value: &SpannedValue,
+) -> Result<(), ValidationError> {
+    let located = |e| ValidationError {
+        span: Some(value.span),
+        ..e
+    };
+    match &value.node {
+        SpannedNode::Object(pairs) => {
+            validator.feed(&Event::StartObject).map_err(located)?;
+            for pair in pairs {
+                validator.feed(&Event::Key(Cow::Borrowed(&pair.key)))?;
+                feed_spanned(validator, &pair.value)?;
+            }
+            validator.feed(&Event::EndObject).map_err(located)
+        }
+        SpannedNode::Array(values) => {
+            validator.feed(&Event::StartArray).map_err(located)?;
+            for value in values {
+                feed_spanned(validator, value)?;
+            }
+            validator.feed(&Event::EndArray).map_err(located)
+        }
+        SpannedNode::Scalar(value) => feed_value(validator, value).map_err(located),
+    }
+}
+
+fn feed_value(validator: &mut StreamValidator, value: &Value) -> Result<(), ValidationError> {
+    match value {
+        Value::Object(pairs) => {
+            validator.feed(&Event::StartObject)?;
+            for pair in pairs {
+                validator.feed(&Event::Key(Cow::Borrowed(&pair.key)))?;
+                feed_value(validator, &pair.value)?;
+            }
+            validator.feed(&Event::EndObject)
+        }
+        Value::Array(values) => {
+            validator.feed(&Event::StartArray)?;
+            for value in values {
+                feed_value(validator, value)?;
+            }
+            validator.feed(&Event::EndArray)
+        }
+        Value::Str(s) => validator.feed(&Event::Str(Cow::Borrowed(s))),
+        Value::Number(n) => validator.feed(&Event::Number(Cow::Borrowed(n.as_str()))),
+        Value::Boolean(b) => validator.feed(&Event::Boolean(*b)),
+        Value::Null => validator.feed(&Event::Null),
+        Value::Raw(raw) => {
+            // raw values are valid JSON, so no event is dropped here
+            for event in EventReader::new(JsonLexer::new(raw.get())).map_while(Result::ok) {
+                validator.feed(&event)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Returns a document valid against `schema`, for mocking and contract testing, or `None` if
+/// none was found, as for `false` or contradictory keywords.
+///
+/// The first value of `enum` is taken, and otherwise one of the first allowed type: objects have
+/// every property and required member, arrays one item, numbers the bound nearest to zero, and
+/// strings a sample of their `format`, such as `"2024-01-01"` for `date`.
+pub fn generate_example(schema: &Schema) -> Option<Value<'static>> {
+    let example = example(schema)?;
+    let mut validator = StreamValidator::new(schema);
+    feed_value(&mut validator, &example).ok()?;
+    Some(example)
+}
+
+fn example(schema: &Schema) -> Option<Value<'static>> {
+    if schema.reject {
+        return None;
+    }
+    if let Some(values) = &schema.values {
+        return values.first().cloned();
+    }
+    let constrained = |t| match t {
+        Type::Object => {
+            !schema.required.is_empty()
+                || !schema.properties.is_empty()
+                || schema.additional_properties.is_some()
+        }
+        Type::Array => schema.items.is_some(),
+        Type::Number => schema.minimum.is_some() || schema.maximum.is_some(),
+        Type::String => schema.format.is_some(),
+        _ => false,
+    };
+    let found = match &schema.types {
+        Some(types) => *types.first()?,
+        None => [Type::Object, Type::Array, Type::Number, Type::String]
+            .into_iter()
+            .find(|t| constrained(*t))
+            .unwrap_or(Type::Null),
+    };
+    Some(match found {
+        Type::Object => {
+            let mut pairs = Vec::new();
+            for (name, property) in &schema.properties {
+                let required = schema.required.contains(name);
+                match example(property) {
+                    Some(value) => pairs.push(Pair {
+                        key: Cow::Owned(escape(name).into_owned()),
+                        value,
+                    }),
+                    None if required => return None,
+                    None => {}
+                }
+            }
+            for name in &schema.required {
+                if !schema.properties.iter().any(|(n, _)| n == name) {
+                    let additional = schema
+                        .additional_properties
+                        .as_deref()
+                        .unwrap_or(&UNCONSTRAINED);
+                    pairs.push(Pair {
+                        key: Cow::Owned(escape(name).into_owned()),
+                        value: example(additional)?,
+                    });
+                }
+            }
+            Value::Object(pairs.into())
+        }
+        Type::Array => Value::Array(
+            schema
+                .items
+                .as_deref()
+                .and_then(example)
+                .into_iter()
+                .collect(),
+        ),
+        Type::String => Value::Str(Cow::Borrowed(
+            match schema.format.as_deref().unwrap_or_default() {
+                "date-time" => "2024-01-01T00:00:00Z",
+                "date" => "2024-01-01",
+                "time" => "00:00:00Z",
+                "email" => "user@example.com",
+                "hostname" => "example.com",
+                "uri" | "url" => "https://example.com/",
+                "uuid" => "00000000-0000-0000-0000-000000000000",
+                "ipv4" => "192.0.2.1",
+                "ipv6" => "2001:db8::1",
+                _ => "string",
+            },
+        )),
+        Type::Number | Type::Integer => {
+            let n = example_number(schema, found == Type::Integer)?;
+            Value::Number(n.into())
+        }
+        Type::Boolean => Value::Boolean(BooleanVal::True),
+        Type::Null => Value::Null,
+    })
+}
+
+/// The number nearest to zero in the bounds of `schema`, or else halfway between them.
+fn example_number(schema: &Schema, integer: bool) -> Option<String> {
+    let (min, max) = (schema.minimum, schema.maximum);
+    let nearest = match (min, max) {
+        (Some(min), _) if min.limit >= 0.0 => above(min, integer),
+        (_, Some(max)) if max.limit <= 0.0 => -above(negated(max), integer),
+        _ => 0.0,
+    };
+    let halfway = min.zip(max).map(|(min, max)| (min.limit + max.limit) / 2.0);
+    let n = [Some(nearest), halfway]
+        .into_iter()
+        .flatten()
+        .find(|n| check_range(schema, *n, n).is_ok() && (!integer || n.fract() == 0.0))?;
+    // turns -0.0, which would be written `-0`, into 0.0
+    let n = n + 0.0;
+    if integer && n.abs() < 1e15 {
+        Some((n as i64).to_string())
+    } else {
+        n.to_json()
+    }
+}
+
+/// The lowest number accepted by a lower bound.
+fn above(bound: Bound, integer: bool) -> f64 {
+    match (integer, bound.exclusive) {
+        (true, true) => bound.limit.floor() + 1.0,
+        (true, false) => bound.limit.ceil(),
+        (false, true) => bound.limit + 1.0,
+        (false, false) => bound.limit,
+    }
+}
+
+fn negated(bound: Bound) -> Bound {
+    Bound {
+        limit: -bound.limit,
+        ..bound
+    }
+}
+
+fn check_values(schema: &Schema, value: &Value) -> Result<(), String> {
+    match &schema.values {
+        Some(values) if !values.contains(value) => Err("value not in enum".to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// Checks that `n`, written `text`, is within the bounds of `schema`.
+fn check_range(schema: &Schema, n: f64, text: impl Display) -> Result<(), String> {
+    if let Some(Bound { limit, exclusive }) = schema.minimum
+        && (n < limit || exclusive && n == limit)
+    {
+        return Err(format!("{text} is below the minimum of {limit}"));
+    }
+    if let Some(Bound { limit, exclusive }) = schema.maximum
+        && (n > limit || exclusive && n == limit)
+    {
+        return Err(format!("{text} is above the maximum of {limit}"));
+    }
+    Ok(())
+}
+
+/// Builds the value of a complete, well-formed sequence of events, or returns `None` at the end
+/// of a container.
+fn build(events: &mut impl Iterator<Item = Event<'static>>) -> Option<Value<'static>> {
+    Some(match events.next()? {
+        Event::StartObject => {
+            let mut pairs = Vec::new();
+            while let Some(Event::Key(key)) = events.next() {
+                let value = build(events)?;
+                pairs.push(Pair { key, value });
+            }
+            Value::Object(pairs.into())
+        }
+        Event::StartArray => Value::Array(std::iter::from_fn(|| build(events)).collect()),
+        Event::Str(s) => Value::Str(s),
+        Event::Number(n) => Value::Number(n.into()),
+        Event::Boolean(b) => Value::Boolean(b),
+        Event::Null => Value::Null,
+        Event::EndObject | Event::EndArray | Event::Key(_) => return None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use mjl::JsonLexer;
+
+    use crate::{
+        ParseOptions,
+        events::{Event, EventReader},
+        parse,
+        schema::{
+            Schema, StreamValidator, ValidateError, generate_example, validate, validate_spanned,
+        },
+        spanned::parse_spanned,
+    };
+
+    const SCHEMA: &str = r#"{
+        "type": "object",
+        "required": ["id", "tags"],
+        "properties": {
+            "id": {"type": "integer", "minimum": 1},
+            "ratio": {"type": "number", "exclusiveMaximum": 1},
+            "kind": {"enum": ["a", "b", null]},
+            "tags": {"type": "array", "items": {"type": "string"}},
+            "origin": {"const": {"x": 0, "y": 0}}
+        },
+        "additionalProperties": false
+    }"#;
+
+    fn schema() -> Schema {
+        Schema::from_value(&parse(JsonLexer::new(SCHEMA)).unwrap().value).unwrap()
+    }
+
+    fn check(input: &str) -> Result<(), ValidateError> {
+        validate(EventReader::new(JsonLexer::new(input)), &schema())
+    }
+
+    fn invalid(input: &str) -> (String, String) {
+        match check(input).unwrap_err() {
+            ValidateError::Invalid(e) => (e.path().to_string(), e.message().to_string()),
+            e => panic!("unexpected error {e:?}"),
+        }
+    }
+
+    #[test]
+    fn accept_valid_documents() {
+        check(r#"{"id": 1, "tags": []}"#).unwrap();
+        check(
+            r#"{"id": 2.0, "ratio": 0.5, "kind": null, "tags": ["x"], "origin": {"y": 0, "x": 0}}"#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn reject_invalid_documents() {
+        assert_eq!(
+            ("".into(), "expected object, found array".into()),
+            invalid("[]")
+        );
+        assert_eq!(
+            ("/id".into(), "expected integer, found number".into()),
+            invalid(r#"{"id": 1.5, "tags": []}"#)
+        );
+        assert_eq!(
+            ("/id".into(), "0 is below the minimum of 1".into()),
+            invalid(r#"{"id": 0, "tags": []}"#)
+        );
+        assert_eq!(
+            ("/ratio".into(), "1 is above the maximum of 1".into()),
+            invalid(r#"{"id": 1, "ratio": 1, "tags": []}"#)
+        );
+        assert_eq!(
+            ("/kind".into(), "value not in enum".into()),
+            invalid(r#"{"id": 1, "kind": "c", "tags": []}"#)
+        );
+        assert_eq!(
+            ("/tags/1".into(), "expected string, found number".into()),
+            invalid(r#"{"id": 1, "tags": ["a", 2]}"#)
+        );
+        assert_eq!(
+            ("/origin".into(), "value not in enum".into()),
+            invalid(r#"{"id": 1, "tags": [], "origin": {"x": 1, "y": 0}}"#)
+        );
+        assert_eq!(
+            ("/extra".into(), "no value is allowed".into()),
+            invalid(r#"{"id": 1, "tags": [], "extra": 1}"#)
+        );
+        assert_eq!(
+            ("".into(), "missing required member \"tags\"".into()),
+            invalid(r#"{"id": 1}"#)
+        );
+        // beyond the range of f64
+        assert_eq!(
+            ("/ratio".into(), "1e400 is above the maximum of 1".into()),
+            invalid(r#"{"id": 1, "ratio": 1e400, "tags": []}"#)
+        );
+        assert_eq!(
+            ("/id".into(), "-1e400 is below the minimum of 1".into()),
+            invalid(r#"{"id": -1e400, "tags": []}"#)
+        );
+    }
+
+    #[test]
+    fn reject_unbalanced_events() {
+        let schema = Schema::from_value(&parse(JsonLexer::new("{}")).unwrap().value).unwrap();
+        for events in [
+            vec![Event::Key("a".into())],
+            vec![Event::StartArray, Event::Key("a".into())],
+            vec![Event::StartObject, Event::EndArray],
+            vec![Event::Null, Event::EndObject],
+        ] {
+            let mut validator = StreamValidator::new(&schema);
+            let last = events.len() - 1;
+            for (i, event) in events.iter().enumerate() {
+                let fed = validator.feed(event);
+                assert_eq!(i == last, fed.is_err(), "{events:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn stop_early() {
+        // the document is rejected before the syntax error is reached
+        assert!(matches!(
+            check(r#"{"id": "1", "tags": [}"#),
+            Err(ValidateError::Invalid(_))
+        ));
+        assert!(matches!(
+            check(r#"{"id": 1, "tags": [}"#),
+            Err(ValidateError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn point_at_source_spans() {
+        let locate = |input: &str, options: ParseOptions| {
+            let value = parse_spanned(JsonLexer::new(input), options).unwrap();
+            let e = validate_spanned(&value, &schema()).unwrap_err();
+            let span = e.span().unwrap();
+            (
+                e.path().to_string(),
+                span.line,
+                span.column,
+                e.render(input),
+            )
+        };
+
+        let input = "{\n  \"id\": 1.5,\n  \"tags\": []\n}";
+        let (path, line, column, rendered) = locate(input, ParseOptions::default());
+        assert_eq!(("/id", 2, 9), (path.as_str(), line, column));
+        assert_eq!(
+            "error: expected integer, found number at /id\n \
+             --> line 2, column 9\n  \
+             |\n\
+             2 |   \"id\": 1.5,\n  \
+             |         ^^^\n",
+            rendered
+        );
+
+        // a missing member is reported at its object, underlined up to the end of the line
+        let input = "[0,\n {\"id\": 1,\n  \"ratio\": 0}]";
+        let value = parse_spanned(JsonLexer::new(input), ParseOptions::default()).unwrap();
+        let e = validate_spanned(value.get_index(1).unwrap(), &schema()).unwrap_err();
+        assert_eq!(
+            "error: missing required member \"tags\" at (root)\n \
+             --> line 2, column 2\n  \
+             |\n\
+             2 |  {\"id\": 1,\n  \
+             |  ^^^^^^^^^\n",
+            e.render(input)
+        );
+
+        // errors inside a raw value point at the whole value
+        let options = ParseOptions {
+            spill_above: Some(20),
+            ..Default::default()
+        };
+        let input = r#"{"id": 1, "tags": ["a", "b", "c", "d", 3]}"#;
+        let (path, line, column, _) = locate(input, options);
+        assert_eq!(("/tags/4", 1, 19), (path.as_str(), line, column));
+    }
+
+    #[test]
+    fn generate_examples() {
+        let example = |schema: &str| {
+            let schema = Schema::from_value(&parse(JsonLexer::new(schema)).unwrap().value);
+            generate_example(&schema.unwrap())
+        };
+        let assert_example = |expected: &str, schema: &str| {
+            let example = example(schema).unwrap();
+            let expected = parse(JsonLexer::new(expected)).unwrap().value;
+            assert!(expected.eq_ordered(&example), "{example:?}");
+        };
+
+        assert_example(
+            r#"{"id": 1, "ratio": 0, "kind": "a", "tags": ["string"], "origin": {"x": 0, "y": 0}}"#,
+            SCHEMA,
+        );
+        assert_example(
+            r#"{"at": "2024-01-01T00:00:00Z", "n": -3, "f": 0.5, "x": null}"#,
+            r#"{"required": ["at", "x"], "properties": {
+                "at": {"type": "string", "format": "date-time"},
+                "none": false,
+                "n": {"type": "integer", "exclusiveMaximum": -2.5},
+                "f": {"exclusiveMinimum": 0.25, "maximum": 0.75}
+            }}"#,
+        );
+        assert_example("true", r#"{"type": ["boolean", "null"]}"#);
+        assert_example("null", "{}");
+
+        // unsatisfiable schemas
+        assert_eq!(None, example("false"));
+        assert_eq!(
+            None,
+            example(r#"{"required": ["a"], "additionalProperties": false}"#)
+        );
+        assert_eq!(
+            None,
+            example(r#"{"type": "integer", "minimum": 0.2, "maximum": 0.8}"#)
+        );
+        assert_eq!(None, example(r#"{"type": "string", "enum": [1]}"#));
+    }
+}
@@ -0,0 +1,1038 @@
+//! Writing values back to JSON text.
+
+use std::{
+    borrow::{Borrow, Cow},
+    convert::Infallible,
+    error::Error,
+    fmt::{self, Display, Write},
+    io,
+};
+
+use crate::{CommentMap, Comments, Json, Pair, PathSegment, Value, events::Event};
+
+/// How strings and keys are escaped when written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EscapePolicy {
+    /// Write the text of string literals exactly as it was parsed.
+    #[default]
+    Preserve,
+    /// Write non-ASCII characters literally, decoding `\uXXXX` escapes (including surrogate
+    /// pairs) of printable non-ASCII characters.
+    Literal,
+    /// Escape every non-ASCII character, astral characters as a surrogate pair of `\uXXXX`
+    /// escapes, so the output is pure ASCII.
+    Ascii,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    pub escape: EscapePolicy,
+    /// Escape every control character (C0, DEL and C1) and invisible formatting character, such
+    /// as line separators and bidirectional overrides, so the output is safe to print to
+    /// terminals and to feed to line-oriented tools.
+    pub escape_non_printable: bool,
+    /// Strings and keys hold decoded text, as parsed with
+    /// [`ParseOptions::decode_escapes`](crate::ParseOptions::decode_escapes), and are escaped as
+    /// they are written. Otherwise they hold the raw text of their literal.
+    pub decoded: bool,
+    /// Spread output over several indented lines. Compact output is written when `None`.
+    pub pretty: Option<PrettyOptions>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrettyOptions {
+    /// Number of spaces per level of nesting.
+    pub indent: usize,
+    /// Indent with a tab per level of nesting instead of spaces. Tabs count as `indent` columns
+    /// against `width`.
+    pub tabs: bool,
+    /// When set, arrays and objects that fit on the rest of their line within this many columns
+    /// are kept on a single line, e.g. `[1, 2, 3]` or `{ "a": 1 }`; larger ones are expanded.
+    /// When unset, every non-empty array and object is expanded.
+    pub width: Option<usize>,
+    /// Write the members of objects ordered by the raw text of their keys, as
+    /// [`MemberOrder::Lexical`](crate::order::MemberOrder::Lexical) would, without reordering
+    /// the value.
+    pub sort_keys: bool,
+    /// End the output with a line break.
+    pub trailing_newline: bool,
+}
+
+impl PrettyOptions {
+    /// The whitespace indenting values nested `depth` levels deep.
+    fn indentation(&self, depth: usize) -> String {
+        if self.tabs {
+            "\t".repeat(depth)
+        } else {
+            " ".repeat(self.indent * depth)
+        }
+    }
+
+    /// The members of an object, in the order they are written.
+    fn members<'p, 'a>(&self, pairs: &'p [Pair<'a>]) -> Vec<&'p Pair<'a>> {
+        let mut members: Vec<_> = pairs.iter().collect();
+        if self.sort_keys {
+            members.sort_by(|a, b| a.key.cmp(&b.key));
+        }
+        members
+    }
+}
+
+impl Default for PrettyOptions {
+    fn default() -> Self {
+        PrettyOptions {
+            indent: 2,
+            tabs: false,
+            width: None,
+            sort_keys: false,
+            trailing_newline: false,
+        }
+    }
+}
+
+impl Value<'_> {
+    /// This value as JSON text spread over indented lines per `pretty`.
+    pub fn to_string_pretty(&self, pretty: &PrettyOptions) -> String {
+        let options = WriteOptions {
+            pretty: Some(pretty.clone()),
+            ..Default::default()
+        };
+        let mut text = String::new();
+        write_value(self, &options, &mut text).unwrap();
+        text
+    }
+}
+
+/// Writes `value` as JSON text, compact unless [`WriteOptions::pretty`] is set.
+pub fn write_value<W: Write>(value: &Value, options: &WriteOptions, out: &mut W) -> fmt::Result {
+    match &options.pretty {
+        Some(pretty) => {
+            write_pretty(value, options, pretty, &mut None, 0, 0, out)?;
+            if pretty.trailing_newline {
+                out.write_char('\n')?;
+            }
+            Ok(())
+        }
+        None => write_compact(value, options, out),
+    }
+}
+
+/// Writes `json` spread over indented lines, per [`WriteOptions::pretty`] or its defaults, along
+/// with the comments attached to its values. Comments are written as they were found in the
+/// input: leading ones on their own lines and trailing ones after the value and its comma.
+pub fn write_commented<W: Write>(json: &Json, options: &WriteOptions, out: &mut W) -> fmt::Result {
+    let default = PrettyOptions::default();
+    let pretty = options.pretty.as_ref().unwrap_or(&default);
+    let mut commented = Some(Commented {
+        comments: &json.comments,
+        pointer: String::new(),
+    });
+    commented.as_ref().unwrap().write_leading("", out)?;
+    write_pretty(&json.value, options, pretty, &mut commented, 0, 0, out)?;
+    commented.as_ref().unwrap().write_trailing("", out)?;
+    if pretty.trailing_newline {
+        out.write_char('\n')?;
+    }
+    Ok(())
+}
+
+/// Compact JSON text, as in `value.to_string()`, or with `{:#}` spread over indented lines per
+/// [`PrettyOptions::default`]. Strings and keys are written as the raw text of their literals.
+impl Display for Value<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let options = WriteOptions {
+            pretty: f.alternate().then(PrettyOptions::default),
+            ..Default::default()
+        };
+        write_value(self, &options, f)
+    }
+}
+
+/// The text of the value of the document, as written by its [`Value`]'s `Display`, without its
+/// comments.
+impl Display for Json<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+fn write_compact<W: Write>(value: &Value, options: &WriteOptions, out: &mut W) -> fmt::Result {
+    match value {
+        Value::Object(pairs) => {
+            out.write_char('{')?;
+            for (i, pair) in pairs.iter().enumerate() {
+                if i > 0 {
+                    out.write_char(',')?;
+                }
+                write_str(&pair.key, options, out)?;
+                out.write_char(':')?;
+                write_compact(&pair.value, options, out)?;
+            }
+            out.write_char('}')
+        }
+        Value::Array(values) => {
+            out.write_char('[')?;
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.write_char(',')?;
+                }
+                write_compact(value, options, out)?;
+            }
+            out.write_char(']')
+        }
+        Value::Str(s) => write_str(s, options, out),
+        Value::Number(n) => out.write_str(n.as_str()),
+        Value::Boolean(b) => write!(out, "{b}"),
+        Value::Null => out.write_str("null"),
+        Value::Raw(raw) => out.write_str(raw.get()),
+    }
+}
+
+/// Writes `value` spread over indented lines. `depth` is the nesting level of `value` and
+/// `column` the number of chars already written on its first line.
+fn write_pretty<W: Write>(
+    value: &Value,
+    options: &WriteOptions,
+    pretty: &PrettyOptions,
+    commented: &mut Option<Commented>,
+    depth: usize,
+    column: usize,
+    out: &mut W,
+) -> fmt::Result {
+    let is_empty = match value {
+        Value::Object(pairs) => pairs.is_empty(),
+        Value::Array(values) => values.is_empty(),
+        _ => true,
+    };
+    if is_empty {
+        return write_compact(value, options, out);
+    }
+    // comments need line breaks around them
+    let nested_comments = commented.as_ref().is_some_and(Commented::has_nested);
+    if let Some(width) = pretty.width.filter(|_| !nested_comments) {
+        // leave room for the comma that may follow
+        let mut budget = Budget(width.saturating_sub(column + 1));
+        if write_single_line(value, options, pretty, &mut budget).is_ok() {
+            return write_single_line(value, options, pretty, out);
+        }
+    }
+
+    let indent = pretty.indentation(depth + 1);
+    let columns = pretty.indent * (depth + 1);
+    match value {
+        Value::Object(pairs) => {
+            out.write_char('{')?;
+            for (i, pair) in pretty.members(pairs).into_iter().enumerate() {
+                write!(out, "\n{indent}")?;
+                let parent = enter(commented, PathSegment::Key(&pair.key));
+                if let Some(c) = commented {
+                    c.write_leading(&indent, out)?;
+                }
+                let mut key = String::new();
+                write_str(&pair.key, options, &mut key)?;
+                write!(out, "{key}: ")?;
+                let column = columns + key.chars().count() + 2;
+                write_pretty(
+                    &pair.value,
+                    options,
+                    pretty,
+                    commented,
+                    depth + 1,
+                    column,
+                    out,
+                )?;
+                if i + 1 < pairs.len() {
+                    out.write_char(',')?;
+                }
+                if let Some(c) = commented {
+                    c.write_trailing(&indent, out)?;
+                    c.pointer.truncate(parent);
+                }
+            }
+            write!(out, "\n{}}}", pretty.indentation(depth))
+        }
+        Value::Array(values) => {
+            out.write_char('[')?;
+            for (i, value) in values.iter().enumerate() {
+                write!(out, "\n{indent}")?;
+                let parent = enter(commented, PathSegment::Index(i));
+                if let Some(c) = commented {
+                    c.write_leading(&indent, out)?;
+                }
+                write_pretty(value, options, pretty, commented, depth + 1, columns, out)?;
+                if i + 1 < values.len() {
+                    out.write_char(',')?;
+                }
+                if let Some(c) = commented {
+                    c.write_trailing(&indent, out)?;
+                    c.pointer.truncate(parent);
+                }
+            }
+            write!(out, "\n{}]", pretty.indentation(depth))
+        }
+        _ => unreachable!("scalars are written compactly"),
+    }
+}
+
+/// Writes [`Event`]s as compact JSON text as they come, without building the document.
+///
+/// The events are expected to form a valid document; anything else produces invalid output.
+pub struct EventWriter<W> {
+    out: W,
+    options: WriteOptions,
+    /// Whether each open container already has a child.
+    has_children: Vec<bool>,
+    after_key: bool,
+}
+
+impl<W: Write> EventWriter<W> {
+    /// Creates a writer escaping strings per `options`. [`WriteOptions::pretty`] is ignored.
+    pub fn new(out: W, options: WriteOptions) -> Self {
+        EventWriter {
+            out,
+            options,
+            has_children: Vec::new(),
+            after_key: false,
+        }
+    }
+
+    pub fn write(&mut self, event: &Event) -> fmt::Result {
+        let closing = matches!(event, Event::EndObject | Event::EndArray);
+        if !closing
+            && !std::mem::take(&mut self.after_key)
+            && let Some(has_children) = self.has_children.last_mut()
+            && std::mem::replace(has_children, true)
+        {
+            self.out.write_char(',')?;
+        }
+
+        match event {
+            Event::StartObject => {
+                self.has_children.push(false);
+                self.out.write_char('{')
+            }
+            Event::StartArray => {
+                self.has_children.push(false);
+                self.out.write_char('[')
+            }
+            Event::EndObject => {
+                self.has_children.pop();
+                self.out.write_char('}')
+            }
+            Event::EndArray => {
+                self.has_children.pop();
+                self.out.write_char(']')
+            }
+            Event::Key(key) => {
+                self.after_key = true;
+                write_str(key, &self.options, &mut self.out)?;
+                self.out.write_char(':')
+            }
+            Event::Str(s) => write_str(s, &self.options, &mut self.out),
+            Event::Number(n) => self.out.write_str(n),
+            Event::Boolean(b) => write!(self.out, "{b}"),
+            Event::Null => self.out.write_str("null"),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+/// Comments to write along with a value, and the JSON pointer of the value being written.
+struct Commented<'c> {
+    comments: &'c CommentMap<'c>,
+    pointer: String,
+}
+
+impl Commented<'_> {
+    fn get(&self) -> Option<&Comments<'_>> {
+        self.comments.get(&self.pointer)
+    }
+
+    /// Whether any value nested in the current one has comments.
+    fn has_nested(&self) -> bool {
+        let prefix = format!("{}/", self.pointer);
+        self.comments
+            .range(prefix.clone()..)
+            .next()
+            .is_some_and(|(pointer, _)| pointer.starts_with(&prefix))
+    }
+
+    fn write_leading<W: Write>(&self, indent: &str, out: &mut W) -> fmt::Result {
+        for text in self.get().map_or(&[][..], |c| &c.leading) {
+            write!(out, "{text}\n{indent}")?;
+        }
+        Ok(())
+    }
+
+    fn write_trailing<W: Write>(&self, indent: &str, out: &mut W) -> fmt::Result {
+        let mut after_line_comment = false;
+        for text in self.get().map_or(&[][..], |c| &c.trailing) {
+            if after_line_comment {
+                write!(out, "\n{indent}")?;
+            } else {
+                out.write_char(' ')?;
+            }
+            out.write_str(text)?;
+            after_line_comment = text.starts_with("//");
+        }
+        Ok(())
+    }
+}
+
+/// Moves the pointer of `commented` to a child of the current value, returning the length of the
+/// parent's pointer to restore it with.
+fn enter(commented: &mut Option<Commented>, segment: PathSegment) -> usize {
+    commented.as_mut().map_or(0, |c| {
+        let parent = c.pointer.len();
+        segment.push_to(&mut c.pointer);
+        parent
+    })
+}
+
+/// Writes `value` on a single line, with spaces after separators.
+fn write_single_line<W: Write>(
+    value: &Value,
+    options: &WriteOptions,
+    pretty: &PrettyOptions,
+    out: &mut W,
+) -> fmt::Result {
+    match value {
+        Value::Object(pairs) if !pairs.is_empty() => {
+            out.write_str("{ ")?;
+            for (i, pair) in pretty.members(pairs).into_iter().enumerate() {
+                if i > 0 {
+                    out.write_str(", ")?;
+                }
+                write_str(&pair.key, options, out)?;
+                out.write_str(": ")?;
+                write_single_line(&pair.value, options, pretty, out)?;
+            }
+            out.write_str(" }")
+        }
+        Value::Array(values) => {
+            out.write_char('[')?;
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.write_str(", ")?;
+                }
+                write_single_line(value, options, pretty, out)?;
+            }
+            out.write_char(']')
+        }
+        v => write_compact(v, options, out),
+    }
+}
+
+/// Writer that fails once more than the given number of chars are written to it.
+struct Budget(usize);
+
+impl Write for Budget {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let len = s.chars().count();
+        self.0 = self.0.checked_sub(len).ok_or(fmt::Error)?;
+        Ok(())
+    }
+}
+
+/// Writes `value` as compact JSON text to an `io::Write`.
+pub fn to_writer<W: io::Write>(
+    value: &Value,
+    options: &WriteOptions,
+    writer: &mut W,
+) -> io::Result<()> {
+    let mut adapter = IoAdapter::new(writer);
+    write_value(value, options, &mut adapter).map_err(|_| adapter.into_error())
+}
+
+/// Writes the values of `iter` as a JSON array to an `io::Write`, one at a time, without
+/// collecting them first.
+pub fn write_array_from_iter<'a, W, V>(
+    writer: &mut W,
+    iter: impl IntoIterator<Item = V>,
+    options: &WriteOptions,
+) -> io::Result<()>
+where
+    W: io::Write,
+    V: Borrow<Value<'a>>,
+{
+    try_write_array_from_iter(writer, iter.into_iter().map(Ok::<_, Infallible>), options)
+}
+
+/// Like [`write_array_from_iter`], for iterators that may fail. The first error stops the
+/// output, leaving it incomplete, and is returned as an [`io::Error`].
+pub fn try_write_array_from_iter<'a, W, V, E>(
+    writer: &mut W,
+    iter: impl IntoIterator<Item = Result<V, E>>,
+    options: &WriteOptions,
+) -> io::Result<()>
+where
+    W: io::Write,
+    V: Borrow<Value<'a>>,
+    E: Into<Box<dyn Error + Send + Sync>>,
+{
+    let members = iter.into_iter().map(|r| r.map(|v| (None::<&str>, v)));
+    write_members_from_iter(writer, ('[', ']'), members, options)
+}
+
+/// Writes the members of `iter` as a JSON object to an `io::Write`, one at a time, without
+/// collecting them first, unless [`PrettyOptions::sort_keys`] has them collected to be sorted.
+/// Keys are escaped as needed.
+pub fn write_object_from_iter<'a, W, K, V>(
+    writer: &mut W,
+    iter: impl IntoIterator<Item = (K, V)>,
+    options: &WriteOptions,
+) -> io::Result<()>
+where
+    W: io::Write,
+    K: AsRef<str>,
+    V: Borrow<Value<'a>>,
+{
+    try_write_object_from_iter(writer, iter.into_iter().map(Ok::<_, Infallible>), options)
+}
+
+/// Like [`write_object_from_iter`], for iterators that may fail. The first error stops the
+/// output, leaving it incomplete, and is returned as an [`io::Error`].
+pub fn try_write_object_from_iter<'a, W, K, V, E>(
+    writer: &mut W,
+    iter: impl IntoIterator<Item = Result<(K, V), E>>,
+    options: &WriteOptions,
+) -> io::Result<()>
+where
+    W: io::Write,
+    K: AsRef<str>,
+    V: Borrow<Value<'a>>,
+    E: Into<Box<dyn Error + Send + Sync>>,
+{
+    let members = iter.into_iter().map(|r| r.map(|(k, v)| (Some(k), v)));
+    write_members_from_iter(writer, ('{', '}'), members, options)
+}
+
+fn write_members_from_iter<'a, W, K, V, E>(
+    writer: &mut W,
+    brackets: (char, char),
+    members: impl Iterator<Item = Result<(Option<K>, V), E>>,
+    options: &WriteOptions,
+) -> io::Result<()>
+where
+    W: io::Write,
+    K: AsRef<str>,
+    V: Borrow<Value<'a>>,
+    E: Into<Box<dyn Error + Send + Sync>>,
+{
+    let mut out = IoAdapter::new(writer);
+    let written = match options.pretty.as_ref().filter(|p| p.sort_keys) {
+        // members can only be sorted once they are all known
+        Some(_) => match members.collect::<Result<Vec<_>, _>>() {
+            Ok(mut members) => {
+                members.sort_by(|(a, _), (b, _)| {
+                    let a = a.as_ref().map(|k| k.as_ref());
+                    a.cmp(&b.as_ref().map(|k| k.as_ref()))
+                });
+                write_members(&mut out, brackets, members.into_iter().map(Ok), options)
+            }
+            Err(e) => Err(Some(e)),
+        },
+        None => write_members(&mut out, brackets, members, options),
+    };
+    match written {
+        Ok(()) => Ok(()),
+        Err(Some(e)) => Err(io::Error::other(e)),
+        Err(None) => Err(out.into_error()),
+    }
+}
+
+/// Writes the members of an array or object, failing with the error of the first member that
+/// failed, or with `None` when writing itself did.
+fn write_members<'a, W, K, V, E>(
+    out: &mut IoAdapter<W>,
+    (open, close): (char, char),
+    members: impl Iterator<Item = Result<(Option<K>, V), E>>,
+    options: &WriteOptions,
+) -> Result<(), Option<E>>
+where
+    W: io::Write,
+    K: AsRef<str>,
+    V: Borrow<Value<'a>>,
+{
+    out.write_char(open).map_err(|_| None)?;
+    let mut empty = true;
+    for member in members {
+        let (key, value) = member.map_err(Some)?;
+        if !empty {
+            out.write_char(',').map_err(|_| None)?;
+        }
+        empty = false;
+        let mut line = String::new();
+        let mut column = 0;
+        if let Some(pretty) = &options.pretty {
+            write!(line, "\n{}", pretty.indentation(1)).unwrap();
+            column = pretty.indent;
+        }
+        if let Some(key) = key {
+            let key = key.as_ref();
+            // decoded keys are escaped as they are written
+            let raw = if options.decoded {
+                Cow::Borrowed(key)
+            } else {
+                escape(key)
+            };
+            let mut quoted = String::new();
+            write_str(&raw, options, &mut quoted).unwrap();
+            column += quoted.chars().count() + 1;
+            line.push_str(&quoted);
+            line.push(':');
+            if options.pretty.is_some() {
+                line.push(' ');
+                column += 1;
+            }
+        }
+        out.write_str(&line).map_err(|_| None)?;
+        let value = value.borrow();
+        let written = match &options.pretty {
+            Some(pretty) => write_pretty(value, options, pretty, &mut None, 1, column, out),
+            None => write_compact(value, options, out),
+        };
+        written.map_err(|_| None)?;
+    }
+    if options.pretty.is_some() && !empty {
+        out.write_char('\n').map_err(|_| None)?;
+    }
+    out.write_char(close).map_err(|_| None)?;
+    match &options.pretty {
+        Some(pretty) if pretty.trailing_newline => out.write_char('\n').map_err(|_| None),
+        _ => Ok(()),
+    }
+}
+
+/// `fmt::Write` over an `io::Write`, keeping the io error that made a write fail.
+pub(crate) struct IoAdapter<'w, W> {
+    writer: &'w mut W,
+    error: Option<io::Error>,
+}
+
+impl<'w, W> IoAdapter<'w, W> {
+    pub(crate) fn new(writer: &'w mut W) -> Self {
+        IoAdapter {
+            writer,
+            error: None,
+        }
+    }
+
+    pub(crate) fn into_error(self) -> io::Error {
+        self.error
+            .unwrap_or_else(|| io::Error::other("formatter error"))
+    }
+}
+
+impl<W: io::Write> IoAdapter<'_, W> {
+    pub(crate) fn flush(self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl<W: io::Write> Write for IoAdapter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.writer.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
+
+/// Escapes `s` into the raw text of a string literal, without quotes, as held by [`Value::Str`]
+/// and object keys.
+pub fn escape(s: &str) -> Cow<'_, str> {
+    if !s.contains(|c: char| c == '"' || c == '\\' || c.is_control()) {
+        return Cow::Borrowed(s);
+    }
+    let mut escaped = String::with_capacity(s.len() + 2);
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\u{8}' => escaped.push_str("\\b"),
+            '\u{c}' => escaped.push_str("\\f"),
+            c => push_char(c, &mut escaped),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+/// Escapes the control chars of the raw text of a string literal, as lexed with
+/// [`ControlChars::Escape`](mjl::ControlChars::Escape).
+pub(crate) fn escape_control_chars(raw: &str) -> Cow<'_, str> {
+    if !raw.contains(char::is_control) {
+        return Cow::Borrowed(raw);
+    }
+    let mut escaped = String::with_capacity(raw.len() + 8);
+    raw.chars().for_each(|c| push_char(c, &mut escaped));
+    Cow::Owned(escaped)
+}
+
+/// Pushes `c`, as a `\u` escape sequence if it is a control char.
+fn push_char(c: char, escaped: &mut String) {
+    if c.is_control() {
+        let mut units = [0; 2];
+        for unit in c.encode_utf16(&mut units) {
+            write!(escaped, "\\u{unit:04x}").unwrap();
+        }
+    } else {
+        escaped.push(c);
+    }
+}
+
+/// Writes the raw text of a string literal, with its quotes, re-escaped per the options.
+fn write_str<W: Write>(raw: &str, options: &WriteOptions, out: &mut W) -> fmt::Result {
+    let escaped;
+    let raw = if options.decoded {
+        escaped = escape(raw);
+        &escaped
+    } else {
+        raw
+    };
+    out.write_char('"')?;
+    if options.escape == EscapePolicy::Preserve && !options.escape_non_printable {
+        out.write_str(raw)?;
+        return out.write_char('"');
+    }
+
+    let escaped = |c: char| {
+        (options.escape == EscapePolicy::Ascii && !c.is_ascii())
+            || (options.escape_non_printable && is_non_printable(c))
+    };
+    let mut rest = raw;
+    while let Some(i) = rest.find(|c| c == '\\' || escaped(c)) {
+        out.write_str(&rest[..i])?;
+        let tail = &rest[i..];
+        let len = if let Some(after) = tail.strip_prefix('\\') {
+            match decode_unicode_escape(tail) {
+                Some((c, len))
+                    if options.escape == EscapePolicy::Literal
+                        && !c.is_ascii()
+                        && !c.is_control()
+                        && !escaped(c) =>
+                {
+                    out.write_char(c)?;
+                    len
+                }
+                // copy any other escape sequence as it is, an escaped backslash included
+                _ => {
+                    let len = after.chars().next().map_or(1, |c| 1 + c.len_utf8());
+                    out.write_str(&tail[..len])?;
+                    len
+                }
+            }
+        } else {
+            let c = tail.chars().next().unwrap();
+            let mut units = [0; 2];
+            for unit in c.encode_utf16(&mut units) {
+                write!(out, "\\u{unit:04x}")?;
+            }
+            c.len_utf8()
+        };
+        rest = &tail[len..];
+    }
+    out.write_str(rest)?;
+    out.write_char('"')
+}
+
+fn is_non_printable(c: char) -> bool {
+    c.is_control()
+        || matches!(
+            c,
+            '\u{200B}'..='\u{200F}'
+                | '\u{2028}'..='\u{202E}'
+                | '\u{2060}'..='\u{2064}'
+                | '\u{2066}'..='\u{2069}'
+                | '\u{FEFF}'
+        )
+}
+
+/// Decodes a `\uXXXX` escape at the start of `s`, or a surrogate pair of them, returning the
+/// escaped char and the length of the escape.
+fn decode_unicode_escape(s: &str) -> Option<(char, usize)> {
+    let unit = |s: &str| {
+        s.strip_prefix("\\u")
+            .and_then(|hex| hex.get(..4))
+            .and_then(|hex| u16::from_str_radix(hex, 16).ok())
+    };
+
+    let high = unit(s)?;
+    if let Some(c) = char::from_u32(high as u32) {
+        return Some((c, 6));
+    }
+    let low = unit(&s[6..])?;
+    char::decode_utf16([high, low])
+        .next()?
+        .ok()
+        .map(|c| (c, 12))
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+
+    use mjl::{CommentMode, JsonLexer, LexerOptions};
+
+    use crate::{
+        ParseOptions, Value, parse, parse_with,
+        ser::{
+            EscapePolicy, PrettyOptions, WriteOptions, try_write_array_from_iter,
+            write_array_from_iter, write_commented, write_object_from_iter, write_value,
+        },
+    };
+
+    fn write(value: &Value, escape: EscapePolicy) -> String {
+        let mut out = String::new();
+        let options = WriteOptions {
+            escape,
+            ..Default::default()
+        };
+        write_value(value, &options, &mut out).unwrap();
+        out
+    }
+
+    fn value(input: &str) -> Value<'_> {
+        parse(JsonLexer::new(input)).unwrap().value
+    }
+
+    #[test]
+    fn astral_round_trip() {
+        let literal = r#"{"asdf":1,"🗻∈🌏":true,"🗻":["é😀\n",42]}"#;
+        let ascii = write(&value(literal), EscapePolicy::Ascii);
+        assert_eq!(
+            r#"{"asdf":1,"\ud83d\uddfb\u2208\ud83c\udf0f":true,"\ud83d\uddfb":["\u00e9\ud83d\ude00\n",42]}"#,
+            ascii
+        );
+        assert_eq!(literal, write(&value(&ascii), EscapePolicy::Literal));
+        assert_eq!(ascii, write(&value(&ascii), EscapePolicy::Preserve));
+    }
+
+    #[test]
+    fn literal_keeps_special_escapes() {
+        let input = r#"["\"\u001f\u0085\ud83d\u00e9\\u00e9"]"#;
+        assert_eq!(
+            r#"["\"\u001f\u0085\ud83dé\\u00e9"]"#,
+            write(&value(input), EscapePolicy::Literal)
+        );
+    }
+
+    #[test]
+    fn escape_non_printable() {
+        // the lexer rejects DEL and C1 characters in strings, so build the value directly
+        let input = Value::Array(vec![Value::Str(Cow::Borrowed(
+            "a\u{7f}\u{85}\u{2028}\u{202e}é",
+        ))]);
+        let options = WriteOptions {
+            escape_non_printable: true,
+            ..Default::default()
+        };
+        let mut out = String::new();
+        write_value(&input, &options, &mut out).unwrap();
+        assert_eq!(r#"["a\u007f\u0085\u2028\u202eé"]"#, out);
+
+        // escapes of non-printable characters are not decoded in literal mode either
+        let options = WriteOptions {
+            escape: EscapePolicy::Literal,
+            escape_non_printable: true,
+            ..Default::default()
+        };
+        let mut literal = String::new();
+        write_value(&value(&out), &options, &mut literal).unwrap();
+        assert_eq!(out, literal);
+    }
+
+    fn pretty(value: &Value, width: Option<usize>) -> String {
+        let options = WriteOptions {
+            pretty: Some(PrettyOptions {
+                width,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut out = String::new();
+        write_value(value, &options, &mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn display() {
+        let input = r#"{ "a\"b": [1, "\u00e9\n", null, true] , "c": {} }"#;
+        let json = parse(JsonLexer::new(input)).unwrap();
+        assert_eq!(
+            r#"{"a\"b":[1,"\u00e9\n",null,true],"c":{}}"#,
+            json.to_string()
+        );
+        assert_eq!(json.value.to_string(), json.to_string());
+        assert_eq!(pretty(&json.value, None), format!("{:#}", json.value));
+    }
+
+    #[test]
+    fn pretty_expands_everything_without_width() {
+        let input = value(r#"{"a": [1, {}], "b": {"c": []}}"#);
+        let expected = r#"{
+  "a": [
+    1,
+    {}
+  ],
+  "b": {
+    "c": []
+  }
+}"#;
+        assert_eq!(expected, pretty(&input, None));
+    }
+
+    #[test]
+    fn pretty_keeps_what_fits_on_one_line() {
+        let input = value(
+            r#"{"name": "mjd", "tags": ["json", "parser"], "deps": {"regex": "1", "lazy_static": "1.5.0"}}"#,
+        );
+        let expected = r#"{
+  "name": "mjd",
+  "tags": ["json", "parser"],
+  "deps": {
+    "regex": "1",
+    "lazy_static": "1.5.0"
+  }
+}"#;
+        assert_eq!(expected, pretty(&input, Some(40)));
+        assert_eq!(
+            r#"{ "a": [1, 2], "b": {} }"#,
+            pretty(&value(r#"{"a": [1, 2], "b": {}}"#), Some(40))
+        );
+    }
+
+    #[test]
+    fn pretty_options() {
+        let input = value(r#"{"b": [1, {"d": 1, "c": 2}], "a": {}}"#);
+        let options = PrettyOptions {
+            tabs: true,
+            sort_keys: true,
+            trailing_newline: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            "{\n\t\"a\": {},\n\t\"b\": [\n\t\t1,\n\t\t{\n\t\t\t\"c\": 2,\n\t\t\t\"d\": 1\n\t\t}\n\t]\n}\n",
+            input.to_string_pretty(&options)
+        );
+        let options = PrettyOptions {
+            indent: 4,
+            width: Some(40),
+            sort_keys: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            "{\n    \"a\": {},\n    \"b\": [1, { \"c\": 2, \"d\": 1 }]\n}",
+            input.to_string_pretty(&options)
+        );
+        assert_eq!(
+            "1\n",
+            value("1").to_string_pretty(&PrettyOptions {
+                trailing_newline: true,
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn comments_round_trip() {
+        let input = r#"// config
+{
+  // the name
+  "name": "x", // trailing
+  "list": [
+    1, /* one */
+    // two
+    2 // last
+  ],
+  "tags": ["a", "b"]
+} // done"#;
+        let lexer = JsonLexer::with_options(
+            input,
+            LexerOptions {
+                comments: CommentMode::Emit,
+                ..Default::default()
+            },
+        );
+        let parse_options = ParseOptions {
+            attach_comments: true,
+            ..Default::default()
+        };
+        let json = parse_with(lexer, parse_options).unwrap();
+        let options = WriteOptions {
+            pretty: Some(PrettyOptions {
+                width: Some(40),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut out = String::new();
+        write_commented(&json, &options, &mut out).unwrap();
+        assert_eq!(input, out);
+    }
+
+    #[test]
+    fn write_from_iterators() {
+        let mut out = Vec::new();
+        let values = (1..=3).map(|i| Value::Number(i.to_string().into()));
+        write_array_from_iter(&mut out, values, &WriteOptions::default()).unwrap();
+        assert_eq!(b"[1,2,3]", &out[..]);
+
+        let mut out = Vec::new();
+        let options = WriteOptions {
+            pretty: Some(PrettyOptions::default()),
+            ..Default::default()
+        };
+        let nested = value("[1, 2]");
+        let members = [("a\"b", &nested), ("c", &Value::Null)];
+        write_object_from_iter(&mut out, members, &options).unwrap();
+        assert_eq!(
+            "{\n  \"a\\\"b\": [\n    1,\n    2\n  ],\n  \"c\": null\n}",
+            String::from_utf8(out).unwrap()
+        );
+
+        let mut out = Vec::new();
+        let options = WriteOptions {
+            decoded: true,
+            pretty: Some(PrettyOptions {
+                tabs: true,
+                sort_keys: true,
+                trailing_newline: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        write_object_from_iter(&mut out, members, &options).unwrap();
+        assert_eq!(
+            "{\n\t\"a\\\"b\": [\n\t\t1,\n\t\t2\n\t],\n\t\"c\": null\n}\n",
+            String::from_utf8(out).unwrap()
+        );
+        let mut out = Vec::new();
+        write_object_from_iter(
+            &mut out,
+            [("c", 1), ("a", 2)].map(|(k, n)| (k, Value::Number(n.to_string().into()))),
+            &options,
+        )
+        .unwrap();
+        assert_eq!(
+            "{\n\t\"a\": 2,\n\t\"c\": 1\n}\n",
+            String::from_utf8(out).unwrap()
+        );
+
+        let mut out = Vec::new();
+        write_array_from_iter(&mut out, std::iter::empty::<Value>(), &options).unwrap();
+        assert_eq!(b"[]\n", &out[..]);
+
+        let mut out = Vec::new();
+        let rows = [Ok(Value::Null), Err("connection lost"), Ok(Value::Null)];
+        let err = try_write_array_from_iter(&mut out, rows, &WriteOptions::default()).unwrap_err();
+        assert_eq!("connection lost", err.to_string());
+        assert_eq!(b"[null", &out[..]);
+    }
+}
@@ -0,0 +1,257 @@
+//! Writing values to a sequence of bounded files, for exports split into parts that fit the
+//! limits of the systems they are loaded into.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    Value,
+    ser::{WriteOptions, write_value},
+};
+
+/// How values are laid out in each shard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShardFormat {
+    /// A JSON array of the values, written in `.json` files.
+    #[default]
+    Array,
+    /// One value per line, as NDJSON, written in `.ndjson` files.
+    Lines,
+}
+
+impl ShardFormat {
+    /// Extension of the files of the format, without its dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ShardFormat::Array => "json",
+            ShardFormat::Lines => "ndjson",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ShardOptions {
+    pub format: ShardFormat,
+    /// Largest size of a shard in bytes. A value that alone exceeds it is written in a shard of
+    /// its own.
+    pub max_bytes: Option<usize>,
+    /// Largest number of values in a shard.
+    pub max_values: Option<usize>,
+    /// How values are written. With [`WriteOptions::pretty`] set, a value may span several
+    /// lines, which NDJSON doesn't allow.
+    pub write: WriteOptions,
+}
+
+/// A writer of values spread over shards, starting a new one whenever the current one would
+/// exceed a limit of its [`ShardOptions`].
+///
+/// Shards are numbered from 1 and opened on demand, so no empty shard is created. A shard is
+/// complete once the next one is started, or once [`ShardedWriter::finish`] returns.
+pub struct ShardedWriter<W, F> {
+    open: F,
+    options: ShardOptions,
+    current: Option<Shard<W>>,
+    shards: usize,
+}
+
+/// The shard being written.
+struct Shard<W> {
+    writer: W,
+    /// Size in bytes, closing bracket included.
+    bytes: usize,
+    values: usize,
+}
+
+impl<W: Write, F: FnMut(usize) -> io::Result<W>> ShardedWriter<W, F> {
+    /// Creates a writer that calls `open` with the number of each shard to start it.
+    pub fn new(open: F, options: ShardOptions) -> Self {
+        ShardedWriter {
+            open,
+            options,
+            current: None,
+            shards: 0,
+        }
+    }
+
+    pub fn write(&mut self, value: &Value) -> io::Result<()> {
+        let mut text = String::new();
+        write_value(value, &self.options.write, &mut text).map_err(io::Error::other)?;
+        // the value is preceded by `[` or `,` in an array, and followed by a line feed in lines
+        let len = text.len() + 1;
+        if let Some(shard) = &self.current {
+            let options = &self.options;
+            if options.max_bytes.is_some_and(|max| shard.bytes + len > max)
+                || options.max_values.is_some_and(|max| shard.values >= max)
+            {
+                self.close()?;
+            }
+        }
+        let (before, after) = match (self.options.format, &self.current) {
+            (ShardFormat::Array, None) => ("[", ""),
+            (ShardFormat::Array, Some(_)) => (",", ""),
+            (ShardFormat::Lines, _) => ("", "\n"),
+        };
+        let shard = match &mut self.current {
+            Some(shard) => shard,
+            None => {
+                self.shards += 1;
+                let writer = (self.open)(self.shards)?;
+                let bytes = usize::from(self.options.format == ShardFormat::Array);
+                self.current.insert(Shard {
+                    writer,
+                    bytes,
+                    values: 0,
+                })
+            }
+        };
+        for text in [before, &text, after] {
+            shard.writer.write_all(text.as_bytes())?;
+        }
+        shard.bytes += len;
+        shard.values += 1;
+        Ok(())
+    }
+
+    /// Completes the last shard, returning the number of shards written.
+    pub fn finish(mut self) -> io::Result<usize> {
+        self.close()?;
+        Ok(self.shards)
+    }
+
+    /// Completes the current shard, if any.
+    fn close(&mut self) -> io::Result<()> {
+        if let Some(mut shard) = self.current.take() {
+            if self.options.format == ShardFormat::Array {
+                shard.writer.write_all(b"]")?;
+            }
+            shard.writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Path of the shard numbered `number` in `dir`, such as `part-0001.json`.
+pub fn shard_path(dir: &Path, number: usize, format: ShardFormat) -> PathBuf {
+    dir.join(format!("part-{number:04}.{}", format.extension()))
+}
+
+/// Creates a writer of shards in the files of `dir` named by [`shard_path`].
+pub fn to_dir(
+    dir: &Path,
+    options: ShardOptions,
+) -> ShardedWriter<BufWriter<File>, impl FnMut(usize) -> io::Result<BufWriter<File>>> {
+    let (dir, format) = (dir.to_path_buf(), options.format);
+    let open = move |number| File::create(shard_path(&dir, number, format)).map(BufWriter::new);
+    ShardedWriter::new(open, options)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, io, rc::Rc};
+
+    use mjl::JsonLexer;
+
+    use crate::{
+        parse,
+        shard::{ShardFormat, ShardOptions, ShardedWriter, shard_path, to_dir},
+    };
+
+    /// A shard held in memory, in the list of shards shared with the test.
+    struct MemoryShard(Rc<RefCell<Vec<String>>>);
+
+    impl io::Write for MemoryShard {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let mut shards = self.0.borrow_mut();
+            shards
+                .last_mut()
+                .unwrap()
+                .push_str(std::str::from_utf8(buf).unwrap());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn shards(inputs: &[&str], options: ShardOptions) -> Vec<String> {
+        let shards = Rc::new(RefCell::new(Vec::new()));
+        let open = |number| {
+            shards.borrow_mut().push(String::new());
+            assert_eq!(shards.borrow().len(), number);
+            Ok(MemoryShard(shards.clone()))
+        };
+        let mut writer = ShardedWriter::new(open, options);
+        for input in inputs {
+            let value = parse(JsonLexer::new(input)).unwrap().value;
+            writer.write(&value).unwrap();
+        }
+        let count = writer.finish().unwrap();
+        let shards = shards.take();
+        assert_eq!(count, shards.len());
+        shards
+    }
+
+    #[test]
+    fn rotate_shards() {
+        let values = ["1", "[2, 3]", r#"{"a": "four"}"#, "5"];
+        assert_eq!(
+            vec![r#"[1,[2,3],{"a":"four"},5]"#],
+            shards(&values, ShardOptions::default())
+        );
+        let by_count = ShardOptions {
+            max_values: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(
+            vec![r#"[1,[2,3],{"a":"four"}]"#, "[5]"],
+            shards(&values, by_count)
+        );
+        // `[1,[2,3]]` is 9 bytes, and `{"a":"four"}` is too large for any shard
+        let by_size = ShardOptions {
+            max_bytes: Some(10),
+            ..Default::default()
+        };
+        assert_eq!(
+            vec!["[1,[2,3]]", r#"[{"a":"four"}]"#, "[5]"],
+            shards(&values, by_size)
+        );
+        let lines = ShardOptions {
+            format: ShardFormat::Lines,
+            max_bytes: Some(10),
+            max_values: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            vec!["1\n[2,3]\n", "{\"a\":\"four\"}\n", "5\n"],
+            shards(&values, lines)
+        );
+        assert!(shards(&[], ShardOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn write_to_dir() {
+        let dir = std::env::temp_dir().join(format!("mjp-shards-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let options = ShardOptions {
+            format: ShardFormat::Lines,
+            max_values: Some(1),
+            ..Default::default()
+        };
+        let mut writer = to_dir(&dir, options);
+        for input in ["1", "2"] {
+            writer
+                .write(&parse(JsonLexer::new(input)).unwrap().value)
+                .unwrap();
+        }
+        assert_eq!(2, writer.finish().unwrap());
+
+        let path = shard_path(&dir, 2, ShardFormat::Lines);
+        assert!(path.ends_with("part-0002.ndjson"));
+        assert_eq!("2\n", std::fs::read_to_string(path).unwrap());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
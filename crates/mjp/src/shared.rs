@@ -0,0 +1,163 @@
+//! Documents with shared, copy-on-write subtrees, for applications that keep many snapshots of a
+//! slowly changing document.
+
+use std::{borrow::Cow, sync::Arc};
+
+use crate::{
+    BooleanVal, Pair, RawValue, Value,
+    lookup::{array_index, pointer_tokens},
+};
+
+/// A value whose containers and strings are reference counted: cloning it is O(1), and mutating
+/// it through [`SharedValue::pointer_mut`] copies only the containers on the way to the mutated
+/// value, which other clones keep sharing the rest with.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SharedValue {
+    Object(Arc<Vec<(Arc<str>, SharedValue)>>),
+    Array(Arc<Vec<SharedValue>>),
+    Str(Arc<str>),
+    Number(Arc<str>),
+    Boolean(BooleanVal),
+    Null,
+    /// The text of a [`Value::Raw`], kept as it was written.
+    Raw(Arc<str>),
+}
+
+impl SharedValue {
+    /// Returns the value at `pointer`, if any.
+    pub fn pointer(&self, pointer: &str) -> Option<&SharedValue> {
+        let mut value = self;
+        for token in pointer_tokens(pointer)? {
+            value = match value {
+                SharedValue::Object(pairs) => &pairs.iter().find(|(k, _)| **k == *token)?.1,
+                SharedValue::Array(values) => values.get(array_index(&token)?)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+
+    /// Returns the value at `pointer` for mutation, if any, first copying the containers on the
+    /// way to it that are shared with other values.
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut SharedValue> {
+        let mut value = self;
+        for token in pointer_tokens(pointer)? {
+            value = match value {
+                SharedValue::Object(pairs) => {
+                    let i = pairs.iter().position(|(k, _)| **k == *token)?;
+                    &mut Arc::make_mut(pairs)[i].1
+                }
+                SharedValue::Array(values) => {
+                    let i = array_index(&token).filter(|i| *i < values.len())?;
+                    &mut Arc::make_mut(values)[i]
+                }
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+
+    /// Converts this value into a [`Value`] owning all of its strings.
+    pub fn to_value(&self) -> Value<'static> {
+        match self {
+            SharedValue::Object(pairs) => Value::Object(
+                pairs
+                    .iter()
+                    .map(|(key, value)| Pair {
+                        key: key.to_string().into(),
+                        value: value.to_value(),
+                    })
+                    .collect(),
+            ),
+            SharedValue::Array(values) => {
+                Value::Array(values.iter().map(SharedValue::to_value).collect())
+            }
+            SharedValue::Str(s) => Value::Str(s.to_string().into()),
+            SharedValue::Number(n) => Value::Number(n.to_string().into()),
+            SharedValue::Boolean(b) => Value::Boolean(*b),
+            SharedValue::Null => Value::Null,
+            SharedValue::Raw(raw) => Value::Raw(RawValue(Cow::Owned(raw.to_string()))),
+        }
+    }
+}
+
+impl From<&Value<'_>> for SharedValue {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Object(pairs) => SharedValue::Object(Arc::new(
+                pairs
+                    .iter()
+                    .map(|p| (Arc::from(p.key.as_ref()), SharedValue::from(&p.value)))
+                    .collect(),
+            )),
+            Value::Array(values) => {
+                SharedValue::Array(Arc::new(values.iter().map(SharedValue::from).collect()))
+            }
+            Value::Str(s) => SharedValue::Str(Arc::from(s.as_ref())),
+            Value::Number(n) => SharedValue::Number(Arc::from(n.as_str())),
+            Value::Boolean(b) => SharedValue::Boolean(*b),
+            Value::Null => SharedValue::Null,
+            Value::Raw(raw) => SharedValue::Raw(Arc::from(raw.get())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use mjl::{Dialect, JsonLexer, LexerOptions};
+
+    use crate::{ParseOptions, parse, parse_with, shared::SharedValue};
+
+    #[test]
+    fn copy_on_write() {
+        let input = r#"{"a": {"b": 1, "c": [1, 2]}, "d": {"e": true}}"#;
+        let value = parse(JsonLexer::new(input)).unwrap().value;
+        let snapshot = SharedValue::from(&value);
+
+        let mut current = snapshot.clone();
+        *current.pointer_mut("/a/c/1").unwrap() = SharedValue::Null;
+        assert!(current.pointer_mut("/a/c/2").is_none());
+
+        assert_eq!(value, snapshot.to_value());
+        assert_eq!(Some(&SharedValue::Null), current.pointer("/a/c/1"));
+        assert_eq!(snapshot.pointer("/a/b"), current.pointer("/a/b"));
+        // the untouched member is still shared
+        match (snapshot.pointer("/d"), current.pointer("/d")) {
+            (Some(SharedValue::Object(a)), Some(SharedValue::Object(b))) => {
+                assert!(Arc::ptr_eq(a, b))
+            }
+            values => panic!("unexpected values {values:?}"),
+        }
+        match (snapshot.pointer("/a"), current.pointer("/a")) {
+            (Some(SharedValue::Object(a)), Some(SharedValue::Object(b))) => {
+                assert!(!Arc::ptr_eq(a, b))
+            }
+            values => panic!("unexpected values {values:?}"),
+        }
+    }
+
+    #[test]
+    fn keep_raw_values() {
+        let input = "{a: [1, 'two', 0x3, 4, 5]}";
+        let lexer = JsonLexer::with_options(
+            input,
+            LexerOptions {
+                dialect: Dialect::Json5,
+                ..Default::default()
+            },
+        );
+        let options = ParseOptions {
+            spill_above: Some(10),
+            ..Default::default()
+        };
+        let value = parse_with(lexer, options).unwrap().value;
+        let shared = SharedValue::from(&value);
+        assert_eq!(
+            Some(&SharedValue::Raw(Arc::from("[1, 'two', 0x3, 4, 5]"))),
+            shared.pointer("/a")
+        );
+        assert_eq!(value, shared.to_value());
+    }
+}
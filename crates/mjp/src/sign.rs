@@ -0,0 +1,147 @@
+//! Signatures of documents for integrity checks, behind the `sign` feature.
+//!
+//! Signatures are JSON Web Signatures (RFC 7515) with a detached payload, `<header>..<mac>`:
+//! HMAC-SHA256 over the [canonical text](Value::to_canonical_string) of a value, which is
+//! stored as is with its signature next to it. Reformatting a signed document or reordering its
+//! members keeps its signature valid, while changing any value invalidates it.
+
+use std::{error::Error, fmt::Display};
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use hmac::{Hmac, Mac};
+use mjl::JsonLexer;
+use sha2::Sha256;
+
+use crate::{Value, canonical::CanonicalError, parse};
+
+/// The protected header of signatures.
+const HEADER: &str = r#"{"alg":"HS256"}"#;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureError {
+    /// The value has no canonical text.
+    Canonical(CanonicalError),
+    /// The signature is not a JWS with a detached payload.
+    Malformed,
+    /// The signature uses an algorithm other than `HS256`.
+    UnsupportedAlgorithm,
+    /// The signature was not made of the value with the key.
+    Mismatch,
+}
+
+impl Display for SignatureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignatureError::Canonical(e) => e.fmt(f),
+            SignatureError::Malformed => write!(f, "malformed signature"),
+            SignatureError::UnsupportedAlgorithm => write!(f, "unsupported signature algorithm"),
+            SignatureError::Mismatch => write!(f, "signature mismatch"),
+        }
+    }
+}
+
+impl Error for SignatureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SignatureError::Canonical(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<CanonicalError> for SignatureError {
+    fn from(e: CanonicalError) -> Self {
+        SignatureError::Canonical(e)
+    }
+}
+
+/// Signs `value` with `key`, returning its detached signature.
+pub fn sign(value: &Value, key: &[u8]) -> Result<String, CanonicalError> {
+    let header = URL_SAFE_NO_PAD.encode(HEADER);
+    let mac = mac(&header, value, key)?.finalize().into_bytes();
+    Ok(format!("{header}..{}", URL_SAFE_NO_PAD.encode(mac)))
+}
+
+/// Checks that `signature` was made of `value` with `key` by [`sign`]. MACs are compared in
+/// constant time.
+pub fn verify(value: &Value, key: &[u8], signature: &str) -> Result<(), SignatureError> {
+    let (header, signature) = signature
+        .split_once("..")
+        .ok_or(SignatureError::Malformed)?;
+    let decoded = URL_SAFE_NO_PAD
+        .decode(header)
+        .map_err(|_| SignatureError::Malformed)?;
+    let decoded = String::from_utf8(decoded).map_err(|_| SignatureError::Malformed)?;
+    let header_value = parse(JsonLexer::new(&decoded))
+        .map_err(|_| SignatureError::Malformed)?
+        .value;
+    if header_value.get("alg") != Some(&Value::Str("HS256".into())) {
+        return Err(SignatureError::UnsupportedAlgorithm);
+    }
+    let signature = URL_SAFE_NO_PAD
+        .decode(signature)
+        .map_err(|_| SignatureError::Malformed)?;
+    mac(header, value, key)?
+        .verify_slice(&signature)
+        .map_err(|_| SignatureError::Mismatch)
+}
+
+/// The MAC of the signing input of `value`, with the encoded `header`.
+fn mac(header: &str, value: &Value, key: &[u8]) -> Result<Hmac<Sha256>, CanonicalError> {
+    let payload = URL_SAFE_NO_PAD.encode(value.to_canonical_string()?);
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(header.as_bytes());
+    mac.update(b".");
+    mac.update(payload.as_bytes());
+    Ok(mac)
+}
+
+#[cfg(test)]
+mod test {
+    use mjl::JsonLexer;
+
+    use crate::{
+        Value, parse,
+        sign::{SignatureError, sign, verify},
+    };
+
+    fn value(input: &str) -> Value<'_> {
+        parse(JsonLexer::new(input)).unwrap().value
+    }
+
+    #[test]
+    fn sign_and_verify() {
+        let config = value(r#"{"b": [1, 2], "a": "x"}"#);
+        let signature = sign(&config, b"secret").unwrap();
+        assert_eq!(
+            "eyJhbGciOiJIUzI1NiJ9..Sy1WRUrbWeLyUdGVXv9visWgr1Nv7-ohVX6bRxLdhZ8",
+            signature
+        );
+        assert_eq!(Ok(()), verify(&config, b"secret", &signature));
+        // formatting doesn't matter, values do
+        let reformatted = value("{\n  \"a\": \"\\u0078\",\n  \"b\": [1.0, 2e0]\n}");
+        assert_eq!(Ok(()), verify(&reformatted, b"secret", &signature));
+        assert_eq!(
+            Err(SignatureError::Mismatch),
+            verify(&value(r#"{"a": "x", "b": [1]}"#), b"secret", &signature)
+        );
+        assert_eq!(
+            Err(SignatureError::Mismatch),
+            verify(&config, b"other", &signature)
+        );
+
+        // {"alg":"none"}
+        assert_eq!(
+            Err(SignatureError::UnsupportedAlgorithm),
+            verify(&config, b"secret", "eyJhbGciOiJub25lIn0..")
+        );
+        assert_eq!(
+            Err(SignatureError::Malformed),
+            verify(&config, b"secret", "eyJhbGciOiJIUzI1NiJ9.e30.abc")
+        );
+        assert!(matches!(
+            verify(&value("[1e400]"), b"secret", &signature),
+            Err(SignatureError::Canonical(_))
+        ));
+    }
+}
@@ -0,0 +1,267 @@
+//! Parsing into a tree whose every node records where it was found in the input, so that
+//! validators can point at the offending value, as in "value at line 52, column 8 must be a
+//! number".
+
+use std::{borrow::Cow, ops::Range};
+
+use mjl::JsonLexer;
+
+use crate::{JsonParseError, JsonParser, Pair, ParseOptions, Value};
+
+/// Where a node was found in the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    /// Byte offset of the start of the node.
+    pub start: usize,
+    /// Byte offset past the end of the node.
+    pub end: usize,
+    /// 1-based line of the start of the node.
+    pub line: usize,
+    /// 1-based column of the start of the node, counted in chars.
+    pub column: usize,
+}
+
+/// A value with the span of every node in it, from [`parse_spanned`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedValue<'a> {
+    pub span: Span,
+    pub node: SpannedNode<'a>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpannedNode<'a> {
+    Object(Vec<SpannedPair<'a>>),
+    Array(Vec<SpannedValue<'a>>),
+    /// Any other value: a string, number, boolean or null, or a value kept raw, whose nodes
+    /// have no spans of their own.
+    Scalar(Value<'a>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedPair<'a> {
+    pub key: Cow<'a, str>,
+    /// Span of the key, quotes included.
+    pub key_span: Span,
+    pub value: SpannedValue<'a>,
+}
+
+impl<'a> SpannedValue<'a> {
+    /// Returns the member `key`, if this is an object that has one.
+    pub fn get(&self, key: &str) -> Option<&SpannedPair<'a>> {
+        match &self.node {
+            SpannedNode::Object(pairs) => pairs.iter().find(|p| p.key == key),
+            _ => None,
+        }
+    }
+
+    /// Returns the element at `index`, if this is an array that has one.
+    pub fn get_index(&self, index: usize) -> Option<&SpannedValue<'a>> {
+        match &self.node {
+            SpannedNode::Array(values) => values.get(index),
+            _ => None,
+        }
+    }
+
+    /// Drops the spans.
+    pub fn into_value(self) -> Value<'a> {
+        match self.node {
+            SpannedNode::Object(pairs) => Value::Object(
+                pairs
+                    .into_iter()
+                    .map(|p| Pair {
+                        key: p.key,
+                        value: p.value.into_value(),
+                    })
+                    .collect(),
+            ),
+            SpannedNode::Array(values) => {
+                Value::Array(values.into_iter().map(SpannedValue::into_value).collect())
+            }
+            SpannedNode::Scalar(value) => value,
+        }
+    }
+}
+
+/// Parses a document, recording the span of every value and key.
+pub fn parse_spanned<'a>(
+    lexer: JsonLexer<'a>,
+    options: ParseOptions,
+) -> Result<SpannedValue<'a>, JsonParseError> {
+    let mut parser = JsonParser::with_options(lexer, options);
+    parser.spans = Some(SpanRecorder::default());
+    let json = parser.parse_json()?;
+    let spans = parser.spans.take().unwrap();
+    let (mut values, mut keys) = (spans.values.into_iter(), spans.keys.into_iter());
+    Ok(spanned(json.value, &mut values, &mut keys))
+}
+
+/// Pairs `value` and its descendants with their spans, which are in the order nodes start in.
+fn spanned<'a>(
+    value: Value<'a>,
+    values: &mut impl Iterator<Item = Span>,
+    keys: &mut impl Iterator<Item = Span>,
+) -> SpannedValue<'a> {
+    let span = values.next().unwrap();
+    let node = match value {
+        Value::Object(pairs) => SpannedNode::Object(
+            pairs
+                .into_iter()
+                .map(|p| SpannedPair {
+                    key: p.key,
+                    key_span: keys.next().unwrap(),
+                    value: spanned(p.value, values, keys),
+                })
+                .collect(),
+        ),
+        Value::Array(elements) => SpannedNode::Array(
+            elements
+                .into_iter()
+                .map(|e| spanned(e, values, keys))
+                .collect(),
+        ),
+        value => SpannedNode::Scalar(value),
+    };
+    SpannedValue { span, node }
+}
+
+/// The spans recorded while parsing, in the order the nodes start in.
+pub(crate) struct SpanRecorder {
+    values: Vec<Span>,
+    keys: Vec<Span>,
+    /// Byte offset, line and column up to which the input was scanned for line breaks.
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Default for SpanRecorder {
+    fn default() -> Self {
+        SpanRecorder {
+            values: Vec::new(),
+            keys: Vec::new(),
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+}
+
+impl SpanRecorder {
+    /// The span of `range`, which must not start before the last one did.
+    fn span(&mut self, input: &str, range: Range<usize>) -> Span {
+        for c in input[self.offset..range.start].chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+        self.offset = range.start;
+        Span {
+            start: range.start,
+            end: range.end,
+            line: self.line,
+            column: self.column,
+        }
+    }
+}
+
+/// The state of the recording when a value started: the index of its span and the number of
+/// key spans recorded before it.
+pub(crate) type Opened = Option<(usize, usize)>;
+
+impl JsonParser<'_> {
+    /// Starts recording the span of the value starting at `start`.
+    pub(crate) fn open_span(&mut self, start: usize) -> Opened {
+        let spans = self.spans.as_mut()?;
+        let opened = (spans.values.len(), spans.keys.len());
+        let span = spans.span(self.lexer.input, start..start);
+        spans.values.push(span);
+        Some(opened)
+    }
+
+    /// Ends the span of a value at the last consumed token. The spans of the nodes in a value
+    /// kept raw are dropped.
+    pub(crate) fn close_span(&mut self, opened: Opened, raw: bool) {
+        let (Some(spans), Some((value, keys))) = (self.spans.as_mut(), opened) else {
+            return;
+        };
+        spans.values[value].end = self.previous.end;
+        if raw {
+            spans.values.truncate(value + 1);
+            spans.keys.truncate(keys);
+        }
+    }
+
+    /// Records the span of the key just consumed.
+    pub(crate) fn record_key_span(&mut self) {
+        if let Some(spans) = self.spans.as_mut() {
+            let span = spans.span(self.lexer.input, self.previous.clone());
+            spans.keys.push(span);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mjl::JsonLexer;
+
+    use crate::{
+        ParseOptions, parse,
+        spanned::{Span, SpannedNode, parse_spanned},
+    };
+
+    fn span(start: usize, end: usize, line: usize, column: usize) -> Span {
+        Span {
+            start,
+            end,
+            line,
+            column,
+        }
+    }
+
+    #[test]
+    fn record_spans() {
+        let input = "{\n  \"name\": \"mjd\",\n  \"ports\": [80, {\"é\": true}]\n}";
+        let root = parse_spanned(JsonLexer::new(input), ParseOptions::default()).unwrap();
+        assert_eq!(span(0, input.len(), 1, 1), root.span);
+
+        let name = root.get("name").unwrap();
+        assert_eq!(span(4, 10, 2, 3), name.key_span);
+        assert_eq!(span(12, 17, 2, 11), name.value.span);
+
+        let ports = &root.get("ports").unwrap().value;
+        assert_eq!(span(30, 48, 3, 12), ports.span);
+        assert_eq!(span(31, 33, 3, 13), ports.get_index(0).unwrap().span);
+        let object = ports.get_index(1).unwrap();
+        let key = object.get("é").unwrap();
+        assert_eq!(span(36, 40, 3, 18), key.key_span);
+        // columns count chars, not bytes
+        assert_eq!(span(42, 46, 3, 23), key.value.span);
+
+        assert_eq!(
+            parse(JsonLexer::new(input)).unwrap().value,
+            root.into_value()
+        );
+    }
+
+    #[test]
+    fn raw_values_have_one_span() {
+        let options = ParseOptions {
+            spill_above: Some(12),
+            ..Default::default()
+        };
+        let input = r#"[{"a": [1, 2, 3, 4]}, {"b": 1}]"#;
+        let root = parse_spanned(JsonLexer::new(input), options).unwrap();
+        let raw = root.get_index(0).unwrap();
+        assert!(matches!(
+            raw.node,
+            SpannedNode::Scalar(crate::Value::Raw(_))
+        ));
+        assert_eq!(span(1, 20, 1, 2), raw.span);
+        let object = root.get_index(1).unwrap();
+        assert_eq!(span(22, 30, 1, 23), object.span);
+        assert_eq!(span(23, 26, 1, 24), object.get("b").unwrap().key_span);
+    }
+}
@@ -0,0 +1,124 @@
+//! Splitting a large root array into several smaller arrays, e.g. to prepare input for parallel
+//! jobs.
+
+use std::io::{self, BufReader, Read, Write};
+
+use crate::{ParseOptions, stream::RawArrayReader};
+
+/// How elements are distributed among the outputs of [`split_array`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Balance {
+    /// Deal elements to the outputs in turn, so they receive the same number of elements (give
+    /// or take one).
+    Count,
+    /// Hand each element to the output that has received the fewest bytes so far.
+    Bytes,
+}
+
+/// What was written to one of the outputs of [`split_array`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Shard {
+    pub elements: usize,
+    /// Bytes of element text, not counting the delimiters of the output array.
+    pub bytes: usize,
+}
+
+/// Streams the root array of `reader` into `outputs`, each of which receives a valid JSON array
+/// holding a subset of the elements in their original relative order. Elements are validated and
+/// then copied byte for byte.
+///
+/// Parse errors are reported as [`io::ErrorKind::InvalidData`] errors wrapping the
+/// [`JsonParseError`](crate::JsonParseError).
+pub fn split_array<R: Read, W: Write>(
+    reader: R,
+    outputs: &mut [W],
+    balance: Balance,
+) -> io::Result<Vec<Shard>> {
+    if outputs.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "at least one output is required",
+        ));
+    }
+
+    let mut shards = vec![Shard::default(); outputs.len()];
+    for output in outputs.iter_mut() {
+        output.write_all(b"[")?;
+    }
+
+    let mut elements = RawArrayReader::new(BufReader::new(reader));
+    while let Some(element) = elements.next_element() {
+        let invalid = |e| io::Error::new(io::ErrorKind::InvalidData, e);
+        let element = element.map_err(invalid)?;
+        element.parse(&ParseOptions::default()).map_err(invalid)?;
+
+        let target = match balance {
+            Balance::Count => element.index % outputs.len(),
+            Balance::Bytes => (0..shards.len()).min_by_key(|&i| shards[i].bytes).unwrap(),
+        };
+        let shard = &mut shards[target];
+        if shard.elements > 0 {
+            outputs[target].write_all(b",")?;
+        }
+        outputs[target].write_all(element.text.as_bytes())?;
+        shard.elements += 1;
+        shard.bytes += element.text.len();
+    }
+
+    for output in outputs.iter_mut() {
+        output.write_all(b"]")?;
+        output.flush()?;
+    }
+    Ok(shards)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::split::{Balance, Shard, split_array};
+
+    #[test]
+    fn split_by_count() {
+        let mut outputs = vec![Vec::new(); 2];
+        let shards = split_array(
+            r#"[1, {"a": [2, 3]}, "x, y", 4, 5]"#.as_bytes(),
+            &mut outputs,
+            Balance::Count,
+        )
+        .unwrap();
+        assert_eq!(b"[1,\"x, y\",5]".as_slice(), outputs[0]);
+        assert_eq!(b"[{\"a\": [2, 3]},4]".as_slice(), outputs[1]);
+        assert_eq!(
+            vec![
+                Shard {
+                    elements: 3,
+                    bytes: 8
+                },
+                Shard {
+                    elements: 2,
+                    bytes: 14
+                }
+            ],
+            shards
+        );
+    }
+
+    #[test]
+    fn split_by_bytes() {
+        let mut outputs = vec![Vec::new(); 2];
+        split_array(
+            r#"["aaaaaaaaaa", 1, 2, 3]"#.as_bytes(),
+            &mut outputs,
+            Balance::Bytes,
+        )
+        .unwrap();
+        assert_eq!(b"[\"aaaaaaaaaa\"]".as_slice(), outputs[0]);
+        assert_eq!(b"[1,2,3]".as_slice(), outputs[1]);
+    }
+
+    #[test]
+    fn invalid_element_is_an_error() {
+        let mut outputs = vec![Vec::new(); 2];
+        let err = split_array("[1, tru]".as_bytes(), &mut outputs, Balance::Count).unwrap_err();
+        assert_eq!(std::io::ErrorKind::InvalidData, err.kind());
+    }
+}
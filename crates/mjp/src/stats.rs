@@ -0,0 +1,202 @@
+//! Statistics of the shape of documents, gathered from their tree or from a stream of their
+//! events, to profile files too large to parse into a tree.
+
+use std::{borrow::Cow, io::Read};
+
+use mjl::JsonLexer;
+
+use crate::{
+    Json, JsonParseError, Value,
+    events::{Event, EventReader, read_events},
+};
+
+/// Counts of the values of a document, returned by [`Json::stats`] and [`stats_stream`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DocumentStats {
+    pub objects: usize,
+    pub arrays: usize,
+    pub strings: usize,
+    pub numbers: usize,
+    pub booleans: usize,
+    pub nulls: usize,
+    /// Members of all objects.
+    pub members: usize,
+    /// Nesting depth of the deepest value, 0 when the root value is a scalar, as in
+    /// [`Metadata::max_depth`](crate::Metadata::max_depth).
+    pub max_depth: usize,
+    /// Most members of an object.
+    pub max_members: usize,
+    /// Most elements of an array.
+    pub max_elements: usize,
+    /// Length in bytes of the longest string, between its quotes and escapes included.
+    pub max_string_len: usize,
+    /// Length in bytes of the longest key, between its quotes and escapes included.
+    pub max_key_len: usize,
+}
+
+impl DocumentStats {
+    /// Number of values, nested ones included.
+    pub fn values(&self) -> usize {
+        self.objects + self.arrays + self.strings + self.numbers + self.booleans + self.nulls
+    }
+}
+
+impl Json<'_> {
+    /// Statistics of the value of this document. Values kept [raw](Value::Raw) are counted
+    /// like the others.
+    pub fn stats(&self) -> DocumentStats {
+        let mut collector = Collector::default();
+        collector.value(&self.value);
+        collector.stats
+    }
+}
+
+/// Statistics of the document read from `reader`, the same as [`Json::stats`] would give, in
+/// memory proportional to the nesting depth of the document and its longest token rather than
+/// to its size.
+pub fn stats_stream<R: Read>(reader: R) -> Result<DocumentStats, JsonParseError> {
+    let mut collector = Collector::default();
+    for event in read_events(reader) {
+        collector.event(&event?);
+    }
+    Ok(collector.stats)
+}
+
+#[derive(Default)]
+struct Collector {
+    stats: DocumentStats,
+    /// Whether each open container is an object, with its number of children so far.
+    open: Vec<(bool, usize)>,
+}
+
+impl Collector {
+    fn event(&mut self, event: &Event) {
+        let stats = &mut self.stats;
+        match event {
+            Event::Key(key) => {
+                stats.members += 1;
+                stats.max_key_len = stats.max_key_len.max(key.len());
+                if let Some((_, children)) = self.open.last_mut() {
+                    *children += 1;
+                }
+                return;
+            }
+            Event::EndObject => {
+                let (_, members) = self.open.pop().unwrap_or_default();
+                stats.max_members = stats.max_members.max(members);
+                return;
+            }
+            Event::EndArray => {
+                let (_, elements) = self.open.pop().unwrap_or_default();
+                stats.max_elements = stats.max_elements.max(elements);
+                return;
+            }
+            _ => {}
+        }
+
+        stats.max_depth = stats.max_depth.max(self.open.len());
+        if let Some((false, elements)) = self.open.last_mut() {
+            *elements += 1;
+        }
+        match event {
+            Event::StartObject => {
+                stats.objects += 1;
+                self.open.push((true, 0));
+            }
+            Event::StartArray => {
+                stats.arrays += 1;
+                self.open.push((false, 0));
+            }
+            Event::Str(s) => {
+                stats.strings += 1;
+                stats.max_string_len = stats.max_string_len.max(s.len());
+            }
+            Event::Number(_) => stats.numbers += 1,
+            Event::Boolean(_) => stats.booleans += 1,
+            Event::Null => stats.nulls += 1,
+            Event::Key(_) | Event::EndObject | Event::EndArray => unreachable!(),
+        }
+    }
+
+    /// Collects the events `value` would be read as.
+    fn value(&mut self, value: &Value) {
+        match value {
+            Value::Object(pairs) => {
+                self.event(&Event::StartObject);
+                for pair in pairs {
+                    self.event(&Event::Key(Cow::Borrowed(&pair.key)));
+                    self.value(&pair.value);
+                }
+                self.event(&Event::EndObject);
+            }
+            Value::Array(values) => {
+                self.event(&Event::StartArray);
+                for value in values {
+                    self.value(value);
+                }
+                self.event(&Event::EndArray);
+            }
+            Value::Str(s) => self.event(&Event::Str(Cow::Borrowed(s))),
+            Value::Number(n) => self.event(&Event::Number(Cow::Borrowed(n.as_str()))),
+            Value::Boolean(b) => self.event(&Event::Boolean(*b)),
+            Value::Null => self.event(&Event::Null),
+            Value::Raw(raw) => {
+                for event in EventReader::new(JsonLexer::new(raw.get())).map_while(Result::ok) {
+                    self.event(&event);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mjl::JsonLexer;
+
+    use crate::{
+        ErrorKind, ParseOptions, parse, parse_with,
+        stats::{DocumentStats, stats_stream},
+    };
+
+    #[test]
+    fn document_stats() {
+        let input =
+            r#"{"a": [1, {"b": null}, "x\"y"], "long key": [true, false, [], {}], "c": 2.5}"#;
+        let json = parse(JsonLexer::new(input)).unwrap();
+        let stats = json.stats();
+        assert_eq!(
+            DocumentStats {
+                objects: 3,
+                arrays: 3,
+                strings: 1,
+                numbers: 2,
+                booleans: 2,
+                nulls: 1,
+                members: 4,
+                max_depth: 3,
+                max_members: 3,
+                max_elements: 4,
+                max_string_len: 4,
+                max_key_len: 8,
+            },
+            stats
+        );
+        assert_eq!(json.metadata.values, stats.values());
+        assert_eq!(json.metadata.max_depth, stats.max_depth);
+        assert_eq!(stats, stats_stream(input.as_bytes()).unwrap());
+
+        // raw values are counted all the same
+        let options = ParseOptions {
+            spill_above: Some(8),
+            ..Default::default()
+        };
+        assert_eq!(
+            stats,
+            parse_with(JsonLexer::new(input), options).unwrap().stats()
+        );
+
+        assert_eq!(0, stats_stream(&b"1"[..]).unwrap().max_depth);
+        let err = stats_stream(&b"[1, 2"[..]).unwrap_err();
+        assert_eq!(ErrorKind::UnexpectedEof, err.kind());
+    }
+}
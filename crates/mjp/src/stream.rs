@@ -0,0 +1,342 @@
+//! Incremental processing of documents whose root is an array.
+//!
+//! The elements of the root array are delimited with a byte-level scan of the input, so only
+//! one element is held in memory at a time, no matter how large the array is.
+
+use std::io::{BufRead, BufReader, Read};
+
+use mjl::JsonLexer;
+
+use crate::{ErrorKind, JsonParseError, ParseOptions, Progress, Value, parse_with, utf8};
+
+/// Parses a document whose root is an array from `reader`, yielding each element as its own
+/// value. Iteration stops after the first error.
+pub fn stream_array<R: Read>(
+    reader: R,
+) -> impl Iterator<Item = Result<Value<'static>, JsonParseError>> {
+    stream_array_with(reader, ParseOptions::default())
+}
+
+/// Like [`stream_array`], parsing every element with the given options. Progress is reported
+/// after each element, with the length of the input unknown.
+pub fn stream_array_with<R: Read>(
+    reader: R,
+    mut options: ParseOptions,
+) -> impl Iterator<Item = Result<Value<'static>, JsonParseError>> {
+    let mut elements = RawArrayReader::new(BufReader::new(reader));
+    elements.invalid_utf8 = options.invalid_utf8;
+    // reported for the whole input rather than for each element
+    let progress = options.progress.take();
+    std::iter::from_fn(move || {
+        let value = elements.next_value(&options);
+        if let Some(progress) = &progress {
+            progress.report(Progress {
+                consumed: elements.offset,
+                total: None,
+            });
+        }
+        value
+    })
+    .fuse()
+}
+
+/// Splits the root array of a document into the raw text of its elements.
+pub struct RawArrayReader<R> {
+    /// How invalid UTF-8 in elements is handled, [`Utf8Policy::Error`](crate::Utf8Policy) by
+    /// default.
+    pub invalid_utf8: crate::Utf8Policy,
+    reader: R,
+    state: State,
+    offset: usize,
+    line: usize,
+    column: usize,
+    index: usize,
+}
+
+#[derive(PartialEq)]
+enum State {
+    Start,
+    Elements,
+    Done,
+}
+
+/// The raw text of an element of the root array, and where it was found in the input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawElement {
+    pub text: String,
+    pub index: usize,
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl RawElement {
+    /// Parses the element, reporting errors relative to the whole document.
+    pub fn parse(&self, options: &ParseOptions) -> Result<Value<'static>, JsonParseError> {
+        parse_with(JsonLexer::new(&self.text), options.clone())
+            .map(|json| json.value.into_owned())
+            .map_err(|e| {
+                e.relocate(
+                    self.offset,
+                    (self.line, self.column),
+                    &format!("/{}", self.index),
+                )
+            })
+    }
+}
+
+impl<R: BufRead> RawArrayReader<R> {
+    pub fn new(reader: R) -> Self {
+        RawArrayReader {
+            invalid_utf8: crate::Utf8Policy::default(),
+            reader,
+            state: State::Start,
+            offset: 0,
+            line: 1,
+            column: 1,
+            index: 0,
+        }
+    }
+
+    /// Returns the next element of the root array, or `None` once the array and the input are
+    /// exhausted.
+    pub fn next_element(&mut self) -> Option<Result<RawElement, JsonParseError>> {
+        if self.state == State::Done {
+            return None;
+        }
+        let result = self.scan_element();
+        if !matches!(result, Ok(Some(_))) {
+            self.state = State::Done;
+        }
+        result.transpose()
+    }
+
+    fn next_value(
+        &mut self,
+        options: &ParseOptions,
+    ) -> Option<Result<Value<'static>, JsonParseError>> {
+        let result = self.next_element()?.and_then(|e| e.parse(options));
+        if result.is_err() {
+            self.state = State::Done;
+        }
+        Some(result)
+    }
+
+    fn scan_element(&mut self) -> Result<Option<RawElement>, JsonParseError> {
+        self.skip_whitespace()?;
+        if self.state == State::Start {
+            match self.peek()? {
+                Some(b'[') => self.bump(None)?,
+                Some(_) => return Err(self.error(ErrorKind::Syntax, "expected root array")),
+                None => return Err(self.error(ErrorKind::UnexpectedEof, "expected root array")),
+            }
+            self.skip_whitespace()?;
+            if self.peek()? == Some(b']') {
+                return self.finish();
+            }
+            self.state = State::Elements;
+        } else {
+            match self.peek()? {
+                Some(b']') => return self.finish(),
+                Some(b',') => self.bump(None)?,
+                Some(_) => return Err(self.error(ErrorKind::Syntax, "expected `,` or `]`")),
+                None => return Err(self.error(ErrorKind::UnexpectedEof, "unclosed array")),
+            }
+            self.skip_whitespace()?;
+        }
+
+        let mut element = RawElement {
+            text: String::new(),
+            index: self.index,
+            offset: self.offset,
+            line: self.line,
+            column: self.column,
+        };
+        let mut bytes = Vec::new();
+        self.scan_value(&mut bytes)?;
+        element.text = utf8::decode(&bytes, self.invalid_utf8)
+            .map(|text| text.into_owned())
+            .map_err(|e| {
+                let offset = element.offset + e.valid_up_to();
+                JsonParseError::new(
+                    ErrorKind::InvalidUtf8,
+                    "invalid UTF-8 in input",
+                    offset,
+                    (element.line, element.column),
+                    format!("/{}", element.index),
+                )
+            })?;
+        self.index += 1;
+        Ok(Some(element))
+    }
+
+    fn scan_value(&mut self, out: &mut Vec<u8>) -> Result<(), JsonParseError> {
+        let mut depth = 0usize;
+        loop {
+            let Some(b) = self.peek()? else {
+                return if depth == 0 && !out.is_empty() {
+                    Ok(())
+                } else {
+                    Err(self.error(ErrorKind::UnexpectedEof, "unclosed array"))
+                };
+            };
+            match b {
+                b'"' => {
+                    self.bump(Some(out))?;
+                    self.scan_string(out)?;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                b'[' | b'{' => {
+                    depth += 1;
+                    self.bump(Some(out))?;
+                }
+                b']' | b'}' if depth > 0 => {
+                    depth -= 1;
+                    self.bump(Some(out))?;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                b',' | b']' | b'}' if depth == 0 => {
+                    return if out.is_empty() {
+                        Err(self.error(ErrorKind::Syntax, "expected a value"))
+                    } else {
+                        Ok(())
+                    };
+                }
+                b if depth == 0 && b.is_ascii_whitespace() => return Ok(()),
+                _ => self.bump(Some(out))?,
+            }
+        }
+    }
+
+    fn scan_string(&mut self, out: &mut Vec<u8>) -> Result<(), JsonParseError> {
+        loop {
+            match self.peek()? {
+                Some(b'"') => return self.bump(Some(out)),
+                Some(b'\\') => {
+                    self.bump(Some(out))?;
+                    if self.peek()?.is_some() {
+                        self.bump(Some(out))?;
+                    }
+                }
+                Some(_) => self.bump(Some(out))?,
+                None => {
+                    return Err(self.error(ErrorKind::UnexpectedEof, "unclosed string literal"));
+                }
+            }
+        }
+    }
+
+    fn finish(&mut self) -> Result<Option<RawElement>, JsonParseError> {
+        self.bump(None)?; // skip over the closing bracket of the root array
+        self.skip_whitespace()?;
+        match self.peek()? {
+            Some(_) => {
+                Err(self.error(ErrorKind::Syntax, "unexpected content following root value"))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), JsonParseError> {
+        while matches!(self.peek()?, Some(b) if b.is_ascii_whitespace()) {
+            self.bump(None)?;
+        }
+        Ok(())
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>, JsonParseError> {
+        match self.reader.fill_buf() {
+            Ok(buf) => Ok(buf.first().copied()),
+            Err(e) => Err(self.error(ErrorKind::Io, e.to_string())),
+        }
+    }
+
+    /// Consumes the byte returned by the last call to `peek`, copying it to `out` if given.
+    fn bump(&mut self, out: Option<&mut Vec<u8>>) -> Result<(), JsonParseError> {
+        let Some(b) = self.peek()? else {
+            return Ok(());
+        };
+        self.reader.consume(1);
+        self.offset += 1;
+        if b == b'\n' {
+            self.line += 1;
+            self.column = 1;
+        } else if b & 0xC0 != 0x80 {
+            // only count the first byte of each UTF-8 sequence
+            self.column += 1;
+        }
+        if let Some(out) = out {
+            out.push(b);
+        }
+        Ok(())
+    }
+
+    fn error(&self, kind: ErrorKind, message: impl Into<String>) -> JsonParseError {
+        let path = if self.state == State::Elements {
+            format!("/{}", self.index)
+        } else {
+            String::new()
+        };
+        JsonParseError::new(kind, message, self.offset, (self.line, self.column), path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mjl::JsonLexer;
+
+    use crate::{ErrorKind, ParseOptions, ProgressHook, parse, stream_array, stream_array_with};
+
+    #[test]
+    fn yields_each_element() {
+        let input = r#" [1, "a,]\"", {"b": [2, {}]}, [], true, null ] "#;
+        let values: Vec<_> = stream_array(input.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let expected = parse(JsonLexer::new(input)).unwrap();
+        assert_eq!(expected.value, crate::Value::Array(values));
+    }
+
+    #[test]
+    fn report_progress() {
+        let reports = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = reports.clone();
+        let options = ParseOptions {
+            progress: Some(ProgressHook::new(move |p| sink.lock().unwrap().push(p))),
+            ..Default::default()
+        };
+        let values = stream_array_with("[1, [2, 3]]".as_bytes(), options).count();
+        assert_eq!(2, values);
+        let consumed: Vec<_> = reports.lock().unwrap().iter().map(|p| p.consumed).collect();
+        assert_eq!(vec![2, 10, 11], consumed);
+        assert!(reports.lock().unwrap().iter().all(|p| p.total.is_none()));
+    }
+
+    #[test]
+    fn empty_array() {
+        assert_eq!(0, stream_array("[ ]".as_bytes()).count());
+    }
+
+    #[test]
+    fn element_errors_are_relative_to_document() {
+        let input = "[1,\n  {\"a\": tru}]";
+        let results: Vec<_> = stream_array(input.as_bytes()).collect();
+        assert_eq!(2, results.len());
+        let err = results[1].as_ref().unwrap_err();
+        assert_eq!(ErrorKind::Syntax, err.kind());
+        assert_eq!((2, 9), (err.line(), err.column()));
+        assert_eq!("/1/a", err.path());
+    }
+
+    #[test]
+    fn rejects_malformed_arrays() {
+        for input in ["{}", "[1 2]", "[1,]", "[1", "[1] 2"] {
+            let last = stream_array(input.as_bytes()).last().unwrap();
+            assert!(last.is_err(), "{input}");
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Elided copies of values, for logging large payloads without flooding the logs.
+
+use std::borrow::Cow;
+
+use crate::{Pair, Value};
+
+/// Key of the member appended to truncated objects.
+pub const ELISION_KEY: &str = "…";
+
+impl<'a> Value<'a> {
+    /// Returns a copy of this value that is cut down to a printable size.
+    ///
+    /// - containers nested `max_depth` levels below this value are replaced by a `"… N more"`
+    ///   string, where `N` is their number of members or elements;
+    /// - arrays keep their first `max_items` elements, followed by a `"… N more"` element;
+    /// - objects keep their first `max_items` members, followed by a `"…": "N more"` member;
+    /// - strings keep their first `max_str_len` characters, followed by `… N more`.
+    ///
+    /// Escape sequences count as a single character and are never split. Values kept raw are
+    /// parsed to be cut down alike.
+    pub fn truncated(&self, max_depth: usize, max_items: usize, max_str_len: usize) -> Value<'a> {
+        self.truncated_at(0, max_depth, max_items, max_str_len)
+    }
+
+    fn truncated_at(
+        &self,
+        depth: usize,
+        max_depth: usize,
+        max_items: usize,
+        max_str_len: usize,
+    ) -> Value<'a> {
+        match self {
+            Value::Object(pairs) if depth >= max_depth => elided(pairs.len()),
+            Value::Array(values) if depth >= max_depth => elided(values.len()),
+            Value::Object(pairs) => {
+                let mut kept: Vec<_> = pairs
+                    .iter()
+                    .take(max_items)
+                    .map(|p| Pair {
+                        key: p.key.clone(),
+                        value: p
+                            .value
+                            .truncated_at(depth + 1, max_depth, max_items, max_str_len),
+                    })
+                    .collect();
+                if pairs.len() > max_items {
+                    kept.push(Pair {
+                        key: Cow::Borrowed(ELISION_KEY),
+                        value: Value::Str(Cow::Owned(format!("{} more", pairs.len() - max_items))),
+                    });
+                }
+                Value::Object(kept.into())
+            }
+            Value::Array(values) => {
+                let mut kept: Vec<_> = values
+                    .iter()
+                    .take(max_items)
+                    .map(|v| v.truncated_at(depth + 1, max_depth, max_items, max_str_len))
+                    .collect();
+                if values.len() > max_items {
+                    kept.push(elided(values.len() - max_items));
+                }
+                Value::Array(kept)
+            }
+            Value::Str(s) => Value::Str(truncate_str(s, max_str_len)),
+            // kept raw, the value may be as large as any other
+            Value::Raw(raw) => match raw.parse() {
+                Ok(value) => value
+                    .truncated_at(depth, max_depth, max_items, max_str_len)
+                    .into_owned(),
+                // JSON5 text, which is kept raw as it was written
+                Err(_) => Value::Str(Cow::Borrowed(ELISION_KEY)),
+            },
+            v => v.clone(),
+        }
+    }
+}
+
+fn elided(count: usize) -> Value<'static> {
+    Value::Str(Cow::Owned(format!("… {count} more")))
+}
+
+fn truncate_str<'a>(s: &Cow<'a, str>, max_len: usize) -> Cow<'a, str> {
+    let starts: Vec<usize> = unit_starts(s).collect();
+    match starts.get(max_len) {
+        None => s.clone(),
+        Some(&cut) => Cow::Owned(format!("{}… {} more", &s[..cut], starts.len() - max_len)),
+    }
+}
+
+/// Byte offsets of the characters of a raw string literal, treating each escape sequence as a
+/// single character so that cutting at one of them keeps the literal valid.
+fn unit_starts(s: &str) -> impl Iterator<Item = usize> + '_ {
+    let mut chars = s.char_indices();
+    std::iter::from_fn(move || {
+        let (i, c) = chars.next()?;
+        if c == '\\' && matches!(chars.next(), Some((_, 'u'))) {
+            chars.nth(3);
+        }
+        Some(i)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use mjl::JsonLexer;
+
+    use crate::{ParseOptions, Value, parse, parse_with};
+
+    fn value(input: &str) -> Value<'_> {
+        parse(JsonLexer::new(input)).unwrap().value
+    }
+
+    #[test]
+    fn truncates_items_and_depth() {
+        let input = r#"{ "a": [1, 2, 3, 4], "b": { "c": { "d": 1 } }, "e": 1 }"#;
+        let expected = r#"{ "a": [1, 2, "… 2 more"], "b": { "c": "… 1 more" }, "…": "1 more" }"#;
+        assert_eq!(value(expected), value(input).truncated(2, 2, 10));
+    }
+
+    #[test]
+    fn truncates_raw_values() {
+        let input = r#"{"a": ["abcdef", 2, 3, 4]}"#;
+        let options = ParseOptions {
+            spill_above: Some(10),
+            ..Default::default()
+        };
+        let spilled = parse_with(JsonLexer::new(input), options).unwrap().value;
+        assert!(matches!(spilled["a"], Value::Raw(_)));
+        assert_eq!(
+            value(r#"{"a": ["abc… 3 more", 2, "… 2 more"]}"#),
+            spilled.truncated(2, 2, 3)
+        );
+    }
+
+    #[test]
+    fn truncates_strings_without_splitting_escapes() {
+        let input = r#"["ab\u00e9cd", "🗻∈🌏"]"#;
+        assert_eq!(
+            value(r#"["ab\u00e9… 2 more", "🗻∈🌏"]"#),
+            value(input).truncated(1, 2, 3)
+        );
+    }
+}
@@ -0,0 +1,198 @@
+//! Handling of invalid UTF-8 in byte input.
+
+use std::{borrow::Cow, fmt::Write, str::Utf8Error};
+
+/// What to do with byte sequences that are not valid UTF-8 when parsing from bytes or readers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Utf8Policy {
+    /// Fail with [`ErrorKind::InvalidUtf8`](crate::ErrorKind::InvalidUtf8).
+    #[default]
+    Error,
+    /// Replace each invalid sequence with U+FFFD REPLACEMENT CHARACTER.
+    Replace,
+    /// Keep the invalid bytes, each encoded as a lone low surrogate escape `\uDC80`..`\uDCFF`
+    /// in uppercase (the "surrogateescape" scheme), so that [`recover_bytes`] gets them back
+    /// from the string's raw text. Escapes of the same form in the input are written in
+    /// lowercase, which stands for the same code unit, so as not to be taken for invalid bytes.
+    ///
+    /// Invalid bytes are only accepted inside string literals, and not right after a backslash,
+    /// where they would make an invalid escape sequence.
+    PassThrough,
+}
+
+/// The raw text of a string as it was in the input, with the invalid bytes that
+/// [`Utf8Policy::PassThrough`] kept restored in place of their escapes.
+pub fn recover_bytes(raw: &str) -> Cow<'_, [u8]> {
+    if !raw.contains("\\uDC") {
+        return Cow::Borrowed(raw.as_bytes());
+    }
+    let mut out = Vec::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(i) = rest.find("\\uDC") {
+        out.extend_from_slice(&rest.as_bytes()[..i]);
+        let escape = &rest[i..];
+        let byte = escape
+            .get(4..6)
+            .filter(|digits| !ends_with_escape(&out) && is_byte_digits(digits))
+            .and_then(|digits| u8::from_str_radix(digits, 16).ok());
+        match byte {
+            Some(byte) => {
+                out.push(byte);
+                rest = &escape[6..];
+            }
+            None => {
+                out.push(b'\\');
+                rest = &escape[1..];
+            }
+        }
+    }
+    out.extend_from_slice(rest.as_bytes());
+    Cow::Owned(out)
+}
+
+pub(crate) fn decode(bytes: &[u8], policy: Utf8Policy) -> Result<Cow<'_, str>, Utf8Error> {
+    match policy {
+        Utf8Policy::Error => std::str::from_utf8(bytes).map(Cow::Borrowed),
+        Utf8Policy::Replace => Ok(String::from_utf8_lossy(bytes)),
+        Utf8Policy::PassThrough => {
+            if let Ok(s) = std::str::from_utf8(bytes)
+                && !s.contains("\\uDC")
+            {
+                return Ok(Cow::Borrowed(s));
+            }
+            let mut out = String::with_capacity(bytes.len());
+            for chunk in bytes.utf8_chunks() {
+                push_unmarked(chunk.valid(), &mut out);
+                for b in chunk.invalid() {
+                    if ends_with_escape(out.as_bytes()) {
+                        // left for the lexer to reject as an invalid escape sequence
+                        out.push(char::REPLACEMENT_CHARACTER);
+                    } else {
+                        write!(out, "\\uDC{b:02X}").unwrap();
+                    }
+                }
+            }
+            Ok(Cow::Owned(out))
+        }
+    }
+}
+
+/// Pushes `text`, with the escapes that would be taken for invalid bytes written in lowercase.
+fn push_unmarked(text: &str, out: &mut String) {
+    let mut rest = text;
+    while let Some(i) = rest.find("\\uDC") {
+        out.push_str(&rest[..i]);
+        let escape = &rest[i..];
+        if !ends_with_escape(out.as_bytes()) && escape.get(4..6).is_some_and(is_byte_digits) {
+            out.push_str(&escape[..6].to_ascii_lowercase());
+            rest = &escape[6..];
+        } else {
+            out.push('\\');
+            rest = &escape[1..];
+        }
+    }
+    out.push_str(rest);
+}
+
+/// Whether `text` ends with a backslash that escapes what follows.
+fn ends_with_escape(text: &[u8]) -> bool {
+    text.iter().rev().take_while(|b| **b == b'\\').count() % 2 == 1
+}
+
+/// Whether `digits` are the last two of an escape `\uDC80`..`\uDCFF`, in uppercase.
+fn is_byte_digits(digits: &str) -> bool {
+    match digits.as_bytes() {
+        [high, low] => {
+            matches!(high, b'8'..=b'9' | b'A'..=b'F') && matches!(low, b'0'..=b'9' | b'A'..=b'F')
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+
+    use crate::{ErrorKind, ParseOptions, Utf8Policy, Value, parse_bytes, utf8::recover_bytes};
+
+    fn parse_str_value(bytes: &[u8], invalid_utf8: Utf8Policy) -> Value<'_> {
+        let options = ParseOptions {
+            invalid_utf8,
+            ..Default::default()
+        };
+        parse_bytes(bytes, options).unwrap().value
+    }
+
+    #[test]
+    fn policies() {
+        let input = b"\"a\xFFb\"";
+        let err = parse_bytes(input, ParseOptions::default()).unwrap_err();
+        assert_eq!(ErrorKind::InvalidUtf8, err.kind());
+        assert_eq!(2, err.offset());
+
+        assert_eq!(
+            Value::Str(Cow::Borrowed("a\u{FFFD}b")),
+            parse_str_value(input, Utf8Policy::Replace)
+        );
+        assert_eq!(
+            Value::Str(Cow::Borrowed("a\\uDCFFb")),
+            parse_str_value(input, Utf8Policy::PassThrough)
+        );
+    }
+
+    #[test]
+    fn recover_passed_through_bytes() {
+        let recovered = |input| match parse_str_value(input, Utf8Policy::PassThrough) {
+            Value::Str(s) => recover_bytes(&s).into_owned(),
+            v => panic!("not a string: {v:?}"),
+        };
+        assert_eq!(b"a\xFFb\xC3", &recovered(b"\"a\xFFb\xC3\"")[..]);
+        // escapes in the input are kept apart from those of invalid bytes
+        assert_eq!(
+            Value::Str(Cow::Borrowed("\\udcff\\uDC00\\\\uDCFF")),
+            parse_str_value(b"\"\\uDCFF\\uDC00\\\\uDCFF\"", Utf8Policy::PassThrough)
+        );
+        assert_eq!(
+            b"\\udcff\\uDC00\\\\uDCFF\xFF",
+            &recovered(b"\"\\uDCFF\\uDC00\\\\uDCFF\xFF\"")[..]
+        );
+        assert_eq!(b"\\\\\x80", &recovered(b"\"\\\\\x80\"")[..]);
+
+        let options = ParseOptions {
+            invalid_utf8: Utf8Policy::PassThrough,
+            ..Default::default()
+        };
+        let err = parse_bytes(b"\"\\\xFF\"", options).unwrap_err();
+        assert_eq!(ErrorKind::Syntax, err.kind());
+    }
+
+    #[test]
+    fn other_encodings() {
+        let utf16: Vec<u8> = "\u{feff}{\"a\": [1, \"\u{e9}\"]}"
+            .encode_utf16()
+            .flat_map(u16::to_be_bytes)
+            .collect();
+        let json = parse_bytes(&utf16, ParseOptions::default()).unwrap();
+        assert_eq!(utf16.len(), json.metadata.byte_length);
+        assert_eq!(
+            Some(&Value::Str(Cow::Borrowed("\u{e9}"))),
+            json.value.pointer("/a/1")
+        );
+
+        let utf32: Vec<u8> = "[1,\n2,]"
+            .chars()
+            .flat_map(|c| u32::from(c).to_le_bytes())
+            .collect();
+        let err = parse_bytes(&utf32, ParseOptions::default()).unwrap_err();
+        assert_eq!(
+            (ErrorKind::Syntax, 2, 3),
+            (err.kind(), err.line(), err.column())
+        );
+        // an unpaired surrogate
+        let err = parse_bytes(b"[\0\n\0\x00\xDC]\0", ParseOptions::default()).unwrap_err();
+        assert_eq!(
+            (ErrorKind::InvalidUtf8, 4, 2, 1),
+            (err.kind(), err.offset(), err.line(), err.column())
+        );
+    }
+}
@@ -0,0 +1,354 @@
+//! Extractors and responders for web frameworks, behind the `axum` and `actix` features, so that
+//! web apps can parse requests and write responses with this crate end to end.
+//!
+//! Request bodies are parsed into any [`FromJson`] type, and rejected requests are answered
+//! with an RFC 7807 problem details document, `application/problem+json`, carrying the
+//! structured error.
+
+use std::{error::Error, fmt::Display};
+
+use crate::{
+    FromJson, JsonParseError, ParseOptions, builder::JsonBuilder, from_json::FromJsonError,
+    media::MediaType, parse_bytes,
+};
+
+/// A request body parsed and converted to `T`, or a value written as a JSON response.
+///
+/// Only requests with a JSON `Content-Type` are accepted. Strings hold their raw text, as
+/// [`FromJson`] gives it, and any [`Value`](crate::Value) can be written back as is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MjdJson<T>(pub T);
+
+/// Why a request was rejected by the [`MjdJson`] extractor.
+#[derive(Debug)]
+pub enum JsonRejection {
+    /// The request had no JSON `Content-Type`.
+    UnsupportedMediaType,
+    /// The body could not be read, with the status the framework gave the failure.
+    Body { status: u16, message: String },
+    /// The body is not valid JSON.
+    Parse(JsonParseError),
+    /// The body doesn't have the shape of the requested type.
+    Convert(FromJsonError),
+}
+
+impl JsonRejection {
+    /// The HTTP status of the response to the rejected request.
+    pub fn status(&self) -> u16 {
+        match self {
+            JsonRejection::UnsupportedMediaType => 415,
+            JsonRejection::Body { status, .. } => *status,
+            JsonRejection::Parse(_) => 400,
+            JsonRejection::Convert(_) => 422,
+        }
+    }
+
+    /// The problem details document describing the rejection: its `type`, `title`, `status`
+    /// and `detail`, and a stable `code` with the location of the error when it has one.
+    pub fn problem(&self) -> String {
+        let (title, code) = match self {
+            JsonRejection::UnsupportedMediaType => {
+                ("Unsupported Media Type", "unsupported_media_type")
+            }
+            JsonRejection::Body { .. } => ("Unreadable Body", "body"),
+            JsonRejection::Parse(e) => ("Bad Request", e.code()),
+            JsonRejection::Convert(_) => ("Unprocessable Content", "invalid_value"),
+        };
+        let problem = JsonBuilder::new()
+            .object()
+            .key("type")
+            .string("about:blank")
+            .key("title")
+            .string(title)
+            .key("status")
+            .number(self.status())
+            .key("detail")
+            .string(&self.to_string())
+            .key("code")
+            .string(code);
+        let problem = match self {
+            JsonRejection::Parse(e) => {
+                let problem = problem
+                    .key("line")
+                    .number(e.line())
+                    .key("column")
+                    .number(e.column())
+                    .key("path")
+                    .string(e.path());
+                match e.hint() {
+                    Some(hint) => problem.key("hint").string(hint),
+                    None => problem,
+                }
+            }
+            JsonRejection::Convert(e) => problem.key("path").string(e.path()),
+            _ => problem,
+        };
+        problem.end().to_string()
+    }
+}
+
+impl Display for JsonRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsonRejection::UnsupportedMediaType => {
+                write!(f, "expected a request with a JSON Content-Type")
+            }
+            JsonRejection::Body { message, .. } => write!(f, "{message}"),
+            JsonRejection::Parse(e) => write!(f, "{e}"),
+            JsonRejection::Convert(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl Error for JsonRejection {}
+
+/// Parses and converts the body of a request with the given `Content-Type`.
+fn extract<T: FromJson>(content_type: Option<&str>, body: &[u8]) -> Result<T, JsonRejection> {
+    if content_type.and_then(MediaType::from_content_type) != Some(MediaType::Json) {
+        return Err(JsonRejection::UnsupportedMediaType);
+    }
+    let json = parse_bytes(body, ParseOptions::default()).map_err(JsonRejection::Parse)?;
+    T::from_json(&json.value).map_err(JsonRejection::Convert)
+}
+
+#[cfg(feature = "axum")]
+mod axum {
+    use axum_core::{
+        body::Body,
+        extract::{FromRequest, Request},
+        response::{IntoResponse, Response},
+    };
+    use bytes::Bytes;
+    use http::{StatusCode, header};
+
+    use super::{JsonRejection, MjdJson, extract};
+    use crate::{FromJson, Value};
+
+    impl<T: FromJson, S: Send + Sync> FromRequest<S> for MjdJson<T> {
+        type Rejection = JsonRejection;
+
+        async fn from_request(req: Request, state: &S) -> Result<Self, JsonRejection> {
+            let content_type = req.headers().get(header::CONTENT_TYPE).cloned();
+            let body = Bytes::from_request(req, state)
+                .await
+                .map_err(|e| JsonRejection::Body {
+                    status: e.status().as_u16(),
+                    message: e.body_text(),
+                })?;
+            let content_type = content_type.as_ref().and_then(|v| v.to_str().ok());
+            extract(content_type, &body).map(MjdJson)
+        }
+    }
+
+    impl IntoResponse for JsonRejection {
+        fn into_response(self) -> Response {
+            let status = StatusCode::from_u16(self.status()).unwrap();
+            let headers = [(header::CONTENT_TYPE, "application/problem+json")];
+            (status, headers, self.problem()).into_response()
+        }
+    }
+
+    impl IntoResponse for MjdJson<Value<'_>> {
+        fn into_response(self) -> Response {
+            let headers = [(header::CONTENT_TYPE, "application/json")];
+            (headers, Body::from(self.0.to_string())).into_response()
+        }
+    }
+}
+
+#[cfg(feature = "actix")]
+mod actix {
+    use std::{future::Future, pin::Pin};
+
+    use actix_web::{
+        FromRequest, HttpRequest, HttpResponse, Responder, ResponseError,
+        body::BoxBody,
+        dev::Payload,
+        http::{StatusCode, header},
+        web::Bytes,
+    };
+
+    use super::{JsonRejection, MjdJson, extract};
+    use crate::{FromJson, Value};
+
+    impl<T: FromJson + 'static> FromRequest for MjdJson<T> {
+        type Error = JsonRejection;
+        type Future = Pin<Box<dyn Future<Output = Result<Self, JsonRejection>>>>;
+
+        fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+            let content_type = req.headers().get(header::CONTENT_TYPE).cloned();
+            let body = Bytes::from_request(req, payload);
+            Box::pin(async move {
+                let body = body.await.map_err(|e| JsonRejection::Body {
+                    status: e.as_response_error().status_code().as_u16(),
+                    message: e.to_string(),
+                })?;
+                let content_type = content_type.as_ref().and_then(|v| v.to_str().ok());
+                extract(content_type, &body).map(MjdJson)
+            })
+        }
+    }
+
+    impl ResponseError for JsonRejection {
+        fn status_code(&self) -> StatusCode {
+            StatusCode::from_u16(self.status()).unwrap()
+        }
+
+        fn error_response(&self) -> HttpResponse {
+            HttpResponse::build(self.status_code())
+                .content_type("application/problem+json")
+                .body(self.problem())
+        }
+    }
+
+    impl Responder for MjdJson<Value<'_>> {
+        type Body = BoxBody;
+
+        fn respond_to(self, _: &HttpRequest) -> HttpResponse {
+            HttpResponse::Ok()
+                .content_type("application/json")
+                .body(self.0.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mjl::JsonLexer;
+
+    use crate::{
+        Value, parse,
+        web::{JsonRejection, extract},
+    };
+
+    fn json(input: &str) -> Value<'_> {
+        parse(JsonLexer::new(input)).unwrap().value
+    }
+
+    fn problem(content_type: &str, body: &str) -> (u16, Value<'static>) {
+        let e = extract::<Vec<u32>>(Some(content_type), body.as_bytes()).unwrap_err();
+        (e.status(), json(&e.problem()).into_owned())
+    }
+
+    #[test]
+    fn problem_details() {
+        let ok = extract::<Vec<u32>>(Some("application/json; charset=utf-8"), b"[1, 2]");
+        assert_eq!(vec![1, 2], ok.unwrap());
+
+        assert_eq!(
+            (
+                400,
+                json(
+                    r#"{"type": "about:blank", "title": "Bad Request", "status": 400,
+                    "detail": "unable to parse token from char T at line 1, column 2; did you mean \"true\"?",
+                    "code": "syntax", "line": 1, "column": 2, "path": "",
+                    "hint": "did you mean \"true\"?"}"#
+                )
+            ),
+            problem("application/json", "[True]")
+        );
+        assert_eq!(
+            (
+                422,
+                json(
+                    r#"{"type": "about:blank", "title": "Unprocessable Content", "status": 422,
+                    "detail": "expected a number, found string at /1", "code": "invalid_value",
+                    "path": "/1"}"#
+                )
+            ),
+            problem("application/json", r#"[1, "2"]"#)
+        );
+        let (status, _) = problem("text/plain", "[]");
+        assert_eq!(415, status);
+        assert!(matches!(
+            extract::<Value>(None, b"[]"),
+            Err(JsonRejection::UnsupportedMediaType)
+        ));
+    }
+
+    #[cfg(feature = "axum")]
+    #[tokio::test]
+    async fn axum_extractor() {
+        use axum_core::{
+            body::Body,
+            extract::{FromRequest, Request},
+            response::IntoResponse,
+        };
+        use http_body_util::BodyExt;
+
+        use crate::web::MjdJson;
+
+        let request = |body: &'static str| {
+            Request::builder()
+                .header("content-type", "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+        let MjdJson(value) = MjdJson::<Value>::from_request(request(r#"{"a": 1}"#), &())
+            .await
+            .unwrap();
+        assert_eq!(json(r#"{"a": 1}"#), value);
+
+        let response = MjdJson(value).into_response();
+        assert_eq!("application/json", response.headers()["content-type"]);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(br#"{"a":1}"#, &body[..]);
+
+        let e = MjdJson::<Value>::from_request(request("[1,"), &())
+            .await
+            .unwrap_err();
+        let response = e.into_response();
+        assert_eq!(400, response.status().as_u16());
+        assert_eq!(
+            "application/problem+json",
+            response.headers()["content-type"]
+        );
+    }
+
+    #[cfg(feature = "actix")]
+    #[tokio::test]
+    async fn actix_extractor() {
+        use actix_web::{
+            FromRequest, Responder, ResponseError, body::MessageBody, test::TestRequest,
+        };
+
+        use crate::web::MjdJson;
+
+        let request = |body: &'static str| {
+            TestRequest::default()
+                .insert_header(("content-type", "application/json"))
+                .set_payload(body)
+                .to_http_parts()
+        };
+        let (req, mut payload) = request(r#"{"a": 1}"#);
+        let MjdJson(value) = MjdJson::<Value>::from_request(&req, &mut payload)
+            .await
+            .unwrap();
+        assert_eq!(json(r#"{"a": 1}"#), value);
+
+        let response = MjdJson(value).respond_to(&req);
+        assert_eq!(
+            Some("application/json"),
+            response
+                .headers()
+                .get("content-type")
+                .map(|v| v.to_str().unwrap())
+        );
+        let body = response.into_body().try_into_bytes().unwrap();
+        assert_eq!(br#"{"a":1}"#, &body[..]);
+
+        let (req, mut payload) = request("[1,");
+        let e = MjdJson::<Value>::from_request(&req, &mut payload)
+            .await
+            .unwrap_err();
+        let response = e.error_response();
+        assert_eq!(400, response.status().as_u16());
+        assert_eq!(
+            Some("application/problem+json"),
+            response
+                .headers()
+                .get("content-type")
+                .map(|v| v.to_str().unwrap())
+        );
+    }
+}
@@ -82,6 +82,15 @@
         checks = {
           inherit mjl mjp;
 
+          mjl-no-std = craneLib.cargoBuild (
+            commonArgs
+            // {
+              inherit cargoArtifacts;
+              pname = "mjl-no-std";
+              cargoExtraArgs = "-p mjl --no-default-features --features memchr";
+            }
+          );
+
           workspace-clippy = craneLib.cargoClippy (
             commonArgs
             // {